@@ -51,14 +51,300 @@ defmodule Cyanea.Align do
   ## Options
 
     * `:mode` - `:global` (default), `:local`, or `:semiglobal`
-    * `:matrix` - `:blosum62` (default), `:blosum45`, `:blosum80`, or `:pam250`
-
+    * `:matrix` - `:blosum62` (default), `:blosum45`, `:blosum50`, `:blosum80`,
+      `:blosum90`, `:pam30`, `:pam70`, `:pam250`, or a matrix from
+      `load_matrix/1` / `parse_matrix/2`
+    * `:gap_open` - gap open penalty (negative)
+    * `:gap_extend` - gap extend penalty (negative)
+
+  Gap penalties default to BLAST's for the chosen matrix (-11/-1 for
+  BLOSUM62). A gap of length `L` scores `gap_open + L * gap_extend`.
   """
   @spec protein(binary(), binary(), keyword()) :: {:ok, struct()} | {:error, term()}
   def protein(query, target, opts \\ []) when is_binary(query) and is_binary(target) do
+    matrix = Keyword.get(opts, :matrix, :blosum62)
+
+    if has_gap_opts?(opts) or not builtin_protein_matrix?(matrix) do
+      with_matrix(query, target, matrix, opts)
+    else
+      mode = mode_string(Keyword.get(opts, :mode, :global))
+      nif_call(fn -> Native.align_protein(query, target, mode, matrix_string(matrix)) end)
+    end
+  end
+
+  defp has_gap_opts?(opts),
+    do: Keyword.has_key?(opts, :gap_open) or Keyword.has_key?(opts, :gap_extend)
+
+  # Matrices `align_protein` scores itself, with its own gap penalties.
+  defp builtin_protein_matrix?(matrix) when is_atom(matrix) or is_binary(matrix),
+    do: matrix_string(matrix) in ~w(blosum62 blosum45 blosum80 pam250)
+
+  defp builtin_protein_matrix?(_matrix), do: false
+
+  # ===========================================================================
+  # Substitution matrices
+  # ===========================================================================
+
+  @doc """
+  Align two sequences scored by a substitution matrix with affine gaps.
+
+  `matrix` is a built-in name (see `matrices/0`) or a
+  `%Cyanea.Native.SubstitutionMatrix{}` from `matrix/1`, `load_matrix/1`,
+  `parse_matrix/2` or `nucleotide_matrix/1`.
+
+  ## Options
+
+    * `:mode` - `:global` (default), `:local`, or `:semiglobal`
+    * `:gap_open` - gap open penalty (default: the matrix's, else -10)
+    * `:gap_extend` - gap extend penalty (default: the matrix's, else -1)
+
+  """
+  @spec with_matrix(binary(), binary(), atom() | binary() | struct(), keyword()) ::
+          {:ok, struct()} | {:error, term()}
+  def with_matrix(query, target, matrix, opts \\ []) when is_binary(query) and is_binary(target) do
     mode = mode_string(Keyword.get(opts, :mode, :global))
-    matrix = matrix_string(Keyword.get(opts, :matrix, :blosum62))
-    nif_call(fn -> Native.align_protein(query, target, mode, matrix) end)
+
+    with {:ok, matrix} <- matrix(matrix) do
+      gap_open = Keyword.get(opts, :gap_open, matrix.gap_open || -10)
+      gap_extend = Keyword.get(opts, :gap_extend, matrix.gap_extend || -1)
+
+      nif_call(fn ->
+        Native.align_with_matrix(query, target, mode, matrix, gap_open, gap_extend)
+      end)
+    end
+  end
+
+  @doc "Names of the built-in substitution matrices."
+  @spec matrices() :: {:ok, [binary()]} | {:error, term()}
+  def matrices, do: nif_call(fn -> Native.substitution_matrices() end)
+
+  @doc """
+  A built-in substitution matrix by name, e.g. `:blosum62` or `"pam30"`,
+  with BLAST's default gap penalties for it. A matrix struct is returned
+  as is.
+  """
+  @spec matrix(atom() | binary() | struct()) :: {:ok, struct()} | {:error, term()}
+  def matrix(%Native.SubstitutionMatrix{} = matrix), do: {:ok, matrix}
+
+  def matrix(name) when is_atom(name) or is_binary(name),
+    do: nif_call(fn -> Native.substitution_matrix(matrix_string(name)) end)
+
+  @doc """
+  Load an NCBI-format matrix file (as shipped with BLAST and EMBOSS, e.g.
+  VTML or PAM matrices not built in). The matrix is named after the file.
+  """
+  @spec load_matrix(binary()) :: {:ok, struct()} | {:error, term()}
+  def load_matrix(path) when is_binary(path),
+    do: nif_call(fn -> Native.read_substitution_matrix(path) end)
+
+  @doc "Parse an NCBI-format matrix from text."
+  @spec parse_matrix(binary(), binary()) :: {:ok, struct()} | {:error, term()}
+  def parse_matrix(text, name \\ "custom") when is_binary(text) and is_binary(name),
+    do: nif_call(fn -> Native.parse_substitution_matrix_text(text, name) end)
+
+  @doc """
+  IUPAC nucleotide matrix. Ambiguity codes score the rounded mean over the
+  bases they stand for; `U` scores as `T`.
+
+  ## Options
+
+    * `:match` - identical bases (default: 5)
+    * `:transition` - A/G and C/T (default: -1)
+    * `:transversion` - other mismatches (default: -4)
+
+  """
+  @spec nucleotide_matrix(keyword()) :: {:ok, struct()} | {:error, term()}
+  def nucleotide_matrix(opts \\ []) do
+    match = Keyword.get(opts, :match, 5)
+    transition = Keyword.get(opts, :transition, -1)
+    transversion = Keyword.get(opts, :transversion, -4)
+    nif_call(fn -> Native.nucleotide_matrix(match, transition, transversion) end)
+  end
+
+  # ===========================================================================
+  # Alignment output
+  # ===========================================================================
+
+  @doc """
+  Identity, similarity and gap counts of an alignment result, with
+  1-based inclusive coordinates and the match line (`|` identical, `:`
+  positive score, `.` zero score).
+
+  ## Options
+
+    * `:matrix` - matrix for similarity (name or struct); without one only
+      identities count as positives
+
+  """
+  @spec summary(struct(), keyword()) :: {:ok, struct()} | {:error, term()}
+  def summary(%Native.AlignmentResult{} = result, opts \\ []) do
+    with {:ok, matrix} <- optional_matrix(Keyword.get(opts, :matrix)) do
+      nif_call(fn -> Native.alignment_summary(result, matrix) end)
+    end
+  end
+
+  @doc """
+  Render an alignment result as BLAST-style text: a score / identities /
+  positives / gaps line, then blocks with coordinates and a match line.
+
+  ## Options
+
+    * `:query_name` - label of the query rows (default: "Query")
+    * `:target_name` - label of the target rows (default: "Sbjct")
+    * `:width` - columns per block (default: 60)
+    * `:matrix` - matrix used to mark similar residues
+
+  """
+  @spec format(struct(), keyword()) :: {:ok, binary()} | {:error, term()}
+  def format(%Native.AlignmentResult{} = result, opts \\ []) do
+    query_name = Keyword.get(opts, :query_name, "Query")
+    target_name = Keyword.get(opts, :target_name, "Sbjct")
+    width = Keyword.get(opts, :width, 60)
+
+    with {:ok, matrix} <- optional_matrix(Keyword.get(opts, :matrix)) do
+      nif_call(fn -> Native.format_alignment(result, query_name, target_name, width, matrix) end)
+    end
+  end
+
+  @doc """
+  A SAM line for an alignment result. `query` is the full query sequence;
+  unaligned ends are soft-clipped. Carries `NM`, `MD` and `AS` tags.
+
+  ## Options
+
+    * `:query_name` - QNAME (default: "query")
+    * `:target_name` - RNAME (default: "target")
+
+  """
+  @spec to_sam(struct(), binary(), keyword()) :: {:ok, binary()} | {:error, term()}
+  def to_sam(%Native.AlignmentResult{} = result, query, opts \\ []) when is_binary(query) do
+    query_name = Keyword.get(opts, :query_name, "query")
+    target_name = Keyword.get(opts, :target_name, "target")
+    nif_call(fn -> Native.alignment_to_sam(result, query, query_name, target_name) end)
+  end
+
+  @doc """
+  A PAF line for an alignment result, with `NM`, `AS` and `cg` tags.
+
+  ## Options
+
+    * `:query_name` - default "query"
+    * `:target_name` - default "target"
+    * `:query_length` - full query length (default: the result's `query_end`)
+    * `:target_length` - full target length (default: the result's `target_end`)
+
+  """
+  @spec to_paf(struct(), keyword()) :: {:ok, binary()} | {:error, term()}
+  def to_paf(%Native.AlignmentResult{} = result, opts \\ []) do
+    query_name = Keyword.get(opts, :query_name, "query")
+    target_name = Keyword.get(opts, :target_name, "target")
+    query_length = Keyword.get(opts, :query_length, result.query_end)
+    target_length = Keyword.get(opts, :target_length, result.target_end)
+
+    nif_call(fn ->
+      Native.alignment_to_paf(result, query_name, query_length, target_name, target_length)
+    end)
+  end
+
+  defp optional_matrix(nil), do: {:ok, nil}
+  defp optional_matrix(matrix), do: matrix(matrix)
+
+  # ===========================================================================
+  # Alignment significance
+  # ===========================================================================
+
+  @doc """
+  Karlin–Altschul λ, K and H for local alignment scores.
+
+  `matrix` is a matrix name or struct, or `:dna` for `dna/3`-style
+  match/mismatch scoring. Gapped parameters are NCBI BLAST's for the
+  built-in protein matrices with the gap costs BLAST supports; other
+  scoring has ungapped parameters only (use `shuffle_test/3` for gapped
+  significance).
+
+  ## Options
+
+    * `:gapped` - gapped parameters (default: true for protein matrices)
+    * `:gap_open` / `:gap_extend` - gap penalties (default: the matrix's)
+    * `:match` / `:mismatch` - scores for `:dna` (default: +2/-1)
+
+  """
+  @spec karlin_altschul(atom() | binary() | struct(), keyword()) ::
+          {:ok, struct()} | {:error, term()}
+  def karlin_altschul(matrix \\ :blosum62, opts \\ [])
+
+  def karlin_altschul(:dna, opts) do
+    match = Keyword.get(opts, :match, 2)
+    mismatch = Keyword.get(opts, :mismatch, -1)
+
+    with {:ok, matrix} <-
+           nucleotide_matrix(match: match, transition: mismatch, transversion: mismatch) do
+      nif_call(fn -> Native.karlin_altschul(matrix, nil, nil) end)
+    end
+  end
+
+  def karlin_altschul(matrix, opts) do
+    with {:ok, matrix} <- matrix(matrix) do
+      {gap_open, gap_extend} =
+        if Keyword.get(opts, :gapped, true) do
+          {Keyword.get(opts, :gap_open, matrix.gap_open),
+           Keyword.get(opts, :gap_extend, matrix.gap_extend)}
+        else
+          {nil, nil}
+        end
+
+      nif_call(fn -> Native.karlin_altschul(matrix, gap_open, gap_extend) end)
+    end
+  end
+
+  @doc """
+  Bit score and E-value of a local alignment score (or result).
+
+  ## Options
+
+    * `:query_length` - query residues (required)
+    * `:database_size` - total database residues (required)
+
+  """
+  @spec significance(integer() | struct(), struct(), keyword()) :: {:ok, struct()} | {:error, term()}
+  def significance(score_or_result, params, opts)
+
+  def significance(%Native.AlignmentResult{score: score}, params, opts),
+    do: significance(score, params, opts)
+
+  def significance(score, %Native.KarlinAltschul{} = params, opts) when is_integer(score) do
+    query_length = Keyword.fetch!(opts, :query_length)
+    database_size = Keyword.fetch!(opts, :database_size)
+    nif_call(fn -> Native.alignment_significance(score, params, query_length, database_size) end)
+  end
+
+  @doc """
+  Empirical significance of the local alignment of `query` and `target`:
+  the target is shuffled (keeping its composition) and realigned, and an
+  extreme value distribution fitted to the shuffled scores gives λ, K and a
+  p-value. Works for any matrix and gap penalties.
+
+  ## Options
+
+    * `:matrix` - name or struct (default: `:blosum62`)
+    * `:gap_open` / `:gap_extend` - default: the matrix's, else -10/-1
+    * `:shuffles` - number of shuffles (default: 100)
+    * `:seed` - random seed (default: 42)
+
+  """
+  @spec shuffle_test(binary(), binary(), keyword()) :: {:ok, struct()} | {:error, term()}
+  def shuffle_test(query, target, opts \\ []) when is_binary(query) and is_binary(target) do
+    shuffles = Keyword.get(opts, :shuffles, 100)
+    seed = Keyword.get(opts, :seed, 42)
+
+    with {:ok, matrix} <- matrix(Keyword.get(opts, :matrix, :blosum62)) do
+      gap_open = Keyword.get(opts, :gap_open, matrix.gap_open || -10)
+      gap_extend = Keyword.get(opts, :gap_extend, matrix.gap_extend || -1)
+
+      nif_call(fn ->
+        Native.shuffle_significance(query, target, matrix, gap_open, gap_extend, shuffles, seed)
+      end)
+    end
   end
 
   # ===========================================================================
@@ -141,4 +427,237 @@ defmodule Cyanea.Align do
   @spec consensus(list()) :: {:ok, binary()} | {:error, term()}
   def consensus(sequences) when is_list(sequences),
     do: nif_call(fn -> Native.poa_consensus(sequences) end)
+
+  # ===========================================================================
+  # Read mapping
+  # ===========================================================================
+
+  @doc """
+  Index a reference FASTA (plain or gzipped) for `map_reads/4` and
+  `map/3`.
+
+  Meant for small genomes (viral, bacterial): every sequence is held in
+  memory with one FM-index over all of them. Returns an opaque reference.
+  """
+  @spec index_reference(binary()) :: {:ok, reference()} | {:error, term()}
+  def index_reference(path) when is_binary(path),
+    do: nif_call(fn -> Native.mapper_index(path) end)
+
+  @doc "List `{name, length}` of the sequences in a reference index."
+  @spec reference_sequences(reference()) :: {:ok, [{binary(), non_neg_integer()}]} | {:error, term()}
+  def reference_sequences(index) when is_reference(index),
+    do: nif_call(fn -> Native.mapper_contigs(index) end)
+
+  @doc """
+  Map the reads of a FASTA/FASTQ file to a reference and write SAM to
+  `output`.
+
+  `reference` is an index from `index_reference/1` or a FASTA path
+  (indexed for this call only). Each read gets its best local alignment
+  or is written unmapped; mapped reads carry MAPQ, CIGAR (`M`/`I`/`D`/`S`)
+  and `NM`, `MD`, `AS` and, when another locus aligned, `XS` tags.
+  Returns a `Cyanea.Native.SamStats` with `avg_mapq` over mapped reads.
+
+  Seeds are maximal exact matches found with the FM-index, chained by
+  diagonal on each strand and extended with banded Smith-Waterman. MAPQ
+  is 60 for reads with one good locus and falls to 0 as the second-best
+  score approaches the best.
+
+  ## Options
+
+    * `:min_seed_len` - shortest exact match used as a seed (default: 19)
+    * `:max_occurrences` - drop seeds with more hits as repeats (default: 500)
+    * `:bandwidth` - largest indel within a read (default: 16)
+    * `:match`, `:mismatch`, `:gap_open`, `:gap_extend` - scoring
+      (defaults: +1/-4/-6/-1)
+    * `:min_score` - report reads scoring lower as unmapped (default: 30)
+
+  """
+  @spec map_reads(reference() | binary(), binary(), binary(), keyword()) ::
+          {:ok, struct()} | {:error, term()}
+  def map_reads(reference, reads, output, opts \\ [])
+      when (is_reference(reference) or is_binary(reference)) and is_binary(reads) and
+             is_binary(output) do
+    options = map_options(opts)
+
+    with {:ok, index} <- reference_index(reference) do
+      nif_call(fn -> Native.map_reads(index, reads, output, options) end)
+    end
+  end
+
+  @doc """
+  Map a list of `Cyanea.Native.FastqRecord` structs, returning a
+  `Cyanea.Native.MappedRead` for each (flag 4 when unmapped).
+
+  Takes a reference index or FASTA path and the options of `map_reads/4`.
+  Reverse-strand reads come back reverse-complemented, as in SAM.
+  """
+  @spec map(reference() | binary(), list(), keyword()) :: {:ok, list()} | {:error, term()}
+  def map(reference, records, opts \\ [])
+      when (is_reference(reference) or is_binary(reference)) and is_list(records) do
+    options = map_options(opts)
+
+    with {:ok, index} <- reference_index(reference) do
+      nif_call(fn -> Native.map_records(index, records, options) end)
+    end
+  end
+
+  defp reference_index(index) when is_reference(index), do: {:ok, index}
+  defp reference_index(path), do: index_reference(path)
+
+  defp map_options(opts) do
+    %Native.MapOptions{
+      min_seed_len: Keyword.get(opts, :min_seed_len, 19),
+      max_occurrences: Keyword.get(opts, :max_occurrences, 500),
+      bandwidth: Keyword.get(opts, :bandwidth, 16),
+      match_score: Keyword.get(opts, :match, 1),
+      mismatch_score: Keyword.get(opts, :mismatch, -4),
+      gap_open: Keyword.get(opts, :gap_open, -6),
+      gap_extend: Keyword.get(opts, :gap_extend, -1),
+      min_score: Keyword.get(opts, :min_score, 30)
+    }
+  end
+
+  # ===========================================================================
+  # Database search
+  # ===========================================================================
+
+  @doc """
+  Word-index a sequence database for `search/3`: a FASTA path (plain or
+  gzipped) or a list of `{name, sequence}` tuples. Returns an opaque
+  reference.
+
+  ## Options
+
+    * `:alphabet` - `:protein`, `:nucleotide`, or `:auto` (default; nucleotide
+      when at least 90% of residues are `ACGTUN`)
+    * `:word_size` - 3 to 5 for protein (default: 3), 1 to 16 for
+      nucleotide (default: 11)
+
+  """
+  @spec index_database(binary() | [{binary(), binary()}], keyword()) ::
+          {:ok, reference()} | {:error, term()}
+  def index_database(database, opts \\ [])
+
+  def index_database(path, opts) when is_binary(path) do
+    alphabet = search_alphabet_string(Keyword.get(opts, :alphabet, :auto))
+    word_size = Keyword.get(opts, :word_size)
+    nif_call(fn -> Native.search_index(path, alphabet, word_size) end)
+  end
+
+  def index_database(sequences, opts) when is_list(sequences) do
+    alphabet = search_alphabet_string(Keyword.get(opts, :alphabet, :auto))
+    word_size = Keyword.get(opts, :word_size)
+    nif_call(fn -> Native.search_index_sequences(sequences, alphabet, word_size) end)
+  end
+
+  @doc "Alphabet, word size, sequence count and total residues of a search database."
+  @spec database_info(reference()) :: {:ok, map()} | {:error, term()}
+  def database_info(index) when is_reference(index) do
+    with {:ok, {alphabet, word_size, sequences, residues}} <-
+           nif_call(fn -> Native.search_index_info(index) end) do
+      {:ok,
+       %{
+         alphabet: String.to_atom(alphabet),
+         word_size: word_size,
+         sequences: sequences,
+         residues: residues
+       }}
+    end
+  end
+
+  @doc """
+  BLAST-like search of `query` against a database: word hits are extended
+  without gaps, promising ones are realigned with gaps, and hits come back
+  as `Cyanea.Native.SearchHit` structs sorted by E-value.
+
+  `database` is an index from `index_database/2`, or a FASTA path or
+  sequence list indexed on the fly (with `:alphabet` and `:word_size`).
+  Each hit's `alignment` is a `Cyanea.Native.AlignmentResult`, so it can be
+  passed to `format/2` or `summary/2`. Nucleotide queries are searched on
+  both strands; on the `"-"` strand the query coordinates are on its
+  reverse complement.
+
+  E-values use BLAST's gapped λ and K for built-in protein matrices with
+  the gap costs BLAST supports, and ungapped parameters otherwise.
+
+  ## Options
+
+    * `:matrix` - name or struct (default: `:blosum62` for protein, a
+      `:match`/`:mismatch` nucleotide matrix for nucleotide)
+    * `:match` / `:mismatch` - nucleotide scores (default: +2/-3)
+    * `:gap_open` / `:gap_extend` - default: the matrix's, else -11/-1 for
+      protein and -5/-2 for nucleotide
+    * `:threshold` - neighbourhood word score for protein (default: 11)
+    * `:window` - two-hit window, 0 for single hits (default: 40 for
+      protein, 0 for nucleotide)
+    * `:x_drop` - ungapped X-drop in bits (default: 7 protein, 20 nucleotide)
+    * `:gap_trigger` - ungapped bits to try a gapped alignment (default: 22
+      protein, 27 nucleotide)
+    * `:evalue` - largest E-value reported (default: 10.0)
+    * `:max_targets` - database sequences reported (default: 500)
+    * `:strand` - `:both` (default), `:plus`, or `:minus` for nucleotide
+
+  """
+  @spec search(reference() | binary() | list(), binary(), keyword()) ::
+          {:ok, [struct()]} | {:error, term()}
+  def search(database, query, opts \\ [])
+      when (is_reference(database) or is_binary(database) or is_list(database)) and
+             is_binary(query) do
+    with {:ok, index} <- search_database(database, opts),
+         {:ok, %{alphabet: alphabet}} <- database_info(index),
+         {:ok, matrix} <- search_matrix(alphabet, opts) do
+      options = search_options(alphabet, matrix, opts)
+      nif_call(fn -> Native.sequence_search(index, query, matrix, options) end)
+    end
+  end
+
+  defp search_database(index, _opts) when is_reference(index), do: {:ok, index}
+  defp search_database(database, opts), do: index_database(database, opts)
+
+  defp search_matrix(:protein, opts), do: matrix(Keyword.get(opts, :matrix, :blosum62))
+
+  defp search_matrix(:nucleotide, opts) do
+    case Keyword.fetch(opts, :matrix) do
+      {:ok, matrix} ->
+        matrix(matrix)
+
+      :error ->
+        mismatch = Keyword.get(opts, :mismatch, -3)
+
+        nucleotide_matrix(
+          match: Keyword.get(opts, :match, 2),
+          transition: mismatch,
+          transversion: mismatch
+        )
+    end
+  end
+
+  @search_defaults %{
+    protein: [gap_open: -11, gap_extend: -1, window: 40, x_drop: 7.0, gap_trigger: 22.0],
+    nucleotide: [gap_open: -5, gap_extend: -2, window: 0, x_drop: 20.0, gap_trigger: 27.0]
+  }
+
+  defp search_options(alphabet, matrix, opts) do
+    matrix_gaps =
+      Enum.reject([gap_open: matrix.gap_open, gap_extend: matrix.gap_extend], &is_nil(elem(&1, 1)))
+
+    opts =
+      @search_defaults
+      |> Map.fetch!(alphabet)
+      |> Keyword.merge(matrix_gaps)
+      |> Keyword.merge(opts)
+
+    %Native.SearchOptions{
+      gap_open: opts[:gap_open],
+      gap_extend: opts[:gap_extend],
+      threshold: Keyword.get(opts, :threshold, 11),
+      window: opts[:window],
+      x_drop: opts[:x_drop] / 1,
+      gap_trigger: opts[:gap_trigger] / 1,
+      max_evalue: Keyword.get(opts, :evalue, 10.0) / 1,
+      max_targets: Keyword.get(opts, :max_targets, 500),
+      strand: search_strand_string(Keyword.get(opts, :strand, :both))
+    }
+  end
 end
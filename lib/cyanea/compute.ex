@@ -26,10 +26,10 @@ defmodule Cyanea.Compute do
   # Sequence validation & operations
   # ===========================================================================
 
-  @doc "Validate and normalize a DNA sequence. Returns `{:ok, binary}` or `{:error, reason}`."
+  @doc "Validate a DNA sequence of IUPAC codes, keeping case. Returns `{:ok, binary}` or `{:error, reason}`."
   def validate_dna(data) when is_binary(data), do: nif_call(fn -> Native.validate_dna(data) end)
 
-  @doc "Validate and normalize an RNA sequence."
+  @doc "Validate an RNA sequence of IUPAC codes, keeping case."
   def validate_rna(data) when is_binary(data), do: nif_call(fn -> Native.validate_rna(data) end)
 
   @doc "Validate and normalize a protein sequence."
@@ -72,13 +72,13 @@ defmodule Cyanea.Compute do
 
   # --- FM-Index (new) -------------------------------------------------------
 
-  @doc "Build an FM-index from text. Returns serialized index data."
+  @doc "Build an FM-index from text. Returns an opaque index reference."
   def fm_index_build(text) when is_binary(text),
     do: nif_call(fn -> Native.fm_index_build(text) end)
 
   @doc "Count occurrences of pattern in FM-index."
-  def fm_index_count(index_data, pattern) when is_binary(index_data) and is_binary(pattern),
-    do: nif_call(fn -> Native.fm_index_count(index_data, pattern) end)
+  def fm_index_count(index, pattern) when is_reference(index) and is_binary(pattern),
+    do: nif_call(fn -> Native.fm_index_count(index, pattern) end)
 
   # --- ORF finding (new) ----------------------------------------------------
 
@@ -31,16 +31,41 @@ defmodule Cyanea.Formats do
   # VCF
   # ===========================================================================
 
-  @doc "Get VCF file statistics (variant counts, chromosomes)."
+  @doc """
+  Get VCF file statistics.
+
+  Site counts (variants, SNVs, indels, PASS, chromosomes) are the ones
+  cyanea-io has always reported. The result also carries per-sample genotype summaries — call rate, heterozygous
+  and homozygous-alternate counts and their ratio — plus the mean
+  alternate allele frequency and a ten-bin allele frequency spectrum.
+  Frequencies come from called genotypes, or from INFO/AF for sites-only
+  files.
+  """
   @spec vcf_stats(binary()) :: {:ok, struct()} | {:error, term()}
   def vcf_stats(path) when is_binary(path),
     do: nif_call(fn -> Native.vcf_stats(path) end)
 
-  @doc "Parse a VCF file and return all variant records."
+  @doc """
+  Parse a VCF file (plain or gzip/bgzip) and return all variant records.
+
+  INFO and per-sample FORMAT values are typed according to the header's
+  `##INFO`/`##FORMAT` definitions: single-valued fields become integers,
+  floats or strings, flags become `true`, and multi-valued fields become
+  lists with `nil` for missing entries. `GT`, `DP`, `GQ` and `AD` are
+  also exposed as per-sample lists (`genotypes`, `depths`,
+  `genotype_qualities`, `allele_depths`). `filter` is the FILTER column
+  as a string (`"PASS"`, `"."` or `"q10;s50"`) and `filters` the same
+  split into IDs.
+  """
   @spec parse_vcf(binary()) :: {:ok, list()} | {:error, term()}
   def parse_vcf(path) when is_binary(path),
     do: nif_call(fn -> Native.parse_vcf(path) end)
 
+  @doc "Read a VCF header: file format, samples, contigs, filters and INFO/FORMAT definitions."
+  @spec vcf_header(binary()) :: {:ok, struct()} | {:error, term()}
+  def vcf_header(path) when is_binary(path),
+    do: nif_call(fn -> Native.vcf_header(path) end)
+
   # ===========================================================================
   # BED
   # ===========================================================================
@@ -98,6 +123,272 @@ defmodule Cyanea.Formats do
   def parse_bam(path) when is_binary(path),
     do: nif_call(fn -> Native.parse_bam(path) end)
 
+  @doc """
+  Fetch the BAM reads overlapping a region using the file's index.
+
+  `start` and `stop` are 0-based and half-open (BED convention); returned
+  records use SAM's 1-based `pos`. The index is looked up as `<path>.bai`,
+  `<path>.csi`, then `<stem>.bai`; build one with `index_bam/2` if missing.
+  Only BAM is supported: CRAM input returns an error, so convert it first
+  with `samtools view -b`.
+  """
+  @spec query_bam(binary(), binary(), non_neg_integer(), non_neg_integer()) ::
+          {:ok, list()} | {:error, term()}
+  def query_bam(path, chrom, start, stop)
+      when is_binary(path) and is_binary(chrom) and is_integer(start) and start >= 0 and
+             is_integer(stop) and stop > start,
+      do: nif_call(fn -> Native.bam_query(path, chrom, start, stop) end)
+
+  @doc """
+  Build an index for a coordinate-sorted BAM next to the file.
+
+  ## Options
+
+    * `:format` - `:bai` (default) or `:csi`. CSI is required for
+      references longer than 512 Mbp.
+
+  Returns the path of the written index.
+  """
+  @spec index_bam(binary(), keyword()) :: {:ok, binary()} | {:error, term()}
+  def index_bam(path, opts \\ []) when is_binary(path) do
+    format = Keyword.get(opts, :format, :bai)
+    nif_call(fn -> Native.bam_index(path, index_format_string(format)) end)
+  end
+
+  # ===========================================================================
+  # Tabix (bgzipped VCF, BED, GFF3, bedGraph)
+  # ===========================================================================
+
+  @doc """
+  Fetch the lines of a bgzipped, tabix-indexed file overlapping a region.
+
+  `region` is `"chr1"`, `"chr1:1000"` or `"chr1:1,000-2,000"` with 1-based,
+  inclusive coordinates, as accepted by `tabix`. The index is looked up as
+  `<path>.tbi`, then `<path>.csi`. Lines are returned unparsed, header
+  lines excluded.
+  """
+  @spec tabix_query(binary(), binary()) :: {:ok, [binary()]} | {:error, term()}
+  def tabix_query(path, region) when is_binary(path) and is_binary(region),
+    do: nif_call(fn -> Native.tabix_query(path, region) end)
+
+  @doc """
+  Compress a sorted, uncompressed VCF/BED/GFF3/bedGraph file with BGZF and
+  index it, writing `<path>.gz` and `<path>.gz.tbi`.
+
+  ## Options
+
+    * `:preset` - `:vcf`, `:bed`, `:gff`, `:bedgraph` or `:auto` (default),
+      which infers the layout from the file extension.
+
+  Returns the path of the compressed file.
+  """
+  @spec index_tabix(binary(), keyword()) :: {:ok, binary()} | {:error, term()}
+  def index_tabix(path, opts \\ []) when is_binary(path) do
+    preset = Keyword.get(opts, :preset, :auto)
+    nif_call(fn -> Native.tabix_index(path, tabix_preset_string(preset)) end)
+  end
+
+  # ===========================================================================
+  # Depth and coverage (BAM)
+  # ===========================================================================
+
+  @doc """
+  Compute per-base read depth of a coordinate-sorted BAM and write it as
+  bedGraph to `output`.
+
+  Deletions and reference skips are not counted; both mates of an
+  overlapping pair are.
+
+  ## Options
+
+    * `:bin_size` - write the mean depth of fixed-size bins instead of runs
+      of equal depth (default: 0, per-base runs)
+    * `:min_mapq` - skip reads below this mapping quality (default: 0)
+    * `:exclude_flags` - skip reads with any of these flag bits (default:
+      `0x704`, i.e. unmapped, secondary, QC-fail and duplicate)
+    * `:min_base_quality` - don't count bases below this phred score
+      (default: 0)
+    * `:include_zero` - also write zero-depth stretches (default: false)
+    * `:bgzf` - compress with BGZF. Defaults to `true` when `output` ends
+      in `.gz`.
+
+  Returns the number of lines written.
+  """
+  @spec bam_depth(binary(), binary(), keyword()) :: {:ok, non_neg_integer()} | {:error, term()}
+  def bam_depth(path, output, opts \\ []) when is_binary(path) and is_binary(output) do
+    {mapq, flags, base_q} = depth_filter(opts)
+
+    nif_call(fn ->
+      Native.bam_depth_bedgraph(path, output, Keyword.get(opts, :bin_size, 0), mapq, flags,
+        base_q, Keyword.get(opts, :include_zero, false), bgzf?(output, opts))
+    end)
+  end
+
+  @doc """
+  Summarize read depth over target regions, for coverage QC.
+
+  `targets` is the path of a BED file or a list of interval maps
+  (`:chrom`, `:start`, `:end`, 0-based half-open). Returns a
+  `Cyanea.Native.DepthReport` with mean, median, min and max depth per
+  target and the fraction of bases at or above each threshold, plus the
+  same figures pooled over all target bases. With a `.bai` or `.csi`
+  index next to the BAM only the reads over the targets are read;
+  without one the whole file is streamed.
+
+  ## Options
+
+    * `:thresholds` - depths to report coverage fractions for
+      (default: `[10, 30]`)
+    * `:min_mapq`, `:exclude_flags`, `:min_base_quality` - as in
+      `bam_depth/3`
+
+  """
+  @spec target_depth(binary(), binary() | list(), keyword()) ::
+          {:ok, struct()} | {:error, term()}
+  def target_depth(path, targets, opts \\ [])
+
+  def target_depth(path, bed, opts) when is_binary(path) and is_binary(bed) do
+    with {:ok, targets} <- parse_bed_intervals(bed), do: target_depth(path, targets, opts)
+  end
+
+  def target_depth(path, targets, opts) when is_binary(path) and is_list(targets) do
+    {mapq, flags, base_q} = depth_filter(opts)
+    thresholds = Keyword.get(opts, :thresholds, [10, 30])
+
+    targets =
+      Enum.map(targets, fn %{chrom: chrom, start: start, end: stop} ->
+        %Native.GenomicInterval{chrom: chrom, start: start, end: stop, strand: "."}
+      end)
+
+    nif_call(fn ->
+      Native.bam_target_depth(path, targets, thresholds, mapq, flags, base_q)
+    end)
+  end
+
+  defp depth_filter(opts) do
+    {Keyword.get(opts, :min_mapq, 0), Keyword.get(opts, :exclude_flags, 0x704),
+     Keyword.get(opts, :min_base_quality, 0)}
+  end
+
+  # ===========================================================================
+  # Writers (VCF, BED, GFF3)
+  # ===========================================================================
+
+  @doc """
+  Write VCF records under a header as returned by `vcf_header/1`.
+
+  The header's `meta` lines are written verbatim, so a parsed header
+  round-trips; entries in `info`, `format`, `contigs` and `filters` that
+  are missing from `meta` are added. Records are `Cyanea.Native.VcfRecord`
+  structs as returned by `parse_vcf/1`: INFO and FORMAT values are taken
+  from `info` and `sample_data`, with `genotypes`, `depths`,
+  `genotype_qualities` and `allele_depths` filling FORMAT keys not present
+  in `sample_data`. FILTER is written from `filters` when it is non-empty,
+  else from the `filter` string.
+
+  ## Options
+
+    * `:bgzf` - compress with BGZF so a sorted file can be indexed with
+      `tabix`. Defaults to `true` when `path` ends in `.gz`.
+
+  Returns the number of records written.
+  """
+  @spec write_vcf(binary(), struct(), [struct()], keyword()) ::
+          {:ok, non_neg_integer()} | {:error, term()}
+  def write_vcf(path, %Native.VcfHeader{} = header, records, opts \\ [])
+      when is_binary(path) and is_list(records) do
+    records = Enum.map(records, &vcf_record_defaults/1)
+    nif_call(fn -> Native.write_vcf(path, header, records, bgzf?(path, opts)) end)
+  end
+
+  @doc """
+  Write BED features, 0-based and half-open.
+
+  Features may be `Cyanea.Native.BedFeature` structs or any map with
+  `:chrom`, `:start` and `:end` (such as `BedRecord` or `GenomicInterval`).
+  The file uses the fewest columns (BED3 to BED12) that hold every feature;
+  optional columns a feature leaves `nil` get BED defaults.
+
+  ## Options
+
+    * `:bgzf` - compress with BGZF. Defaults to `true` when `path` ends
+      in `.gz`.
+
+  Returns the number of features written.
+  """
+  @spec write_bed(binary(), [map()], keyword()) :: {:ok, non_neg_integer()} | {:error, term()}
+  def write_bed(path, features, opts \\ []) when is_binary(path) and is_list(features) do
+    features = Enum.map(features, &bed_feature/1)
+    nif_call(fn -> Native.write_bed(path, features, bgzf?(path, opts)) end)
+  end
+
+  @doc """
+  Write GFF3 features, 1-based and inclusive, under a `##gff-version 3`
+  header.
+
+  Features may be `Cyanea.Native.GffFeature` structs, maps with the same
+  keys, or `Cyanea.Native.GffGene` records from `parse_gff3/1`. Attributes
+  are `{tag, value}` pairs written in order; list values are joined with
+  commas. Reserved characters are percent-encoded.
+
+  ## Options
+
+    * `:bgzf` - compress with BGZF. Defaults to `true` when `path` ends
+      in `.gz`.
+
+  Returns the number of features written.
+  """
+  @spec write_gff3(binary(), [map()], keyword()) :: {:ok, non_neg_integer()} | {:error, term()}
+  def write_gff3(path, features, opts \\ []) when is_binary(path) and is_list(features) do
+    features = Enum.map(features, &gff_feature/1)
+    nif_call(fn -> Native.write_gff3(path, features, bgzf?(path, opts)) end)
+  end
+
+  defp bgzf?(path, opts), do: Keyword.get(opts, :bgzf, String.ends_with?(path, ".gz"))
+
+  defp vcf_record_defaults(%Native.VcfRecord{} = r) do
+    lists = [:ids, :alt_alleles, :filters, :format, :genotypes, :depths,
+             :genotype_qualities, :allele_depths, :sample_data]
+
+    r
+    |> Map.merge(Map.new(lists, &{&1, Map.get(r, &1) || []}))
+    |> Map.update!(:filter, &(&1 || "."))
+    |> Map.update!(:info, &(&1 || %{}))
+    |> Map.update!(:quality, &maybe_float/1)
+  end
+
+  defp bed_feature(%Native.BedFeature{} = f), do: %{f | score: maybe_float(f.score)}
+
+  defp bed_feature(%{chrom: _, start: _, end: _} = f) do
+    fields = f |> Map.delete(:__struct__) |> Map.update(:score, nil, &maybe_float/1)
+    struct(Native.BedFeature, fields)
+  end
+
+  defp gff_feature(%Native.GffGene{} = g) do
+    attributes =
+      Enum.reject([{"ID", g.id}, {"Name", g.symbol}, {"biotype", g.gene_type}], fn {_, v} ->
+        v in [nil, ""]
+      end)
+
+    %Native.GffFeature{seqid: g.chrom, feature_type: "gene", start: g.start, end: g.end,
+                       strand: g.strand, attributes: attributes}
+  end
+
+  defp gff_feature(%{seqid: _, start: _, end: _} = f) do
+    feature = struct(Native.GffFeature, Map.delete(f, :__struct__))
+
+    attributes =
+      Enum.map(feature.attributes, fn
+        {k, v} when is_list(v) -> {to_string(k), Enum.map_join(v, ",", &to_string/1)}
+        {k, v} -> {to_string(k), to_string(v)}
+      end)
+
+    %{feature | score: maybe_float(feature.score), attributes: attributes}
+  end
+
+  defp maybe_float(n) when is_integer(n), do: n * 1.0
+  defp maybe_float(n), do: n
+
   # ===========================================================================
   # Parquet
   # ===========================================================================
@@ -190,4 +481,77 @@ defmodule Cyanea.Formats do
   @spec bedgraph_stats(binary()) :: {:ok, struct()} | {:error, term()}
   def bedgraph_stats(path) when is_binary(path),
     do: nif_call(fn -> Native.bedgraph_stats(path) end)
+
+  @doc """
+  Fetch the bigWig records overlapping a region.
+
+  `start` and `stop` are 0-based and half-open. Returns
+  `Cyanea.Native.BedGraphRecord` structs, not clipped to the region. Only
+  the index and the blocks overlapping the region are read.
+  """
+  @spec bigwig_values(binary(), binary(), non_neg_integer(), non_neg_integer()) ::
+          {:ok, list()} | {:error, term()}
+  def bigwig_values(path, chrom, start, stop)
+      when is_binary(path) and is_binary(chrom) and is_integer(start) and start >= 0 and
+             is_integer(stop) and stop > start,
+      do: nif_call(fn -> Native.bigwig_values(path, chrom, start, stop) end)
+
+  @doc """
+  Summarize a bigWig region in `bins` equal bins.
+
+  Returns one `Cyanea.Native.BigWigBin` per bin with the mean, min and max
+  over bases with data (`nil` when there are none) and `coverage`, the
+  fraction of the bin with data (0 for the zero-width bins left when `bins`
+  exceeds the region length). By default values come from the coarsest
+  zoom level finer than half a bin, like the UCSC browser, so summarizing a
+  whole chromosome reads only a few blocks.
+
+  ## Options
+
+    * `:exact` - compute from the full-resolution data instead of the zoom
+      levels (default `false`)
+
+  """
+  @spec bigwig_summary(binary(), binary(), non_neg_integer(), non_neg_integer(), pos_integer(),
+          keyword()) :: {:ok, list()} | {:error, term()}
+  def bigwig_summary(path, chrom, start, stop, bins, opts \\ [])
+      when is_binary(path) and is_binary(chrom) and is_integer(start) and start >= 0 and
+             is_integer(stop) and stop > start and is_integer(bins) and bins > 0 do
+    exact = Keyword.get(opts, :exact, false)
+    nif_call(fn -> Native.bigwig_summary(path, chrom, start, stop, bins, exact) end)
+  end
+
+  @doc """
+  Write bedGraph records to a bigWig file with zoom levels.
+
+  `records` are `Cyanea.Native.BedGraphRecord` structs or maps with
+  `:chrom`, `:start`, `:end` (0-based, half-open) and `:value`; they must
+  not overlap but need not be sorted. `chrom_sizes` is a list or map of
+  `{chrom, length}` or the path of a chrom.sizes / `.fai` file, and must
+  cover every record's chromosome. Returns the number of records written.
+  """
+  @spec write_bigwig(binary(), list(), binary() | list() | map()) ::
+          {:ok, non_neg_integer()} | {:error, term()}
+  def write_bigwig(path, records, chrom_sizes) when is_binary(path) and is_list(records) do
+    with {:ok, sizes} <- genome_sizes(chrom_sizes) do
+      nif_call(fn -> Native.write_bigwig(path, Enum.map(records, &bedgraph_record/1), sizes) end)
+    end
+  end
+
+  @doc """
+  Convert a bedGraph file to bigWig, like `bedGraphToBigWig`.
+
+  `chrom_sizes` is as for `write_bigwig/3`. Returns the number of records
+  written.
+  """
+  @spec bedgraph_to_bigwig(binary(), binary(), binary() | list() | map()) ::
+          {:ok, non_neg_integer()} | {:error, term()}
+  def bedgraph_to_bigwig(input, output, chrom_sizes) when is_binary(input) and is_binary(output) do
+    with {:ok, sizes} <- genome_sizes(chrom_sizes) do
+      nif_call(fn -> Native.bedgraph_to_bigwig(input, output, sizes) end)
+    end
+  end
+
+  defp bedgraph_record(%{chrom: chrom, start: start, end: stop, value: value}),
+    do: %Native.BedGraphRecord{chrom: chrom, start: start, end: stop, value: value * 1.0}
 end
@@ -198,6 +198,11 @@ defmodule Cyanea.ML do
   @doc """
   Fit a random forest classifier.
 
+  Returns a versioned model binary holding the fitted trees. Binaries from
+  earlier releases, which stored only the training set, are still accepted
+  by `predict_forest/3` and `predict_forest_batch/3`; refit them to use
+  `predict_forest_proba/3` or `forest_info/1`.
+
   ## Options
 
     * `:n_trees` - number of trees (default: 10)
@@ -220,6 +225,23 @@ defmodule Cyanea.ML do
       when is_binary(model) and is_list(sample) and is_integer(n_features),
       do: nif_call(fn -> Native.random_forest_predict(model, sample, n_features) end)
 
+  @doc "Predict class labels for every row of a flat row-major matrix."
+  @spec predict_forest_batch(binary(), list(), integer()) :: {:ok, [integer()]} | {:error, term()}
+  def predict_forest_batch(model, data, n_features)
+      when is_binary(model) and is_list(data) and is_integer(n_features),
+      do: nif_call(fn -> Native.random_forest_predict_batch(model, data, n_features) end)
+
+  @doc "Per-class probabilities for every row of a flat row-major matrix."
+  @spec predict_forest_proba(binary(), list(), integer()) :: {:ok, [[float()]]} | {:error, term()}
+  def predict_forest_proba(model, data, n_features)
+      when is_binary(model) and is_list(data) and is_integer(n_features),
+      do: nif_call(fn -> Native.random_forest_predict_proba(model, data, n_features) end)
+
+  @doc "Summarize a fitted forest: tree/class counts, out-of-bag error, feature importances."
+  @spec forest_info(binary()) :: {:ok, struct()} | {:error, term()}
+  def forest_info(model) when is_binary(model),
+    do: nif_call(fn -> Native.random_forest_info(model) end)
+
   # ===========================================================================
   # HMM
   # ===========================================================================
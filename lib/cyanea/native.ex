@@ -57,10 +57,10 @@ defmodule Cyanea.Native do
 
   # --- Sequence validation --------------------------------------------------
 
-  @doc "Validate and uppercase a DNA sequence (IUPAC alphabet)"
+  @doc "Validate a DNA sequence of IUPAC codes, keeping case"
   def validate_dna(_data), do: :erlang.nif_error(:nif_not_loaded)
 
-  @doc "Validate and uppercase an RNA sequence (IUPAC alphabet)"
+  @doc "Validate an RNA sequence of IUPAC codes, keeping case"
   def validate_rna(_data), do: :erlang.nif_error(:nif_not_loaded)
 
   @doc "Validate and uppercase a protein sequence"
@@ -68,15 +68,18 @@ defmodule Cyanea.Native do
 
   # --- DNA operations -------------------------------------------------------
 
-  @doc "Return the reverse complement of a DNA sequence"
+  @doc "Return the reverse complement of a DNA sequence, complementing IUPAC codes and keeping case"
   def dna_reverse_complement(_data), do: :erlang.nif_error(:nif_not_loaded)
 
   @doc "Transcribe DNA to RNA (T → U)"
   def dna_transcribe(_data), do: :erlang.nif_error(:nif_not_loaded)
 
-  @doc "Calculate GC content of a DNA sequence (fraction 0.0–1.0)"
+  @doc "Calculate GC content of a DNA sequence (fraction 0.0–1.0), excluding N and other ambiguous bases"
   def dna_gc_content(_data), do: :erlang.nif_error(:nif_not_loaded)
 
+  @doc "List lowercase (soft-masked) runs of a nucleotide sequence as 0-based {start, end} tuples"
+  def soft_masked_regions(_data), do: :erlang.nif_error(:nif_not_loaded)
+
   # --- RNA operations -------------------------------------------------------
 
   @doc "Translate an RNA sequence to protein (NCBI Table 1)"
@@ -87,6 +90,9 @@ defmodule Cyanea.Native do
   @doc "Extract k-mers from a DNA sequence as a list of binaries"
   def sequence_kmers(_data, _k), do: :erlang.nif_error(:nif_not_loaded)
 
+  @doc "Canonical k-mer histogram, top k-mers and genome size/heterozygosity estimate for a FASTA/FASTQ file"
+  def kmer_spectrum(_path, _k, _max_count, _top_n), do: :erlang.nif_error(:nif_not_loaded)
+
   # --- FASTQ ----------------------------------------------------------------
 
   @doc "Parse a FASTQ file and return all records"
@@ -95,6 +101,64 @@ defmodule Cyanea.Native do
   @doc "Get streaming statistics from a FASTQ file"
   def fastq_stats(_path), do: :erlang.nif_error(:nif_not_loaded)
 
+  # --- Streaming reader -----------------------------------------------------
+
+  @doc "Open a FASTA/FASTQ file (plain, gzip or zstd) for chunked reading. Returns a reader reference"
+  def seq_reader_open(_path), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Read up to n records from an open reader. Returns [] at end of file"
+  def seq_reader_next_chunk(_reader, _n), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Close a reader and release its file handle. Returns false if already closed"
+  def seq_reader_close(_reader), do: :erlang.nif_error(:nif_not_loaded)
+
+  # --- FASTA records and .fai access -----------------------------------------
+
+  @doc "Parse every record of a FASTA file into FastaRecord structs"
+  def parse_fasta(_path), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Index an uncompressed FASTA, writing <path>.fai; returns FaiEntry structs"
+  def fasta_index(_path), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Open a FASTA for random access, using <path>.fai when present"
+  def faidx_open(_path), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "List the FaiEntry structs of an open indexed FASTA"
+  def faidx_sequences(_handle), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Fetch a samtools-style region (chr1:1000-2000), reverse-complemented when reverse is true"
+  def faidx_fetch(_handle, _region, _reverse), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Close an indexed FASTA, releasing its file handle"
+  def faidx_close(_handle), do: :erlang.nif_error(:nif_not_loaded)
+
+  # --- Paired-end FASTQ -----------------------------------------------------
+
+  @doc "Open R1/R2 FASTQ files, or one interleaved file when r2 is nil, for chunked pair reading"
+  def paired_reader_open(_r1, _r2), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Read up to n {read1, read2} pairs, validating mate names. Returns [] at end of file"
+  def paired_reader_next_chunk(_reader, _n), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Close a paired reader. Returns false if already closed"
+  def paired_reader_close(_reader), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Per-mate statistics and overlap-based insert sizes for R1/R2 or interleaved FASTQ"
+  def paired_fastq_stats(_r1, _r2), do: :erlang.nif_error(:nif_not_loaded)
+
+  # --- Read trimming --------------------------------------------------------
+
+  @doc "Trim a FASTQ file (adapters, poly-G/A, quality, min length) into output; returns a TrimReport"
+  def trim_fastq(_input, _output, _options, _bgzf), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Trim a list of FastqRecord structs with TrimOptions, dropping reads that end up too short"
+  def trim_reads(_records, _options), do: :erlang.nif_error(:nif_not_loaded)
+
+  # --- FASTQ quality report -------------------------------------------------
+
+  @doc "FastQC-style per-position quality, composition, GC, length and duplication report for a FASTQ file"
+  def fastq_qc(_path, _max_reads), do: :erlang.nif_error(:nif_not_loaded)
+
   # --- Protein --------------------------------------------------------------
 
   @doc "Calculate molecular weight of a protein sequence (Daltons)"
@@ -108,19 +172,53 @@ defmodule Cyanea.Native do
   @doc "Approximate pattern matching using Myers bit-parallel algorithm"
   def myers_search(_text, _pattern, _max_dist), do: :erlang.nif_error(:nif_not_loaded)
 
+  @doc "Horspool search; with `iupac_codes`, IUPAC codes in the pattern match the bases they stand for (case-insensitive)"
+  def horspool_search(_text, _pattern, _iupac_codes), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Myers search; with `iupac_codes`, base matching is IUPAC-aware and case-insensitive"
+  def myers_search(_text, _pattern, _max_dist, _iupac_codes),
+    do: :erlang.nif_error(:nif_not_loaded)
+
   # --- FM-Index (new) -------------------------------------------------------
 
-  @doc "Build an FM-index from text. Returns serialized index as binary"
+  @doc "Build an FM-index from text (DirtyCpu). Returns an opaque index reference"
   def fm_index_build(_text), do: :erlang.nif_error(:nif_not_loaded)
 
-  @doc "Count occurrences of pattern in FM-index"
-  def fm_index_count(_index_data, _pattern), do: :erlang.nif_error(:nif_not_loaded)
+  @doc "Count occurrences of pattern in a built FM-index"
+  def fm_index_count(_index, _pattern), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Count occurrences of each pattern in a built FM-index (DirtyCpu)"
+  def fm_index_count_batch(_index, _patterns), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Return sorted 0-based start positions of pattern in a built FM-index"
+  def fm_index_locate(_index, _pattern), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Save a built FM-index to disk. Returns the written path"
+  def fm_index_save(_index, _path), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Load an FM-index previously written by fm_index_save/2"
+  def fm_index_load(_path), do: :erlang.nif_error(:nif_not_loaded)
 
   # --- ORF finding (new) ----------------------------------------------------
 
   @doc "Find open reading frames in both strands of a DNA sequence"
   def find_orfs(_seq, _min_length), do: :erlang.nif_error(:nif_not_loaded)
 
+  @doc "Find open reading frames with an NCBI genetic code, optionally with its alternative start codons"
+  def find_orfs_with_code(_seq, _min_length, _table_id, _alternative_starts),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  # --- Genetic codes --------------------------------------------------------
+
+  @doc "Translate DNA or RNA with an NCBI genetic code, optionally stopping at the first stop codon"
+  def translate(_data, _table_id, _to_stop), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Translate the three forward and three reverse-complement frames"
+  def six_frame_translate(_data, _table_id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "List the supported NCBI genetic codes as {id, name} tuples"
+  def genetic_codes(), do: :erlang.nif_error(:nif_not_loaded)
+
   # --- MinHash (new) --------------------------------------------------------
 
   @doc "Compute MinHash sketch of a sequence"
@@ -150,6 +248,9 @@ defmodule Cyanea.Native do
   @doc "Parse a VCF file and return all variant records"
   def parse_vcf(_path), do: :erlang.nif_error(:nif_not_loaded)
 
+  @doc "Read a VCF header (samples, contigs, filters, typed INFO/FORMAT definitions)"
+  def vcf_header(_path), do: :erlang.nif_error(:nif_not_loaded)
+
   @doc "Parse a BED file and return all records"
   def parse_bed(_path), do: :erlang.nif_error(:nif_not_loaded)
 
@@ -171,6 +272,57 @@ defmodule Cyanea.Native do
   @doc "Parse a BED file and return genomic intervals"
   def parse_bed_intervals(_path), do: :erlang.nif_error(:nif_not_loaded)
 
+  # --- Indexed region queries ------------------------------------------------
+
+  @doc "Fetch BAM reads overlapping chrom:[start, end) (0-based, half-open) via the .bai/.csi index"
+  def bam_query(_path, _chrom, _start, _end), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Build an index for a coordinate-sorted BAM. Format: \"bai\" or \"csi\". Returns the index path"
+  def bam_index(_path, _format), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Fetch lines of a bgzipped, tabix-indexed VCF/BED/GFF3/bedGraph overlapping a region (\"chr1:1000-2000\", 1-based)"
+  def tabix_query(_path, _region), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Bgzip a sorted, uncompressed file to <path>.gz and write a .tbi. Preset: \"vcf\", \"bed\", \"gff\", \"bedgraph\" or \"auto\""
+  def tabix_index(_path, _preset), do: :erlang.nif_error(:nif_not_loaded)
+
+  # --- Depth and coverage ----------------------------------------------------
+
+  @doc "Write per-base depth of a sorted BAM as bedGraph (runs, or bin means when bin_size > 0). Returns the line count"
+  def bam_depth_bedgraph(_path, _output, _bin_size, _min_mapq, _exclude_flags, _min_base_quality,
+                         _include_zero, _bgzf),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Depth summary (mean/median/min/max, fraction >= thresholds) per target interval and pooled"
+  def bam_target_depth(_path, _targets, _thresholds, _min_mapq, _exclude_flags, _min_base_quality),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  # --- bigWig signal ---------------------------------------------------------
+
+  @doc "Fetch bigWig records overlapping chrom:[start, end) (0-based, half-open) as BedGraphRecord structs"
+  def bigwig_values(_path, _chrom, _start, _end), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Summarize chrom:[start, end) of a bigWig in n equal bins (mean/min/max/coverage), from zoom levels unless exact"
+  def bigwig_summary(_path, _chrom, _start, _end, _bins, _exact),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Write BedGraphRecord structs to a bigWig with zoom levels. chrom_sizes: [{chrom, length}]. Returns the record count"
+  def write_bigwig(_path, _records, _chrom_sizes), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Convert a bedGraph file to bigWig. chrom_sizes: [{chrom, length}]. Returns the record count"
+  def bedgraph_to_bigwig(_input, _output, _chrom_sizes), do: :erlang.nif_error(:nif_not_loaded)
+
+  # --- Writers ---------------------------------------------------------------
+
+  @doc "Write a VCF header and records, optionally BGZF-compressed. Returns the record count"
+  def write_vcf(_path, _header, _records, _bgzf), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Write BedFeature structs as BED3-BED12, optionally BGZF-compressed. Returns the feature count"
+  def write_bed(_path, _features, _bgzf), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Write GffFeature structs as GFF3, optionally BGZF-compressed. Returns the feature count"
+  def write_gff3(_path, _features, _bgzf), do: :erlang.nif_error(:nif_not_loaded)
+
   # ===========================================================================
   # cyanea-align — Sequence Alignment
   # ===========================================================================
@@ -237,6 +389,86 @@ defmodule Cyanea.Native do
   @doc "Split CIGAR at a reference coordinate. Returns {left, right} CIGAR strings"
   def split_cigar(_cigar, _ref_pos), do: :erlang.nif_error(:nif_not_loaded)
 
+  # --- Substitution matrices -------------------------------------------------
+
+  @doc "Built-in substitution matrix by name, with BLAST's default gap penalties"
+  def substitution_matrix(_name), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Names of the built-in substitution matrices"
+  def substitution_matrices(), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Parse an NCBI-format substitution matrix from text"
+  def parse_substitution_matrix_text(_text, _name), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Read an NCBI-format substitution matrix file"
+  def read_substitution_matrix(_path), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "IUPAC nucleotide matrix from match, transition and transversion scores"
+  def nucleotide_matrix(_match, _transition, _transversion), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Affine-gap alignment scored by a SubstitutionMatrix struct"
+  def align_with_matrix(_query, _target, _mode, _matrix, _gap_open, _gap_extend),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  # --- Alignment output ------------------------------------------------------
+
+  @doc "Identity/similarity/gap summary of an AlignmentResult; matrix may be nil"
+  def alignment_summary(_result, _matrix), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "BLAST-style text rendering of an AlignmentResult; matrix may be nil"
+  def format_alignment(_result, _query_name, _target_name, _width, _matrix),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "SAM line for an AlignmentResult, soft-clipping unaligned query ends"
+  def alignment_to_sam(_result, _query, _query_name, _target_name),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "PAF line for an AlignmentResult"
+  def alignment_to_paf(_result, _query_name, _query_len, _target_name, _target_len),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  # --- Alignment significance ------------------------------------------------
+
+  @doc "Karlin-Altschul parameters for a SubstitutionMatrix; gapped when both gap penalties are given"
+  def karlin_altschul(_matrix, _gap_open, _gap_extend), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Bit score and E-value of a score given KarlinAltschul parameters and search space"
+  def alignment_significance(_score, _params, _query_len, _db_len),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Extreme value fit of local scores against shuffled targets. Returns ShuffleFit"
+  def shuffle_significance(_query, _target, _matrix, _gap_open, _gap_extend, _shuffles, _seed),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  # --- Read mapping ----------------------------------------------------------
+
+  @doc "Index the sequences of a reference FASTA for read mapping. Returns an opaque reference"
+  def mapper_index(_path), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "List {name, length} of every sequence in a mapper index"
+  def mapper_contigs(_index), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Map a FASTA/FASTQ file against a mapper index and write SAM. Returns SamStats"
+  def map_reads(_index, _reads, _output, _options), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Map a list of FastqRecord structs against a mapper index. Returns MappedRead structs"
+  def map_records(_index, _records, _options), do: :erlang.nif_error(:nif_not_loaded)
+
+  # --- Database search -------------------------------------------------------
+
+  @doc "Word-index a FASTA file for sequence_search (alphabet protein, nucleotide or auto). Returns an opaque reference"
+  def search_index(_path, _alphabet, _word_size), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Word-index a list of {name, sequence} tuples for sequence_search. Returns an opaque reference"
+  def search_index_sequences(_sequences, _alphabet, _word_size),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Return {alphabet, word_size, sequences, residues} of a search index"
+  def search_index_info(_index), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "BLAST-like search of a query against a search index. Returns SearchHit structs, best first"
+  def sequence_search(_index, _query, _matrix, _options), do: :erlang.nif_error(:nif_not_loaded)
+
   # ===========================================================================
   # cyanea-stats — Statistical Methods
   # ===========================================================================
@@ -302,6 +534,39 @@ defmodule Cyanea.Native do
   def genomic_coverage(_chroms, _starts, _ends, _query_chrom),
     do: :erlang.nif_error(:nif_not_loaded)
 
+  @doc "bedtools intersect over GenomicInterval lists. Report: overlap, a, b, both, any or none; strand: ignore, same or opposite"
+  def interval_intersect(_a, _b, _report, _strand, _min_overlap),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "bedtools subtract: parts of each A interval not covered by B"
+  def interval_subtract(_a, _b, _strand), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "bedtools complement against a genome given as [{chrom, length}]"
+  def interval_complement(_intervals, _genome), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "bedtools closest -D a -t all: nearest B interval(s) for each A"
+  def interval_closest(_a, _b, _strand), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "bedtools window: A/B pairs with B within left/right bases of A"
+  def interval_window(_a, _b, _left, _right, _strand_relative, _strand),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Predict consequences and HGVS c./p. for {chrom, pos, ref, alt} alleles against a GFF3 and reference FASTA"
+  def annotate_variants(_variants, _gff_path, _fasta_path, _most_severe),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Trim, left-align and optionally split {chrom, pos, ref, alts} variants against a reference FASTA"
+  def normalize_variants(_variants, _fasta_path, _split),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Normalize every record of a VCF against a reference FASTA and write the result; returns NormalizeStats"
+  def normalize_vcf(_input, _output, _fasta_path, _split, _bgzf),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Compare a query VCF against a truth VCF after normalization; TP/FP/FN and precision/recall/F1 by variant class"
+  def vcf_concordance(_truth, _query, _fasta_path, _confident_bed, _pass_only),
+    do: :erlang.nif_error(:nif_not_loaded)
+
   @doc "Compute summary statistics for an expression matrix (2D list of floats)"
   def expression_summary(_data, _feature_names, _sample_names),
     do: :erlang.nif_error(:nif_not_loaded)
@@ -360,7 +625,7 @@ defmodule Cyanea.Native do
   def linear_regression_predict(_weights, _bias, _queries, _n_features),
     do: :erlang.nif_error(:nif_not_loaded)
 
-  @doc "Fit a random forest classifier. Returns serialized model as binary"
+  @doc "Fit a random forest classifier. Returns serialized model (fitted trees) as binary"
   def random_forest_fit(_data, _n_features, _labels, _n_trees, _max_depth, _seed),
     do: :erlang.nif_error(:nif_not_loaded)
 
@@ -368,6 +633,17 @@ defmodule Cyanea.Native do
   def random_forest_predict(_model_data, _sample, _n_features),
     do: :erlang.nif_error(:nif_not_loaded)
 
+  @doc "Predict class labels for a flat row-major matrix (DirtyCpu)"
+  def random_forest_predict_batch(_model_data, _data, _n_features),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Per-class probabilities for each row of a flat row-major matrix (DirtyCpu)"
+  def random_forest_predict_proba(_model_data, _data, _n_features),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Inspect a serialized random forest (trees, classes, OOB error, feature importances)"
+  def random_forest_info(_model_data), do: :erlang.nif_error(:nif_not_loaded)
+
   @doc "HMM Viterbi decoding. Returns {most_likely_path, log_probability}"
   def hmm_viterbi(_n_states, _n_symbols, _initial, _transition, _emission, _observations),
     do: :erlang.nif_error(:nif_not_loaded)
@@ -588,7 +864,7 @@ defmodule Cyanea.Native.CsvInfo do
 end
 
 defmodule Cyanea.Native.FastqRecord do
-  @moduledoc "FASTQ record (cyanea-seq)"
+  @moduledoc "FASTQ record (cyanea-seq). Streamed FASTA records have an empty quality"
   defstruct [:name, :description, :sequence, :quality]
 end
 
@@ -598,9 +874,77 @@ defmodule Cyanea.Native.FastqStats do
              :mean_quality, :q20_fraction, :q30_fraction]
 end
 
+defmodule Cyanea.Native.PairedFastqStats do
+  @moduledoc "Paired-end FASTQ statistics with per-mate FastqStats and insert sizes (cyanea-seq)"
+  defstruct [:pair_count, :read1, :read2, :overlapping_pairs, :insert_size_mean,
+             :insert_size_median, :insert_size_sd, :insert_size_histogram]
+end
+
+defmodule Cyanea.Native.TrimOptions do
+  @moduledoc "Read trimming settings (cyanea-seq)"
+  defstruct [:adapters, :detect_adapter, :adapter_error_rate, :min_adapter_overlap,
+             :quality_cutoff, :window_size, :window_quality, :poly_g, :poly_a, :min_length]
+end
+
+defmodule Cyanea.Native.TrimReport do
+  @moduledoc "Reads and bases removed by each trimming step (cyanea-seq)"
+  defstruct [:reads_in, :reads_out, :too_short, :bases_in, :bases_out,
+             :adapter_reads, :adapter_bases, :quality_reads, :quality_bases,
+             :poly_g_reads, :poly_g_bases, :poly_a_reads, :poly_a_bases, :adapters]
+end
+
+defmodule Cyanea.Native.FastaRecord do
+  @moduledoc "FASTA record (cyanea-seq)"
+  defstruct [:name, :description, :sequence]
+end
+
+defmodule Cyanea.Native.FaiEntry do
+  @moduledoc "FASTA index (.fai) entry (cyanea-seq)"
+  defstruct [:name, :length, :offset, :line_bases, :line_width]
+end
+
+defmodule Cyanea.Native.PositionQuality do
+  @moduledoc "Quality percentiles for 1-based read positions start..end (cyanea-seq)"
+  defstruct [:start, :end, :mean, :median, :lower_quartile, :upper_quartile, :p10, :p90]
+end
+
+defmodule Cyanea.Native.PositionContent do
+  @moduledoc "Base percentages for 1-based read positions start..end (cyanea-seq)"
+  defstruct [:start, :end, :a, :c, :g, :t, :n]
+end
+
+defmodule Cyanea.Native.OverrepresentedSequence do
+  @moduledoc "Sequence making up an unexpectedly large share of reads (cyanea-seq)"
+  defstruct [:sequence, :count, :percentage]
+end
+
+defmodule Cyanea.Native.FastqQcReport do
+  @moduledoc "FastQC-style FASTQ quality report (cyanea-seq)"
+  defstruct [:read_count, :total_bases, :min_length, :max_length, :gc_percent,
+             :per_base_quality, :per_base_content, :per_sequence_quality,
+             :gc_histogram, :length_distribution, :duplication_levels,
+             :deduplicated_percent, :overrepresented, :statuses]
+end
+
+defmodule Cyanea.Native.GenomeEstimate do
+  @moduledoc "Genome size and heterozygosity estimated from a k-mer histogram (cyanea-seq)"
+  defstruct [:error_cutoff, :kmer_coverage, :heterozygous_peak, :genome_size,
+             :heterozygosity, :repeat_fraction]
+end
+
+defmodule Cyanea.Native.KmerSpectrum do
+  @moduledoc "Canonical k-mer abundance histogram and most frequent k-mers (cyanea-seq)"
+  defstruct [:k, :total_kmers, :distinct_kmers, :histogram, :top_kmers, :genome]
+end
+
 defmodule Cyanea.Native.OrfResult do
   @moduledoc "Open reading frame result (cyanea-seq)"
-  defstruct [:start, :end, :frame, :strand, :sequence]
+  defstruct [:start, :end, :frame, :strand, :sequence, :protein]
+end
+
+defmodule Cyanea.Native.FrameTranslation do
+  @moduledoc "Translation of one reading frame (cyanea-seq)"
+  defstruct [:strand, :frame, :protein]
 end
 
 # --- cyanea-align ---
@@ -613,6 +957,13 @@ defmodule Cyanea.Native.AlignmentResult do
              :num_gaps, :alignment_length]
 end
 
+defmodule Cyanea.Native.AlignmentSummary do
+  @moduledoc "Identity, similarity and gap summary of a pairwise alignment (cyanea-align)"
+  defstruct [:length, :identities, :positives, :mismatches, :gaps, :gap_opens,
+             :identity, :similarity, :gap_fraction, :query_start, :query_end,
+             :target_start, :target_end, :match_line]
+end
+
 defmodule Cyanea.Native.CigarStats do
   @moduledoc "CIGAR string statistics (cyanea-align)"
   defstruct [:cigar_string, :reference_consumed, :query_consumed,
@@ -625,6 +976,49 @@ defmodule Cyanea.Native.MsaResult do
   defstruct [:aligned, :n_sequences, :n_columns, :conservation]
 end
 
+defmodule Cyanea.Native.SubstitutionMatrix do
+  @moduledoc "Substitution matrix scores and default gap penalties (cyanea-align)"
+  defstruct [:name, :alphabet, :scores, :gap_open, :gap_extend]
+end
+
+defmodule Cyanea.Native.KarlinAltschul do
+  @moduledoc "Karlin-Altschul lambda, K and relative entropy H (cyanea-align)"
+  defstruct [:lambda, :k, :h, :gapped]
+end
+
+defmodule Cyanea.Native.AlignmentSignificance do
+  @moduledoc "Bit score and E-value of an alignment score (cyanea-align)"
+  defstruct [:score, :bit_score, :evalue]
+end
+
+defmodule Cyanea.Native.ShuffleFit do
+  @moduledoc "Extreme value fit to alignment scores against shuffled targets (cyanea-align)"
+  defstruct [:score, :shuffles, :mean, :sd, :lambda, :k, :p_value, :empirical_p]
+end
+
+defmodule Cyanea.Native.MapOptions do
+  @moduledoc "Read mapper seeding, chaining and scoring settings (cyanea-align)"
+  defstruct [:min_seed_len, :max_occurrences, :bandwidth, :match_score,
+             :mismatch_score, :gap_open, :gap_extend, :min_score]
+end
+
+defmodule Cyanea.Native.MappedRead do
+  @moduledoc "Primary alignment of a mapped read with its SAM tags (cyanea-align)"
+  defstruct [:qname, :flag, :rname, :pos, :mapq, :cigar, :sequence, :quality,
+             :score, :suboptimal_score, :edit_distance, :md]
+end
+
+defmodule Cyanea.Native.SearchOptions do
+  @moduledoc "Database search seeding, extension and reporting settings (cyanea-align)"
+  defstruct [:gap_open, :gap_extend, :threshold, :window, :x_drop, :gap_trigger,
+             :max_evalue, :max_targets, :strand]
+end
+
+defmodule Cyanea.Native.SearchHit do
+  @moduledoc "Gapped hit of a database search with its E-value and alignment (cyanea-align)"
+  defstruct [:target, :target_length, :strand, :score, :bit_score, :evalue, :alignment]
+end
+
 # --- cyanea-stats ---
 
 defmodule Cyanea.Native.DescriptiveStats do
@@ -652,6 +1046,43 @@ defmodule Cyanea.Native.GenomicInterval do
   defstruct [:chrom, :start, :end, :strand]
 end
 
+defmodule Cyanea.Native.IntervalPair do
+  @moduledoc "Interval with its partner from a second set (cyanea-omics)"
+  defstruct [:a, :b]
+end
+
+defmodule Cyanea.Native.ClosestInterval do
+  @moduledoc "Nearest interval and signed distance (cyanea-omics)"
+  defstruct [:a, :b, :distance]
+end
+
+defmodule Cyanea.Native.VariantEffect do
+  @moduledoc "Predicted effect of a variant allele on a transcript, with HGVS (cyanea-omics)"
+  defstruct [:chrom, :position, :ref_allele, :alt_allele, :gene, :transcript,
+             :consequences, :impact, :hgvs_c, :hgvs_p]
+end
+
+defmodule Cyanea.Native.NormalizedVariant do
+  @moduledoc "A trimmed, left-aligned variant; `index` points at the input it came from (cyanea-omics)"
+  defstruct [:index, :chrom, :position, :ref_allele, :alt_alleles, :changed]
+end
+
+defmodule Cyanea.Native.NormalizeStats do
+  @moduledoc "Record counts from VCF normalization (cyanea-omics)"
+  defstruct [:records_in, :records_out, :normalized, :split]
+end
+
+defmodule Cyanea.Native.ConcordanceCounts do
+  @moduledoc "Truth/query match counts and rates for one variant class (cyanea-omics)"
+  defstruct [:truth_total, :query_total, :true_positives, :false_positives, :false_negatives,
+             :genotype_mismatches, :precision, :recall, :f1]
+end
+
+defmodule Cyanea.Native.Concordance do
+  @moduledoc "VCF concordance stratified into SNV, indel, other and all alleles (cyanea-omics)"
+  defstruct [:snv, :indel, :other, :all]
+end
+
 defmodule Cyanea.Native.ExpressionSummary do
   @moduledoc "Expression matrix summary (cyanea-omics)"
   defstruct [:n_features, :n_samples, :feature_names, :sample_names,
@@ -661,8 +1092,10 @@ end
 # --- cyanea-io (format stats) ---
 
 defmodule Cyanea.Native.VcfStats do
-  @moduledoc "VCF file statistics (cyanea-io)"
-  defstruct [:variant_count, :snv_count, :indel_count, :pass_count, :chromosomes]
+  @moduledoc "VCF file statistics with per-sample genotype summaries (cyanea-io)"
+  defstruct [:variant_count, :snv_count, :indel_count, :pass_count, :chromosomes,
+             :sample_names, :call_rate, :het_count, :hom_alt_count, :het_hom_ratio,
+             :mean_allele_frequency, :allele_frequency_spectrum]
 end
 
 defmodule Cyanea.Native.BedStats do
@@ -679,8 +1112,20 @@ end
 # --- cyanea-io (record types — new) ---
 
 defmodule Cyanea.Native.VcfRecord do
-  @moduledoc "VCF variant record (cyanea-io)"
-  defstruct [:chrom, :position, :ref_allele, :alt_alleles, :quality, :filter]
+  @moduledoc "VCF variant record with typed INFO and per-sample FORMAT data (cyanea-io)"
+  defstruct [:chrom, :position, :ids, :ref_allele, :alt_alleles, :quality, :filter,
+             :filters, :info, :format, :genotypes, :depths, :genotype_qualities,
+             :allele_depths, :sample_data]
+end
+
+defmodule Cyanea.Native.VcfField do
+  @moduledoc "VCF ##INFO/##FORMAT definition (cyanea-io)"
+  defstruct [:id, :number, :value_type, :description]
+end
+
+defmodule Cyanea.Native.VcfHeader do
+  @moduledoc "VCF header: raw ## lines plus parsed samples, contigs, filters and definitions (cyanea-io)"
+  defstruct meta: [], fileformat: nil, samples: [], contigs: [], filters: [], info: [], format: []
 end
 
 defmodule Cyanea.Native.BedRecord do
@@ -693,6 +1138,29 @@ defmodule Cyanea.Native.GffGene do
   defstruct [:id, :symbol, :chrom, :start, :end, :strand, :gene_type, :transcript_count]
 end
 
+defmodule Cyanea.Native.TargetDepth do
+  @moduledoc "Read depth over one target region (cyanea-io)"
+  defstruct [:chrom, :start, :end, :mean_depth, :median_depth, :min_depth, :max_depth,
+             :fraction_above]
+end
+
+defmodule Cyanea.Native.DepthReport do
+  @moduledoc "Per-target and pooled read depth summary (cyanea-io)"
+  defstruct [:thresholds, :targets, :total_bases, :mean_depth, :median_depth, :fraction_above]
+end
+
+defmodule Cyanea.Native.BedFeature do
+  @moduledoc "BED feature for writing, 0-based half-open (cyanea-io)"
+  defstruct [:chrom, :start, :end, :name, :score, :strand, :thick_start, :thick_end,
+             :item_rgb, block_sizes: [], block_starts: []]
+end
+
+defmodule Cyanea.Native.GffFeature do
+  @moduledoc "GFF3 feature for writing, 1-based inclusive (cyanea-io)"
+  defstruct [:seqid, :start, :end, :score, :phase, source: ".", feature_type: "region",
+             strand: ".", attributes: []]
+end
+
 defmodule Cyanea.Native.SamRecord do
   @moduledoc "SAM/BAM alignment record (cyanea-io)"
   defstruct [:qname, :flag, :rname, :pos, :mapq, :cigar, :sequence, :quality]
@@ -741,6 +1209,11 @@ defmodule Cyanea.Native.LinearRegressionResult do
   defstruct [:weights, :bias, :r_squared]
 end
 
+defmodule Cyanea.Native.RandomForestInfo do
+  @moduledoc "Random forest model summary (cyanea-ml)"
+  defstruct [:n_trees, :n_features, :n_classes, :oob_error, :feature_importances]
+end
+
 # --- cyanea-chem ---
 
 defmodule Cyanea.Native.MolecularProperties do
@@ -857,3 +1330,13 @@ defmodule Cyanea.Native.BedGraphStats do
   @moduledoc "bedGraph file statistics (cyanea-io)"
   defstruct [:record_count, :chrom_count]
 end
+
+defmodule Cyanea.Native.BedGraphRecord do
+  @moduledoc "bedGraph / bigWig data record, 0-based half-open (cyanea-io)"
+  defstruct [:chrom, :start, :end, :value]
+end
+
+defmodule Cyanea.Native.BigWigBin do
+  @moduledoc "Summary of one bin of a bigWig region (cyanea-io)"
+  defstruct [:chrom, :start, :end, :mean, :min, :max, :coverage]
+end
@@ -37,6 +37,10 @@ defmodule Cyanea.NifHelper do
   def matrix_string(:blosum45), do: "blosum45"
   def matrix_string(:blosum80), do: "blosum80"
   def matrix_string(:pam250), do: "pam250"
+  def matrix_string(:blosum50), do: "blosum50"
+  def matrix_string(:blosum90), do: "blosum90"
+  def matrix_string(:pam30), do: "pam30"
+  def matrix_string(:pam70), do: "pam70"
   def matrix_string(s) when is_binary(s), do: s
 
   @doc "Convert atom/string evolutionary model to string."
@@ -65,4 +69,57 @@ defmodule Cyanea.NifHelper do
   def msa_mode_string(:dna), do: "dna"
   def msa_mode_string(:protein), do: "protein"
   def msa_mode_string(s) when is_binary(s), do: s
+
+  @doc "Convert atom/string genomic index format to string."
+  def index_format_string(:bai), do: "bai"
+  def index_format_string(:csi), do: "csi"
+  def index_format_string(s) when is_binary(s), do: s
+
+  @doc "Convert atom/string tabix preset to string."
+  def tabix_preset_string(:auto), do: "auto"
+  def tabix_preset_string(:vcf), do: "vcf"
+  def tabix_preset_string(:bed), do: "bed"
+  def tabix_preset_string(:gff), do: "gff"
+  def tabix_preset_string(:bedgraph), do: "bedgraph"
+  def tabix_preset_string(s) when is_binary(s), do: s
+
+  @doc "Convert atom/string interval strand mode to string."
+  def strand_mode_string(:ignore), do: "ignore"
+  def strand_mode_string(:same), do: "same"
+  def strand_mode_string(:opposite), do: "opposite"
+  def strand_mode_string(s) when is_binary(s), do: s
+
+  @doc "Convert atom/string interval intersect report to string."
+  def intersect_report_string(:overlap), do: "overlap"
+  def intersect_report_string(:a), do: "a"
+  def intersect_report_string(:b), do: "b"
+  def intersect_report_string(:both), do: "both"
+  def intersect_report_string(:any), do: "any"
+  def intersect_report_string(:none), do: "none"
+  def intersect_report_string(s) when is_binary(s), do: s
+
+  @doc "Convert atom/string search database alphabet to string."
+  def search_alphabet_string(:auto), do: "auto"
+  def search_alphabet_string(:protein), do: "protein"
+  def search_alphabet_string(:nucleotide), do: "nucleotide"
+  def search_alphabet_string(s) when is_binary(s), do: s
+
+  @doc "Convert atom/string search query strand to string."
+  def search_strand_string(:both), do: "both"
+  def search_strand_string(:plus), do: "plus"
+  def search_strand_string(:minus), do: "minus"
+  def search_strand_string(s) when is_binary(s), do: s
+
+  @doc "Normalize chrom sizes (list or map of {chrom, length}, or a chrom.sizes/.fai path) to {:ok, list}."
+  def genome_sizes(path) when is_binary(path) do
+    with {:ok, text} <- File.read(path) do
+      {:ok,
+       for line <- String.split(text, "\n", trim: true),
+           [chrom, len | _] <- [String.split(line, "\t")],
+           do: {chrom, String.to_integer(String.trim(len))}}
+    end
+  end
+
+  def genome_sizes(sizes) when is_list(sizes) or is_map(sizes),
+    do: {:ok, Enum.map(sizes, fn {chrom, len} -> {to_string(chrom), len} end)}
 end
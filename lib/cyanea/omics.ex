@@ -38,4 +38,276 @@ defmodule Cyanea.Omics do
     pseudocount = Keyword.get(opts, :pseudocount, 1.0)
     nif_call(fn -> Native.log_transform_matrix(data, pseudocount) end)
   end
+
+  # ===========================================================================
+  # Interval algebra
+  # ===========================================================================
+
+  # Interval arguments are `Cyanea.Native.GenomicInterval` structs or any map
+  # with `:chrom`, `:start`, `:end` and optionally `:strand` (BED records,
+  # BED features), 0-based and half-open. Lookups go through an interval
+  # tree per chromosome, so inputs need not be sorted.
+
+  @doc """
+  Intersect two interval sets, like `bedtools intersect`.
+
+  ## Options
+
+    * `:report` - what to return (default: `:overlap`):
+      * `:overlap` - the overlapping part of A for each overlapping pair
+      * `:a` - A itself, once per overlap (`-wa`)
+      * `:b` - `{overlap, b}` tuples (`-wb`)
+      * `:both` - `{a, b}` tuples (`-wa -wb`)
+      * `:any` - each A overlapping anything, once (`-u`)
+      * `:none` - each A overlapping nothing (`-v`)
+    * `:strand` - `:ignore` (default), `:same` (`-s`) or `:opposite` (`-S`).
+      Unstranded intervals never match under `:same`/`:opposite`.
+    * `:min_overlap` - fraction of A that must be covered (`-f`, default: 0.0)
+
+  """
+  @spec intersect(list(), list(), keyword()) :: {:ok, list()} | {:error, term()}
+  def intersect(a, b, opts \\ []) when is_list(a) and is_list(b) do
+    report = Keyword.get(opts, :report, :overlap)
+    strand = Keyword.get(opts, :strand, :ignore)
+    min_overlap = Keyword.get(opts, :min_overlap, 0.0) * 1.0
+
+    with {:ok, pairs} <-
+           nif_call(fn ->
+             Native.interval_intersect(to_intervals(a), to_intervals(b),
+               intersect_report_string(report), strand_mode_string(strand), min_overlap)
+           end) do
+      if report in [:b, :both, "b", "both"],
+        do: {:ok, Enum.map(pairs, &{&1.a, &1.b})},
+        else: {:ok, Enum.map(pairs, & &1.a)}
+    end
+  end
+
+  @doc """
+  Remove the parts of each A interval covered by B, like `bedtools subtract`.
+
+  ## Options
+
+    * `:strand` - `:ignore` (default), `:same` or `:opposite`
+
+  """
+  @spec subtract(list(), list(), keyword()) :: {:ok, list()} | {:error, term()}
+  def subtract(a, b, opts \\ []) when is_list(a) and is_list(b) do
+    strand = Keyword.get(opts, :strand, :ignore)
+
+    nif_call(fn ->
+      Native.interval_subtract(to_intervals(a), to_intervals(b), strand_mode_string(strand))
+    end)
+  end
+
+  @doc """
+  Regions of the genome covered by no interval, like `bedtools complement`.
+
+  `genome` is a list of `{chrom, length}` (output follows its order), a map,
+  or the path of a chrom-sizes or `.fai` file. Intervals on chromosomes
+  missing from the genome are an error.
+  """
+  @spec complement(list(), list() | map() | binary()) :: {:ok, list()} | {:error, term()}
+  def complement(intervals, genome) when is_list(intervals) do
+    with {:ok, sizes} <- genome_sizes(genome) do
+      nif_call(fn -> Native.interval_complement(to_intervals(intervals), sizes) end)
+    end
+  end
+
+  @doc """
+  Find the nearest B interval(s) for each A, like `bedtools closest -D a -t all`.
+
+  Returns `Cyanea.Native.ClosestInterval` structs, one per A and nearest B
+  (ties all reported). `distance` is 0 for overlaps, otherwise at least 1
+  and negative when B is upstream of A on A's strand; `b` and `distance`
+  are `nil` when no B is on A's chromosome.
+
+  ## Options
+
+    * `:strand` - `:ignore` (default), `:same` or `:opposite`
+
+  """
+  @spec closest(list(), list(), keyword()) :: {:ok, list()} | {:error, term()}
+  def closest(a, b, opts \\ []) when is_list(a) and is_list(b) do
+    strand = Keyword.get(opts, :strand, :ignore)
+
+    nif_call(fn ->
+      Native.interval_closest(to_intervals(a), to_intervals(b), strand_mode_string(strand))
+    end)
+  end
+
+  @doc """
+  Pair each A with the B intervals within a window around it, like
+  `bedtools window`. Returns `{a, b}` tuples.
+
+  ## Options
+
+    * `:window` - bases added on both sides (default: 1000)
+    * `:left` / `:right` - override `:window` per side (`-l` / `-r`)
+    * `:strand_relative` - treat `:left` as upstream on A's strand (`-sw`,
+      default: false)
+    * `:strand` - `:ignore` (default), `:same` or `:opposite`
+
+  """
+  @spec window(list(), list(), keyword()) :: {:ok, list()} | {:error, term()}
+  def window(a, b, opts \\ []) when is_list(a) and is_list(b) do
+    size = Keyword.get(opts, :window, 1000)
+    left = Keyword.get(opts, :left, size)
+    right = Keyword.get(opts, :right, size)
+    relative = Keyword.get(opts, :strand_relative, false)
+    strand = Keyword.get(opts, :strand, :ignore)
+
+    with {:ok, pairs} <-
+           nif_call(fn ->
+             Native.interval_window(to_intervals(a), to_intervals(b), left, right, relative,
+               strand_mode_string(strand))
+           end) do
+      {:ok, Enum.map(pairs, &{&1.a, &1.b})}
+    end
+  end
+
+  @doc """
+  Predict the effect of variants on the transcripts of a GFF3 gene model.
+
+  `variants` are `Cyanea.Native.VcfRecord` structs or maps with `:chrom`,
+  `:position` (1-based, VCF style), `:ref` and `:alt` (a string or list);
+  each ALT allele is annotated separately. Transcripts are built from the
+  exon/CDS/UTR features of `gff_path`, and REF alleles are checked against
+  `fasta_path`. The FASTA must be uncompressed; it is read one chromosome
+  at a time through its `.fai` index (built in memory when missing).
+  Codons are read from the phase of the first CDS segment and translated
+  with the NCBI table in a `transl_table` attribute, else table 2 on
+  `chrM`/`MT` and the standard code elsewhere.
+
+  Returns `Cyanea.Native.VariantEffect` structs, one per allele and
+  overlapping transcript (one `intergenic_variant` when none overlaps),
+  with Sequence Ontology consequences (intron, UTR, synonymous, missense,
+  stop gained/lost, frameshift, splice donor/acceptor/region, ...), the
+  VEP impact class, and HGVS `c.`/`n.` and `p.` descriptions. Symbolic and
+  `*` alleles are skipped.
+
+  ## Options
+
+    * `:most_severe` - keep only the most severe effect per allele
+      (default `false`)
+
+  """
+  @spec annotate_variants(list(), binary(), binary(), keyword()) ::
+          {:ok, list()} | {:error, term()}
+  def annotate_variants(variants, gff_path, fasta_path, opts \\ [])
+      when is_list(variants) and is_binary(gff_path) and is_binary(fasta_path) do
+    most_severe = Keyword.get(opts, :most_severe, false)
+    alleles = Enum.flat_map(variants, &variant_alleles/1)
+    nif_call(fn -> Native.annotate_variants(alleles, gff_path, fasta_path, most_severe) end)
+  end
+
+  @doc """
+  Normalize variants against a reference FASTA so that equivalent
+  representations compare equal.
+
+  Shared leading and trailing bases are trimmed and indels are shifted to
+  their leftmost position in repeats (the vt / `bcftools norm` definition).
+  `variants` take the same forms as in `annotate_variants/4`; a REF that
+  does not match `fasta_path` is an error. Symbolic alleles are returned
+  unchanged. As in `annotate_variants/4`, the FASTA must be uncompressed.
+
+  Returns `Cyanea.Native.NormalizedVariant` structs whose `index` is the
+  position of the input variant and `changed` tells whether it moved or
+  lost bases.
+
+  ## Options
+
+    * `:split` - return one biallelic variant per ALT allele
+      (default `false`)
+
+  """
+  @spec normalize_variants(list(), binary(), keyword()) :: {:ok, list()} | {:error, term()}
+  def normalize_variants(variants, fasta_path, opts \\ [])
+      when is_list(variants) and is_binary(fasta_path) do
+    split = Keyword.get(opts, :split, false)
+    variants = Enum.map(variants, &variant_site/1)
+    nif_call(fn -> Native.normalize_variants(variants, fasta_path, split) end)
+  end
+
+  @doc """
+  Normalize every record of the VCF at `input` and write it to `output`.
+
+  Records are normalized as in `normalize_variants/3` and written in
+  position order; INFO, FORMAT and sample columns are kept. When splitting,
+  `Number=A/R/G` values are subset to each allele and other ALT alleles in
+  `GT` become `0`. `fasta_path` must be uncompressed and is read one
+  chromosome at a time, as the VCF reaches it.
+
+  Returns a `Cyanea.Native.NormalizeStats` struct.
+
+  ## Options
+
+    * `:split` - split multi-allelic records into biallelic ones
+      (default `false`)
+    * `:bgzf` - BGZF-compress the output (default `false`)
+
+  """
+  @spec normalize_vcf(binary(), binary(), binary(), keyword()) ::
+          {:ok, struct()} | {:error, term()}
+  def normalize_vcf(input, output, fasta_path, opts \\ [])
+      when is_binary(input) and is_binary(output) and is_binary(fasta_path) do
+    split = Keyword.get(opts, :split, false)
+    bgzf = Keyword.get(opts, :bgzf, false)
+    nif_call(fn -> Native.normalize_vcf(input, output, fasta_path, split, bgzf) end)
+  end
+
+  @doc """
+  Benchmark the calls in the `query` VCF against a `truth` VCF.
+
+  Both files are split into biallelic records and normalized against
+  `fasta_path` (see `normalize_vcf/4`), then matched on chromosome,
+  position, REF and ALT. Records whose first sample does not carry the
+  ALT allele (e.g. `0/0`) and symbolic alleles are ignored.
+
+  Returns a `Cyanea.Native.Concordance` struct with
+  `Cyanea.Native.ConcordanceCounts` for `:snv`, `:indel`, `:other` (MNPs
+  and complex alleles) and `:all`: true positives, false positives
+  (query only), false negatives (truth only), true positives whose
+  genotypes differ, and precision, recall and F1 (`nil` when undefined).
+
+  ## Options
+
+    * `:confident_regions` - BED file; only alleles whose REF span lies
+      inside these regions are counted
+    * `:pass_only` - ignore records with a FILTER other than `PASS`
+      (default `true`)
+
+  """
+  @spec vcf_concordance(binary(), binary(), binary(), keyword()) ::
+          {:ok, struct()} | {:error, term()}
+  def vcf_concordance(truth, query, fasta_path, opts \\ [])
+      when is_binary(truth) and is_binary(query) and is_binary(fasta_path) do
+    confident = Keyword.get(opts, :confident_regions)
+    pass_only = Keyword.get(opts, :pass_only, true)
+    nif_call(fn -> Native.vcf_concordance(truth, query, fasta_path, confident, pass_only) end)
+  end
+
+  defp variant_alleles(%{chrom: chrom, position: pos, ref_allele: ref, alt_alleles: alts}),
+    do: for(alt <- alts, do: {chrom, pos, ref, alt})
+
+  defp variant_alleles(%{chrom: chrom, position: pos, ref: ref, alt: alts}) when is_list(alts),
+    do: for(alt <- alts, do: {chrom, pos, ref, alt})
+
+  defp variant_alleles(%{chrom: chrom, position: pos, ref: ref, alt: alt}) when is_binary(alt),
+    do: [{chrom, pos, ref, alt}]
+
+  defp variant_site(%{chrom: chrom, position: pos, ref_allele: ref, alt_alleles: alts}),
+    do: {chrom, pos, ref, alts}
+
+  defp variant_site(%{chrom: chrom, position: pos, ref: ref, alt: alts}) when is_list(alts),
+    do: {chrom, pos, ref, alts}
+
+  defp variant_site(%{chrom: chrom, position: pos, ref: ref, alt: alt}) when is_binary(alt),
+    do: {chrom, pos, ref, [alt]}
+
+  defp to_intervals(intervals) do
+    Enum.map(intervals, fn %{chrom: chrom, start: start, end: stop} = iv ->
+      %Native.GenomicInterval{chrom: chrom, start: start, end: stop,
+                              strand: Map.get(iv, :strand) || "."}
+    end)
+  end
 end
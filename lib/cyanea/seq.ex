@@ -10,7 +10,14 @@ defmodule Cyanea.Seq do
 
   @type seq_type :: :dna | :rna | :protein
 
-  @doc "Validate and normalize a sequence of the given type. Returns `{:ok, binary}` or `{:error, reason}`."
+  @doc """
+  Validate and normalize a sequence of the given type. Returns `{:ok, binary}`
+  or `{:error, reason}`.
+
+  DNA and RNA may hold any IUPAC nucleotide code (`N`, `R`, `Y`, ...) and
+  keep their case, as in `reverse_complement/1`, `transcribe/1` and
+  `gc_content/1`, so soft-masked bases stay lowercase.
+  """
   @spec validate(binary(), seq_type()) :: {:ok, binary()} | {:error, term()}
   def validate(seq, :dna) when is_binary(seq), do: nif_call(fn -> Native.validate_dna(seq) end)
   def validate(seq, :rna) when is_binary(seq), do: nif_call(fn -> Native.validate_rna(seq) end)
@@ -29,7 +36,7 @@ defmodule Cyanea.Seq do
   # Operations
   # ===========================================================================
 
-  @doc "Reverse complement a DNA sequence."
+  @doc "Reverse complement a DNA sequence. IUPAC codes are complemented (`R` <-> `Y`, ...) and case is kept."
   @spec reverse_complement(binary()) :: {:ok, binary()} | {:error, term()}
   def reverse_complement(seq) when is_binary(seq),
     do: nif_call(fn -> Native.dna_reverse_complement(seq) end)
@@ -39,16 +46,63 @@ defmodule Cyanea.Seq do
   def transcribe(seq) when is_binary(seq),
     do: nif_call(fn -> Native.dna_transcribe(seq) end)
 
-  @doc "Translate RNA to protein (NCBI Table 1)."
-  @spec translate(binary()) :: {:ok, binary()} | {:error, term()}
-  def translate(seq) when is_binary(seq),
-    do: nif_call(fn -> Native.rna_translate(seq) end)
+  @doc """
+  Translate DNA or RNA to protein.
+
+  Codons are read from the first base and a trailing partial codon is
+  dropped. Stops read as `*`; codons with IUPAC ambiguity codes read as
+  the amino acid all their expansions share, else `X`.
+
+  ## Options
+
+    * `:table` - NCBI genetic code ID, see `genetic_codes/0` (default: 1)
+    * `:to_stop` - end before the first stop codon (default: false)
+
+  """
+  @spec translate(binary(), keyword()) :: {:ok, binary()} | {:error, term()}
+  def translate(seq, opts \\ []) when is_binary(seq) do
+    table = Keyword.get(opts, :table, 1)
+    to_stop = Keyword.get(opts, :to_stop, false)
+    nif_call(fn -> Native.translate(seq, table, to_stop) end)
+  end
+
+  @doc """
+  Translate all six reading frames of a DNA or RNA sequence.
+
+  Returns `Cyanea.Native.FrameTranslation` structs for frames 0-2 of the
+  `"+"` strand, then of the reverse complement (`"-"`). Takes the
+  `:table` option of `translate/2`.
+  """
+  @spec six_frame_translate(binary(), keyword()) :: {:ok, list()} | {:error, term()}
+  def six_frame_translate(seq, opts \\ []) when is_binary(seq) do
+    table = Keyword.get(opts, :table, 1)
+    nif_call(fn -> Native.six_frame_translate(seq, table) end)
+  end
 
-  @doc "Calculate GC content of a DNA sequence (fraction 0.0-1.0)."
+  @doc """
+  List the supported NCBI genetic codes as `{id, name}` tuples, e.g.
+  `{2, "Vertebrate Mitochondrial"}` or
+  `{11, "Bacterial, Archaeal and Plant Plastid"}`.
+  """
+  @spec genetic_codes() :: {:ok, [{pos_integer(), binary()}]} | {:error, term()}
+  def genetic_codes, do: nif_call(fn -> Native.genetic_codes() end)
+
+  @doc """
+  Calculate GC content of a DNA sequence (fraction 0.0-1.0).
+
+  `G`, `C` and `S` count as GC over the bases known to be strong or weak
+  (`A`, `T`, `W` besides); `N` and other ambiguity codes are left out.
+  """
   @spec gc_content(binary()) :: {:ok, float()} | {:error, term()}
   def gc_content(seq) when is_binary(seq),
     do: nif_call(fn -> Native.dna_gc_content(seq) end)
 
+  @doc "List lowercase (soft-masked) runs as 0-based, end-exclusive `{start, end}` tuples."
+  @spec soft_masked_regions(binary()) ::
+          {:ok, [{non_neg_integer(), non_neg_integer()}]} | {:error, term()}
+  def soft_masked_regions(seq) when is_binary(seq),
+    do: nif_call(fn -> Native.soft_masked_regions(seq) end)
+
   @doc "Extract k-mers from a DNA sequence."
   @spec kmers(binary(), pos_integer()) :: {:ok, [binary()]} | {:error, term()}
   def kmers(seq, k) when is_binary(seq) and is_integer(k),
@@ -70,6 +124,10 @@ defmodule Cyanea.Seq do
 
     * `:algorithm` - `:horspool` (default, exact) or `:myers` (approximate)
     * `:max_distance` - maximum edit distance for Myers (default: 1)
+    * `:iupac` - match nucleotides ignoring case, with IUPAC codes in the
+      pattern matching any base they stand for (`N` matches anything, `R`
+      matches `A`, `G` or `R`); an `N` in the text only matches `N`
+      (default: false)
 
   """
   @spec search(binary(), binary(), keyword()) :: {:ok, list()} | {:error, term()}
@@ -77,14 +135,15 @@ defmodule Cyanea.Seq do
 
   def search(text, pattern, opts) when is_binary(text) and is_binary(pattern) do
     algorithm = Keyword.get(opts, :algorithm, :horspool)
+    iupac = Keyword.get(opts, :iupac, false)
 
     case algorithm do
       :horspool ->
-        nif_call(fn -> Native.horspool_search(text, pattern) end)
+        nif_call(fn -> Native.horspool_search(text, pattern, iupac) end)
 
       :myers ->
         max_dist = Keyword.get(opts, :max_distance, 1)
-        nif_call(fn -> Native.myers_search(text, pattern, max_dist) end)
+        nif_call(fn -> Native.myers_search(text, pattern, max_dist, iupac) end)
     end
   end
 
@@ -92,15 +151,44 @@ defmodule Cyanea.Seq do
   # FM-Index
   # ===========================================================================
 
-  @doc "Build an FM-index from text. Returns serialized index data."
-  @spec build_index(binary()) :: {:ok, binary()} | {:error, term()}
+  @doc """
+  Build an FM-index from text (DirtyCpu scheduler).
+
+  Returns an opaque reference that can be queried repeatedly without
+  rebuilding the suffix array.
+  """
+  @spec build_index(binary()) :: {:ok, reference()} | {:error, term()}
   def build_index(text) when is_binary(text),
     do: nif_call(fn -> Native.fm_index_build(text) end)
 
-  @doc "Count occurrences of pattern in FM-index."
-  @spec count_occurrences(binary(), binary()) :: {:ok, non_neg_integer()} | {:error, term()}
-  def count_occurrences(index_data, pattern) when is_binary(index_data) and is_binary(pattern),
-    do: nif_call(fn -> Native.fm_index_count(index_data, pattern) end)
+  @doc """
+  Count occurrences of a pattern in an FM-index.
+
+  Pass a list of patterns to count them all in one call; the result is a
+  list of counts in the same order.
+  """
+  @spec count_occurrences(reference(), binary() | [binary()]) ::
+          {:ok, non_neg_integer() | [non_neg_integer()]} | {:error, term()}
+  def count_occurrences(index, pattern) when is_reference(index) and is_binary(pattern),
+    do: nif_call(fn -> Native.fm_index_count(index, pattern) end)
+
+  def count_occurrences(index, patterns) when is_reference(index) and is_list(patterns),
+    do: nif_call(fn -> Native.fm_index_count_batch(index, patterns) end)
+
+  @doc "Find sorted 0-based start positions of a pattern in an FM-index."
+  @spec locate_occurrences(reference(), binary()) :: {:ok, [non_neg_integer()]} | {:error, term()}
+  def locate_occurrences(index, pattern) when is_reference(index) and is_binary(pattern),
+    do: nif_call(fn -> Native.fm_index_locate(index, pattern) end)
+
+  @doc "Save an FM-index to disk so it can be reloaded with `load_index/1`."
+  @spec save_index(reference(), binary()) :: {:ok, binary()} | {:error, term()}
+  def save_index(index, path) when is_reference(index) and is_binary(path),
+    do: nif_call(fn -> Native.fm_index_save(index, path) end)
+
+  @doc "Load an FM-index previously written by `save_index/2`."
+  @spec load_index(binary()) :: {:ok, reference()} | {:error, term()}
+  def load_index(path) when is_binary(path),
+    do: nif_call(fn -> Native.fm_index_load(path) end)
 
   # ===========================================================================
   # ORF finding
@@ -109,15 +197,26 @@ defmodule Cyanea.Seq do
   @doc """
   Find open reading frames in both strands.
 
+  An ORF runs from the first start codon after a stop to the next stop
+  codon in the same frame. Returns `Cyanea.Native.OrfResult` structs with
+  the span on the forward strand (0-based, end exclusive, stop codon
+  included), the nucleotides read on the ORF's strand and the protein,
+  whose first residue is always `M`.
+
   ## Options
 
     * `:min_length` - minimum ORF length in nucleotides (default: 100)
+    * `:table` - NCBI genetic code ID, see `genetic_codes/0` (default: 1)
+    * `:alternative_starts` - also start at the table's alternative start
+      codons, e.g. `GTG` and `TTG` with table 11 (default: false)
 
   """
   @spec find_orfs(binary(), keyword()) :: {:ok, list()} | {:error, term()}
   def find_orfs(seq, opts \\ []) when is_binary(seq) do
     min_length = Keyword.get(opts, :min_length, 100)
-    nif_call(fn -> Native.find_orfs(seq, min_length) end)
+    table = Keyword.get(opts, :table, 1)
+    alternative_starts = Keyword.get(opts, :alternative_starts, false)
+    nif_call(fn -> Native.find_orfs_with_code(seq, min_length, table, alternative_starts) end)
   end
 
   # ===========================================================================
@@ -163,4 +262,423 @@ defmodule Cyanea.Seq do
   @spec parse_fastq(binary()) :: {:ok, list()} | {:error, term()}
   def parse_fastq(path) when is_binary(path),
     do: nif_call(fn -> Native.parse_fastq(path) end)
+
+  # ===========================================================================
+  # Streaming reader
+  # ===========================================================================
+
+  @doc """
+  Open a FASTA or FASTQ file for chunked reading.
+
+  Gzip (including BGZF) and zstd inputs are decompressed on the fly.
+  Returns an opaque reader reference for `next_chunk/2`.
+  """
+  @spec open_reader(binary()) :: {:ok, reference()} | {:error, term()}
+  def open_reader(path) when is_binary(path),
+    do: nif_call(fn -> Native.seq_reader_open(path) end)
+
+  @doc "Read up to `n` records from an open reader. Returns `{:ok, []}` at end of file."
+  @spec next_chunk(reference(), pos_integer()) :: {:ok, list()} | {:error, term()}
+  def next_chunk(reader, n) when is_reference(reader) and is_integer(n) and n > 0,
+    do: nif_call(fn -> Native.seq_reader_next_chunk(reader, n) end)
+
+  @doc "Close a reader, releasing its file handle."
+  @spec close_reader(reference()) :: {:ok, boolean()} | {:error, term()}
+  def close_reader(reader) when is_reference(reader),
+    do: nif_call(fn -> Native.seq_reader_close(reader) end)
+
+  @doc """
+  Lazily stream records from a FASTA/FASTQ file in chunks.
+
+  The file is opened when the stream is first enumerated and closed when it
+  halts, so `Stream.take/2` on a multi-gigabyte run only decodes what is
+  consumed.
+
+  ## Options
+
+    * `:chunk_size` - records fetched per NIF call (default: 10_000)
+
+  """
+  @spec stream_records(binary(), keyword()) :: Enumerable.t()
+  def stream_records(path, opts \\ []) when is_binary(path) do
+    chunk_size = Keyword.get(opts, :chunk_size, 10_000)
+
+    Stream.resource(
+      fn -> open_reader!(path) end,
+      fn reader ->
+        case next_chunk(reader, chunk_size) do
+          {:ok, []} -> {:halt, reader}
+          {:ok, records} -> {records, reader}
+          {:error, reason} -> raise ArgumentError, "reading #{path} failed: #{inspect(reason)}"
+        end
+      end,
+      &close_reader/1
+    )
+  end
+
+  # ===========================================================================
+  # FASTA records and indexed access
+  # ===========================================================================
+
+  @doc "Parse all records of a FASTA file into `Cyanea.Native.FastaRecord` structs."
+  @spec parse_fasta(binary()) :: {:ok, list()} | {:error, term()}
+  def parse_fasta(path) when is_binary(path),
+    do: nif_call(fn -> Native.parse_fasta(path) end)
+
+  @doc """
+  Lazily stream `Cyanea.Native.FastaRecord` structs from a FASTA file.
+
+  Built on `stream_records/2` and takes the same options; raises if the
+  file turns out to be FASTQ.
+  """
+  @spec stream_fasta(binary(), keyword()) :: Enumerable.t()
+  def stream_fasta(path, opts \\ []) when is_binary(path) do
+    path
+    |> stream_records(opts)
+    |> Stream.map(fn
+      %Native.FastqRecord{quality: quality} = record when quality in ["", []] ->
+        %Native.FastaRecord{
+          name: record.name,
+          description: record.description,
+          sequence: record.sequence
+        }
+
+      _ ->
+        raise ArgumentError, "#{path} is not a FASTA file"
+    end)
+  end
+
+  @doc """
+  Build the `samtools faidx` index of an uncompressed FASTA and write it
+  to `<path>.fai`. Returns the `Cyanea.Native.FaiEntry` structs.
+
+  Every line of a sequence but the last must have the same length.
+  """
+  @spec index_fasta(binary()) :: {:ok, list()} | {:error, term()}
+  def index_fasta(path) when is_binary(path),
+    do: nif_call(fn -> Native.fasta_index(path) end)
+
+  @doc """
+  Open a FASTA for random access with `fetch_sequence/3`.
+
+  Uses `<path>.fai` when present and otherwise indexes the file in memory
+  (see `index_fasta/1` to write the index once).
+  """
+  @spec open_fasta(binary()) :: {:ok, reference()} | {:error, term()}
+  def open_fasta(path) when is_binary(path),
+    do: nif_call(fn -> Native.faidx_open(path) end)
+
+  @doc "List the `Cyanea.Native.FaiEntry` structs of an open FASTA."
+  @spec fasta_sequences(reference()) :: {:ok, list()} | {:error, term()}
+  def fasta_sequences(fasta) when is_reference(fasta),
+    do: nif_call(fn -> Native.faidx_sequences(fasta) end)
+
+  @doc "Close a FASTA opened with `open_fasta/1`."
+  @spec close_fasta(reference()) :: {:ok, boolean()} | {:error, term()}
+  def close_fasta(fasta) when is_reference(fasta),
+    do: nif_call(fn -> Native.faidx_close(fasta) end)
+
+  @doc """
+  Fetch a reference slice, like `samtools faidx`.
+
+  `fasta` is a reference from `open_fasta/1` or a path (opened for this
+  call only). `region` is one of:
+
+    * a samtools region string - `"chr1"`, `"chr1:1000"` or
+      `"chr1:1,000-2,000"` (1-based, inclusive)
+    * `{chrom, start, stop}` - 1-based, inclusive
+    * a `Cyanea.Native.GenomicInterval` - 0-based, half-open; a `"-"`
+      strand fetches the reverse complement
+
+  Ends past the sequence are clipped. Case is kept as stored.
+
+  ## Options
+
+    * `:strand` - `"+"` or `"-"`; `"-"` returns the reverse complement
+      (default: `"+"`, or the interval's strand)
+
+  """
+  @spec fetch_sequence(reference() | binary(), term(), keyword()) ::
+          {:ok, binary()} | {:error, term()}
+  def fetch_sequence(fasta, region, opts \\ [])
+
+  def fetch_sequence(path, region, opts) when is_binary(path) do
+    with {:ok, fasta} <- open_fasta(path) do
+      try do
+        fetch_sequence(fasta, region, opts)
+      after
+        close_fasta(fasta)
+      end
+    end
+  end
+
+  def fetch_sequence(fasta, %Native.GenomicInterval{} = iv, opts) when is_reference(fasta) do
+    strand = if iv.strand == "-", do: "-", else: "+"
+    fetch_sequence(fasta, {iv.chrom, iv.start + 1, iv.end}, Keyword.put_new(opts, :strand, strand))
+  end
+
+  def fetch_sequence(fasta, {chrom, start, stop}, opts)
+      when is_reference(fasta) and is_binary(chrom) and is_integer(start) and is_integer(stop),
+      do: fetch_sequence(fasta, "#{chrom}:#{start}-#{stop}", opts)
+
+  def fetch_sequence(fasta, region, opts) when is_reference(fasta) and is_binary(region) do
+    reverse = Keyword.get(opts, :strand, "+") == "-"
+    nif_call(fn -> Native.faidx_fetch(fasta, region, reverse) end)
+  end
+
+  # ===========================================================================
+  # Paired-end FASTQ
+  # ===========================================================================
+
+  @doc """
+  Open paired-end FASTQ for chunked reading.
+
+  `paths` is `{r1, r2}` for split mate files or a single path to an
+  interleaved file (read 1 and read 2 alternating). Returns an opaque
+  reader reference for `next_pair_chunk/2`.
+  """
+  @spec open_paired_reader({binary(), binary()} | binary()) :: {:ok, reference()} | {:error, term()}
+  def open_paired_reader(paths) do
+    {r1, r2} = mate_paths(paths)
+    nif_call(fn -> Native.paired_reader_open(r1, r2) end)
+  end
+
+  @doc """
+  Read up to `n` `{read1, read2}` pairs from an open paired reader.
+
+  Mate names must match once a trailing `/1` or `/2` is removed, and both
+  inputs must hold the same number of reads; otherwise an error names the
+  offending reads. Returns `{:ok, []}` at end of file.
+  """
+  @spec next_pair_chunk(reference(), pos_integer()) :: {:ok, list()} | {:error, term()}
+  def next_pair_chunk(reader, n) when is_reference(reader) and is_integer(n) and n > 0,
+    do: nif_call(fn -> Native.paired_reader_next_chunk(reader, n) end)
+
+  @doc "Close a paired reader, releasing its file handles."
+  @spec close_paired_reader(reference()) :: {:ok, boolean()} | {:error, term()}
+  def close_paired_reader(reader) when is_reference(reader),
+    do: nif_call(fn -> Native.paired_reader_close(reader) end)
+
+  @doc """
+  Lazily stream `{read1, read2}` pairs, like `stream_records/2`.
+
+  `paths` is `{r1, r2}` or an interleaved file path.
+
+  ## Options
+
+    * `:chunk_size` - pairs fetched per NIF call (default: 10_000)
+
+  """
+  @spec stream_pairs({binary(), binary()} | binary(), keyword()) :: Enumerable.t()
+  def stream_pairs(paths, opts \\ []) do
+    {r1, _} = mate_paths(paths)
+    chunk_size = Keyword.get(opts, :chunk_size, 10_000)
+
+    Stream.resource(
+      fn ->
+        case open_paired_reader(paths) do
+          {:ok, reader} -> reader
+          {:error, reason} -> raise ArgumentError, "cannot open #{r1}: #{inspect(reason)}"
+        end
+      end,
+      fn reader ->
+        case next_pair_chunk(reader, chunk_size) do
+          {:ok, []} -> {:halt, reader}
+          {:ok, pairs} -> {pairs, reader}
+          {:error, reason} -> raise ArgumentError, "reading #{r1} failed: #{inspect(reason)}"
+        end
+      end,
+      &close_paired_reader/1
+    )
+  end
+
+  @doc """
+  Statistics for paired-end FASTQ (`{r1, r2}` or an interleaved path).
+
+  Returns a `Cyanea.Native.PairedFastqStats` struct with a
+  `Cyanea.Native.FastqStats` for each mate. Insert sizes are estimated
+  without a reference by aligning read 1 to the reverse complement of
+  read 2 (at least 30 overlapping bases, at most 5 mismatches and one per
+  5 bases); pairs whose fragment is too long for the mates to overlap are
+  left out of the insert-size figures.
+  """
+  @spec paired_fastq_stats({binary(), binary()} | binary()) :: {:ok, struct()} | {:error, term()}
+  def paired_fastq_stats(paths) do
+    {r1, r2} = mate_paths(paths)
+    nif_call(fn -> Native.paired_fastq_stats(r1, r2) end)
+  end
+
+  defp mate_paths({r1, r2}) when is_binary(r1) and is_binary(r2), do: {r1, r2}
+  defp mate_paths(path) when is_binary(path), do: {path, nil}
+
+  # ===========================================================================
+  # Read trimming
+  # ===========================================================================
+
+  @doc """
+  Trim the reads of a FASTQ file and write the survivors to `output`.
+
+  Steps run in this order: poly-G tail, adapter, poly-A tail, sliding
+  window, 3' quality, minimum length. Full adapter occurrences are found
+  with the Myers bit-parallel matcher (as in `search/3`); an adapter prefix
+  of at least `:min_adapter_overlap` bases at the 3' end is removed too.
+  When `:adapters` is not given, the Illumina TruSeq, Nextera and small
+  RNA adapters are counted in the first 100,000 reads and the most
+  frequent one is used.
+
+  Returns a `Cyanea.Native.TrimReport` with the reads and bases removed by
+  each step and the adapters used.
+
+  ## Options
+
+    * `:adapters` - adapter sequences (default: auto-detected)
+    * `:adapter_error_rate` - edit distance allowed per adapter base
+      (default: 0.1)
+    * `:min_adapter_overlap` - shortest 3' adapter prefix removed (default: 3)
+    * `:quality_cutoff` - BWA-style 3' quality cutoff, 0 to disable
+      (default: 20)
+    * `:window_size` - sliding window width, 0 to disable (default: 0)
+    * `:window_quality` - minimum mean window quality (default: 20)
+    * `:poly_g` - shortest poly-G tail removed, 0 to disable (default: 10)
+    * `:poly_a` - shortest poly-A tail removed, 0 to disable (default: 0)
+    * `:min_length` - drop reads shorter than this after trimming
+      (default: 15)
+    * `:bgzf` - BGZF-compress the output (default: false)
+
+  """
+  @spec trim_fastq(binary(), binary(), keyword()) :: {:ok, struct()} | {:error, term()}
+  def trim_fastq(input, output, opts \\ []) when is_binary(input) and is_binary(output) do
+    options = trim_options(opts)
+    bgzf = Keyword.get(opts, :bgzf, false)
+    nif_call(fn -> Native.trim_fastq(input, output, options, bgzf) end)
+  end
+
+  @doc """
+  Trim a list of `Cyanea.Native.FastqRecord` structs, dropping reads that
+  end up shorter than `:min_length`.
+
+  Takes the options of `trim_fastq/3`; adapters are not auto-detected, so
+  pass `:adapters` to remove them. Records without qualities are trimmed
+  on sequence alone; a quality string whose length differs from the
+  sequence is an error.
+  """
+  @spec trim_reads(list(), keyword()) :: {:ok, list()} | {:error, term()}
+  def trim_reads(records, opts \\ []) when is_list(records) do
+    options = trim_options(Keyword.put_new(opts, :adapters, []))
+    nif_call(fn -> Native.trim_reads(records, options) end)
+  end
+
+  @doc """
+  Lazily trim a stream of FASTQ records, e.g. from `stream_records/2`.
+
+  Records are trimmed in chunks with `trim_reads/2`; takes the same
+  options plus `:chunk_size` (default: 10_000).
+  """
+  @spec trim_stream(Enumerable.t(), keyword()) :: Enumerable.t()
+  def trim_stream(records, opts \\ []) do
+    {chunk_size, opts} = Keyword.pop(opts, :chunk_size, 10_000)
+
+    records
+    |> Stream.chunk_every(chunk_size)
+    |> Stream.flat_map(fn chunk ->
+      case trim_reads(chunk, opts) do
+        {:ok, trimmed} -> trimmed
+        {:error, reason} -> raise ArgumentError, "trimming failed: #{inspect(reason)}"
+      end
+    end)
+  end
+
+  defp trim_options(opts) do
+    adapters = Keyword.get(opts, :adapters)
+
+    %Native.TrimOptions{
+      adapters: adapters || [],
+      detect_adapter: is_nil(adapters),
+      adapter_error_rate: Keyword.get(opts, :adapter_error_rate, 0.1) / 1,
+      min_adapter_overlap: Keyword.get(opts, :min_adapter_overlap, 3),
+      quality_cutoff: Keyword.get(opts, :quality_cutoff, 20),
+      window_size: Keyword.get(opts, :window_size, 0),
+      window_quality: Keyword.get(opts, :window_quality, 20),
+      poly_g: Keyword.get(opts, :poly_g, 10),
+      poly_a: Keyword.get(opts, :poly_a, 0),
+      min_length: Keyword.get(opts, :min_length, 15)
+    }
+  end
+
+  # ===========================================================================
+  # FASTQ quality report
+  # ===========================================================================
+
+  @doc """
+  Build a FastQC-style quality report for a FASTQ file.
+
+  Returns a `Cyanea.Native.FastqQcReport` with per-position quality
+  percentiles and base composition (per base up to 75 bp, otherwise the
+  first nine bases singly and the rest in groups), per-read mean quality,
+  GC and length histograms, duplication levels and the most frequent
+  overrepresented sequences. `statuses` maps each module to `:pass`,
+  `:warn` or `:fail` using FastQC's default thresholds, ready for
+  `qc_badge/1`.
+
+  ## Options
+
+    * `:max_reads` - only read the first N reads (default: all)
+
+  """
+  @spec fastq_qc(binary(), keyword()) :: {:ok, struct()} | {:error, term()}
+  def fastq_qc(path, opts \\ []) when is_binary(path) do
+    max_reads = Keyword.get(opts, :max_reads)
+
+    with {:ok, report} <- nif_call(fn -> Native.fastq_qc(path, max_reads) end) do
+      statuses =
+        Map.new(report.statuses, fn {module, status} ->
+          {String.to_atom(module), String.to_atom(status)}
+        end)
+
+      {:ok, %{report | statuses: statuses}}
+    end
+  end
+
+  # ===========================================================================
+  # K-mer spectrum
+  # ===========================================================================
+
+  @doc """
+  Count canonical k-mers in a FASTA or FASTQ file (plain or gzipped).
+
+  A k-mer and its reverse complement are counted together; k-mers with
+  bases other than `ACGT` are skipped. Returns a `Cyanea.Native.KmerSpectrum`
+  with the abundance histogram as `{count, distinct_kmers}` pairs (bins
+  above `:max_count` are folded into the last one), the most frequent
+  k-mers and, when the histogram shows an error trough followed by a
+  coverage peak, a `Cyanea.Native.GenomeEstimate` in `genome` (otherwise
+  `nil`).
+
+  The estimate is peak-based: homozygous k-mer coverage comes from the
+  main peak (or the peak at twice its coverage when the main one is the
+  heterozygous peak), genome size is the genomic k-mer total divided by
+  that coverage and heterozygosity is derived from the k-mers in the
+  half-coverage peak. Expect it to be rough below ~15x k-mer coverage.
+
+  ## Options
+
+    * `:k` - k-mer length, 1 to 32 (default: 21)
+    * `:max_count` - histogram cap (default: 10_000)
+    * `:top` - number of most frequent k-mers to return (default: 10)
+
+  """
+  @spec kmer_spectrum(binary(), keyword()) :: {:ok, struct()} | {:error, term()}
+  def kmer_spectrum(path, opts \\ []) when is_binary(path) do
+    k = Keyword.get(opts, :k, 21)
+    max_count = Keyword.get(opts, :max_count, 10_000)
+    top = Keyword.get(opts, :top, 10)
+    nif_call(fn -> Native.kmer_spectrum(path, k, max_count, top) end)
+  end
+
+  defp open_reader!(path) do
+    case open_reader(path) do
+      {:ok, reader} -> reader
+      {:error, reason} -> raise ArgumentError, "cannot open #{path}: #{inspect(reason)}"
+    end
+  end
 end
@@ -1,7 +1,15 @@
 //! cyanea-align NIFs — Pairwise alignment, batch, MSA, banded, POA.
 
 use crate::bridge::*;
+use crate::mapper::{self, ReferenceIndex};
+use crate::pairwise::{self, Scoring};
+use crate::reader::SeqRecord;
+use crate::render;
+use crate::search::{Alphabet, SearchIndex, SearchOptions};
+use crate::significance::{self, KarlinAltschul};
+use crate::substitution::{self, SubstitutionMatrix};
 use crate::to_nif_error;
+use rustler::ResourceArc;
 
 // ===========================================================================
 // Helpers
@@ -269,3 +277,309 @@ pub fn split_cigar(cigar: String, ref_pos: usize) -> Result<(String, String), St
         cyanea_align::cigar::cigar_string(&right),
     ))
 }
+
+// ===========================================================================
+// Substitution matrices
+// ===========================================================================
+
+/// A built-in matrix with BLAST's default gap penalties for it.
+#[rustler::nif]
+pub fn substitution_matrix(name: String) -> Result<SubstitutionMatrixNif, String> {
+    let matrix = SubstitutionMatrix::builtin(&name)?;
+    let gaps = substitution::default_gaps(&name);
+    Ok(SubstitutionMatrixNif {
+        gap_open: gaps.map(|(open, _)| open),
+        gap_extend: gaps.map(|(_, extend)| extend),
+        ..matrix.into()
+    })
+}
+
+#[rustler::nif]
+pub fn substitution_matrices() -> Vec<String> {
+    substitution::builtin_names().map(str::to_string).collect()
+}
+
+/// Parse an NCBI-format matrix (as shipped with BLAST and EMBOSS).
+#[rustler::nif]
+pub fn parse_substitution_matrix_text(text: String, name: String) -> Result<SubstitutionMatrixNif, String> {
+    SubstitutionMatrix::parse(&name, &text).map(SubstitutionMatrixNif::from)
+}
+
+/// Read an NCBI-format matrix file; the matrix is named after the file.
+#[rustler::nif]
+pub fn read_substitution_matrix(path: String) -> Result<SubstitutionMatrixNif, String> {
+    let text = std::fs::read_to_string(&path).map_err(|e| format!("{path}: {e}"))?;
+    let name = std::path::Path::new(&path)
+        .file_stem()
+        .map_or_else(|| path.clone(), |s| s.to_string_lossy().into_owned());
+    SubstitutionMatrix::parse(&name, &text).map(SubstitutionMatrixNif::from)
+}
+
+/// IUPAC nucleotide matrix from match, transition and transversion scores.
+#[rustler::nif]
+pub fn nucleotide_matrix(match_score: i32, transition: i32, transversion: i32) -> SubstitutionMatrixNif {
+    SubstitutionMatrix::nucleotide(match_score, transition, transversion).into()
+}
+
+/// Affine-gap alignment scored by any substitution matrix.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn align_with_matrix(
+    query: Vec<u8>,
+    target: Vec<u8>,
+    mode: String,
+    matrix: SubstitutionMatrixNif,
+    gap_open: i32,
+    gap_extend: i32,
+) -> Result<AlignmentResultNif, String> {
+    let mode = pairwise::Mode::parse(&mode)?;
+    let matrix = SubstitutionMatrix::try_from(matrix)?;
+    let scoring = Scoring::new(&matrix, gap_open, gap_extend)?;
+    Ok(pairwise::align(&query, &target, mode, &scoring).into())
+}
+
+// ===========================================================================
+// Alignment output
+// ===========================================================================
+
+fn optional_matrix(matrix: Option<SubstitutionMatrixNif>) -> Result<Option<SubstitutionMatrix>, String> {
+    matrix.map(SubstitutionMatrix::try_from).transpose()
+}
+
+/// Identity / similarity / gap counts and the match line; similarity
+/// needs a matrix, otherwise it equals identity.
+#[rustler::nif]
+pub fn alignment_summary(
+    result: AlignmentResultNif,
+    matrix: Option<SubstitutionMatrixNif>,
+) -> Result<AlignmentSummaryNif, String> {
+    let matrix = optional_matrix(matrix)?;
+    Ok(render::Summary::new(&result.into(), matrix.as_ref()).into())
+}
+
+/// BLAST-style text rendering, `width` columns per block.
+#[rustler::nif]
+pub fn format_alignment(
+    result: AlignmentResultNif,
+    query_name: String,
+    target_name: String,
+    width: usize,
+    matrix: Option<SubstitutionMatrixNif>,
+) -> Result<String, String> {
+    let matrix = optional_matrix(matrix)?;
+    Ok(render::pretty(&result.into(), matrix.as_ref(), &query_name, &target_name, width))
+}
+
+/// One SAM line; `query` is the full query so unaligned ends are
+/// soft-clipped.
+#[rustler::nif]
+pub fn alignment_to_sam(
+    result: AlignmentResultNif,
+    query: Vec<u8>,
+    query_name: String,
+    target_name: String,
+) -> Result<String, String> {
+    render::sam_record(&result.into(), &query, &query_name, &target_name)
+}
+
+/// One PAF line.
+#[rustler::nif]
+pub fn alignment_to_paf(
+    result: AlignmentResultNif,
+    query_name: String,
+    query_len: usize,
+    target_name: String,
+    target_len: usize,
+) -> String {
+    render::paf_record(&result.into(), &query_name, query_len, &target_name, target_len)
+}
+
+// ===========================================================================
+// Alignment significance
+// ===========================================================================
+
+/// Gapped parameters when both gap penalties are given (built-in matrices
+/// with BLAST's gap costs only), otherwise ungapped parameters computed
+/// from the matrix.
+#[rustler::nif]
+pub fn karlin_altschul(
+    matrix: SubstitutionMatrixNif,
+    gap_open: Option<i32>,
+    gap_extend: Option<i32>,
+) -> Result<KarlinAltschulNif, String> {
+    let matrix = SubstitutionMatrix::try_from(matrix)?;
+    match (gap_open, gap_extend) {
+        (Some(open), Some(extend)) => KarlinAltschul::gapped(&matrix, open, extend)
+            .map(KarlinAltschulNif::from)
+            .ok_or_else(|| {
+                format!(
+                    "no gapped parameters for {} with gaps {open}/{extend}; estimate them with shuffle_significance",
+                    matrix.name
+                )
+            }),
+        _ => KarlinAltschul::ungapped(&matrix).map(KarlinAltschulNif::from),
+    }
+}
+
+#[rustler::nif]
+pub fn alignment_significance(
+    score: i32,
+    params: KarlinAltschulNif,
+    query_len: usize,
+    db_len: usize,
+) -> AlignmentSignificanceNif {
+    let params = KarlinAltschul::from(params);
+    AlignmentSignificanceNif {
+        score,
+        bit_score: params.bit_score(score),
+        evalue: params.evalue(score, query_len, db_len),
+    }
+}
+
+/// Local alignment score of `query` and `target` against `shuffles`
+/// composition-preserving shuffles of `target`.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn shuffle_significance(
+    query: Vec<u8>,
+    target: Vec<u8>,
+    matrix: SubstitutionMatrixNif,
+    gap_open: i32,
+    gap_extend: i32,
+    shuffles: usize,
+    seed: u64,
+) -> Result<ShuffleFitNif, String> {
+    let matrix = SubstitutionMatrix::try_from(matrix)?;
+    let scoring = Scoring::new(&matrix, gap_open, gap_extend)?;
+    significance::shuffle_fit(&query, &target, &scoring, shuffles, seed).map(ShuffleFitNif::from)
+}
+
+// ===========================================================================
+// Read mapping
+// ===========================================================================
+
+/// An indexed reference held by the BEAM as an opaque reference.
+pub struct ReferenceIndexResource {
+    pub(crate) index: ReferenceIndex,
+}
+
+#[rustler::resource_impl]
+impl rustler::Resource for ReferenceIndexResource {}
+
+/// Index every sequence of a FASTA file for `map_reads`/`map_records`.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn mapper_index(path: String) -> Result<ResourceArc<ReferenceIndexResource>, String> {
+    let index = ReferenceIndex::build(&path)?;
+    Ok(ResourceArc::new(ReferenceIndexResource { index }))
+}
+
+#[rustler::nif]
+pub fn mapper_contigs(index: ResourceArc<ReferenceIndexResource>) -> Vec<(String, usize)> {
+    index
+        .index
+        .contigs()
+        .map(|(name, len)| (name.to_string(), len))
+        .collect()
+}
+
+/// Map a FASTA/FASTQ file and write SAM to `output`; `avg_mapq` is over
+/// mapped reads.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn map_reads(
+    index: ResourceArc<ReferenceIndexResource>,
+    reads: String,
+    output: String,
+    options: MapOptionsNif,
+) -> Result<SamStatsNif, String> {
+    let r = mapper::map_file(&index.index, &reads, &output, &options.into())?;
+    Ok(SamStatsNif {
+        total_reads: r.reads,
+        mapped: r.mapped,
+        unmapped: r.reads - r.mapped,
+        avg_mapq: r.mapq_sum as f64 / r.mapped.max(1) as f64,
+        avg_length: r.bases as f64 / r.reads.max(1) as f64,
+    })
+}
+
+/// Map a chunk of records, returning one `MappedRead` per record.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn map_records(
+    index: ResourceArc<ReferenceIndexResource>,
+    records: Vec<FastqRecordNif>,
+    options: MapOptionsNif,
+) -> Result<Vec<MappedReadNif>, String> {
+    let options = options.into();
+    records
+        .into_iter()
+        .map(|r| {
+            let rec = SeqRecord {
+                name: r.name,
+                description: r.description,
+                sequence: r.sequence,
+                quality: r.quality,
+            };
+            index.index.map(&rec, &options).map(MappedReadNif::from)
+        })
+        .collect()
+}
+
+// ===========================================================================
+// Database search
+// ===========================================================================
+
+/// A word-indexed sequence database held by the BEAM as an opaque
+/// reference.
+pub struct SearchIndexResource {
+    pub(crate) index: SearchIndex,
+}
+
+#[rustler::resource_impl]
+impl rustler::Resource for SearchIndexResource {}
+
+/// Index a FASTA file for `sequence_search`. `alphabet` is `"protein"`,
+/// `"nucleotide"` or `"auto"`; `word_size` defaults to 3 for proteins and
+/// 11 for nucleotides.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn search_index(
+    path: String,
+    alphabet: String,
+    word_size: Option<usize>,
+) -> Result<ResourceArc<SearchIndexResource>, String> {
+    let index = SearchIndex::build(&path, Alphabet::parse(&alphabet)?, word_size)?;
+    Ok(ResourceArc::new(SearchIndexResource { index }))
+}
+
+/// Index `{name, sequence}` pairs for `sequence_search`.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn search_index_sequences(
+    sequences: Vec<(String, Vec<u8>)>,
+    alphabet: String,
+    word_size: Option<usize>,
+) -> Result<ResourceArc<SearchIndexResource>, String> {
+    let index = SearchIndex::from_sequences(sequences, Alphabet::parse(&alphabet)?, word_size)?;
+    Ok(ResourceArc::new(SearchIndexResource { index }))
+}
+
+/// `(alphabet, word_size, sequences, residues)` of a search index.
+#[rustler::nif]
+pub fn search_index_info(index: ResourceArc<SearchIndexResource>) -> (String, usize, usize, usize) {
+    let index = &index.index;
+    (
+        index.alphabet().name().to_string(),
+        index.word_size(),
+        index.sequences(),
+        index.residues(),
+    )
+}
+
+/// Gapped HSPs of `query` against an indexed database, best first.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn sequence_search(
+    index: ResourceArc<SearchIndexResource>,
+    query: Vec<u8>,
+    matrix: SubstitutionMatrixNif,
+    options: SearchOptionsNif,
+) -> Result<Vec<SearchHitNif>, String> {
+    let matrix = SubstitutionMatrix::try_from(matrix)?;
+    let options = SearchOptions::try_from(options)?;
+    let hits = index.index.search(&query, &matrix, &options)?;
+    Ok(hits.into_iter().map(SearchHitNif::from).collect())
+}
@@ -0,0 +1,464 @@
+//! Record-level BAM reader with virtual-offset seeking.
+//!
+//! The reader works directly on BGZF blocks and decodes one alignment at a
+//! time, tracking the virtual offset each record starts at. Region queries
+//! jump straight to the chunks an index points at, and index building
+//! records those offsets as it scans.
+
+use crate::bgzf::BgzfReader;
+use crate::binning::{BinningIndex, IndexBuilder, BAI_DEPTH, BAI_MIN_SHIFT};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+
+const SEQ_CODES: &[u8; 16] = b"=ACMGRSVTWYHKDBN";
+const CIGAR_OPS: &[u8; 9] = b"MIDNSHP=X";
+
+/// Reference sequences from the BAM preamble.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct BamHeader {
+    pub refs: Vec<(String, u64)>,
+}
+
+impl BamHeader {
+    pub(crate) fn ref_id(&self, name: &str) -> Option<usize> {
+        self.refs.iter().position(|(n, _)| n == name)
+    }
+
+    pub(crate) fn ref_name(&self, id: i32) -> &str {
+        usize::try_from(id)
+            .ok()
+            .and_then(|i| self.refs.get(i))
+            .map_or("*", |(n, _)| n.as_str())
+    }
+}
+
+/// One decoded alignment. Coordinates are 0-based as stored in BAM.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct BamRecord {
+    pub ref_id: i32,
+    pub pos: i32,
+    pub mapq: u8,
+    pub flag: u16,
+    pub qname: String,
+    /// `(length, op)` pairs with ops as SAM characters.
+    pub cigar: Vec<(u32, u8)>,
+    pub sequence: Vec<u8>,
+    /// Raw phred scores; empty when absent (stored as 0xff).
+    pub quality: Vec<u8>,
+}
+
+impl BamRecord {
+    pub(crate) fn is_unmapped(&self) -> bool {
+        self.flag & 0x4 != 0
+    }
+
+    /// Bases of reference consumed by the alignment.
+    pub(crate) fn reference_len(&self) -> u64 {
+        self.cigar
+            .iter()
+            .filter(|(_, op)| matches!(op, b'M' | b'D' | b'N' | b'=' | b'X'))
+            .map(|&(len, _)| len as u64)
+            .sum()
+    }
+
+    /// Exclusive 0-based end on the reference; unmapped or CIGAR-less
+    /// records occupy a single base.
+    pub(crate) fn end(&self) -> u64 {
+        let start = self.pos.max(0) as u64;
+        let len = if self.is_unmapped() { 0 } else { self.reference_len() };
+        start + len.max(1)
+    }
+
+    pub(crate) fn cigar_string(&self) -> String {
+        if self.cigar.is_empty() {
+            return "*".into();
+        }
+        self.cigar
+            .iter()
+            .map(|&(len, op)| format!("{len}{}", op as char))
+            .collect()
+    }
+
+    /// Phred+33 quality string, `*` when absent.
+    pub(crate) fn quality_string(&self) -> String {
+        if self.quality.is_empty() {
+            return "*".into();
+        }
+        self.quality.iter().map(|&q| q.saturating_add(33) as char).collect()
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, String> {
+        if data.len() < 32 {
+            return Err("truncated BAM record".into());
+        }
+        let i32_at = |o: usize| i32::from_le_bytes(data[o..o + 4].try_into().unwrap());
+        let u16_at = |o: usize| u16::from_le_bytes(data[o..o + 2].try_into().unwrap());
+        let l_read_name = data[8] as usize;
+        let n_cigar = u16_at(12) as usize;
+        let l_seq = usize::try_from(i32_at(16)).map_err(|_| "negative BAM sequence length")?;
+
+        let name_end = 32 + l_read_name;
+        let cigar_end = name_end + 4 * n_cigar;
+        let seq_end = cigar_end + l_seq.div_ceil(2);
+        let qual_end = seq_end + l_seq;
+        if data.len() < qual_end {
+            return Err("truncated BAM record".into());
+        }
+
+        let qname = String::from_utf8_lossy(&data[32..name_end])
+            .trim_end_matches('\0')
+            .to_string();
+        let cigar = data[name_end..cigar_end]
+            .chunks_exact(4)
+            .map(|c| {
+                let v = u32::from_le_bytes(c.try_into().unwrap());
+                let op = CIGAR_OPS.get((v & 0xf) as usize).copied().unwrap_or(b'?');
+                (v >> 4, op)
+            })
+            .collect();
+        let packed = &data[cigar_end..seq_end];
+        let sequence = (0..l_seq)
+            .map(|i| {
+                let b = packed[i / 2];
+                let code = if i % 2 == 0 { b >> 4 } else { b & 0xf };
+                SEQ_CODES[code as usize]
+            })
+            .collect();
+        let qual = &data[seq_end..qual_end];
+        let quality = if qual.first() == Some(&0xff) {
+            Vec::new()
+        } else {
+            qual.to_vec()
+        };
+
+        Ok(Self {
+            ref_id: i32_at(0),
+            pos: i32_at(4),
+            mapq: data[9],
+            flag: u16_at(14),
+            qname,
+            cigar,
+            sequence,
+            quality,
+        })
+    }
+}
+
+pub(crate) struct BamReader {
+    inner: BgzfReader<BufReader<File>>,
+    header: BamHeader,
+    buf: Vec<u8>,
+}
+
+impl BamReader {
+    /// Open `path` and read its header. Only BAM is read: CRAM is not BGZF
+    /// and needs the reference to decode, so it is rejected by name before
+    /// any BGZF parsing.
+    pub(crate) fn open(path: &str) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("{path}: {e}"))?;
+        let mut buffered = BufReader::new(file);
+        if buffered.fill_buf().map_err(|e| e.to_string())?.starts_with(b"CRAM") {
+            return Err(format!(
+                "{path}: CRAM is not supported; convert it with `samtools view -b`"
+            ));
+        }
+        let mut inner = BgzfReader::new(buffered);
+        let mut magic = [0u8; 4];
+        inner.read_exact(&mut magic).map_err(|e| e.to_string())?;
+        if &magic != b"BAM\x01" {
+            return Err(format!("{path}: not a BAM file"));
+        }
+        let mut r = Raw(&mut inner);
+        let l_text = r.len()?;
+        r.bytes(l_text)?;
+        let n_ref = r.len()?;
+        let mut refs = Vec::with_capacity(n_ref);
+        for _ in 0..n_ref {
+            let l_name = r.len()?;
+            let name = String::from_utf8_lossy(&r.bytes(l_name)?)
+                .trim_end_matches('\0')
+                .to_string();
+            refs.push((name, r.len()? as u64));
+        }
+        Ok(Self {
+            inner,
+            header: BamHeader { refs },
+            buf: Vec::new(),
+        })
+    }
+
+    pub(crate) fn header(&self) -> &BamHeader {
+        &self.header
+    }
+
+    pub(crate) fn virtual_offset(&self) -> u64 {
+        self.inner.virtual_offset()
+    }
+
+    pub(crate) fn seek_virtual(&mut self, voffset: u64) -> Result<(), String> {
+        self.inner.seek_virtual(voffset).map_err(|e| e.to_string())
+    }
+
+    /// Next alignment, or `None` at end of file.
+    pub(crate) fn next_record(&mut self) -> Result<Option<BamRecord>, String> {
+        let mut size = [0u8; 4];
+        if !self
+            .inner
+            .read_exact_or_eof(&mut size)
+            .map_err(|e| e.to_string())?
+        {
+            return Ok(None);
+        }
+        let size = u32::from_le_bytes(size) as usize;
+        self.buf.resize(size, 0);
+        self.inner
+            .read_exact(&mut self.buf)
+            .map_err(|e| e.to_string())?;
+        BamRecord::decode(&self.buf).map(Some)
+    }
+}
+
+/// Locate the index for `path`: `x.bam.bai`, `x.bam.csi`, then `x.bai`.
+pub(crate) fn find_index(path: &str) -> Option<String> {
+    let mut candidates = vec![format!("{path}.bai"), format!("{path}.csi")];
+    if let Some(stem) = path.strip_suffix(".bam") {
+        candidates.push(format!("{stem}.bai"));
+    }
+    candidates
+        .into_iter()
+        .find(|c| std::path::Path::new(c).is_file())
+}
+
+/// Read the BAI/CSI index of `path` (see [`find_index`]).
+pub(crate) fn read_index(path: &str) -> Result<BinningIndex, String> {
+    let index_path = find_index(path)
+        .ok_or_else(|| format!("{path}: no .bai or .csi index found; build one first"))?;
+    let bytes = std::fs::read(&index_path).map_err(|e| format!("{index_path}: {e}"))?;
+    BinningIndex::from_bytes(&bytes).map(|(index, _)| index)
+}
+
+/// Call `f` with each record of reference `tid` overlapping the 0-based
+/// half-open `[beg, end)`, in file order, reading only the chunks `index`
+/// points to.
+pub(crate) fn fetch<F>(
+    reader: &mut BamReader,
+    index: &BinningIndex,
+    tid: usize,
+    beg: u64,
+    end: u64,
+    mut f: F,
+) -> Result<(), String>
+where
+    F: FnMut(BamRecord) -> Result<(), String>,
+{
+    for chunk in index.query(tid, beg, end) {
+        reader.seek_virtual(chunk.beg)?;
+        while reader.virtual_offset() < chunk.end {
+            let Some(rec) = reader.next_record()? else {
+                break;
+            };
+            if rec.ref_id != tid as i32 || rec.pos as u64 >= end {
+                break;
+            }
+            if rec.end() > beg {
+                f(rec)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Records on `chrom` overlapping the 0-based half-open `[beg, end)`,
+/// fetched through the file's BAI/CSI index.
+pub(crate) fn query(
+    path: &str,
+    chrom: &str,
+    beg: u64,
+    end: u64,
+) -> Result<(BamHeader, Vec<BamRecord>), String> {
+    if beg >= end {
+        return Err(format!("empty region {chrom}:{beg}-{end}"));
+    }
+    let index = read_index(path)?;
+    let mut reader = BamReader::open(path)?;
+    let tid = reader
+        .header()
+        .ref_id(chrom)
+        .ok_or_else(|| format!("reference {chrom} not found in {path}"))?;
+
+    let mut hits = Vec::new();
+    fetch(&mut reader, &index, tid, beg, end, |rec| {
+        hits.push(rec);
+        Ok(())
+    })?;
+    Ok((reader.header, hits))
+}
+
+/// Index a coordinate-sorted BAM, writing `<path>.bai` (or `<path>.csi`
+/// when `csi` is set, needed for references over 512 Mbp). Returns the
+/// index path.
+pub(crate) fn build_index(path: &str, csi: bool) -> Result<String, String> {
+    let mut reader = BamReader::open(path)?;
+    let n_refs = reader.header().refs.len();
+    let depth = if csi {
+        let longest = reader.header().refs.iter().map(|(_, l)| *l).max();
+        csi_depth(BAI_MIN_SHIFT, longest.unwrap_or(0))
+    } else {
+        BAI_DEPTH
+    };
+    let mut builder = IndexBuilder::new(n_refs, BAI_MIN_SHIFT, depth, !csi);
+    if !csi {
+        let max = BinningIndex::max_len(BAI_MIN_SHIFT, BAI_DEPTH);
+        if let Some((name, len)) = reader.header().refs.iter().find(|(_, l)| *l > max) {
+            return Err(format!(
+                "reference {name} ({len} bp) is too long for BAI; build a CSI index"
+            ));
+        }
+    }
+    loop {
+        let vbeg = reader.virtual_offset();
+        let Some(rec) = reader.next_record()? else {
+            break;
+        };
+        let vend = reader.virtual_offset();
+        if rec.ref_id < 0 {
+            builder.push_unplaced();
+            continue;
+        }
+        let beg = rec.pos.max(0) as u64;
+        builder
+            .push(rec.ref_id as usize, beg, rec.end(), vbeg, vend, !rec.is_unmapped())
+            .map_err(|e| format!("{path}: {e} (at read {})", rec.qname))?;
+    }
+    let index = builder.finish();
+    let (index_path, bytes) = if csi {
+        (format!("{path}.csi"), index.to_csi()?)
+    } else {
+        (format!("{path}.bai"), index.to_bai())
+    };
+    std::fs::write(&index_path, bytes).map_err(|e| format!("{index_path}: {e}"))?;
+    Ok(index_path)
+}
+
+/// Levels a CSI index with `min_shift` needs to address `max_len`, as
+/// htslib picks them (with 256 bp of slack past the longest reference).
+fn csi_depth(min_shift: u32, max_len: u64) -> u32 {
+    let max_len = max_len + 256;
+    let mut depth = 0;
+    while BinningIndex::max_len(min_shift, depth) < max_len {
+        depth += 1;
+    }
+    depth
+}
+
+/// Length-prefixed header field reader.
+struct Raw<'a, R: Read>(&'a mut R);
+
+impl<R: Read> Raw<'_, R> {
+    fn len(&mut self) -> Result<usize, String> {
+        let mut b = [0u8; 4];
+        self.0.read_exact(&mut b).map_err(|e| e.to_string())?;
+        usize::try_from(i32::from_le_bytes(b)).map_err(|_| "negative length in BAM header".into())
+    }
+
+    fn bytes(&mut self, n: usize) -> Result<Vec<u8>, String> {
+        let mut v = vec![0u8; n];
+        self.0.read_exact(&mut v).map_err(|e| e.to_string())?;
+        Ok(v)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::bgzf::BgzfWriter;
+    use std::io::Write;
+
+    /// A BAM with one reference of `ref_len` bp and a 10M read at each of
+    /// `positions` (sorted).
+    pub(crate) fn write_bam(path: &std::path::Path, ref_len: u32, positions: &[i32]) {
+        let mut out = b"BAM\x01".to_vec();
+        out.extend_from_slice(&0i32.to_le_bytes());
+        out.extend_from_slice(&1i32.to_le_bytes());
+        out.extend_from_slice(&5i32.to_le_bytes());
+        out.extend_from_slice(b"chr1\0");
+        out.extend_from_slice(&ref_len.to_le_bytes());
+        for (i, &pos) in positions.iter().enumerate() {
+            let name = format!("r{i}\0");
+            let mut rec = Vec::new();
+            rec.extend_from_slice(&0i32.to_le_bytes());
+            rec.extend_from_slice(&pos.to_le_bytes());
+            rec.push(name.len() as u8);
+            rec.push(60);
+            rec.extend_from_slice(&0u16.to_le_bytes());
+            rec.extend_from_slice(&1u16.to_le_bytes());
+            rec.extend_from_slice(&0u16.to_le_bytes());
+            rec.extend_from_slice(&10i32.to_le_bytes());
+            rec.extend_from_slice(&(-1i32).to_le_bytes());
+            rec.extend_from_slice(&(-1i32).to_le_bytes());
+            rec.extend_from_slice(&0i32.to_le_bytes());
+            rec.extend_from_slice(name.as_bytes());
+            rec.extend_from_slice(&(10u32 << 4).to_le_bytes());
+            rec.extend_from_slice(&[0x12; 5]);
+            rec.extend_from_slice(&[30; 10]);
+            out.extend_from_slice(&(rec.len() as u32).to_le_bytes());
+            out.extend_from_slice(&rec);
+        }
+        let mut writer = BgzfWriter::new(Vec::new());
+        writer.write_all(&out).unwrap();
+        std::fs::write(path, writer.finish().unwrap()).unwrap();
+    }
+
+    fn names(path: &str, beg: u64, end: u64) -> Vec<String> {
+        let (_, records) = query(path, "chr1", beg, end).unwrap();
+        records.into_iter().map(|r| r.qname).collect()
+    }
+
+    #[test]
+    fn csi_covers_references_longer_than_bai() {
+        let dir = std::env::temp_dir().join(format!("cyanea-bam-csi-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let bam = dir.join("long.bam");
+        let positions = [100, 600_000_000, 1_000_000_000, 2_000_000_000];
+        write_bam(&bam, 2_100_000_000, &positions);
+        let path = bam.to_str().unwrap();
+
+        assert!(build_index(path, false).unwrap_err().contains("CSI"));
+        let index = build_index(path, true).unwrap();
+        assert!(index.ends_with(".csi"));
+        let (parsed, kind) = BinningIndex::from_bytes(&std::fs::read(&index).unwrap()).unwrap();
+        assert_eq!(kind, crate::binning::IndexKind::Csi);
+        assert_eq!((parsed.min_shift, parsed.depth), (14, 6));
+
+        assert_eq!(names(path, 0, 1_000), ["r0"]);
+        assert_eq!(names(path, 599_999_995, 600_000_001), ["r1"]);
+        assert_eq!(names(path, 900_000_000, 2_050_000_000), ["r2", "r3"]);
+        assert!(names(path, 1_500_000_000, 1_600_000_000).is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn bai_round_trip() {
+        let dir = std::env::temp_dir().join(format!("cyanea-bam-bai-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let bam = dir.join("short.bam");
+        write_bam(&bam, 1_000_000, &[10, 20_000, 500_000]);
+        let path = bam.to_str().unwrap();
+
+        assert!(build_index(path, false).unwrap().ends_with(".bai"));
+        assert_eq!(names(path, 0, 15), ["r0"]);
+        assert_eq!(names(path, 19_995, 600_000), ["r1", "r2"]);
+        assert!(names(path, 100_000, 400_000).is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_cram_by_name() {
+        let name = format!("cyanea-bam-{}.cram", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, b"CRAM\x03\x00file-id-and-containers").unwrap();
+        let err = BamReader::open(path.to_str().unwrap()).err().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(err.contains("CRAM is not supported"), "{err}");
+    }
+}
@@ -0,0 +1,313 @@
+//! BGZF block I/O with virtual file offsets.
+//!
+//! BAM, tabix-compressed text and CSI/TBI indexes are all BGZF: a series
+//! of independent gzip members of at most 64 KiB each. A virtual offset is
+//! `compressed_block_start << 16 | offset_within_uncompressed_block`, which
+//! is what BAI/CSI/TBI chunks point at.
+
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::{Compression, Crc};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+/// Largest uncompressed payload per block (htslib's `BGZF_BLOCK_SIZE`).
+const MAX_BLOCK_DATA: usize = 0xff00;
+
+/// Canonical empty block marking end-of-file.
+const EOF_BLOCK: [u8; 28] = [
+    0x1f, 0x8b, 0x08, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0x06, 0x00, 0x42, 0x43, 0x02,
+    0x00, 0x1b, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+/// Random-access BGZF reader.
+pub(crate) struct BgzfReader<R> {
+    inner: R,
+    block: Vec<u8>,
+    block_pos: usize,
+    block_offset: u64,
+    next_block_offset: u64,
+}
+
+impl<R: Read + Seek> BgzfReader<R> {
+    pub(crate) fn new(inner: R) -> Self {
+        Self {
+            inner,
+            block: Vec::new(),
+            block_pos: 0,
+            block_offset: 0,
+            next_block_offset: 0,
+        }
+    }
+
+    /// Virtual offset of the next byte to be read.
+    pub(crate) fn virtual_offset(&self) -> u64 {
+        if self.block_pos >= self.block.len() {
+            self.next_block_offset << 16
+        } else {
+            (self.block_offset << 16) | self.block_pos as u64
+        }
+    }
+
+    /// Position the reader at a virtual offset.
+    pub(crate) fn seek_virtual(&mut self, voffset: u64) -> io::Result<()> {
+        let coffset = voffset >> 16;
+        let uoffset = (voffset & 0xffff) as usize;
+        self.inner.seek(SeekFrom::Start(coffset))?;
+        self.next_block_offset = coffset;
+        self.load_block()?;
+        if uoffset > self.block.len() {
+            return Err(invalid("virtual offset past end of BGZF block"));
+        }
+        self.block_pos = uoffset;
+        Ok(())
+    }
+
+    /// Read and inflate the block at `next_block_offset`. Returns `false`
+    /// at end of file.
+    fn load_block(&mut self) -> io::Result<bool> {
+        let mut header = [0u8; 12];
+        match read_full(&mut self.inner, &mut header)? {
+            0 => {
+                self.block.clear();
+                self.block_pos = 0;
+                return Ok(false);
+            }
+            12 => {}
+            _ => return Err(invalid("truncated BGZF block header")),
+        }
+        if header[0] != 0x1f || header[1] != 0x8b || header[3] & 0x04 == 0 {
+            return Err(invalid("not a BGZF block"));
+        }
+        let xlen = u16::from_le_bytes([header[10], header[11]]) as usize;
+        let mut extra = vec![0u8; xlen];
+        self.inner.read_exact(&mut extra)?;
+        let mut bsize = None;
+        let mut i = 0;
+        while i + 4 <= extra.len() {
+            let slen = u16::from_le_bytes([extra[i + 2], extra[i + 3]]) as usize;
+            if extra[i] == b'B' && extra[i + 1] == b'C' && slen == 2 && i + 6 <= extra.len() {
+                bsize = Some(u16::from_le_bytes([extra[i + 4], extra[i + 5]]) as usize + 1);
+            }
+            i += 4 + slen;
+        }
+        let bsize = bsize.ok_or_else(|| invalid("BGZF block without BC field"))?;
+        let cdata_len = bsize
+            .checked_sub(12 + xlen + 8)
+            .ok_or_else(|| invalid("invalid BGZF block size"))?;
+        let mut cdata = vec![0u8; cdata_len];
+        self.inner.read_exact(&mut cdata)?;
+        let mut trailer = [0u8; 8];
+        self.inner.read_exact(&mut trailer)?;
+        let crc32 = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+        let isize = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]) as usize;
+        if isize > 0x10000 {
+            return Err(invalid("BGZF block ISIZE over 64 KiB"));
+        }
+
+        self.block.clear();
+        self.block.reserve(isize);
+        DeflateDecoder::new(&cdata[..]).read_to_end(&mut self.block)?;
+        if self.block.len() != isize {
+            return Err(invalid("BGZF block ISIZE mismatch"));
+        }
+        let mut crc = Crc::new();
+        crc.update(&self.block);
+        if crc.sum() != crc32 {
+            return Err(invalid("BGZF block CRC32 mismatch"));
+        }
+        self.block_offset = self.next_block_offset;
+        self.next_block_offset += bsize as u64;
+        self.block_pos = 0;
+        Ok(true)
+    }
+
+    /// Append the next line (without its `\n` or `\r\n`) to `line`.
+    /// Returns `Ok(false)` at end of file.
+    pub(crate) fn read_line(&mut self, line: &mut Vec<u8>) -> io::Result<bool> {
+        let mut any = false;
+        loop {
+            if self.block_pos >= self.block.len() && !self.load_block()? {
+                break;
+            }
+            if self.block.is_empty() {
+                continue;
+            }
+            any = true;
+            let rest = &self.block[self.block_pos..];
+            match rest.iter().position(|&b| b == b'\n') {
+                Some(i) => {
+                    line.extend_from_slice(&rest[..i]);
+                    self.block_pos += i + 1;
+                    break;
+                }
+                None => {
+                    line.extend_from_slice(rest);
+                    self.block_pos = self.block.len();
+                }
+            }
+        }
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        Ok(any)
+    }
+
+    /// Fill `buf` completely; returns `Ok(false)` on a clean EOF before the
+    /// first byte, and an error on a partial read.
+    pub(crate) fn read_exact_or_eof(&mut self, buf: &mut [u8]) -> io::Result<bool> {
+        let n = read_full(self, buf)?;
+        if n == 0 {
+            Ok(false)
+        } else if n < buf.len() {
+            Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated BGZF record"))
+        } else {
+            Ok(true)
+        }
+    }
+}
+
+impl<R: Read + Seek> Read for BgzfReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.block_pos >= self.block.len() {
+            if !self.load_block()? {
+                return Ok(0);
+            }
+        }
+        let n = buf.len().min(self.block.len() - self.block_pos);
+        buf[..n].copy_from_slice(&self.block[self.block_pos..self.block_pos + n]);
+        self.block_pos += n;
+        Ok(n)
+    }
+}
+
+/// Read until `buf` is full or EOF; returns bytes read.
+fn read_full<R: Read + ?Sized>(r: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match r.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+/// BGZF writer that tracks virtual offsets for index building.
+pub(crate) struct BgzfWriter<W: Write> {
+    inner: W,
+    buf: Vec<u8>,
+    compressed_offset: u64,
+}
+
+impl<W: Write> BgzfWriter<W> {
+    pub(crate) fn new(inner: W) -> Self {
+        Self {
+            inner,
+            buf: Vec::with_capacity(MAX_BLOCK_DATA),
+            compressed_offset: 0,
+        }
+    }
+
+    /// Virtual offset at which the next written byte will land.
+    pub(crate) fn virtual_offset(&self) -> u64 {
+        (self.compressed_offset << 16) | self.buf.len() as u64
+    }
+
+    fn flush_block(&mut self) -> io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&self.buf)?;
+        let mut cdata = encoder.finish()?;
+        if cdata.len() + 26 > 0x10000 {
+            let mut stored = DeflateEncoder::new(Vec::new(), Compression::none());
+            stored.write_all(&self.buf)?;
+            cdata = stored.finish()?;
+        }
+        let mut crc = Crc::new();
+        crc.update(&self.buf);
+        let bsize = (cdata.len() + 25) as u16;
+
+        let mut block = Vec::with_capacity(cdata.len() + 26);
+        block.extend_from_slice(&[0x1f, 0x8b, 0x08, 0x04, 0, 0, 0, 0, 0, 0xff, 6, 0, b'B', b'C', 2, 0]);
+        block.extend_from_slice(&bsize.to_le_bytes());
+        block.extend_from_slice(&cdata);
+        block.extend_from_slice(&crc.sum().to_le_bytes());
+        block.extend_from_slice(&(self.buf.len() as u32).to_le_bytes());
+        self.inner.write_all(&block)?;
+        self.compressed_offset += block.len() as u64;
+        self.buf.clear();
+        Ok(())
+    }
+
+    /// Flush pending data, append the EOF marker and return the inner writer.
+    pub(crate) fn finish(mut self) -> io::Result<W> {
+        self.flush_block()?;
+        self.inner.write_all(&EOF_BLOCK)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for BgzfWriter<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let n = data.len().min(MAX_BLOCK_DATA - self.buf.len());
+        self.buf.extend_from_slice(&data[..n]);
+        if self.buf.len() >= MAX_BLOCK_DATA {
+            self.flush_block()?;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flush_block()?;
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn compressed(data: &[u8]) -> Vec<u8> {
+        let mut writer = BgzfWriter::new(Vec::new());
+        writer.write_all(data).unwrap();
+        writer.finish().unwrap()
+    }
+
+    fn read_all(bytes: Vec<u8>) -> io::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        BgzfReader::new(Cursor::new(bytes)).read_to_end(&mut out)?;
+        Ok(out)
+    }
+
+    #[test]
+    fn round_trips_across_blocks() {
+        let data: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        assert_eq!(read_all(compressed(&data)).unwrap(), data);
+    }
+
+    #[test]
+    fn rejects_bad_crc_and_isize() {
+        let good = compressed(b"ACGTACGTACGT\n");
+        let trailer = good.len() - EOF_BLOCK.len() - 8;
+
+        let mut bad_crc = good.clone();
+        bad_crc[trailer] ^= 0xff;
+        let err = read_all(bad_crc).unwrap_err();
+        assert!(err.to_string().contains("CRC32"), "{err}");
+
+        let mut bad_isize = good;
+        bad_isize[trailer + 4] += 1;
+        let err = read_all(bad_isize).unwrap_err();
+        assert!(err.to_string().contains("ISIZE"), "{err}");
+    }
+}
@@ -0,0 +1,939 @@
+//! bigWig region queries, zoom-level summaries and writing.
+//!
+//! Queries follow the chromosome B+ tree and the R-tree indexes to the
+//! zlib-compressed data and zoom blocks overlapping a region, so a track
+//! viewer can fetch values or per-bin summaries without reading the whole
+//! file. The writer produces version 4 files (as `bedGraphToBigWig` does)
+//! from sorted, non-overlapping bedGraph records, with zoom levels
+//! precomputed.
+
+use crate::cursor::Cursor;
+use crate::reader::open_input;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+
+const BIGWIG_MAGIC: u32 = 0x888F_FC26;
+const CHROM_TREE_MAGIC: u32 = 0x78CA_8C91;
+const R_TREE_MAGIC: u32 = 0x2468_ACE0;
+
+/// Items per R-tree / B+ tree node and records per data block, the
+/// `bedGraphToBigWig` defaults.
+const BLOCK_SIZE: usize = 256;
+const ITEMS_PER_SLOT: usize = 1024;
+const MAX_ZOOM_LEVELS: usize = 10;
+const ZOOM_INCREMENT: u64 = 4;
+
+const HEADER_SIZE: u64 = 64;
+const ZOOM_HEADER_SIZE: u64 = 24;
+const SECTION_HEADER_SIZE: usize = 24;
+const ZOOM_RECORD_SIZE: usize = 32;
+
+const SECTION_BEDGRAPH: u8 = 1;
+const SECTION_VARSTEP: u8 = 2;
+const SECTION_FIXEDSTEP: u8 = 3;
+
+/// Summary of the values over some bases: the bigWig zoom record payload.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Summary {
+    /// Bases with data. Fractional when prorated from zoom records.
+    pub bases: f64,
+    pub min: f64,
+    pub max: f64,
+    pub sum: f64,
+    pub sum_squares: f64,
+}
+
+impl Default for Summary {
+    fn default() -> Self {
+        Self {
+            bases: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            sum: 0.0,
+            sum_squares: 0.0,
+        }
+    }
+}
+
+impl Summary {
+    fn add_value(&mut self, value: f64, bases: f64) {
+        self.add(&Summary {
+            bases,
+            min: value,
+            max: value,
+            sum: value * bases,
+            sum_squares: value * value * bases,
+        });
+    }
+
+    fn add(&mut self, other: &Summary) {
+        self.bases += other.bases;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.sum += other.sum;
+        self.sum_squares += other.sum_squares;
+    }
+
+    pub(crate) fn mean(&self) -> Option<f64> {
+        (self.bases > 0.0).then(|| self.sum / self.bases)
+    }
+}
+
+/// One summary bin of a region: `(start, end, summary)`.
+pub(crate) type Bin = (u64, u64, Summary);
+
+/// A position in the R-tree's `(chrom id, base)` key space.
+type Key = (u32, u32);
+
+/// A zoom record: `(chrom id, start, end, summary)`.
+type ZoomRecord = (u32, u32, u32, Summary);
+
+/// Random-access bigWig reader (little-endian files).
+pub(crate) struct BigWigReader {
+    file: BufReader<File>,
+    path: String,
+    compressed: bool,
+    full_index_offset: u64,
+    /// `(reduction level, R-tree offset)` per zoom level.
+    zooms: Vec<(u32, u64)>,
+    /// Name -> `(chrom id, size)`.
+    chroms: HashMap<String, (u32, u32)>,
+}
+
+impl BigWigReader {
+    pub(crate) fn open(path: &str) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("{path}: {e}"))?;
+        let mut reader = Self {
+            file: BufReader::new(file),
+            path: path.to_string(),
+            compressed: false,
+            full_index_offset: 0,
+            zooms: Vec::new(),
+            chroms: HashMap::new(),
+        };
+        let header = reader.read_at(0, HEADER_SIZE as usize)?;
+        let mut c = Cursor::new(&header, "bigWig header");
+        match c.u32()? {
+            BIGWIG_MAGIC => {}
+            m if m.swap_bytes() == BIGWIG_MAGIC => {
+                return Err(format!("{path}: big-endian bigWig files are not supported"))
+            }
+            _ => return Err(format!("{path}: not a bigWig file")),
+        }
+        let _version = c.u16()?;
+        let zoom_levels = c.u16()? as usize;
+        let chrom_tree_offset = c.u64()?;
+        let _full_data_offset = c.u64()?;
+        reader.full_index_offset = c.u64()?;
+        c.take(2 + 2 + 8 + 8)?;
+        reader.compressed = c.u32()? > 0;
+
+        let zoom_headers = reader.read_at(HEADER_SIZE, zoom_levels * ZOOM_HEADER_SIZE as usize)?;
+        let mut c = Cursor::new(&zoom_headers, "bigWig header");
+        for _ in 0..zoom_levels {
+            let reduction = c.u32()?;
+            let _reserved = c.u32()?;
+            let _data_offset = c.u64()?;
+            reader.zooms.push((reduction, c.u64()?));
+        }
+        reader.read_chrom_tree(chrom_tree_offset)?;
+        Ok(reader)
+    }
+
+    fn read_at(&mut self, offset: u64, len: usize) -> Result<Vec<u8>, String> {
+        let mut buf = vec![0; len];
+        self.file
+            .seek(SeekFrom::Start(offset))
+            .and_then(|_| self.file.read_exact(&mut buf))
+            .map_err(|e| format!("{}: {e}", self.path))?;
+        Ok(buf)
+    }
+
+    fn read_chrom_tree(&mut self, offset: u64) -> Result<(), String> {
+        let header = self.read_at(offset, 32)?;
+        let mut c = Cursor::new(&header, "bigWig chromosome tree");
+        if c.u32()? != CHROM_TREE_MAGIC {
+            return Err(format!("{}: bad chromosome tree", self.path));
+        }
+        let _block_size = c.u32()?;
+        let key_size = c.u32()? as usize;
+        let val_size = c.u32()? as usize;
+        if val_size != 8 {
+            return Err(format!("{}: bad chromosome tree", self.path));
+        }
+        let mut stack = vec![offset + 32];
+        while let Some(node) = stack.pop() {
+            let head = self.read_at(node, 4)?;
+            let (is_leaf, count) = (
+                head[0] != 0,
+                u16::from_le_bytes([head[2], head[3]]) as usize,
+            );
+            let items = self.read_at(node + 4, count * (key_size + 8))?;
+            let mut c = Cursor::new(&items, "bigWig chromosome tree");
+            for _ in 0..count {
+                let key = c.take(key_size)?;
+                let name_len = key.iter().position(|&b| b == 0).unwrap_or(key_size);
+                if is_leaf {
+                    let name = String::from_utf8_lossy(&key[..name_len]).into_owned();
+                    let id = c.u32()?;
+                    self.chroms.insert(name, (id, c.u32()?));
+                } else {
+                    stack.push(c.u64()?);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn chrom(&self, chrom: &str) -> Result<(u32, u32), String> {
+        self.chroms
+            .get(chrom)
+            .copied()
+            .ok_or_else(|| format!("{}: chromosome {chrom} not found", self.path))
+    }
+
+    /// `(offset, size)` of the blocks under the R-tree at `index_offset`
+    /// that overlap `start..end` on chromosome `id`.
+    fn blocks(
+        &mut self,
+        index_offset: u64,
+        id: u32,
+        start: u32,
+        end: u32,
+    ) -> Result<Vec<(u64, u64)>, String> {
+        let header = self.read_at(index_offset, 48)?;
+        if Cursor::new(&header, "bigWig index").u32()? != R_TREE_MAGIC {
+            return Err(format!("{}: bad R-tree index", self.path));
+        }
+        let mut found = Vec::new();
+        let mut stack = vec![index_offset + 48];
+        while let Some(node) = stack.pop() {
+            let head = self.read_at(node, 4)?;
+            let (is_leaf, count) = (
+                head[0] != 0,
+                u16::from_le_bytes([head[2], head[3]]) as usize,
+            );
+            let item_size = if is_leaf { 32 } else { 24 };
+            let items = self.read_at(node + 4, count * item_size)?;
+            let mut c = Cursor::new(&items, "bigWig index");
+            for _ in 0..count {
+                let lo = (c.u32()?, c.u32()?);
+                let hi = (c.u32()?, c.u32()?);
+                let overlaps = (id, start) < hi && (id, end) > lo;
+                if is_leaf {
+                    let (offset, size) = (c.u64()?, c.u64()?);
+                    if overlaps {
+                        found.push((offset, size));
+                    }
+                } else {
+                    let child = c.u64()?;
+                    if overlaps {
+                        stack.push(child);
+                    }
+                }
+            }
+        }
+        found.sort_unstable();
+        Ok(found)
+    }
+
+    fn block(&mut self, offset: u64, size: u64) -> Result<Vec<u8>, String> {
+        let raw = self.read_at(offset, size as usize)?;
+        if !self.compressed {
+            return Ok(raw);
+        }
+        let mut data = Vec::new();
+        ZlibDecoder::new(&raw[..])
+            .read_to_end(&mut data)
+            .map_err(|e| format!("{}: {e}", self.path))?;
+        Ok(data)
+    }
+
+    /// Data records `(start, end, value)` overlapping `start..end`,
+    /// unclipped, in position order.
+    pub(crate) fn intervals(
+        &mut self,
+        chrom: &str,
+        start: u64,
+        end: u64,
+    ) -> Result<Vec<(u64, u64, f32)>, String> {
+        let (id, size) = self.chrom(chrom)?;
+        let (start, end) = (start.min(size as u64) as u32, end.min(size as u64) as u32);
+        let mut out = Vec::new();
+        if start >= end {
+            return Ok(out);
+        }
+        for (offset, len) in self.blocks(self.full_index_offset, id, start, end)? {
+            let data = self.block(offset, len)?;
+            let mut c = Cursor::new(&data, "bigWig block");
+            while c.remaining() >= SECTION_HEADER_SIZE {
+                let section_chrom = c.u32()?;
+                let section_start = c.u32()?;
+                let _section_end = c.u32()?;
+                let step = c.u32()?;
+                let span = c.u32()?;
+                let kind = c.u8()?;
+                let _reserved = c.u8()?;
+                let count = c.u16()?;
+                for i in 0..count as u32 {
+                    let (s, e, v) = match kind {
+                        SECTION_BEDGRAPH => (c.u32()?, c.u32()?, c.f32()?),
+                        SECTION_VARSTEP => {
+                            let s = c.u32()?;
+                            (s, s.saturating_add(span), c.f32()?)
+                        }
+                        SECTION_FIXEDSTEP => {
+                            let s = section_start.saturating_add(i.saturating_mul(step));
+                            (s, s.saturating_add(span), c.f32()?)
+                        }
+                        other => {
+                            return Err(format!("{}: unknown section type {other}", self.path))
+                        }
+                    };
+                    if section_chrom == id && s < end && e > start {
+                        out.push((s as u64, e as u64, v));
+                    }
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    /// Zoom records overlapping `start..end` at the level with R-tree
+    /// `index_offset`, as `(start, end, summary)`.
+    fn zoom_records(
+        &mut self,
+        index_offset: u64,
+        id: u32,
+        start: u32,
+        end: u32,
+    ) -> Result<Vec<Bin>, String> {
+        let mut out = Vec::new();
+        for (offset, len) in self.blocks(index_offset, id, start, end)? {
+            let data = self.block(offset, len)?;
+            let mut c = Cursor::new(&data, "bigWig block");
+            while c.remaining() >= ZOOM_RECORD_SIZE {
+                let chrom = c.u32()?;
+                let (s, e) = (c.u32()?, c.u32()?);
+                let summary = Summary {
+                    bases: c.u32()? as f64,
+                    min: c.f32()? as f64,
+                    max: c.f32()? as f64,
+                    sum: c.f32()? as f64,
+                    sum_squares: c.f32()? as f64,
+                };
+                if chrom == id && s < end && e > start {
+                    out.push((s as u64, e as u64, summary));
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    /// Split `start..end` into `bins` equal bins and summarize each. Unless
+    /// `exact`, uses the coarsest zoom level whose resolution is at most
+    /// half a bin (as the UCSC browser does), prorating zoom records that
+    /// straddle a bin edge; otherwise reads the full-resolution data.
+    pub(crate) fn summary(
+        &mut self,
+        chrom: &str,
+        start: u64,
+        end: u64,
+        bins: usize,
+        exact: bool,
+    ) -> Result<Vec<Bin>, String> {
+        let (id, size) = self.chrom(chrom)?;
+        let end = end.min(size as u64);
+        if bins == 0 || start >= end {
+            return Err(format!("{chrom}:{start}-{end}: empty region or no bins"));
+        }
+        let span = end - start;
+        let edges: Vec<u64> = (0..=bins as u64)
+            .map(|i| start + i * span / bins as u64)
+            .collect();
+        let mut out: Vec<Bin> = edges
+            .windows(2)
+            .map(|w| (w[0], w[1], Summary::default()))
+            .collect();
+
+        let desired = span / bins as u64 / 2;
+        let zoom = self
+            .zooms
+            .iter()
+            .filter(|&&(r, _)| !exact && desired > 1 && r as u64 <= desired)
+            .max_by_key(|&&(r, _)| r)
+            .copied();
+        let records = match zoom {
+            Some((_, index)) => self.zoom_records(index, id, start as u32, end as u32)?,
+            None => self
+                .intervals(chrom, start, end)?
+                .into_iter()
+                .map(|(s, e, v)| {
+                    let mut summary = Summary::default();
+                    summary.add_value(v as f64, (e - s) as f64);
+                    (s, e, summary)
+                })
+                .collect(),
+        };
+
+        for (s, e, summary) in records {
+            // First bin whose end is past `s`.
+            let first = edges[1..].partition_point(|&edge| edge <= s);
+            for (bs, be, bin) in out[first.min(bins)..].iter_mut() {
+                if *bs >= e {
+                    break;
+                }
+                let overlap = e.min(*be).saturating_sub(s.max(*bs));
+                if overlap == 0 {
+                    continue;
+                }
+                let factor = overlap as f64 / (e - s) as f64;
+                bin.add(&Summary {
+                    bases: summary.bases * factor,
+                    min: summary.min,
+                    max: summary.max,
+                    sum: summary.sum * factor,
+                    sum_squares: summary.sum_squares * factor,
+                });
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// Read bedGraph records `(chrom, start, end, value)` from a plain or
+/// compressed file, one line at a time. `track`, `browser` and `#` lines
+/// are skipped.
+pub(crate) fn read_bedgraph(path: &str) -> Result<Vec<(String, u64, u64, f64)>, String> {
+    let mut input = open_input(path)?;
+    let mut records = Vec::new();
+    let mut line = String::new();
+    let mut line_no = 0u64;
+    loop {
+        line.clear();
+        if input
+            .read_line(&mut line)
+            .map_err(|e| format!("{path}: {e}"))?
+            == 0
+        {
+            break;
+        }
+        line_no += 1;
+        let l = line.trim_end_matches(['\n', '\r']);
+        if l.trim().is_empty()
+            || l.starts_with('#')
+            || l.starts_with("track")
+            || l.starts_with("browser")
+        {
+            continue;
+        }
+        let bad = || format!("{path}: line {line_no}: expected chrom, start, end and value");
+        let mut cols = l.split('\t');
+        let (Some(chrom), Some(start), Some(end), Some(value)) =
+            (cols.next(), cols.next(), cols.next(), cols.next())
+        else {
+            return Err(bad());
+        };
+        records.push((
+            chrom.to_string(),
+            start.parse().map_err(|_| bad())?,
+            end.parse().map_err(|_| bad())?,
+            value.trim().parse().map_err(|_| bad())?,
+        ));
+    }
+    Ok(records)
+}
+
+/// One record as stored: chromosome id, 0-based half-open span, value.
+#[derive(Debug, Clone, Copy)]
+struct Item {
+    chrom: u32,
+    start: u32,
+    end: u32,
+    value: f32,
+}
+
+/// Leaf entry of an R-tree: the span a block covers and where it is.
+#[derive(Debug, Clone, Copy)]
+struct IndexItem {
+    lo: Key,
+    hi: Key,
+    offset: u64,
+    size: u64,
+}
+
+/// Write a bigWig from bedGraph records `(chrom, start, end, value)`.
+/// Every chromosome must be in `chrom_sizes`; records are sorted here but
+/// must not overlap. Returns the number of records written.
+pub(crate) fn write(
+    path: &str,
+    records: &[(String, u64, u64, f64)],
+    chrom_sizes: &[(String, u64)],
+) -> Result<usize, String> {
+    // Chromosome ids follow name order, as the B+ tree requires.
+    let mut chroms: Vec<(&str, u32)> = Vec::with_capacity(chrom_sizes.len());
+    for (name, size) in chrom_sizes {
+        let size = u32::try_from(*size).map_err(|_| format!("{name}: too long for bigWig"))?;
+        chroms.push((name, size));
+    }
+    chroms.sort_unstable();
+    chroms.dedup_by(|a, b| a.0 == b.0);
+    let ids: HashMap<&str, (u32, u32)> = chroms
+        .iter()
+        .enumerate()
+        .map(|(i, &(name, size))| (name, (i as u32, size)))
+        .collect();
+
+    let mut items = Vec::with_capacity(records.len());
+    for (chrom, start, end, value) in records {
+        let &(id, size) = ids
+            .get(chrom.as_str())
+            .ok_or_else(|| format!("{chrom} is not in the chromosome sizes"))?;
+        if start >= end || *end > size as u64 {
+            return Err(format!(
+                "{chrom}:{start}-{end}: empty or outside the chromosome ({size} bp)"
+            ));
+        }
+        items.push(Item {
+            chrom: id,
+            start: *start as u32,
+            end: *end as u32,
+            value: *value as f32,
+        });
+    }
+    items.sort_unstable_by_key(|i| (i.chrom, i.start, i.end));
+    if let Some(w) = items
+        .windows(2)
+        .find(|w| w[0].chrom == w[1].chrom && w[1].start < w[0].end)
+    {
+        return Err(format!(
+            "{}:{}-{} overlaps the previous record",
+            chroms[w[1].chrom as usize].0, w[1].start, w[1].end
+        ));
+    }
+
+    let mut total = Summary::default();
+    for i in &items {
+        total.add_value(i.value as f64, (i.end - i.start) as f64);
+    }
+    let zooms = zoom_levels(&items);
+
+    let file = File::create(path).map_err(|e| format!("{path}: {e}"))?;
+    let mut out = BigWigWriter {
+        out: BufWriter::with_capacity(1 << 16, file),
+        pos: 0,
+        max_block: 0,
+    };
+    let io = |e: std::io::Error| format!("{path}: {e}");
+    out.put(&vec![
+        0;
+        (HEADER_SIZE + zooms.len() as u64 * ZOOM_HEADER_SIZE)
+            as usize
+    ])
+    .map_err(io)?;
+
+    let total_summary_offset = out.pos;
+    let mut buf = Vec::with_capacity(40);
+    buf.extend((total.bases as u64).to_le_bytes());
+    for v in [total.min, total.max, total.sum, total.sum_squares] {
+        buf.extend((if total.bases > 0.0 { v } else { 0.0 }).to_le_bytes());
+    }
+    out.put(&buf).map_err(io)?;
+
+    let chrom_tree_offset = out.pos;
+    out.chrom_tree(&chroms).map_err(io)?;
+
+    let data_offset = out.pos;
+    let sections = sections(&items);
+    out.put(&(sections.len() as u64).to_le_bytes())
+        .map_err(io)?;
+    let mut index = Vec::with_capacity(sections.len());
+    for (lo, hi, bytes) in &sections {
+        index.push(out.block(*lo, *hi, bytes).map_err(io)?);
+    }
+    let index_offset = out.pos;
+    out.r_tree(&index, ITEMS_PER_SLOT).map_err(io)?;
+
+    let mut zoom_headers = Vec::with_capacity(zooms.len() * ZOOM_HEADER_SIZE as usize);
+    for (reduction, records) in &zooms {
+        let zoom_data_offset = out.pos;
+        out.put(&(records.len() as u32).to_le_bytes()).map_err(io)?;
+        let mut index = Vec::new();
+        for chunk in chunk_by_chrom(records, |r| r.0) {
+            let mut bytes = Vec::with_capacity(chunk.len() * ZOOM_RECORD_SIZE);
+            for (chrom, s, e, summary) in chunk {
+                bytes.extend(chrom.to_le_bytes());
+                bytes.extend(s.to_le_bytes());
+                bytes.extend(e.to_le_bytes());
+                bytes.extend((summary.bases as u32).to_le_bytes());
+                for v in [summary.min, summary.max, summary.sum, summary.sum_squares] {
+                    bytes.extend((v as f32).to_le_bytes());
+                }
+            }
+            let (first, last) = (chunk[0], chunk[chunk.len() - 1]);
+            index.push(
+                out.block((first.0, first.1), (last.0, last.2), &bytes)
+                    .map_err(io)?,
+            );
+        }
+        let zoom_index_offset = out.pos;
+        out.r_tree(&index, ITEMS_PER_SLOT).map_err(io)?;
+        zoom_headers.extend(reduction.to_le_bytes());
+        zoom_headers.extend(0u32.to_le_bytes());
+        zoom_headers.extend(zoom_data_offset.to_le_bytes());
+        zoom_headers.extend(zoom_index_offset.to_le_bytes());
+    }
+    out.put(&BIGWIG_MAGIC.to_le_bytes()).map_err(io)?;
+
+    let mut header = Vec::with_capacity(HEADER_SIZE as usize);
+    header.extend(BIGWIG_MAGIC.to_le_bytes());
+    header.extend(4u16.to_le_bytes());
+    header.extend((zooms.len() as u16).to_le_bytes());
+    header.extend(chrom_tree_offset.to_le_bytes());
+    header.extend(data_offset.to_le_bytes());
+    header.extend(index_offset.to_le_bytes());
+    header.extend(0u16.to_le_bytes()); // field count
+    header.extend(0u16.to_le_bytes()); // defined field count
+    header.extend(0u64.to_le_bytes()); // autoSql offset
+    header.extend(total_summary_offset.to_le_bytes());
+    header.extend((out.max_block as u32).to_le_bytes());
+    header.extend(0u64.to_le_bytes()); // extension offset
+    header.extend(zoom_headers);
+    out.out
+        .seek(SeekFrom::Start(0))
+        .and_then(|_| out.out.write_all(&header))
+        .and_then(|_| out.out.flush())
+        .map_err(io)?;
+    Ok(items.len())
+}
+
+/// Runs of at most `ITEMS_PER_SLOT` consecutive records on one chromosome.
+fn chunk_by_chrom<T, F: Fn(&T) -> u32>(records: &[T], chrom: F) -> Vec<&[T]> {
+    let mut chunks = Vec::new();
+    let mut rest = records;
+    while !rest.is_empty() {
+        let id = chrom(&rest[0]);
+        let n = rest
+            .iter()
+            .take(ITEMS_PER_SLOT)
+            .take_while(|r| chrom(r) == id)
+            .count();
+        let (chunk, tail) = rest.split_at(n);
+        chunks.push(chunk);
+        rest = tail;
+    }
+    chunks
+}
+
+/// Uncompressed bedGraph sections with the span each covers.
+fn sections(items: &[Item]) -> Vec<(Key, Key, Vec<u8>)> {
+    chunk_by_chrom(items, |i| i.chrom)
+        .into_iter()
+        .map(|chunk| {
+            let (first, last) = (chunk[0], chunk[chunk.len() - 1]);
+            let mut bytes = Vec::with_capacity(SECTION_HEADER_SIZE + chunk.len() * 12);
+            bytes.extend(first.chrom.to_le_bytes());
+            bytes.extend(first.start.to_le_bytes());
+            bytes.extend(last.end.to_le_bytes());
+            bytes.extend(0u32.to_le_bytes()); // item step
+            bytes.extend(0u32.to_le_bytes()); // item span
+            bytes.push(SECTION_BEDGRAPH);
+            bytes.push(0);
+            bytes.extend((chunk.len() as u16).to_le_bytes());
+            for i in chunk {
+                bytes.extend(i.start.to_le_bytes());
+                bytes.extend(i.end.to_le_bytes());
+                bytes.extend(i.value.to_le_bytes());
+            }
+            ((first.chrom, first.start), (first.chrom, last.end), bytes)
+        })
+        .collect()
+}
+
+/// Zoom records `(chrom, start, end, summary)` over bins of `reduction`
+/// bases aligned to the chromosome start, each trimmed to the data in it.
+fn zoom_records(items: &[Item], reduction: u64) -> Vec<ZoomRecord> {
+    let mut out: Vec<ZoomRecord> = Vec::new();
+    for item in items {
+        let mut s = item.start as u64;
+        while s < item.end as u64 {
+            let k = s / reduction;
+            let bin_end = ((k + 1) * reduction).min(item.end as u64);
+            let bases = (bin_end - s) as f64;
+            match out.last_mut() {
+                Some((c, bs, be, summary)) if *c == item.chrom && *bs as u64 / reduction == k => {
+                    *be = bin_end as u32;
+                    summary.add_value(item.value as f64, bases);
+                }
+                _ => {
+                    let mut summary = Summary::default();
+                    summary.add_value(item.value as f64, bases);
+                    out.push((item.chrom, s as u32, bin_end as u32, summary));
+                }
+            }
+            s = bin_end;
+        }
+    }
+    out
+}
+
+/// Zoom levels to store: the first at ten times the mean record span,
+/// each next one `ZOOM_INCREMENT` times coarser, stopping once a level no
+/// longer halves the record count.
+fn zoom_levels(items: &[Item]) -> Vec<(u32, Vec<ZoomRecord>)> {
+    if items.is_empty() {
+        return Vec::new();
+    }
+    let bases: u64 = items.iter().map(|i| (i.end - i.start) as u64).sum();
+    let mut reduction = (bases / items.len() as u64 * 10).max(10);
+    let mut previous = items.len();
+    let mut levels = Vec::new();
+    while levels.len() < MAX_ZOOM_LEVELS && reduction <= u32::MAX as u64 {
+        let records = zoom_records(items, reduction);
+        if records.len() * 2 > previous {
+            break;
+        }
+        previous = records.len();
+        levels.push((reduction as u32, records));
+        reduction *= ZOOM_INCREMENT;
+    }
+    levels
+}
+
+struct BigWigWriter {
+    out: BufWriter<File>,
+    pos: u64,
+    /// Largest uncompressed block, recorded in the header for readers.
+    max_block: usize,
+}
+
+impl BigWigWriter {
+    fn put(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        self.out.write_all(bytes)?;
+        self.pos += bytes.len() as u64;
+        Ok(())
+    }
+
+    /// Compress and write one data or zoom block.
+    fn block(&mut self, lo: Key, hi: Key, bytes: &[u8]) -> std::io::Result<IndexItem> {
+        self.max_block = self.max_block.max(bytes.len());
+        let mut z = ZlibEncoder::new(Vec::new(), Compression::default());
+        z.write_all(bytes)?;
+        let compressed = z.finish()?;
+        let offset = self.pos;
+        self.put(&compressed)?;
+        Ok(IndexItem {
+            lo,
+            hi,
+            offset,
+            size: compressed.len() as u64,
+        })
+    }
+
+    /// Chromosome B+ tree: keys are names zero-padded to the longest one,
+    /// values `(id, size)`. Nodes are padded to full size, root first.
+    fn chrom_tree(&mut self, chroms: &[(&str, u32)]) -> std::io::Result<()> {
+        let key_size = chroms.iter().map(|c| c.0.len()).max().unwrap_or(1).max(1);
+        let block = chroms.len().clamp(1, BLOCK_SIZE);
+        let mut header = Vec::with_capacity(32);
+        header.extend(CHROM_TREE_MAGIC.to_le_bytes());
+        header.extend((block as u32).to_le_bytes());
+        header.extend((key_size as u32).to_le_bytes());
+        header.extend(8u32.to_le_bytes());
+        header.extend((chroms.len() as u64).to_le_bytes());
+        header.extend(0u64.to_le_bytes());
+        self.put(&header)?;
+
+        let key = |name: &str| {
+            let mut k = name.as_bytes().to_vec();
+            k.resize(key_size, 0);
+            k
+        };
+        let node_size = (4 + block * (key_size + 8)) as u64;
+        let levels = level_widths(chroms.len(), block);
+        let mut level_offset = self.pos;
+        for (depth, &width) in levels.iter().enumerate().rev() {
+            // Items per node at this level; children live one level down.
+            let stride = block.pow(depth as u32);
+            let child_offset = level_offset + width as u64 * node_size;
+            for node in 0..width {
+                let first = node * block * stride;
+                let count = if depth == 0 {
+                    chroms.len().saturating_sub(first).min(block)
+                } else {
+                    (chroms.len() - first).div_ceil(stride).min(block)
+                };
+                let mut bytes = vec![(depth == 0) as u8, 0];
+                bytes.extend((count as u16).to_le_bytes());
+                for slot in 0..count {
+                    let (name, size) = chroms[first + slot * stride];
+                    bytes.extend(key(name));
+                    if depth == 0 {
+                        bytes.extend(((first + slot) as u32).to_le_bytes());
+                        bytes.extend(size.to_le_bytes());
+                    } else {
+                        let child = node * block + slot;
+                        bytes.extend((child_offset + child as u64 * node_size).to_le_bytes());
+                    }
+                }
+                bytes.resize(node_size as usize, 0);
+                self.put(&bytes)?;
+            }
+            level_offset = child_offset;
+        }
+        Ok(())
+    }
+
+    /// R-tree (UCSC "cirTree") over `items`, root first, nodes padded to
+    /// full size.
+    fn r_tree(&mut self, items: &[IndexItem], items_per_slot: usize) -> std::io::Result<()> {
+        let end_offset = self.pos;
+        let lo = items.iter().map(|i| i.lo).min().unwrap_or_default();
+        let hi = items.iter().map(|i| i.hi).max().unwrap_or_default();
+        let mut header = Vec::with_capacity(48);
+        header.extend(R_TREE_MAGIC.to_le_bytes());
+        header.extend((BLOCK_SIZE as u32).to_le_bytes());
+        header.extend((items.len() as u64).to_le_bytes());
+        for v in [lo.0, lo.1, hi.0, hi.1] {
+            header.extend(v.to_le_bytes());
+        }
+        header.extend(end_offset.to_le_bytes());
+        header.extend((items_per_slot as u32).to_le_bytes());
+        header.extend(0u32.to_le_bytes());
+        self.put(&header)?;
+
+        // Bounding boxes per level, leaves (the items themselves) first.
+        let mut levels: Vec<Vec<(Key, Key)>> = vec![items.iter().map(|i| (i.lo, i.hi)).collect()];
+        while levels.last().unwrap().len() > BLOCK_SIZE {
+            let next = levels
+                .last()
+                .unwrap()
+                .chunks(BLOCK_SIZE)
+                .map(|c| {
+                    let lo = c.iter().map(|b| b.0).min().unwrap();
+                    let hi = c.iter().map(|b| b.1).max().unwrap();
+                    (lo, hi)
+                })
+                .collect::<Vec<_>>();
+            levels.push(next);
+        }
+        // `levels[d]` holds the entries of the nodes at height `d`; the
+        // root holds the last level's entries.
+        let node_size = |leaf: bool| (4 + BLOCK_SIZE * if leaf { 32 } else { 24 }) as u64;
+        let mut level_offset = self.pos;
+        for depth in (0..levels.len()).rev() {
+            let entries = &levels[depth];
+            let leaf = depth == 0;
+            let nodes = entries.len().div_ceil(BLOCK_SIZE).max(1);
+            let child_offset = level_offset + nodes as u64 * node_size(leaf);
+            for node in 0..nodes {
+                let chunk = entries.chunks(BLOCK_SIZE).nth(node).unwrap_or(&[]);
+                let mut bytes = vec![leaf as u8, 0];
+                bytes.extend((chunk.len() as u16).to_le_bytes());
+                for (slot, (lo, hi)) in chunk.iter().enumerate() {
+                    for v in [lo.0, lo.1, hi.0, hi.1] {
+                        bytes.extend(v.to_le_bytes());
+                    }
+                    let at = node * BLOCK_SIZE + slot;
+                    if leaf {
+                        bytes.extend(items[at].offset.to_le_bytes());
+                        bytes.extend(items[at].size.to_le_bytes());
+                    } else {
+                        bytes.extend(
+                            (child_offset + at as u64 * node_size(depth == 1)).to_le_bytes(),
+                        );
+                    }
+                }
+                bytes.resize(node_size(leaf) as usize, 0);
+                self.put(&bytes)?;
+            }
+            level_offset = child_offset;
+        }
+        Ok(())
+    }
+}
+
+/// Node count per B+ tree level, leaves first, for `n` items and
+/// `block` items per node.
+fn level_widths(n: usize, block: usize) -> Vec<usize> {
+    let mut widths = vec![n.div_ceil(block).max(1)];
+    while *widths.last().unwrap() > 1 {
+        widths.push(widths.last().unwrap().div_ceil(block));
+    }
+    widths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bedgraph_round_trip() {
+        let dir = std::env::temp_dir().join(format!("cyanea-bigwig-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (bedgraph, bigwig) = (dir.join("in.bedGraph"), dir.join("out.bw"));
+        // chr1: 3000 10 bp records with gaps, enough for several blocks
+        // and zoom levels; chr2: one record.
+        let mut text = String::from("track type=bedGraph\n# comment\n");
+        let mut expected = Vec::new();
+        for i in 0..3000u64 {
+            let value = (i % 7) as f32 * 0.5;
+            text.push_str(&format!("chr1\t{}\t{}\t{value}\n", i * 20, i * 20 + 10));
+            expected.push((i * 20, i * 20 + 10, value));
+        }
+        text.push_str("chr2\t5\t15\t-2.25\n");
+        std::fs::write(&bedgraph, text).unwrap();
+        let sizes = [("chr1".to_string(), 100_000), ("chr2".to_string(), 1_000)];
+
+        let records = read_bedgraph(bedgraph.to_str().unwrap()).unwrap();
+        let path = bigwig.to_str().unwrap();
+        assert_eq!(write(path, &records, &sizes).unwrap(), 3001);
+
+        let mut reader = BigWigReader::open(path).unwrap();
+        assert!(!reader.zooms.is_empty());
+        assert_eq!(reader.intervals("chr1", 0, 100_000).unwrap(), expected);
+        assert_eq!(
+            reader.intervals("chr1", 25, 45).unwrap(),
+            [(20, 30, 0.5), (40, 50, 1.0)]
+        );
+        assert_eq!(
+            reader.intervals("chr2", 0, 1_000).unwrap(),
+            [(5, 15, -2.25)]
+        );
+
+        // Zoom records are pro-rated across bin edges, so only the totals
+        // match exactly.
+        let exact = reader.summary("chr1", 0, 60_000, 4, true).unwrap();
+        let zoomed = reader.summary("chr1", 0, 60_000, 4, false).unwrap();
+        let total = |bins: &[Bin]| bins.iter().map(|(_, _, b)| b.bases).sum::<f64>();
+        assert_eq!(total(&exact), 30_000.0);
+        assert!((total(&zoomed) - 30_000.0).abs() < 1e-6);
+        for ((s, e, a), (_, _, b)) in exact.iter().zip(&zoomed) {
+            assert_eq!(a.bases, 7_500.0, "{s}-{e}");
+            assert_eq!((a.min, a.max), (0.0, 3.0));
+            assert!((a.bases - b.bases).abs() < 0.01 * a.bases, "{s}-{e}");
+            assert!(
+                (a.mean().unwrap() - b.mean().unwrap()).abs() < 0.01,
+                "{s}-{e}"
+            );
+        }
+        assert!(reader.summary("chr3", 0, 10, 1, true).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_malformed_bedgraph_lines() {
+        let path = std::env::temp_dir().join(format!("cyanea-bedgraph-{}.bg", std::process::id()));
+        std::fs::write(&path, "chr1\t0\t10\t1.5\nchr1\t10\tx\t2\n").unwrap();
+        let err = read_bedgraph(path.to_str().unwrap()).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert!(
+            err.ends_with("line 2: expected chrom, start, end and value"),
+            "{err}"
+        );
+    }
+}
@@ -0,0 +1,498 @@
+//! UCSC/htslib hierarchical binning indexes (BAI, CSI and the tabix body).
+//!
+//! All three formats share the same per-reference layout: bins holding
+//! chunks of BGZF virtual offsets, plus either a linear index of 16 KiB
+//! windows (BAI/TBI) or a per-bin lowest offset (CSI). Queries collect the
+//! chunks of every bin overlapping a region and merge them, so a reader only
+//! decompresses blocks that can contain overlapping records.
+
+use crate::bgzf::BgzfWriter;
+use crate::cursor::Cursor;
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+
+/// BAI/TBI geometry: 16 KiB leaf bins, 5 levels, 512 Mbp maximum.
+pub(crate) const BAI_MIN_SHIFT: u32 = 14;
+pub(crate) const BAI_DEPTH: u32 = 5;
+
+/// A `[beg, end)` range of BGZF virtual offsets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct Chunk {
+    pub beg: u64,
+    pub end: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RefIndex {
+    /// Bin number -> (lowest record offset in bin, chunks). The offset is
+    /// only stored on disk for CSI.
+    pub bins: BTreeMap<u32, (u64, Vec<Chunk>)>,
+    /// Lowest record offset per leaf window (BAI/TBI only).
+    pub linear: Vec<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct BinningIndex {
+    pub min_shift: u32,
+    pub depth: u32,
+    pub refs: Vec<RefIndex>,
+    /// Unplaced records counted after the last reference, if recorded.
+    pub n_no_coor: Option<u64>,
+    /// Tabix header plus sequence names for TBI and tabix-CSI indexes;
+    /// empty for BAM indexes.
+    pub aux: Vec<u8>,
+}
+
+/// Bin number of the smallest bin fully containing `[beg, end)`.
+pub(crate) fn reg2bin(beg: u64, end: u64, min_shift: u32, depth: u32) -> u32 {
+    let end = end.max(beg + 1) - 1;
+    let mut s = min_shift;
+    let mut t = ((1u64 << (depth * 3)) - 1) / 7;
+    let mut l = depth;
+    while l > 0 {
+        if beg >> s == end >> s {
+            return (t + (beg >> s)) as u32;
+        }
+        l -= 1;
+        s += 3;
+        t -= 1 << (l * 3);
+    }
+    0
+}
+
+/// Every bin that may hold records overlapping `[beg, end)`.
+pub(crate) fn reg2bins(beg: u64, end: u64, min_shift: u32, depth: u32) -> Vec<u32> {
+    let end = end.max(beg + 1) - 1;
+    let mut bins = Vec::new();
+    let mut s = min_shift + depth * 3;
+    let mut t = 0u64;
+    for l in 0..=depth {
+        let b = t + (beg >> s);
+        let e = t + (end >> s).min((1u64 << (l * 3)) - 1);
+        bins.extend((b..=e).map(|x| x as u32));
+        t += 1 << (l * 3);
+        s = s.saturating_sub(3);
+    }
+    bins
+}
+
+/// First bin number past the last real bin; used for the metadata pseudo-bin.
+fn pseudo_bin(depth: u32) -> u32 {
+    (((1u64 << ((depth + 1) * 3)) - 1) / 7) as u32 + 1
+}
+
+/// Index of the leaf window where `bin` starts.
+fn first_window(bin: u32, depth: u32) -> usize {
+    let mut level_start = 0u32;
+    for l in 0..=depth {
+        let width = 1u32 << (l * 3);
+        if bin < level_start + width {
+            return ((bin - level_start) as usize) << ((depth - l) * 3);
+        }
+        level_start += width;
+    }
+    0
+}
+
+impl BinningIndex {
+    /// Largest coordinate this index geometry can address.
+    pub(crate) fn max_len(min_shift: u32, depth: u32) -> u64 {
+        1u64 << (min_shift + depth * 3)
+    }
+
+    /// Merged chunks that may contain records of reference `tid`
+    /// overlapping `[beg, end)`.
+    pub(crate) fn query(&self, tid: usize, beg: u64, end: u64) -> Vec<Chunk> {
+        let Some(r) = self.refs.get(tid) else {
+            return Vec::new();
+        };
+        let min_off = if !r.linear.is_empty() {
+            let w = ((beg >> self.min_shift) as usize).min(r.linear.len() - 1);
+            r.linear[w]
+        } else {
+            // CSI: lowest offset of the deepest existing bin containing `beg`.
+            let mut bin = reg2bin(beg, beg + 1, self.min_shift, self.depth);
+            loop {
+                if let Some((loff, _)) = r.bins.get(&bin) {
+                    break *loff;
+                }
+                if bin == 0 {
+                    break 0;
+                }
+                bin = (bin - 1) >> 3;
+            }
+        };
+
+        let pseudo = pseudo_bin(self.depth);
+        let mut chunks: Vec<Chunk> = reg2bins(beg, end, self.min_shift, self.depth)
+            .into_iter()
+            .filter(|&b| b != pseudo)
+            .filter_map(|b| r.bins.get(&b))
+            .flat_map(|(_, c)| c.iter().copied())
+            .filter(|c| c.end > min_off)
+            .map(|c| Chunk {
+                beg: c.beg.max(min_off),
+                end: c.end,
+            })
+            .collect();
+        chunks.sort_unstable();
+
+        let mut merged: Vec<Chunk> = Vec::with_capacity(chunks.len());
+        for c in chunks {
+            match merged.last_mut() {
+                Some(last) if c.beg <= last.end => last.end = last.end.max(c.end),
+                _ => merged.push(c),
+            }
+        }
+        merged
+    }
+
+    /// Parse a BAI (`BAI\1`), TBI body or CSI (`CSI\1`) index. BGZF-compressed
+    /// input is inflated first.
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<(Self, IndexKind), String> {
+        let inflated;
+        let bytes = if bytes.starts_with(&[0x1f, 0x8b]) {
+            let mut out = Vec::new();
+            flate2::read::MultiGzDecoder::new(bytes)
+                .read_to_end(&mut out)
+                .map_err(|e| e.to_string())?;
+            inflated = out;
+            &inflated[..]
+        } else {
+            bytes
+        };
+        let mut cur = Cursor::new(bytes, "index file");
+        let magic = cur.take(4)?;
+        match magic {
+            b"BAI\x01" => {
+                let refs = read_linear_refs(&mut cur)?;
+                let n_no_coor = cur.u64().ok();
+                Ok((
+                    Self {
+                        min_shift: BAI_MIN_SHIFT,
+                        depth: BAI_DEPTH,
+                        refs,
+                        n_no_coor,
+                        aux: Vec::new(),
+                    },
+                    IndexKind::Bai,
+                ))
+            }
+            b"TBI\x01" => {
+                let n_ref = cur.count()?;
+                let header = cur.take(28)?.to_vec();
+                let l_nm = i32::from_le_bytes(header[24..28].try_into().unwrap()) as usize;
+                let names = cur.take(l_nm)?;
+                let mut aux = header;
+                aux.extend_from_slice(names);
+                let refs = (0..n_ref)
+                    .map(|_| read_linear_ref(&mut cur))
+                    .collect::<Result<_, _>>()?;
+                let n_no_coor = cur.u64().ok();
+                Ok((
+                    Self {
+                        min_shift: BAI_MIN_SHIFT,
+                        depth: BAI_DEPTH,
+                        refs,
+                        n_no_coor,
+                        aux,
+                    },
+                    IndexKind::Tbi,
+                ))
+            }
+            b"CSI\x01" => {
+                let min_shift = cur.i32()? as u32;
+                let depth = cur.i32()? as u32;
+                let l_aux = cur.count()?;
+                let aux = cur.take(l_aux)?.to_vec();
+                let n_ref = cur.count()?;
+                let mut refs = Vec::with_capacity(n_ref);
+                for _ in 0..n_ref {
+                    let n_bin = cur.count()?;
+                    let mut r = RefIndex::default();
+                    for _ in 0..n_bin {
+                        let bin = cur.u32()?;
+                        let loffset = cur.u64()?;
+                        let chunks = read_chunks(&mut cur)?;
+                        r.bins.insert(bin, (loffset, chunks));
+                    }
+                    refs.push(r);
+                }
+                let n_no_coor = cur.u64().ok();
+                Ok((
+                    Self {
+                        min_shift,
+                        depth,
+                        refs,
+                        n_no_coor,
+                        aux,
+                    },
+                    IndexKind::Csi,
+                ))
+            }
+            _ => Err("unrecognized index format (expected BAI, TBI or CSI)".into()),
+        }
+    }
+
+    /// Write the per-reference section shared by BAI and TBI.
+    fn write_linear_refs(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&(self.refs.len() as i32).to_le_bytes());
+        for r in &self.refs {
+            out.extend_from_slice(&(r.bins.len() as i32).to_le_bytes());
+            for (&bin, (_, chunks)) in &r.bins {
+                out.extend_from_slice(&bin.to_le_bytes());
+                write_chunks(out, chunks);
+            }
+            out.extend_from_slice(&(r.linear.len() as i32).to_le_bytes());
+            for off in &r.linear {
+                out.extend_from_slice(&off.to_le_bytes());
+            }
+        }
+    }
+
+    /// Serialize as BAI. Only valid for the default 14/5 geometry.
+    pub(crate) fn to_bai(&self) -> Vec<u8> {
+        let mut out = b"BAI\x01".to_vec();
+        self.write_linear_refs(&mut out);
+        if let Some(n) = self.n_no_coor {
+            out.extend_from_slice(&n.to_le_bytes());
+        }
+        out
+    }
+
+    /// Serialize as a BGZF-compressed tabix index. `aux` must hold the
+    /// 28-byte tabix header followed by the sequence names.
+    pub(crate) fn to_tbi(&self) -> Result<Vec<u8>, String> {
+        let mut out = b"TBI\x01".to_vec();
+        out.extend_from_slice(&(self.refs.len() as i32).to_le_bytes());
+        out.extend_from_slice(&self.aux);
+        let mut refs = Vec::new();
+        self.write_linear_refs(&mut refs);
+        // Skip the reference count `write_linear_refs` leads with; TBI
+        // stores it before the header.
+        out.extend_from_slice(&refs[4..]);
+        if let Some(n) = self.n_no_coor {
+            out.extend_from_slice(&n.to_le_bytes());
+        }
+        bgzf_compress(&out)
+    }
+
+    /// Serialize as CSI, BGZF-compressed.
+    pub(crate) fn to_csi(&self) -> Result<Vec<u8>, String> {
+        let mut out = b"CSI\x01".to_vec();
+        out.extend_from_slice(&(self.min_shift as i32).to_le_bytes());
+        out.extend_from_slice(&(self.depth as i32).to_le_bytes());
+        out.extend_from_slice(&(self.aux.len() as i32).to_le_bytes());
+        out.extend_from_slice(&self.aux);
+        out.extend_from_slice(&(self.refs.len() as i32).to_le_bytes());
+        for r in &self.refs {
+            out.extend_from_slice(&(r.bins.len() as i32).to_le_bytes());
+            for (&bin, (loffset, chunks)) in &r.bins {
+                out.extend_from_slice(&bin.to_le_bytes());
+                out.extend_from_slice(&loffset.to_le_bytes());
+                write_chunks(&mut out, chunks);
+            }
+        }
+        if let Some(n) = self.n_no_coor {
+            out.extend_from_slice(&n.to_le_bytes());
+        }
+        bgzf_compress(&out)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum IndexKind {
+    Bai,
+    Tbi,
+    Csi,
+}
+
+fn write_chunks(out: &mut Vec<u8>, chunks: &[Chunk]) {
+    out.extend_from_slice(&(chunks.len() as i32).to_le_bytes());
+    for c in chunks {
+        out.extend_from_slice(&c.beg.to_le_bytes());
+        out.extend_from_slice(&c.end.to_le_bytes());
+    }
+}
+
+fn bgzf_compress(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let mut writer = BgzfWriter::new(Vec::new());
+    writer.write_all(bytes).map_err(|e| e.to_string())?;
+    writer.finish().map_err(|e| e.to_string())
+}
+
+fn read_chunks(cur: &mut Cursor) -> Result<Vec<Chunk>, String> {
+    let n_chunk = cur.count()?;
+    (0..n_chunk)
+        .map(|_| {
+            Ok(Chunk {
+                beg: cur.u64()?,
+                end: cur.u64()?,
+            })
+        })
+        .collect()
+}
+
+fn read_linear_ref(cur: &mut Cursor) -> Result<RefIndex, String> {
+    let n_bin = cur.count()?;
+    let mut r = RefIndex::default();
+    for _ in 0..n_bin {
+        let bin = cur.u32()?;
+        let chunks = read_chunks(cur)?;
+        r.bins.insert(bin, (0, chunks));
+    }
+    let n_intv = cur.count()?;
+    r.linear = (0..n_intv).map(|_| cur.u64()).collect::<Result<_, _>>()?;
+    Ok(r)
+}
+
+fn read_linear_refs(cur: &mut Cursor) -> Result<Vec<RefIndex>, String> {
+    let n_ref = cur.count()?;
+    (0..n_ref).map(|_| read_linear_ref(cur)).collect()
+}
+
+/// Incremental index construction over coordinate-sorted records.
+pub(crate) struct IndexBuilder {
+    min_shift: u32,
+    depth: u32,
+    linear: bool,
+    refs: Vec<RefIndex>,
+    /// Per-reference (first offset, last offset, mapped, unmapped).
+    meta: Vec<(u64, u64, u64, u64)>,
+    last: Option<(usize, u64)>,
+    n_no_coor: u64,
+}
+
+impl IndexBuilder {
+    /// `linear` selects BAI/TBI-style linear windows instead of per-bin
+    /// lowest offsets (CSI).
+    pub(crate) fn new(n_refs: usize, min_shift: u32, depth: u32, linear: bool) -> Self {
+        Self {
+            min_shift,
+            depth,
+            linear,
+            refs: vec![RefIndex::default(); n_refs],
+            meta: vec![(u64::MAX, 0, 0, 0); n_refs],
+            last: None,
+            n_no_coor: 0,
+        }
+    }
+
+    /// Record one entry on reference `tid` covering `[beg, end)` stored at
+    /// virtual offsets `[vbeg, vend)`. References past `n_refs` are added
+    /// on demand. Errors if input is not sorted.
+    pub(crate) fn push(
+        &mut self,
+        tid: usize,
+        beg: u64,
+        end: u64,
+        vbeg: u64,
+        vend: u64,
+        mapped: bool,
+    ) -> Result<(), String> {
+        if tid >= self.refs.len() {
+            self.refs.resize(tid + 1, RefIndex::default());
+            self.meta.resize(tid + 1, (u64::MAX, 0, 0, 0));
+        }
+        if let Some((last_tid, last_beg)) = self.last {
+            if tid < last_tid || (tid == last_tid && beg < last_beg) {
+                return Err("input is not coordinate-sorted".into());
+            }
+        }
+        let max = BinningIndex::max_len(self.min_shift, self.depth);
+        if end > max {
+            return Err(format!(
+                "position {end} exceeds the {max} bp limit of this index; use CSI"
+            ));
+        }
+        self.last = Some((tid, beg));
+
+        let end = end.max(beg + 1);
+        let bin = reg2bin(beg, end, self.min_shift, self.depth);
+        let r = &mut self.refs[tid];
+        let (loffset, chunks) = r.bins.entry(bin).or_insert((vbeg, Vec::new()));
+        *loffset = (*loffset).min(vbeg);
+        match chunks.last_mut() {
+            Some(last) if last.end == vbeg || (last.end >> 16) == (vbeg >> 16) => {
+                last.end = vend
+            }
+            _ => chunks.push(Chunk {
+                beg: vbeg,
+                end: vend,
+            }),
+        }
+        // The linear index is kept for CSI too: per-bin offsets are derived
+        // from it in `finish`.
+        let first = (beg >> self.min_shift) as usize;
+        let last = ((end - 1) >> self.min_shift) as usize;
+        if r.linear.len() <= last {
+            r.linear.resize(last + 1, u64::MAX);
+        }
+        for w in &mut r.linear[first..=last] {
+            *w = (*w).min(vbeg);
+        }
+
+        let m = &mut self.meta[tid];
+        m.0 = m.0.min(vbeg);
+        m.1 = m.1.max(vend);
+        if mapped {
+            m.2 += 1;
+        } else {
+            m.3 += 1;
+        }
+        Ok(())
+    }
+
+    /// Count a record with no reference (these trail a sorted BAM).
+    pub(crate) fn push_unplaced(&mut self) {
+        self.n_no_coor += 1;
+    }
+
+    pub(crate) fn finish(mut self) -> BinningIndex {
+        let pseudo = pseudo_bin(self.depth);
+        for (r, &(first, last, mapped, unmapped)) in self.refs.iter_mut().zip(&self.meta) {
+            // Windows no record overlaps inherit the previous offset so a
+            // query starting there never skips records.
+            let mut prev = 0;
+            for w in r.linear.iter_mut() {
+                if *w == u64::MAX {
+                    *w = prev;
+                }
+                prev = *w;
+            }
+            if !self.linear {
+                // CSI: a bin's offset is the linear offset of its first window.
+                for (&bin, (loffset, _)) in r.bins.iter_mut() {
+                    let w = first_window(bin, self.depth);
+                    *loffset = r.linear.get(w).copied().unwrap_or(*loffset);
+                }
+                r.linear.clear();
+            }
+            if first != u64::MAX {
+                r.bins.insert(
+                    pseudo,
+                    (
+                        0,
+                        vec![
+                            Chunk {
+                                beg: first,
+                                end: last,
+                            },
+                            Chunk {
+                                beg: mapped,
+                                end: unmapped,
+                            },
+                        ],
+                    ),
+                );
+            }
+        }
+        BinningIndex {
+            min_shift: self.min_shift,
+            depth: self.depth,
+            refs: self.refs,
+            n_no_coor: Some(self.n_no_coor),
+            aux: Vec::new(),
+        }
+    }
+}
@@ -4,7 +4,8 @@
 //! Cyanea Labs type.  All `#[module = "..."]` values must match the Elixir
 //! `defstruct` module in `native.ex`.
 
-use rustler::NifStruct;
+use rustler::{NifStruct, NifUntaggedEnum};
+use std::collections::HashMap;
 
 // ── Traits needed for conversions ──────────────────────────────────────────
 
@@ -43,6 +44,17 @@ pub struct FastqRecordNif {
     pub quality: Vec<u8>,
 }
 
+impl From<crate::reader::SeqRecord> for FastqRecordNif {
+    fn from(r: crate::reader::SeqRecord) -> Self {
+        Self {
+            name: r.name,
+            description: r.description,
+            sequence: r.sequence,
+            quality: r.quality,
+        }
+    }
+}
+
 impl From<cyanea_seq::FastqRecord> for FastqRecordNif {
     fn from(r: cyanea_seq::FastqRecord) -> Self {
         let name = r.name().to_string();
@@ -84,6 +96,278 @@ impl From<cyanea_seq::FastqStats> for FastqStatsNif {
     }
 }
 
+impl From<&crate::paired::ReadStats> for FastqStatsNif {
+    fn from(s: &crate::paired::ReadStats) -> Self {
+        Self {
+            sequence_count: s.reads,
+            total_bases: s.bases,
+            gc_content: s.fraction(s.gc),
+            avg_length: s.mean_length(),
+            mean_quality: s.fraction(s.quality_sum),
+            q20_fraction: s.fraction(s.q20),
+            q30_fraction: s.fraction(s.q30),
+        }
+    }
+}
+
+/// Statistics for both mates of a paired-end run. Insert sizes come from
+/// pairs whose mates overlap; `insert_size_histogram[n]` counts fragments
+/// of length `n`.
+#[derive(Debug, NifStruct)]
+#[module = "Cyanea.Native.PairedFastqStats"]
+pub struct PairedFastqStatsNif {
+    pub pair_count: u64,
+    pub read1: FastqStatsNif,
+    pub read2: FastqStatsNif,
+    pub overlapping_pairs: u64,
+    pub insert_size_mean: Option<f64>,
+    pub insert_size_median: Option<f64>,
+    pub insert_size_sd: Option<f64>,
+    pub insert_size_histogram: Vec<u64>,
+}
+
+/// Trimming settings; zero disables a quality or poly-X step. With no
+/// `adapters` and `detect_adapter`, file trimming picks a known adapter.
+#[derive(Debug, NifStruct)]
+#[module = "Cyanea.Native.TrimOptions"]
+pub struct TrimOptionsNif {
+    pub adapters: Vec<String>,
+    pub detect_adapter: bool,
+    pub adapter_error_rate: f64,
+    pub min_adapter_overlap: usize,
+    pub quality_cutoff: u8,
+    pub window_size: usize,
+    pub window_quality: u8,
+    pub poly_g: usize,
+    pub poly_a: usize,
+    pub min_length: usize,
+}
+
+#[derive(Debug, NifStruct)]
+#[module = "Cyanea.Native.TrimReport"]
+pub struct TrimReportNif {
+    pub reads_in: u64,
+    pub reads_out: u64,
+    pub too_short: u64,
+    pub bases_in: u64,
+    pub bases_out: u64,
+    pub adapter_reads: u64,
+    pub adapter_bases: u64,
+    pub quality_reads: u64,
+    pub quality_bases: u64,
+    pub poly_g_reads: u64,
+    pub poly_g_bases: u64,
+    pub poly_a_reads: u64,
+    pub poly_a_bases: u64,
+    pub adapters: Vec<String>,
+}
+
+#[derive(Debug, NifStruct)]
+#[module = "Cyanea.Native.FastaRecord"]
+pub struct FastaRecordNif {
+    pub name: String,
+    pub description: String,
+    pub sequence: Vec<u8>,
+}
+
+impl From<crate::reader::SeqRecord> for FastaRecordNif {
+    fn from(r: crate::reader::SeqRecord) -> Self {
+        Self {
+            name: r.name,
+            description: r.description,
+            sequence: r.sequence,
+        }
+    }
+}
+
+/// One `.fai` line: sequence length, byte offset of its first base, and
+/// bases and bytes per line.
+#[derive(Debug, NifStruct)]
+#[module = "Cyanea.Native.FaiEntry"]
+pub struct FaiEntryNif {
+    pub name: String,
+    pub length: u64,
+    pub offset: u64,
+    pub line_bases: u64,
+    pub line_width: u64,
+}
+
+impl From<&crate::faidx::FaiEntry> for FaiEntryNif {
+    fn from(e: &crate::faidx::FaiEntry) -> Self {
+        Self {
+            name: e.name.clone(),
+            length: e.length,
+            offset: e.offset,
+            line_bases: e.line_bases,
+            line_width: e.line_width,
+        }
+    }
+}
+
+/// Quality percentiles for read positions `start..=end` (1-based).
+#[derive(Debug, NifStruct)]
+#[module = "Cyanea.Native.PositionQuality"]
+pub struct PositionQualityNif {
+    pub start: usize,
+    pub end: usize,
+    pub mean: f64,
+    pub median: f64,
+    pub lower_quartile: f64,
+    pub upper_quartile: f64,
+    pub p10: f64,
+    pub p90: f64,
+}
+
+impl From<crate::fastq_qc::PositionQuality> for PositionQualityNif {
+    fn from(p: crate::fastq_qc::PositionQuality) -> Self {
+        Self {
+            start: p.start + 1,
+            end: p.end,
+            mean: p.mean,
+            median: p.median,
+            lower_quartile: p.lower_quartile,
+            upper_quartile: p.upper_quartile,
+            p10: p.p10,
+            p90: p.p90,
+        }
+    }
+}
+
+/// Base percentages for read positions `start..=end` (1-based).
+#[derive(Debug, NifStruct)]
+#[module = "Cyanea.Native.PositionContent"]
+pub struct PositionContentNif {
+    pub start: usize,
+    pub end: usize,
+    pub a: f64,
+    pub c: f64,
+    pub g: f64,
+    pub t: f64,
+    pub n: f64,
+}
+
+impl From<crate::fastq_qc::PositionContent> for PositionContentNif {
+    fn from(p: crate::fastq_qc::PositionContent) -> Self {
+        let [a, c, g, t, n] = p.percent;
+        Self {
+            start: p.start + 1,
+            end: p.end,
+            a,
+            c,
+            g,
+            t,
+            n,
+        }
+    }
+}
+
+#[derive(Debug, NifStruct)]
+#[module = "Cyanea.Native.OverrepresentedSequence"]
+pub struct OverrepresentedSequenceNif {
+    pub sequence: String,
+    pub count: u64,
+    pub percentage: f64,
+}
+
+/// FastQC-style report. Histograms are `{value, reads}` pairs except
+/// `gc_histogram`, indexed by GC percentage; `statuses` pairs each module
+/// with `"pass"`, `"warn"` or `"fail"`.
+#[derive(Debug, NifStruct)]
+#[module = "Cyanea.Native.FastqQcReport"]
+pub struct FastqQcReportNif {
+    pub read_count: u64,
+    pub total_bases: u64,
+    pub min_length: usize,
+    pub max_length: usize,
+    pub gc_percent: f64,
+    pub per_base_quality: Vec<PositionQualityNif>,
+    pub per_base_content: Vec<PositionContentNif>,
+    pub per_sequence_quality: Vec<(u32, u64)>,
+    pub gc_histogram: Vec<u64>,
+    pub length_distribution: Vec<(usize, u64)>,
+    pub duplication_levels: Vec<(String, f64)>,
+    pub deduplicated_percent: f64,
+    pub overrepresented: Vec<OverrepresentedSequenceNif>,
+    pub statuses: Vec<(String, String)>,
+}
+
+impl From<crate::fastq_qc::QcReport> for FastqQcReportNif {
+    fn from(r: crate::fastq_qc::QcReport) -> Self {
+        Self {
+            read_count: r.reads,
+            total_bases: r.bases,
+            min_length: r.min_length,
+            max_length: r.max_length,
+            gc_percent: r.gc_percent,
+            per_base_quality: r.per_base_quality.into_iter().map(Into::into).collect(),
+            per_base_content: r.per_base_content.into_iter().map(Into::into).collect(),
+            per_sequence_quality: r.per_sequence_quality,
+            gc_histogram: r.gc_histogram,
+            length_distribution: r.length_distribution,
+            duplication_levels: r
+                .duplication_levels
+                .into_iter()
+                .map(|(level, pct)| (level.to_string(), pct))
+                .collect(),
+            deduplicated_percent: r.deduplicated_percent,
+            overrepresented: r
+                .overrepresented
+                .into_iter()
+                .map(|(sequence, count, percentage)| OverrepresentedSequenceNif {
+                    sequence,
+                    count,
+                    percentage,
+                })
+                .collect(),
+            statuses: r
+                .statuses
+                .into_iter()
+                .map(|(module, status)| (module.to_string(), status.to_string()))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, NifStruct)]
+#[module = "Cyanea.Native.GenomeEstimate"]
+pub struct GenomeEstimateNif {
+    pub error_cutoff: u32,
+    pub kmer_coverage: f64,
+    pub heterozygous_peak: bool,
+    pub genome_size: u64,
+    pub heterozygosity: f64,
+    pub repeat_fraction: f64,
+}
+
+impl From<crate::kmer::GenomeEstimate> for GenomeEstimateNif {
+    fn from(g: crate::kmer::GenomeEstimate) -> Self {
+        Self {
+            error_cutoff: g.error_cutoff,
+            kmer_coverage: g.kmer_coverage,
+            heterozygous_peak: g.heterozygous_peak,
+            genome_size: g.genome_size,
+            heterozygosity: g.heterozygosity,
+            repeat_fraction: g.repeat_fraction,
+        }
+    }
+}
+
+/// Canonical k-mer spectrum. `histogram` holds `{count, distinct_kmers}`
+/// for non-empty bins only; the last bin collects everything above the
+/// histogram cap.
+#[derive(Debug, NifStruct)]
+#[module = "Cyanea.Native.KmerSpectrum"]
+pub struct KmerSpectrumNif {
+    pub k: usize,
+    pub total_kmers: u64,
+    pub distinct_kmers: usize,
+    pub histogram: Vec<(u32, u64)>,
+    pub top_kmers: Vec<(String, u32)>,
+    pub genome: Option<GenomeEstimateNif>,
+}
+
+/// ORF span on the forward strand (0-based, half-open, stop included);
+/// `sequence` reads from the start codon on `strand`.
 #[derive(Debug, NifStruct)]
 #[module = "Cyanea.Native.OrfResult"]
 pub struct OrfResultNif {
@@ -92,6 +376,38 @@ pub struct OrfResultNif {
     pub frame: usize,
     pub strand: String,
     pub sequence: Vec<u8>,
+    pub protein: Vec<u8>,
+}
+
+impl From<crate::genetic_code::Orf> for OrfResultNif {
+    fn from(o: crate::genetic_code::Orf) -> Self {
+        Self {
+            start: o.start,
+            end: o.end,
+            frame: o.frame,
+            strand: o.strand.to_string(),
+            sequence: o.sequence,
+            protein: o.protein,
+        }
+    }
+}
+
+#[derive(Debug, NifStruct)]
+#[module = "Cyanea.Native.FrameTranslation"]
+pub struct FrameTranslationNif {
+    pub strand: String,
+    pub frame: usize,
+    pub protein: Vec<u8>,
+}
+
+impl From<crate::genetic_code::FrameTranslation> for FrameTranslationNif {
+    fn from(f: crate::genetic_code::FrameTranslation) -> Self {
+        Self {
+            strand: f.strand.to_string(),
+            frame: f.frame,
+            protein: f.protein,
+        }
+    }
 }
 
 // ===========================================================================
@@ -126,16 +442,32 @@ pub struct VcfStatsNif {
     pub indel_count: u64,
     pub pass_count: u64,
     pub chromosomes: Vec<String>,
-}
-
-impl From<cyanea_io::VcfStats> for VcfStatsNif {
-    fn from(s: cyanea_io::VcfStats) -> Self {
+    pub sample_names: Vec<String>,
+    /// Per sample: fraction of sites with a fully called genotype.
+    pub call_rate: Vec<f64>,
+    pub het_count: Vec<u64>,
+    pub hom_alt_count: Vec<u64>,
+    pub het_hom_ratio: Vec<Option<f64>>,
+    pub mean_allele_frequency: Option<f64>,
+    /// Sites per alternate allele frequency decile.
+    pub allele_frequency_spectrum: Vec<u64>,
+}
+
+impl From<(cyanea_io::VcfStats, crate::vcf::VcfSummary)> for VcfStatsNif {
+    fn from((sites, s): (cyanea_io::VcfStats, crate::vcf::VcfSummary)) -> Self {
         Self {
-            variant_count: s.variant_count,
-            snv_count: s.snv_count,
-            indel_count: s.indel_count,
-            pass_count: s.pass_count,
-            chromosomes: s.chromosomes,
+            call_rate: s.call_rate(),
+            het_hom_ratio: s.het_hom_ratio(),
+            mean_allele_frequency: s.mean_allele_frequency(),
+            variant_count: sites.variant_count,
+            snv_count: sites.snv_count,
+            indel_count: sites.indel_count,
+            pass_count: sites.pass_count,
+            chromosomes: sites.chromosomes,
+            sample_names: s.samples,
+            het_count: s.het,
+            hom_alt_count: s.hom_alt,
+            allele_frequency_spectrum: s.af_spectrum,
         }
     }
 }
@@ -180,15 +512,99 @@ impl From<cyanea_io::GffStats> for GffStatsNif {
     }
 }
 
+/// A typed INFO/FORMAT value; encodes as a bare Elixir term (`true`,
+/// integer, float, binary, or a list with `nil` for missing entries).
+#[derive(Debug, NifUntaggedEnum)]
+pub enum VcfValueNif {
+    Flag(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    IntegerList(Vec<Option<i64>>),
+    FloatList(Vec<Option<VcfFloat>>),
+    StringList(Vec<Option<String>>),
+}
+
+/// A Float list entry. Decodes from integers too, so a list mixing whole
+/// and fractional numbers (`[1, 2.5]`) is still a Float list.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VcfFloat(pub f64);
+
+impl rustler::Encoder for VcfFloat {
+    fn encode<'a>(&self, env: rustler::Env<'a>) -> rustler::Term<'a> {
+        self.0.encode(env)
+    }
+}
+
+impl<'a> rustler::Decoder<'a> for VcfFloat {
+    fn decode(term: rustler::Term<'a>) -> rustler::NifResult<Self> {
+        term.decode::<f64>()
+            .or_else(|_| term.decode::<i64>().map(|x| x as f64))
+            .map(Self)
+    }
+}
+
+impl std::fmt::Display for VcfFloat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
 #[derive(Debug, NifStruct)]
 #[module = "Cyanea.Native.VcfRecord"]
 pub struct VcfRecordNif {
     pub chrom: String,
     pub position: u64,
+    pub ids: Vec<String>,
     pub ref_allele: String,
     pub alt_alleles: Vec<String>,
     pub quality: Option<f64>,
+    /// FILTER column as written: `"PASS"`, `"."` or `;`-joined filter IDs.
     pub filter: String,
+    /// FILTER split into IDs (empty for `"."`).
+    pub filters: Vec<String>,
+    pub info: HashMap<String, VcfValueNif>,
+    pub format: Vec<String>,
+    /// Per sample, `GT` as written (`"."` when absent).
+    pub genotypes: Vec<String>,
+    pub depths: Vec<Option<i64>>,
+    pub genotype_qualities: Vec<Option<i64>>,
+    pub allele_depths: Vec<Vec<Option<i64>>>,
+    /// Per sample, every FORMAT field decoded by its header type.
+    pub sample_data: Vec<HashMap<String, VcfValueNif>>,
+}
+
+#[derive(Debug, NifStruct)]
+#[module = "Cyanea.Native.VcfField"]
+pub struct VcfFieldNif {
+    pub id: String,
+    pub number: String,
+    pub value_type: String,
+    pub description: String,
+}
+
+impl From<&crate::vcf::FieldDef> for VcfFieldNif {
+    fn from(d: &crate::vcf::FieldDef) -> Self {
+        Self {
+            id: d.id.clone(),
+            number: d.number.clone(),
+            value_type: d.value_type.as_str().to_string(),
+            description: d.description.clone(),
+        }
+    }
+}
+
+#[derive(Debug, NifStruct)]
+#[module = "Cyanea.Native.VcfHeader"]
+pub struct VcfHeaderNif {
+    /// Every `##` line verbatim (without the `##`), so headers round-trip.
+    pub meta: Vec<String>,
+    pub fileformat: Option<String>,
+    pub samples: Vec<String>,
+    pub contigs: Vec<String>,
+    pub filters: Vec<String>,
+    pub info: Vec<VcfFieldNif>,
+    pub format: Vec<VcfFieldNif>,
 }
 
 #[derive(Debug, NifStruct)]
@@ -202,6 +618,67 @@ pub struct BedRecordNif {
     pub strand: String,
 }
 
+/// A BED feature for writing. Optional columns left `nil` are filled with
+/// defaults when other features in the file need them; empty block lists
+/// mean no BED12 blocks.
+#[derive(Debug, NifStruct)]
+#[module = "Cyanea.Native.BedFeature"]
+pub struct BedFeatureNif {
+    pub chrom: String,
+    pub start: u64,
+    pub end: u64,
+    pub name: Option<String>,
+    pub score: Option<f64>,
+    pub strand: Option<String>,
+    pub thick_start: Option<u64>,
+    pub thick_end: Option<u64>,
+    pub item_rgb: Option<String>,
+    pub block_sizes: Vec<u64>,
+    pub block_starts: Vec<u64>,
+}
+
+/// A GFF3 feature for writing; `start`/`end` are 1-based and inclusive.
+#[derive(Debug, NifStruct)]
+#[module = "Cyanea.Native.GffFeature"]
+pub struct GffFeatureNif {
+    pub seqid: String,
+    pub source: String,
+    pub feature_type: String,
+    pub start: u64,
+    pub end: u64,
+    pub score: Option<f64>,
+    pub strand: String,
+    pub phase: Option<u8>,
+    pub attributes: Vec<(String, String)>,
+}
+
+/// Read depth over one target region; `fraction_above[i]` is the fraction
+/// of bases with depth of at least the report's `thresholds[i]`.
+#[derive(Debug, NifStruct)]
+#[module = "Cyanea.Native.TargetDepth"]
+pub struct TargetDepthNif {
+    pub chrom: String,
+    pub start: u64,
+    pub end: u64,
+    pub mean_depth: f64,
+    pub median_depth: f64,
+    pub min_depth: u32,
+    pub max_depth: u32,
+    pub fraction_above: Vec<f64>,
+}
+
+/// Per-target depth plus the same summary pooled over all target bases.
+#[derive(Debug, NifStruct)]
+#[module = "Cyanea.Native.DepthReport"]
+pub struct DepthReportNif {
+    pub thresholds: Vec<u32>,
+    pub targets: Vec<TargetDepthNif>,
+    pub total_bases: u64,
+    pub mean_depth: f64,
+    pub median_depth: f64,
+    pub fraction_above: Vec<f64>,
+}
+
 #[derive(Debug, NifStruct)]
 #[module = "Cyanea.Native.GffGene"]
 pub struct GffGeneNif {
@@ -280,6 +757,82 @@ impl From<cyanea_align::AlignmentResult> for AlignmentResultNif {
     }
 }
 
+impl From<crate::pairwise::Alignment> for AlignmentResultNif {
+    fn from(r: crate::pairwise::Alignment) -> Self {
+        Self {
+            score: r.score,
+            cigar: r.cigar(),
+            identity: r.identity(),
+            num_matches: r.matches(),
+            num_mismatches: r.mismatches(),
+            num_gaps: r.gaps(),
+            alignment_length: r.length(),
+            aligned_query: r.aligned_query,
+            aligned_target: r.aligned_target,
+            query_start: r.query_start,
+            query_end: r.query_end,
+            target_start: r.target_start,
+            target_end: r.target_end,
+        }
+    }
+}
+
+impl From<AlignmentResultNif> for crate::pairwise::Alignment {
+    fn from(r: AlignmentResultNif) -> Self {
+        Self {
+            score: r.score,
+            aligned_query: r.aligned_query,
+            aligned_target: r.aligned_target,
+            query_start: r.query_start,
+            query_end: r.query_end,
+            target_start: r.target_start,
+            target_end: r.target_end,
+        }
+    }
+}
+
+/// Column summary of a pairwise alignment; coordinates are 1-based and
+/// inclusive, fractions are over alignment columns.
+#[derive(Debug, NifStruct)]
+#[module = "Cyanea.Native.AlignmentSummary"]
+pub struct AlignmentSummaryNif {
+    pub length: usize,
+    pub identities: usize,
+    pub positives: usize,
+    pub mismatches: usize,
+    pub gaps: usize,
+    pub gap_opens: usize,
+    pub identity: f64,
+    pub similarity: f64,
+    pub gap_fraction: f64,
+    pub query_start: usize,
+    pub query_end: usize,
+    pub target_start: usize,
+    pub target_end: usize,
+    pub match_line: String,
+}
+
+impl From<crate::render::Summary> for AlignmentSummaryNif {
+    fn from(s: crate::render::Summary) -> Self {
+        Self {
+            identity: s.identity(),
+            similarity: s.similarity(),
+            gap_fraction: s.gap_fraction(),
+            length: s.length,
+            identities: s.identities,
+            positives: s.positives,
+            mismatches: s.mismatches,
+            gaps: s.gaps,
+            gap_opens: s.gap_opens,
+            query_start: s.query_start,
+            query_end: s.query_end,
+            target_start: s.target_start,
+            target_end: s.target_end,
+            match_line: s.match_line,
+        }
+    }
+}
+
 #[derive(Debug, NifStruct)]
 #[module = "Cyanea.Native.CigarStats"]
 pub struct CigarStatsNif {
@@ -303,6 +856,240 @@ pub struct MsaResultNif {
     pub conservation: f64,
 }
 
+/// A substitution matrix; `scores` rows follow `alphabet`. Gap penalties
+/// are BLAST's defaults for built-in matrices and `nil` otherwise.
+#[derive(Debug, NifStruct)]
+#[module = "Cyanea.Native.SubstitutionMatrix"]
+pub struct SubstitutionMatrixNif {
+    pub name: String,
+    pub alphabet: String,
+    pub scores: Vec<Vec<i32>>,
+    pub gap_open: Option<i32>,
+    pub gap_extend: Option<i32>,
+}
+
+impl From<crate::substitution::SubstitutionMatrix> for SubstitutionMatrixNif {
+    fn from(m: crate::substitution::SubstitutionMatrix) -> Self {
+        Self {
+            scores: m.rows(),
+            alphabet: String::from_utf8_lossy(&m.alphabet).into_owned(),
+            name: m.name,
+            gap_open: None,
+            gap_extend: None,
+        }
+    }
+}
+
+impl TryFrom<SubstitutionMatrixNif> for crate::substitution::SubstitutionMatrix {
+    type Error = String;
+
+    fn try_from(m: SubstitutionMatrixNif) -> Result<Self, String> {
+        if m.scores.iter().any(|row| row.len() != m.alphabet.len()) {
+            return Err(format!("every matrix row needs {} scores", m.alphabet.len()));
+        }
+        Self::new(&m.name, m.alphabet.into_bytes(), m.scores.concat())
+    }
+}
+
+/// Karlin–Altschul parameters; `gapped` is false for parameters computed
+/// from the matrix alone.
+#[derive(Debug, NifStruct)]
+#[module = "Cyanea.Native.KarlinAltschul"]
+pub struct KarlinAltschulNif {
+    pub lambda: f64,
+    pub k: f64,
+    pub h: f64,
+    pub gapped: bool,
+}
+
+impl From<crate::significance::KarlinAltschul> for KarlinAltschulNif {
+    fn from(p: crate::significance::KarlinAltschul) -> Self {
+        Self {
+            lambda: p.lambda,
+            k: p.k,
+            h: p.h,
+            gapped: p.gapped,
+        }
+    }
+}
+
+impl From<KarlinAltschulNif> for crate::significance::KarlinAltschul {
+    fn from(p: KarlinAltschulNif) -> Self {
+        Self {
+            lambda: p.lambda,
+            k: p.k,
+            h: p.h,
+            gapped: p.gapped,
+        }
+    }
+}
+
+#[derive(Debug, NifStruct)]
+#[module = "Cyanea.Native.AlignmentSignificance"]
+pub struct AlignmentSignificanceNif {
+    pub score: i32,
+    pub bit_score: f64,
+    pub evalue: f64,
+}
+
+/// Extreme value fit to scores against shuffled targets.
+#[derive(Debug, NifStruct)]
+#[module = "Cyanea.Native.ShuffleFit"]
+pub struct ShuffleFitNif {
+    pub score: i32,
+    pub shuffles: usize,
+    pub mean: f64,
+    pub sd: f64,
+    pub lambda: f64,
+    pub k: f64,
+    pub p_value: f64,
+    pub empirical_p: f64,
+}
+
+impl From<crate::significance::ShuffleFit> for ShuffleFitNif {
+    fn from(f: crate::significance::ShuffleFit) -> Self {
+        Self {
+            score: f.score,
+            shuffles: f.shuffles,
+            mean: f.mean,
+            sd: f.sd,
+            lambda: f.lambda,
+            k: f.k,
+            p_value: f.p_value,
+            empirical_p: f.empirical_p,
+        }
+    }
+}
+
+/// Read mapper settings; penalties are negative scores.
+#[derive(Debug, NifStruct)]
+#[module = "Cyanea.Native.MapOptions"]
+pub struct MapOptionsNif {
+    pub min_seed_len: usize,
+    pub max_occurrences: usize,
+    pub bandwidth: usize,
+    pub match_score: i32,
+    pub mismatch_score: i32,
+    pub gap_open: i32,
+    pub gap_extend: i32,
+    pub min_score: i32,
+}
+
+impl From<MapOptionsNif> for crate::mapper::MapOptions {
+    fn from(o: MapOptionsNif) -> Self {
+        Self {
+            min_seed_len: o.min_seed_len,
+            max_occurrences: o.max_occurrences,
+            bandwidth: o.bandwidth,
+            match_score: o.match_score,
+            mismatch_score: o.mismatch_score,
+            gap_open: o.gap_open,
+            gap_extend: o.gap_extend,
+            min_score: o.min_score,
+        }
+    }
+}
+
+/// Primary alignment of a read, laid out like a SAM line plus its tags.
+/// Unmapped reads have flag 4, `pos` 0 and empty `rname`/`cigar`/`md`.
+#[derive(Debug, NifStruct)]
+#[module = "Cyanea.Native.MappedRead"]
+pub struct MappedReadNif {
+    pub qname: String,
+    pub flag: u16,
+    pub rname: String,
+    pub pos: u64,
+    pub mapq: u8,
+    pub cigar: String,
+    pub sequence: Vec<u8>,
+    pub quality: Vec<u8>,
+    pub score: i32,
+    pub suboptimal_score: Option<i32>,
+    pub edit_distance: u32,
+    pub md: String,
+}
+
+impl From<crate::mapper::MappedRead> for MappedReadNif {
+    fn from(r: crate::mapper::MappedRead) -> Self {
+        Self {
+            qname: r.name,
+            flag: r.flag,
+            rname: r.contig,
+            pos: r.pos,
+            mapq: r.mapq,
+            cigar: r.cigar,
+            sequence: r.sequence,
+            quality: r.quality,
+            score: r.score,
+            suboptimal_score: r.suboptimal_score,
+            edit_distance: r.edit_distance,
+            md: r.md,
+        }
+    }
+}
+
+/// Database search settings; penalties are negative scores, `x_drop` and
+/// `gap_trigger` are in bits.
+#[derive(Debug, NifStruct)]
+#[module = "Cyanea.Native.SearchOptions"]
+pub struct SearchOptionsNif {
+    pub gap_open: i32,
+    pub gap_extend: i32,
+    pub threshold: i32,
+    pub window: usize,
+    pub x_drop: f64,
+    pub gap_trigger: f64,
+    pub max_evalue: f64,
+    pub max_targets: usize,
+    pub strand: String,
+}
+
+impl TryFrom<SearchOptionsNif> for crate::search::SearchOptions {
+    type Error = String;
+
+    fn try_from(o: SearchOptionsNif) -> Result<Self, String> {
+        Ok(Self {
+            gap_open: o.gap_open,
+            gap_extend: o.gap_extend,
+            threshold: o.threshold,
+            window: o.window,
+            x_drop: o.x_drop,
+            gap_trigger: o.gap_trigger,
+            max_evalue: o.max_evalue,
+            max_targets: o.max_targets,
+            strand: crate::search::Strand::parse(&o.strand)?,
+        })
+    }
+}
+
+/// One gapped HSP of a database search. On the `"-"` strand the query
+/// coordinates are on the reverse complement of the query.
+#[derive(Debug, NifStruct)]
+#[module = "Cyanea.Native.SearchHit"]
+pub struct SearchHitNif {
+    pub target: String,
+    pub target_length: usize,
+    pub strand: String,
+    pub score: i32,
+    pub bit_score: f64,
+    pub evalue: f64,
+    pub alignment: AlignmentResultNif,
+}
+
+impl From<crate::search::SearchHit> for SearchHitNif {
+    fn from(h: crate::search::SearchHit) -> Self {
+        Self {
+            target: h.target,
+            target_length: h.target_length,
+            strand: if h.reverse { "-" } else { "+" }.to_string(),
+            score: h.alignment.score,
+            bit_score: h.bit_score,
+            evalue: h.evalue,
+            alignment: h.alignment.into(),
+        }
+    }
+}
+
 // ===========================================================================
 // cyanea-stats
 // ===========================================================================
@@ -405,6 +1192,102 @@ impl From<cyanea_omics::GenomicInterval> for GenomicIntervalNif {
     }
 }
 
+impl From<crate::intervals::Interval> for GenomicIntervalNif {
+    fn from(iv: crate::intervals::Interval) -> Self {
+        Self {
+            chrom: iv.chrom,
+            start: iv.start,
+            end: iv.end,
+            strand: iv.strand.to_string(),
+        }
+    }
+}
+
+/// An interval from the first set with its partner from the second; `b`
+/// is `nil` when the operation reports only the first.
+#[derive(Debug, NifStruct)]
+#[module = "Cyanea.Native.IntervalPair"]
+pub struct IntervalPairNif {
+    pub a: GenomicIntervalNif,
+    pub b: Option<GenomicIntervalNif>,
+}
+
+/// Nearest interval to `a`; `distance` is signed (negative upstream of `a`)
+/// and 0 for overlaps. `b` and `distance` are `nil` when nothing is on the
+/// same chromosome.
+#[derive(Debug, NifStruct)]
+#[module = "Cyanea.Native.ClosestInterval"]
+pub struct ClosestIntervalNif {
+    pub a: GenomicIntervalNif,
+    pub b: Option<GenomicIntervalNif>,
+    pub distance: Option<i64>,
+}
+
+/// Predicted effect of one variant allele on one transcript. `gene`,
+/// `transcript` and the HGVS fields are `nil` for intergenic variants;
+/// `consequences` are Sequence Ontology terms, most severe first.
+#[derive(Debug, NifStruct)]
+#[module = "Cyanea.Native.VariantEffect"]
+pub struct VariantEffectNif {
+    pub chrom: String,
+    pub position: u64,
+    pub ref_allele: String,
+    pub alt_allele: String,
+    pub gene: Option<String>,
+    pub transcript: Option<String>,
+    pub consequences: Vec<String>,
+    pub impact: String,
+    pub hgvs_c: Option<String>,
+    pub hgvs_p: Option<String>,
+}
+
+/// A variant after trimming and left-alignment. `index` is the position of
+/// the input variant it came from, so split alleles can be traced back.
+#[derive(Debug, NifStruct)]
+#[module = "Cyanea.Native.NormalizedVariant"]
+pub struct NormalizedVariantNif {
+    pub index: usize,
+    pub chrom: String,
+    pub position: u64,
+    pub ref_allele: String,
+    pub alt_alleles: Vec<String>,
+    pub changed: bool,
+}
+
+#[derive(Debug, NifStruct)]
+#[module = "Cyanea.Native.NormalizeStats"]
+pub struct NormalizeStatsNif {
+    pub records_in: u64,
+    pub records_out: u64,
+    pub normalized: u64,
+    pub split: u64,
+}
+
+/// Truth/query counts for one variant class. Rates are `nil` when their
+/// denominator is zero.
+#[derive(Debug, NifStruct)]
+#[module = "Cyanea.Native.ConcordanceCounts"]
+pub struct ConcordanceCountsNif {
+    pub truth_total: u64,
+    pub query_total: u64,
+    pub true_positives: u64,
+    pub false_positives: u64,
+    pub false_negatives: u64,
+    pub genotype_mismatches: u64,
+    pub precision: Option<f64>,
+    pub recall: Option<f64>,
+    pub f1: Option<f64>,
+}
+
+#[derive(Debug, NifStruct)]
+#[module = "Cyanea.Native.Concordance"]
+pub struct ConcordanceNif {
+    pub snv: ConcordanceCountsNif,
+    pub indel: ConcordanceCountsNif,
+    pub other: ConcordanceCountsNif,
+    pub all: ConcordanceCountsNif,
+}
+
 #[derive(Debug, NifStruct)]
 #[module = "Cyanea.Native.ExpressionSummary"]
 pub struct ExpressionSummaryNif {
@@ -537,6 +1420,16 @@ pub struct LinearRegressionResultNif {
     pub r_squared: f64,
 }
 
+#[derive(Debug, NifStruct)]
+#[module = "Cyanea.Native.RandomForestInfo"]
+pub struct RandomForestInfoNif {
+    pub n_trees: usize,
+    pub n_features: usize,
+    pub n_classes: usize,
+    pub oob_error: Option<f64>,
+    pub feature_importances: Vec<f64>,
+}
+
 // ===========================================================================
 // cyanea-chem
 // ===========================================================================
@@ -741,6 +1634,32 @@ pub struct BedGraphStatsNif {
     pub chrom_count: usize,
 }
 
+/// One bedGraph / bigWig data record (0-based, half-open).
+#[derive(Debug, NifStruct)]
+#[module = "Cyanea.Native.BedGraphRecord"]
+pub struct BedGraphRecordNif {
+    pub chrom: String,
+    pub start: u64,
+    pub end: u64,
+    pub value: f64,
+}
+
+/// Summary of one bin of a bigWig region. `mean`, `min` and `max` are
+/// `nil` when the bin has no data; `coverage` is the fraction of the bin
+/// with data, and 0 for the zero-width bins left when `bins` exceeds the
+/// region length.
+#[derive(Debug, NifStruct)]
+#[module = "Cyanea.Native.BigWigBin"]
+pub struct BigWigBinNif {
+    pub chrom: String,
+    pub start: u64,
+    pub end: u64,
+    pub mean: Option<f64>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub coverage: f64,
+}
+
 // ===========================================================================
 // Helper: structure_to_pdb_info
 // ===========================================================================
@@ -0,0 +1,229 @@
+//! Truth/query comparison of two VCF call sets.
+//!
+//! Both files are split into biallelic records and normalized against the
+//! same reference (see [`crate::normalize`]) before matching, so calls that
+//! differ only in representation agree. Alleles match on chromosome,
+//! position, REF and ALT; genotypes of the first sample are compared for
+//! the matches. Counts follow the hap.py convention of true positives,
+//! false positives (query only) and false negatives (truth only),
+//! stratified into SNVs, indels and other (MNP and complex) alleles.
+
+use crate::faidx::ChromCache;
+use crate::intervals::{Interval, IntervalIndex, StrandMode};
+use crate::normalize::{normalize_alleles, split_alleles};
+use crate::vcf::{VcfReader, VcfRecord};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum VariantClass {
+    Snv,
+    Indel,
+    Other,
+}
+
+impl VariantClass {
+    fn of(ref_allele: &str, alt: &str) -> Self {
+        match (ref_allele.len(), alt.len()) {
+            (1, 1) => Self::Snv,
+            (r, a) if r != a => Self::Indel,
+            _ => Self::Other,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct ClassCounts {
+    pub truth: u64,
+    pub query: u64,
+    pub true_positives: u64,
+    pub false_positives: u64,
+    pub false_negatives: u64,
+    /// True positives whose genotypes are both called but differ.
+    pub genotype_mismatches: u64,
+}
+
+impl ClassCounts {
+    fn add(&mut self, other: &Self) {
+        self.truth += other.truth;
+        self.query += other.query;
+        self.true_positives += other.true_positives;
+        self.false_positives += other.false_positives;
+        self.false_negatives += other.false_negatives;
+        self.genotype_mismatches += other.genotype_mismatches;
+    }
+
+    pub(crate) fn precision(&self) -> Option<f64> {
+        let called = self.true_positives + self.false_positives;
+        (called > 0).then(|| self.true_positives as f64 / called as f64)
+    }
+
+    pub(crate) fn recall(&self) -> Option<f64> {
+        let expected = self.true_positives + self.false_negatives;
+        (expected > 0).then(|| self.true_positives as f64 / expected as f64)
+    }
+
+    pub(crate) fn f1(&self) -> Option<f64> {
+        let (p, r) = (self.precision()?, self.recall()?);
+        (p + r > 0.0).then(|| 2.0 * p * r / (p + r))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Concordance {
+    pub snv: ClassCounts,
+    pub indel: ClassCounts,
+    pub other: ClassCounts,
+}
+
+impl Concordance {
+    fn class_mut(&mut self, class: VariantClass) -> &mut ClassCounts {
+        match class {
+            VariantClass::Snv => &mut self.snv,
+            VariantClass::Indel => &mut self.indel,
+            VariantClass::Other => &mut self.other,
+        }
+    }
+
+    pub(crate) fn all(&self) -> ClassCounts {
+        let mut all = self.snv;
+        all.add(&self.indel);
+        all.add(&self.other);
+        all
+    }
+}
+
+/// `(chrom, pos, ref, alt)` of a normalized biallelic call.
+type Key = (String, u64, String, String);
+
+/// Calls keyed by allele, with the sorted called genotype of the first
+/// sample (`None` when absent or not fully called).
+type Calls = HashMap<Key, Option<Vec<u32>>>;
+
+/// Compare `query` against `truth`. With `confident`, only alleles whose
+/// REF span lies inside the BED regions count; with `pass_only`, records
+/// with a FILTER other than `PASS` are ignored.
+pub(crate) fn compare(
+    truth: &str,
+    query: &str,
+    fasta: &str,
+    confident: Option<&str>,
+    pass_only: bool,
+) -> Result<Concordance, String> {
+    let truth_records = read_calls(truth, pass_only)?;
+    let query_records = read_calls(query, pass_only)?;
+    let mut genome = ChromCache::open(fasta)?;
+    let regions = confident.map(ConfidentRegions::load).transpose()?;
+
+    // Grouped by chromosome so each is read from the FASTA once per file.
+    let mut normalize = |mut records: Vec<VcfRecord>, path: &str| -> Result<Calls, String> {
+        records.sort_by(|a, b| a.chrom.cmp(&b.chrom));
+        let mut calls = Calls::new();
+        for rec in records {
+            let seq = genome.get(&rec.chrom)?;
+            let (pos, ref_allele, alts) =
+                normalize_alleles(seq, rec.pos, &rec.ref_allele, &rec.alt_alleles)
+                    .map_err(|e| format!("{path}: {}:{}: {e}", rec.chrom, rec.pos))?;
+            let end = pos - 1 + ref_allele.len() as u64;
+            if regions
+                .as_ref()
+                .is_some_and(|r| !r.contains(&rec.chrom, pos - 1, end))
+            {
+                continue;
+            }
+            let genotype = rec.genotype(0).filter(|g| g.is_called()).map(|g| {
+                let mut alleles: Vec<u32> = g.alleles.into_iter().flatten().collect();
+                alleles.sort_unstable();
+                alleles
+            });
+            let alt = alts.into_iter().next().unwrap_or_default();
+            calls.insert((rec.chrom, pos, ref_allele, alt), genotype);
+        }
+        Ok(calls)
+    };
+    let truth_calls = normalize(truth_records, truth)?;
+    let query_calls = normalize(query_records, query)?;
+
+    let mut out = Concordance::default();
+    for (key, genotype) in &truth_calls {
+        let counts = out.class_mut(VariantClass::of(&key.2, &key.3));
+        counts.truth += 1;
+        match query_calls.get(key) {
+            Some(query_genotype) => {
+                counts.true_positives += 1;
+                if let (Some(t), Some(q)) = (genotype, query_genotype) {
+                    counts.genotype_mismatches += (t != q) as u64;
+                }
+            }
+            None => counts.false_negatives += 1,
+        }
+    }
+    for key in query_calls.keys() {
+        let counts = out.class_mut(VariantClass::of(&key.2, &key.3));
+        counts.query += 1;
+        if !truth_calls.contains_key(key) {
+            counts.false_positives += 1;
+        }
+    }
+    Ok(out)
+}
+
+/// Biallelic records of `path` that carry a sequence ALT allele, called in
+/// the first sample when the file has genotypes.
+fn read_calls(path: &str, pass_only: bool) -> Result<Vec<VcfRecord>, String> {
+    let mut reader = VcfReader::open(path)?;
+    let header = reader.header().clone();
+    let mut out = Vec::new();
+    while let Some(rec) = reader.next_record()? {
+        if pass_only && !(rec.filters.is_empty() || rec.filters == ["PASS"]) {
+            continue;
+        }
+        for r in split_alleles(&rec, &header) {
+            let Some(alt) = r.alt_alleles.first() else {
+                continue;
+            };
+            if VcfRecord::is_symbolic(alt) || alt == "." {
+                continue;
+            }
+            let has_gt = r.format.iter().any(|f| f == "GT") && !r.samples.is_empty();
+            if has_gt && !r.genotype(0).is_some_and(|g| g.alleles.contains(&Some(1))) {
+                continue;
+            }
+            out.push(r);
+        }
+    }
+    Ok(out)
+}
+
+/// Confident regions from a BED file.
+struct ConfidentRegions {
+    intervals: Vec<Interval>,
+    index: IntervalIndex,
+}
+
+impl ConfidentRegions {
+    fn load(path: &str) -> Result<Self, String> {
+        let intervals = cyanea_io::parse_bed(path)
+            .map_err(crate::to_nif_error)?
+            .into_iter()
+            .map(|r| Interval::new(r.interval.chrom, r.interval.start, r.interval.end, "."))
+            .collect::<Result<Vec<_>, _>>()?;
+        let index = IntervalIndex::new(&intervals, StrandMode::Ignore);
+        Ok(Self { intervals, index })
+    }
+
+    /// Whether `[beg, end)` is covered by the union of the regions.
+    fn contains(&self, chrom: &str, beg: u64, end: u64) -> bool {
+        let mut covered = beg;
+        for i in self.index.overlaps_on(chrom, beg, end) {
+            let iv = &self.intervals[i];
+            if iv.start > covered {
+                return false;
+            }
+            covered = covered.max(iv.end);
+            if covered >= end {
+                return true;
+            }
+        }
+        false
+    }
+}
@@ -0,0 +1,1042 @@
+//! Variant consequence prediction against a GFF3 gene model and FASTA.
+//!
+//! Transcripts are built from the GFF3 by following `Parent` links from
+//! exon, CDS and UTR features, so whatever feature type plays the
+//! transcript (mRNA, transcript, lnc_RNA, ...) is picked up. Consequences
+//! use the Sequence Ontology terms VEP reports, and HGVS is given as c./n.
+//! (3'-shifted within the exon or intron, as HGVS requires) and p. with
+//! three-letter codes.
+
+use crate::faidx::ChromCache;
+use crate::genetic_code::{three_letter, GeneticCode};
+use crate::intervals::{Interval, IntervalIndex, StrandMode};
+use crate::iupac;
+use crate::reader::open_input;
+use crate::writer::gff_unescape;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// Consequence terms, most severe first (VEP's ranking).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) enum Consequence {
+    SpliceAcceptor,
+    SpliceDonor,
+    StopGained,
+    Frameshift,
+    StopLost,
+    StartLost,
+    InframeInsertion,
+    InframeDeletion,
+    Missense,
+    SpliceRegion,
+    Synonymous,
+    StopRetained,
+    CodingSequence,
+    FivePrimeUtr,
+    ThreePrimeUtr,
+    NonCodingExon,
+    Intron,
+    Intergenic,
+}
+
+impl Consequence {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::SpliceAcceptor => "splice_acceptor_variant",
+            Self::SpliceDonor => "splice_donor_variant",
+            Self::StopGained => "stop_gained",
+            Self::Frameshift => "frameshift_variant",
+            Self::StopLost => "stop_lost",
+            Self::StartLost => "start_lost",
+            Self::InframeInsertion => "inframe_insertion",
+            Self::InframeDeletion => "inframe_deletion",
+            Self::Missense => "missense_variant",
+            Self::SpliceRegion => "splice_region_variant",
+            Self::Synonymous => "synonymous_variant",
+            Self::StopRetained => "stop_retained_variant",
+            Self::CodingSequence => "coding_sequence_variant",
+            Self::FivePrimeUtr => "5_prime_UTR_variant",
+            Self::ThreePrimeUtr => "3_prime_UTR_variant",
+            Self::NonCodingExon => "non_coding_transcript_exon_variant",
+            Self::Intron => "intron_variant",
+            Self::Intergenic => "intergenic_variant",
+        }
+    }
+
+    /// VEP impact class: HIGH, MODERATE, LOW or MODIFIER.
+    pub(crate) fn impact(self) -> &'static str {
+        match self {
+            Self::SpliceAcceptor
+            | Self::SpliceDonor
+            | Self::StopGained
+            | Self::Frameshift
+            | Self::StopLost
+            | Self::StartLost => "HIGH",
+            Self::InframeInsertion | Self::InframeDeletion | Self::Missense => "MODERATE",
+            Self::SpliceRegion | Self::Synonymous | Self::StopRetained => "LOW",
+            _ => "MODIFIER",
+        }
+    }
+}
+
+/// One transcript model. Coordinates are 0-based and half-open.
+#[derive(Debug, Clone)]
+pub(crate) struct Transcript {
+    pub id: String,
+    pub gene: Option<String>,
+    pub chrom: String,
+    pub reverse: bool,
+    /// Exons in genomic order, non-overlapping.
+    exons: Vec<(u64, u64)>,
+    /// Genomic span of the CDS, stop codon included.
+    cds: Option<(u64, u64)>,
+    /// Phase of the 5'-most CDS segment: bases before its first complete
+    /// codon.
+    phase: u64,
+    /// From a `transl_table` attribute, else table 2 on mitochondrial
+    /// contigs and the standard code elsewhere.
+    code: GeneticCode,
+}
+
+impl Transcript {
+    fn start(&self) -> u64 {
+        self.exons[0].0
+    }
+
+    fn end(&self) -> u64 {
+        self.exons[self.exons.len() - 1].1
+    }
+
+    fn len(&self) -> u64 {
+        self.exons.iter().map(|(s, e)| e - s).sum()
+    }
+
+    /// Offset of genomic base `g` from the transcript's 5' end, if exonic.
+    fn offset(&self, g: u64) -> Option<u64> {
+        let mut before = 0;
+        for &(s, e) in &self.exons {
+            if g >= s && g < e {
+                let forward = before + g - s;
+                return Some(if self.reverse {
+                    self.len() - 1 - forward
+                } else {
+                    forward
+                });
+            }
+            before += e - s;
+        }
+        None
+    }
+
+    /// CDS as transcript offsets `[start, end)`.
+    fn cds_offsets(&self) -> Option<(u64, u64)> {
+        let (cs, ce) = self.cds?;
+        let (a, b) = (self.offset(cs)?, self.offset(ce - 1)?);
+        Some((a.min(b), a.max(b) + 1))
+    }
+
+    /// Spliced sequence, 5' to 3'.
+    fn sequence(&self, genome: &[u8]) -> Vec<u8> {
+        let mut seq = Vec::with_capacity(self.len() as usize);
+        for &(s, e) in &self.exons {
+            seq.extend_from_slice(&genome[s as usize..e as usize]);
+        }
+        if self.reverse {
+            iupac::reverse_complement(&mut seq);
+        }
+        seq
+    }
+
+    fn introns(&self) -> impl Iterator<Item = (u64, u64)> + '_ {
+        self.exons.windows(2).map(|w| (w[0].1, w[1].0))
+    }
+
+    /// HGVS position of genomic base `g` (without the `c.`/`n.` prefix):
+    /// exonic positions count from the A of ATG (`-n` in the 5' UTR,
+    /// `*n` in the 3' UTR); intronic ones are offsets from the nearest
+    /// exon base, ties going to the upstream exon.
+    fn hgvs_position(&self, g: u64) -> Option<String> {
+        if let Some(t) = self.offset(g) {
+            return Some(self.exonic_position(t));
+        }
+        let (left, right) = self.introns().find(|&(s, e)| g >= s && g < e)?;
+        let (left, right) = (left - 1, right);
+        let (dl, dr) = (g - left, right - g);
+        let (upstream, down) = if self.reverse {
+            ((right, dr), (left, dl))
+        } else {
+            ((left, dl), (right, dr))
+        };
+        Some(if upstream.1 <= down.1 {
+            format!(
+                "{}+{}",
+                self.exonic_position(self.offset(upstream.0)?),
+                upstream.1
+            )
+        } else {
+            format!("{}-{}", self.exonic_position(self.offset(down.0)?), down.1)
+        })
+    }
+
+    fn exonic_position(&self, t: u64) -> String {
+        match self.cds_offsets() {
+            None => (t + 1).to_string(),
+            Some((c0, _)) if t < c0 => format!("-{}", c0 - t),
+            Some((c0, c1)) if t < c1 => (t - c0 + 1).to_string(),
+            Some((_, c1)) => format!("*{}", t - c1 + 1),
+        }
+    }
+}
+
+/// Predicted effect of one allele on one transcript (`None` when
+/// intergenic).
+#[derive(Debug, Clone)]
+pub(crate) struct Effect {
+    pub transcript: Option<usize>,
+    /// Most severe first.
+    pub consequences: Vec<Consequence>,
+    pub hgvs_c: Option<String>,
+    pub hgvs_p: Option<String>,
+}
+
+/// Transcripts of a GFF3 plus the reference they lie on. The reference
+/// is read one chromosome at a time, so annotating variants grouped by
+/// chromosome reads each sequence once.
+pub(crate) struct Annotator {
+    pub transcripts: Vec<Transcript>,
+    index: IntervalIndex,
+    /// Chromosomes carrying transcripts; only these are read.
+    chroms: HashSet<String>,
+    genome: ChromCache,
+}
+
+impl Annotator {
+    /// Load transcripts from `gff_path` and index the FASTA at
+    /// `fasta_path`.
+    pub(crate) fn load(gff_path: &str, fasta_path: &str) -> Result<Self, String> {
+        let transcripts = load_transcripts(gff_path)?;
+        let chroms = transcripts.iter().map(|t| t.chrom.clone()).collect();
+        let genome = ChromCache::open(fasta_path)?;
+        let spans: Vec<Interval> = transcripts
+            .iter()
+            .map(|t| Interval::new(t.chrom.clone(), t.start(), t.end(), "."))
+            .collect::<Result<_, _>>()?;
+        let index = IntervalIndex::new(&spans, StrandMode::Ignore);
+        Ok(Self {
+            transcripts,
+            index,
+            chroms,
+            genome,
+        })
+    }
+
+    /// Effects of `alt` at VCF position `pos` (1-based, `ref_allele`
+    /// padded as in VCF), one per overlapping transcript. Empty for
+    /// symbolic, `*` or no-change alleles.
+    pub(crate) fn annotate(
+        &mut self,
+        chrom: &str,
+        pos: u64,
+        ref_allele: &str,
+        alt: &str,
+    ) -> Result<Vec<Effect>, String> {
+        let is_bases = |s: &str| !s.is_empty() && s.bytes().all(|b| b"ACGTNacgtn".contains(&b));
+        if !is_bases(alt) {
+            return Ok(Vec::new());
+        }
+        if pos == 0 || !is_bases(ref_allele) {
+            return Err(format!(
+                "{chrom}:{pos}: invalid position or REF {ref_allele}"
+            ));
+        }
+        let (ref_bytes, alt_bytes) = (
+            ref_allele.to_ascii_uppercase().into_bytes(),
+            alt.to_ascii_uppercase().into_bytes(),
+        );
+        let start = pos - 1;
+        let genome = if self.chroms.contains(chrom) {
+            Some(self.genome.get(chrom)?)
+        } else {
+            None
+        };
+        if let Some(seq) = genome {
+            let end = start as usize + ref_bytes.len();
+            let actual = seq
+                .get(start as usize..end)
+                .ok_or_else(|| format!("{chrom}:{pos}: past the end of the reference"))?;
+            if actual != ref_bytes.as_slice() {
+                return Err(format!(
+                    "{chrom}:{pos}: REF {ref_allele} does not match the reference ({})",
+                    String::from_utf8_lossy(actual)
+                ));
+            }
+        }
+
+        // Minimal representation: trim the shared prefix, then suffix.
+        let prefix = ref_bytes
+            .iter()
+            .zip(&alt_bytes)
+            .take_while(|(a, b)| a == b)
+            .count();
+        let (r, a) = (&ref_bytes[prefix..], &alt_bytes[prefix..]);
+        let suffix = r
+            .iter()
+            .rev()
+            .zip(a.iter().rev())
+            .take_while(|(x, y)| x == y)
+            .count();
+        let (r, a) = (&r[..r.len() - suffix], &a[..a.len() - suffix]);
+        if r.is_empty() && a.is_empty() {
+            return Ok(Vec::new());
+        }
+        let s = start + prefix as u64;
+        let e = s + r.len() as u64;
+        let variant = Edit {
+            s,
+            e,
+            alt: a.to_vec(),
+        };
+
+        let mut effects = Vec::new();
+        let (qs, qe) = (s.saturating_sub(1), e.max(s + 1));
+        let mut hits = self.index.overlaps_on(chrom, qs, qe);
+        hits.sort_unstable();
+        for i in hits {
+            let tx = &self.transcripts[i];
+            if variant.hits(tx.start(), tx.end()) {
+                let genome = genome.ok_or_else(|| format!("no reference sequence for {chrom}"))?;
+                effects.push(effect(i, tx, &variant, genome));
+            }
+        }
+        if effects.is_empty() {
+            effects.push(Effect {
+                transcript: None,
+                consequences: vec![Consequence::Intergenic],
+                hgvs_c: None,
+                hgvs_p: None,
+            });
+        }
+        Ok(effects)
+    }
+}
+
+/// A minimal edit: reference `[s, e)` replaced by `alt` (either may be
+/// empty; an insertion goes between `s - 1` and `s`).
+#[derive(Debug, Clone)]
+struct Edit {
+    s: u64,
+    e: u64,
+    alt: Vec<u8>,
+}
+
+impl Edit {
+    /// Whether the edit touches `[a, b)`; an insertion must fall strictly
+    /// inside it.
+    fn hits(&self, a: u64, b: u64) -> bool {
+        if a >= b {
+            false
+        } else if self.s == self.e {
+            self.s > a && self.s < b
+        } else {
+            self.s < b && self.e > a
+        }
+    }
+
+    fn within(&self, a: u64, b: u64) -> bool {
+        if self.s == self.e {
+            self.s > a && self.s < b
+        } else {
+            a <= self.s && self.e <= b
+        }
+    }
+}
+
+fn effect(index: usize, tx: &Transcript, v: &Edit, genome: &[u8]) -> Effect {
+    let mut cons = BTreeSet::new();
+    for (is, ie) in tx.introns() {
+        if ie <= is {
+            continue;
+        }
+        if v.hits(is, ie) {
+            cons.insert(Consequence::Intron);
+        }
+        let head = (is, (is + 2).min(ie));
+        let tail = (ie.saturating_sub(2).max(is), ie);
+        let (donor, acceptor) = if tx.reverse {
+            (tail, head)
+        } else {
+            (head, tail)
+        };
+        let mut splice_site = false;
+        if v.hits(donor.0, donor.1) {
+            cons.insert(Consequence::SpliceDonor);
+            splice_site = true;
+        }
+        if v.hits(acceptor.0, acceptor.1) {
+            cons.insert(Consequence::SpliceAcceptor);
+            splice_site = true;
+        }
+        let near = v.hits(is.saturating_sub(3), (is + 8).min(ie))
+            || v.hits(ie.saturating_sub(8).max(is), ie + 3);
+        if near && !splice_site {
+            cons.insert(Consequence::SpliceRegion);
+        }
+    }
+
+    let mut hgvs_p = None;
+    if tx.exons.iter().any(|&(a, b)| v.hits(a, b)) {
+        match tx.cds {
+            None => {
+                cons.insert(Consequence::NonCodingExon);
+            }
+            Some((cs, ce)) => {
+                let (left, right) = if tx.reverse {
+                    (Consequence::ThreePrimeUtr, Consequence::FivePrimeUtr)
+                } else {
+                    (Consequence::FivePrimeUtr, Consequence::ThreePrimeUtr)
+                };
+                for &(a, b) in &tx.exons {
+                    if v.hits(a, b.min(cs)) {
+                        cons.insert(left);
+                    }
+                    if v.hits(a.max(ce), b) {
+                        cons.insert(right);
+                    }
+                }
+                if v.hits(cs, ce) {
+                    let contained = tx
+                        .exons
+                        .iter()
+                        .any(|&(a, b)| v.within(a.max(cs), b.min(ce)));
+                    let coding = if contained {
+                        coding_effect(tx, v, genome)
+                    } else {
+                        None
+                    };
+                    match coding {
+                        Some((c, p)) => {
+                            cons.extend(c);
+                            hgvs_p = p;
+                        }
+                        None => {
+                            cons.insert(Consequence::CodingSequence);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    if cons.is_empty() {
+        // Insertions exactly on a UTR/CDS edge.
+        cons.insert(if tx.cds.is_some() {
+            Consequence::CodingSequence
+        } else {
+            Consequence::NonCodingExon
+        });
+    }
+    let prefix = if tx.cds.is_some() { "c." } else { "n." };
+    Effect {
+        transcript: Some(index),
+        consequences: cons.into_iter().collect(),
+        hgvs_c: hgvs_c(tx, v, genome).map(|d| format!("{prefix}{d}")),
+        hgvs_p,
+    }
+}
+
+/// Coding consequences and HGVS p. for an edit inside one CDS exon, by
+/// translating the reference CDS and the edited transcript (read on into
+/// the 3' UTR for frameshifts and lost stops).
+fn coding_effect(
+    tx: &Transcript,
+    v: &Edit,
+    genome: &[u8],
+) -> Option<(Vec<Consequence>, Option<String>)> {
+    use Consequence::*;
+    let seq = tx.sequence(genome);
+    let (c0, c1) = tx.cds_offsets()?;
+    // Edit in transcript orientation: replace seq[ts..te] with alt.
+    let (ts, te) = match (v.s == v.e, tx.reverse) {
+        (true, true) => {
+            let t = tx.offset(v.s - 1)?;
+            (t, t)
+        }
+        (true, false) => {
+            let t = tx.offset(v.s)?;
+            (t, t)
+        }
+        (false, true) => (tx.offset(v.e - 1)?, tx.offset(v.s)? + 1),
+        (false, false) => (tx.offset(v.s)?, tx.offset(v.e - 1)? + 1),
+    };
+    let mut alt = v.alt.clone();
+    if tx.reverse {
+        iupac::reverse_complement(&mut alt);
+    }
+    // Codons are counted from the first complete one of the CDS.
+    let c0 = c0 + tx.phase;
+    if ts < c0 || c0 >= c1 {
+        return None;
+    }
+    let mut edited = seq[..ts as usize].to_vec();
+    edited.extend_from_slice(&alt);
+    edited.extend_from_slice(&seq[te as usize..]);
+
+    let cds = &seq[c0 as usize..c1 as usize];
+    let mut reference = translate(&tx.code, cds);
+    let mut altered = translate(&tx.code, &edited[c0 as usize..]);
+    // A start codon reads as Met whatever it codes for inside a protein;
+    // the edited one still does if unchanged or ATG.
+    if cds.len() >= 3 && tx.code.is_start(&cds[..3], true) {
+        reference[0] = b'M';
+        let first = edited.get(c0 as usize..c0 as usize + 3);
+        if first.is_some_and(|f| f == &cds[..3] || tx.code.is_start(f, false)) {
+            altered[0] = b'M';
+        }
+    }
+    let delta = alt.len() as i64 - (te - ts) as i64;
+    let codon = ((ts - c0) / 3) as usize;
+    let last_codon = ((te.max(ts + 1) - 1 - c0) / 3) as usize;
+    let aa = |p: &[u8], i: usize| three_letter(p[i]);
+
+    let a = reference
+        .iter()
+        .zip(&altered)
+        .take_while(|(x, y)| x == y)
+        .count();
+    if a == reference.len() && altered.len() >= reference.len() && reference.last() == Some(&b'*') {
+        // Protein unchanged through the stop codon.
+        let term = if reference.get(codon) == Some(&b'*') {
+            StopRetained
+        } else {
+            Synonymous
+        };
+        let p =
+            (codon < reference.len()).then(|| format!("p.{}{}=", aa(&reference, codon), codon + 1));
+        return Some((vec![term], p));
+    }
+    if a >= reference.len() {
+        // Incomplete CDS without a stop codon: nothing to compare against.
+        return Some((vec![CodingSequence], None));
+    }
+    let new_stop = altered.last() == Some(&b'*');
+    let ext = |n: usize| if new_stop { n.to_string() } else { "?".into() };
+
+    if a == 0 && reference[0] == b'M' {
+        let mut c = vec![StartLost];
+        if delta % 3 != 0 {
+            c.push(Frameshift);
+        }
+        return Some((c, Some("p.Met1?".into())));
+    }
+    if reference[a] == b'*' {
+        let mut c = vec![StopLost];
+        if delta % 3 != 0 {
+            c.push(Frameshift);
+        }
+        let p = altered.get(a).map(|&x| {
+            format!(
+                "p.Ter{}{}extTer{}",
+                a + 1,
+                three_letter(x),
+                ext(altered.len() - a)
+            )
+        });
+        return Some((c, p));
+    }
+    if delta % 3 != 0 {
+        if altered.get(a) == Some(&b'*') {
+            return Some((
+                vec![StopGained, Frameshift],
+                Some(format!("p.{}{}Ter", aa(&reference, a), a + 1)),
+            ));
+        }
+        let p = altered.get(a).map(|&x| {
+            format!(
+                "p.{}{}{}fsTer{}",
+                aa(&reference, a),
+                a + 1,
+                three_letter(x),
+                ext(altered.len() - a)
+            )
+        });
+        return Some((vec![Frameshift], p));
+    }
+
+    // In frame.
+    let expected_stop = (reference.len() as i64 - 1 + delta / 3) as usize;
+    if new_stop && altered.len() - 1 < expected_stop {
+        let p = if altered[a] == b'*' {
+            format!("p.{}{}Ter", aa(&reference, a), a + 1)
+        } else {
+            let end = last_codon.max(a).min(reference.len() - 1);
+            format!(
+                "p.{}delins{}",
+                range(&reference, a, end),
+                three_letters(&altered[a..])
+            )
+        };
+        return Some((vec![StopGained], Some(p)));
+    }
+    let b = reference[a..]
+        .iter()
+        .rev()
+        .zip(altered[a..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let r = &reference[a..reference.len() - b];
+    let q = &altered[a..altered.len() - b];
+    let term = match delta.signum() {
+        0 => Missense,
+        1 => InframeInsertion,
+        _ => InframeDeletion,
+    };
+    let p = match (r.len(), q.len()) {
+        (1, 1) => Some(format!(
+            "p.{}{}{}",
+            three_letter(r[0]),
+            a + 1,
+            three_letter(q[0])
+        )),
+        (k, 0) => Some(format!("p.{}del", range(&reference, a, a + k - 1))),
+        (0, m) if a >= m && &reference[a - m..a] == q => {
+            Some(format!("p.{}dup", range(&reference, a - m, a - 1)))
+        }
+        (0, _) if a >= 1 => Some(format!(
+            "p.{}ins{}",
+            range(&reference, a - 1, a),
+            three_letters(q)
+        )),
+        (0, _) => None,
+        (k, _) => Some(format!(
+            "p.{}delins{}",
+            range(&reference, a, a + k - 1),
+            three_letters(q)
+        )),
+    };
+    Some((vec![term], p))
+}
+
+/// `Xaa{i+1}` or `Xaa{i+1}_Yaa{j+1}` over protein indices `i..=j`.
+fn range(protein: &[u8], i: usize, j: usize) -> String {
+    if i == j {
+        format!("{}{}", three_letter(protein[i]), i + 1)
+    } else {
+        format!(
+            "{}{}_{}{}",
+            three_letter(protein[i]),
+            i + 1,
+            three_letter(protein[j]),
+            j + 1
+        )
+    }
+}
+
+/// HGVS c./n. description (without prefix) of `v`, shifted 3' along the
+/// transcript within the exon or intron holding it.
+fn hgvs_c(tx: &Transcript, v: &Edit, genome: &[u8]) -> Option<String> {
+    let (mut s, mut e, mut alt) = (v.s, v.e, v.alt.clone());
+    let regions = tx.exons.iter().copied().chain(tx.introns());
+    let bound = regions
+        .filter(|&(a, b)| a <= s && e <= b)
+        .min_by_key(|&(a, b)| b - a);
+    if let Some((lo, hi)) = bound {
+        let base = |i: u64| genome[i as usize];
+        match (s == e, alt.is_empty()) {
+            (false, true) if tx.reverse => {
+                while s > lo && base(s - 1) == base(e - 1) {
+                    s -= 1;
+                    e -= 1;
+                }
+            }
+            (false, true) => {
+                while e < hi && base(s) == base(e) {
+                    s += 1;
+                    e += 1;
+                }
+            }
+            (true, false) if tx.reverse => {
+                while s > lo && base(s - 1) == alt[alt.len() - 1] {
+                    alt.rotate_right(1);
+                    s -= 1;
+                }
+                e = s;
+            }
+            (true, false) => {
+                while s < hi && base(s) == alt[0] {
+                    alt.rotate_left(1);
+                    s += 1;
+                }
+                e = s;
+            }
+            _ => {}
+        }
+    }
+
+    let pos = |g: u64| tx.hgvs_position(g);
+    // Span [s, e) in transcript order.
+    let span = |s: u64, e: u64| -> Option<String> {
+        let (first, last) = if tx.reverse { (e - 1, s) } else { (s, e - 1) };
+        Some(if first == last {
+            pos(first)?
+        } else {
+            format!("{}_{}", pos(first)?, pos(last)?)
+        })
+    };
+    let oriented = |seq: &[u8]| {
+        let mut seq = seq.to_vec();
+        if tx.reverse {
+            iupac::reverse_complement(&mut seq);
+        }
+        String::from_utf8_lossy(&seq).into_owned()
+    };
+    let m = alt.len() as u64;
+    if s == e {
+        let dup = if tx.reverse {
+            genome.get(s as usize..(s + m) as usize) == Some(&alt[..])
+        } else {
+            s >= m && genome[(s - m) as usize..s as usize] == alt[..]
+        };
+        if dup {
+            let (ds, de) = if tx.reverse { (s, s + m) } else { (s - m, s) };
+            return Some(format!("{}dup", span(ds, de)?));
+        }
+        if s == 0 {
+            return None;
+        }
+        return Some(format!("{}ins{}", span(s - 1, s + 1)?, oriented(&alt)));
+    }
+    if alt.is_empty() {
+        return Some(format!("{}del", span(s, e)?));
+    }
+    if e - s == 1 && m == 1 {
+        let r = oriented(&genome[s as usize..e as usize]);
+        return Some(format!("{}{r}>{}", pos(s)?, oriented(&alt)));
+    }
+    Some(format!("{}delins{}", span(s, e)?, oriented(&alt)))
+}
+
+/// Read transcript models from a GFF3 (plain or compressed).
+pub(crate) fn load_transcripts(path: &str) -> Result<Vec<Transcript>, String> {
+    #[derive(Default)]
+    struct Parts {
+        chrom: String,
+        reverse: bool,
+        exons: Vec<(u64, u64)>,
+        /// `(start, end, phase)`.
+        cds: Vec<(u64, u64, u64)>,
+        utrs: Vec<(u64, u64)>,
+        transl_table: Option<String>,
+    }
+    struct Feature {
+        parents: Vec<String>,
+        attributes: HashMap<String, String>,
+    }
+
+    let mut features: HashMap<String, Feature> = HashMap::new();
+    let mut parts: HashMap<String, Parts> = HashMap::new();
+    let mut order: Vec<String> = Vec::new();
+    let mut input = open_input(path)?;
+    let mut line = String::new();
+    let mut line_no = 0u64;
+    loop {
+        line.clear();
+        if input
+            .read_line(&mut line)
+            .map_err(|e| format!("{path}: {e}"))?
+            == 0
+        {
+            break;
+        }
+        line_no += 1;
+        let l = line.trim_end_matches(['\n', '\r']);
+        if l.starts_with("##FASTA") {
+            break;
+        }
+        if l.is_empty() || l.starts_with('#') {
+            continue;
+        }
+        let cols: Vec<&str> = l.split('\t').collect();
+        if cols.len() < 9 {
+            return Err(format!("{path}: line {line_no}: expected 9 columns"));
+        }
+        let parse = |s: &str| {
+            s.parse::<u64>()
+                .map_err(|_| format!("{path}: line {line_no}: bad coordinate {s}"))
+        };
+        let (start, end) = (parse(cols[3])?, parse(cols[4])?);
+        if start == 0 || end < start {
+            return Err(format!(
+                "{path}: line {line_no}: bad coordinates {start}-{end}"
+            ));
+        }
+        let attributes: HashMap<String, String> = cols[8]
+            .split(';')
+            .filter_map(|kv| kv.trim().split_once('='))
+            .map(|(k, v)| (k.to_string(), gff_unescape(v)))
+            .collect();
+        let parents: Vec<String> = attributes
+            .get("Parent")
+            .map(|p| p.split(',').map(str::to_string).collect())
+            .unwrap_or_default();
+        let span = (start - 1, end);
+        let kind = match cols[2] {
+            "exon" => Some(0),
+            "CDS" => Some(1),
+            "five_prime_UTR" | "three_prime_UTR" | "UTR" => Some(2),
+            _ => None,
+        };
+        if let Some(kind) = kind {
+            for p in &parents {
+                let entry = parts.entry(p.clone()).or_insert_with(|| {
+                    order.push(p.clone());
+                    Parts {
+                        chrom: cols[0].to_string(),
+                        reverse: cols[6] == "-",
+                        ..Default::default()
+                    }
+                });
+                match kind {
+                    0 => entry.exons.push(span),
+                    1 => {
+                        let phase =
+                            match cols[7] {
+                                "." => 0,
+                                p => p.parse().ok().filter(|&p| p < 3).ok_or_else(|| {
+                                    format!("{path}: line {line_no}: bad phase {p}")
+                                })?,
+                            };
+                        entry.cds.push((span.0, span.1, phase));
+                        if let Some(t) = attributes.get("transl_table") {
+                            entry.transl_table = Some(t.clone());
+                        }
+                    }
+                    _ => entry.utrs.push(span),
+                }
+            }
+        } else if let Some(id) = attributes.get("ID") {
+            features.insert(
+                id.clone(),
+                Feature {
+                    parents,
+                    attributes,
+                },
+            );
+        }
+    }
+
+    let mut transcripts = Vec::with_capacity(order.len());
+    for id in order {
+        let p = parts.remove(&id).unwrap();
+        let mut exons = if p.exons.is_empty() {
+            p.cds
+                .iter()
+                .map(|&(s, e, _)| (s, e))
+                .chain(p.utrs.iter().copied())
+                .collect()
+        } else {
+            p.exons
+        };
+        exons.sort_unstable();
+        let mut merged: Vec<(u64, u64)> = Vec::with_capacity(exons.len());
+        for (s, e) in exons {
+            match merged.last_mut() {
+                Some(last) if s <= last.1 => last.1 = last.1.max(e),
+                _ => merged.push((s, e)),
+            }
+        }
+        let cds = p
+            .cds
+            .iter()
+            .map(|c| c.0)
+            .min()
+            .zip(p.cds.iter().map(|c| c.1).max());
+        let feature = features.get(&id);
+        let attr = |f: Option<&Feature>, keys: &[&str]| {
+            f.and_then(|f| keys.iter().find_map(|k| f.attributes.get(*k).cloned()))
+        };
+        let first_cds = if p.reverse {
+            p.cds.iter().max_by_key(|c| c.1)
+        } else {
+            p.cds.iter().min_by_key(|c| c.0)
+        };
+        let table = match attr(feature, &["transl_table"]).or(p.transl_table) {
+            Some(t) => t
+                .parse()
+                .map_err(|_| format!("{path}: transcript {id}: bad transl_table {t}"))?,
+            None if is_mitochondrial(&p.chrom) => 2,
+            None => 1,
+        };
+        let code = GeneticCode::ncbi(table).map_err(|e| format!("{path}: transcript {id}: {e}"))?;
+        let gene_feature = feature
+            .and_then(|f| f.parents.first())
+            .and_then(|g| features.get(g));
+        let gene = attr(gene_feature, &["Name", "gene_name", "gene_id", "ID"])
+            .or_else(|| attr(feature, &["gene_name", "gene", "gene_id"]));
+        transcripts.push(Transcript {
+            id: attr(feature, &["transcript_id"]).unwrap_or(id),
+            gene,
+            chrom: p.chrom,
+            reverse: p.reverse,
+            exons: merged,
+            cds,
+            phase: first_cds.map_or(0, |c| c.2),
+            code,
+        });
+    }
+    Ok(transcripts)
+}
+
+/// `chrM`, `MT` and the like.
+fn is_mitochondrial(chrom: &str) -> bool {
+    let name = chrom.strip_prefix("chr").unwrap_or(chrom);
+    matches!(name, "M" | "MT" | "Mt" | "mitochondrion")
+}
+
+/// Translate through the first stop codon, which is kept; a trailing
+/// partial codon is dropped.
+fn translate(code: &GeneticCode, seq: &[u8]) -> Vec<u8> {
+    let mut protein = code.translate(seq, true);
+    if protein.len() < seq.len() / 3 {
+        protein.push(b'*');
+    }
+    protein
+}
+
+fn three_letters(protein: &[u8]) -> String {
+    protein.iter().map(|&a| three_letter(a)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two-exon gene: 5' UTR, CDS `ATG AAA CCC | GGG TTT TAA` (M K P G F *)
+    /// across a GT...AG intron, 3' UTR.
+    const CHR1: &str = "GGGGGGGGGGCCCCCATGAAACCCGTAAGTTTTTTTTTTTTCAGGGGTTTTAACCCCCCCCCGGGGGGGGGG";
+
+    fn reverse(seq: &str) -> String {
+        let mut rc = seq.as_bytes().to_vec();
+        iupac::reverse_complement(&mut rc);
+        String::from_utf8(rc).unwrap()
+    }
+
+    /// GFF3 lines of the gene on `chrom`; `cds_phase` shifts the first CDS
+    /// segment one base upstream with that phase.
+    fn gene(chrom: &str, id: &str, minus: bool, cds_phase: u64) -> String {
+        let len = CHR1.len() as u64;
+        // 1-based inclusive spans on the plus-strand layout.
+        let spans = [
+            ("exon", 11, 24, "."),
+            ("exon", 45, 62, "."),
+            ("CDS", 16 - cds_phase, 24, &cds_phase.to_string()[..]),
+            ("CDS", 45, 53, "0"),
+        ];
+        let strand = if minus { '-' } else { '+' };
+        let mut out = format!("{chrom}\t.\tmRNA\t11\t62\t.\t{strand}\t.\tID={id}\n");
+        for (kind, s, e, phase) in spans {
+            let (s, e) = if minus {
+                (len + 1 - e, len + 1 - s)
+            } else {
+                (s, e)
+            };
+            out.push_str(&format!(
+                "{chrom}\t.\t{kind}\t{s}\t{e}\t.\t{strand}\t{phase}\tParent={id}\n"
+            ));
+        }
+        out
+    }
+
+    fn annotator(name: &str) -> Annotator {
+        let dir = std::env::temp_dir().join(format!("cyanea_csq_{}_{name}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let fasta = dir.join("ref.fa");
+        let gff = dir.join("genes.gff3");
+        std::fs::write(
+            &fasta,
+            format!(
+                ">chr1\n{CHR1}\n>chr2\n{}\n>chrM\n{CHR1}\n>chr3\n{CHR1}\n",
+                reverse(CHR1)
+            ),
+        )
+        .unwrap();
+        std::fs::write(
+            &gff,
+            format!(
+                "##gff-version 3\n{}{}{}{}",
+                gene("chr1", "tx1", false, 0),
+                gene("chr2", "tx2", true, 0),
+                gene("chrM", "txM", false, 0),
+                gene("chr3", "tx3", false, 1)
+            ),
+        )
+        .unwrap();
+        Annotator::load(gff.to_str().unwrap(), fasta.to_str().unwrap()).unwrap()
+    }
+
+    fn one(an: &mut Annotator, chrom: &str, pos: u64, r: &str, a: &str) -> Effect {
+        let mut effects = an.annotate(chrom, pos, r, a).unwrap();
+        assert_eq!(effects.len(), 1);
+        effects.pop().unwrap()
+    }
+
+    #[test]
+    fn missense_snv() {
+        let mut an = annotator("missense");
+        let e = one(&mut an, "chr1", 21, "A", "C");
+        assert_eq!(e.consequences, [Consequence::Missense]);
+        assert_eq!(e.hgvs_c.as_deref(), Some("c.6A>C"));
+        assert_eq!(e.hgvs_p.as_deref(), Some("p.Lys2Asn"));
+    }
+
+    #[test]
+    fn frameshift_deletion() {
+        let mut an = annotator("frameshift");
+        let e = one(&mut an, "chr1", 18, "GA", "G");
+        assert_eq!(e.consequences, [Consequence::Frameshift]);
+        assert_eq!(e.hgvs_c.as_deref(), Some("c.6del"));
+        assert_eq!(e.hgvs_p.as_deref(), Some("p.Lys2AsnfsTer?"));
+    }
+
+    #[test]
+    fn splice_donor() {
+        let mut an = annotator("splice");
+        let e = one(&mut an, "chr1", 26, "T", "A");
+        assert_eq!(
+            e.consequences,
+            [Consequence::SpliceDonor, Consequence::Intron]
+        );
+        assert_eq!(e.hgvs_c.as_deref(), Some("c.9+2T>A"));
+        assert_eq!(e.hgvs_p, None);
+    }
+
+    #[test]
+    fn minus_strand_matches_plus_strand() {
+        let mut an = annotator("minus");
+        let len = CHR1.len() as u64;
+        // The chr1 missense SNV (0-based 20, A>C) mirrored onto chr2.
+        let e = one(&mut an, "chr2", len - 20, "T", "G");
+        assert_eq!(e.consequences, [Consequence::Missense]);
+        assert_eq!(e.hgvs_c.as_deref(), Some("c.6A>C"));
+        assert_eq!(e.hgvs_p.as_deref(), Some("p.Lys2Asn"));
+        let e = one(&mut an, "chr2", len - 25, "A", "T");
+        assert_eq!(e.hgvs_c.as_deref(), Some("c.9+2T>A"));
+        assert_eq!(e.consequences[0], Consequence::SpliceDonor);
+    }
+
+    #[test]
+    fn mitochondrial_code() {
+        let mut an = annotator("mitochondrial");
+        // AAA>AGA: arginine in the standard code, a stop in table 2.
+        let e = one(&mut an, "chr1", 20, "A", "G");
+        assert_eq!(e.hgvs_p.as_deref(), Some("p.Lys2Arg"));
+        let e = one(&mut an, "chrM", 20, "A", "G");
+        assert_eq!(e.consequences, [Consequence::StopGained]);
+        assert_eq!(e.hgvs_p.as_deref(), Some("p.Lys2Ter"));
+    }
+
+    #[test]
+    fn cds_phase() {
+        let mut an = annotator("cds");
+        let e = one(&mut an, "chr3", 21, "A", "C");
+        assert_eq!(e.consequences, [Consequence::Missense]);
+        assert_eq!(e.hgvs_p.as_deref(), Some("p.Lys2Asn"));
+    }
+}
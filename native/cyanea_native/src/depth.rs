@@ -0,0 +1,437 @@
+//! Per-base read depth from coordinate-sorted BAM files.
+//!
+//! Alignments are streamed once through a sliding window that starts at
+//! the current read position, so memory is bounded by the longest
+//! reference span of a single read rather than by chromosome length.
+//! Depth comes out as runs of equal depth covering every reference from 0
+//! to its length, which the bedGraph and binning output consume. Target
+//! summaries read only the reads over the targets through the BAM index
+//! when there is one. Like `samtools depth`, deletions and reference skips
+//! are not counted and overlapping mates are each counted.
+
+use crate::bam::{self, BamReader, BamRecord};
+use crate::intervals::{Interval, IntervalIndex, StrandMode};
+use crate::writer::OutputFile;
+use std::collections::{BTreeMap, VecDeque};
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct DepthFilter {
+    pub min_mapq: u8,
+    /// Reads with any of these flag bits set are skipped.
+    pub exclude_flags: u16,
+    /// Aligned bases below this phred score are not counted.
+    pub min_base_quality: u8,
+}
+
+impl DepthFilter {
+    fn accepts(&self, rec: &BamRecord) -> bool {
+        rec.ref_id >= 0 && rec.flag & self.exclude_flags == 0 && rec.mapq >= self.min_mapq
+    }
+}
+
+/// Sliding pileup over one reference at a time. `window[i]` is the depth
+/// at `win_start + i`; positions before `win_start` have been emitted.
+struct Pileup<'a, F> {
+    refs: &'a [(String, u64)],
+    emit: F,
+    /// Run being extended: `(start, end, depth)` on `tid`.
+    pending: Option<(u64, u64, u32)>,
+    tid: usize,
+    win_start: u64,
+    window: VecDeque<u32>,
+}
+
+impl<F: FnMut(&str, u64, u64, u32) -> Result<(), String>> Pileup<'_, F> {
+    fn segment(&mut self, start: u64, end: u64, depth: u32) -> Result<(), String> {
+        match &mut self.pending {
+            Some((_, e, d)) if *e == start && *d == depth => *e = end,
+            pending => {
+                if let Some((s, e, d)) = pending.replace((start, end, depth)) {
+                    (self.emit)(&self.refs[self.tid].0, s, e, d)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Emit every position below `pos`, clamped to the reference end.
+    fn advance(&mut self, pos: u64) -> Result<(), String> {
+        let pos = pos.min(self.refs[self.tid].1);
+        while self.win_start < pos {
+            let start = self.win_start;
+            match self.window.pop_front() {
+                Some(d) => {
+                    self.segment(start, start + 1, d)?;
+                    self.win_start += 1;
+                }
+                None => {
+                    self.segment(start, pos, 0)?;
+                    self.win_start = pos;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Emit everything below `end` and the run in progress.
+    fn finish(&mut self, end: u64) -> Result<(), String> {
+        self.advance(end)?;
+        if let Some((s, e, d)) = self.pending.take() {
+            (self.emit)(&self.refs[self.tid].0, s, e, d)?;
+        }
+        Ok(())
+    }
+
+    /// Finish the current reference and move to the next one.
+    fn next_ref(&mut self) -> Result<(), String> {
+        self.finish(u64::MAX)?;
+        self.start(self.tid + 1, 0);
+        Ok(())
+    }
+
+    /// Start over at `pos` on reference `tid`.
+    fn start(&mut self, tid: usize, pos: u64) {
+        self.window.clear();
+        self.win_start = pos;
+        self.tid = tid;
+    }
+
+    /// Count the aligned bases of `rec`, which must not start before the
+    /// last position passed to [`Pileup::advance`]. Bases before the
+    /// window (a region's start) or past the reference end are dropped.
+    fn pile(&mut self, rec: &BamRecord, min_base_quality: u8) {
+        let ref_len = self.refs[self.tid].1;
+        let (mut r, mut q) = (rec.pos.max(0) as u64, 0usize);
+        for &(len, op) in &rec.cigar {
+            let len = len as usize;
+            match op {
+                b'M' | b'=' | b'X' => {
+                    for i in 0..len {
+                        let at = r + i as u64;
+                        if at >= ref_len {
+                            break;
+                        }
+                        let qual_ok = rec
+                            .quality
+                            .get(q + i)
+                            .is_none_or(|&bq| bq >= min_base_quality);
+                        if qual_ok && at >= self.win_start {
+                            self.add(at);
+                        }
+                    }
+                    r += len as u64;
+                    q += len;
+                }
+                b'D' | b'N' => r += len as u64,
+                b'I' | b'S' => q += len,
+                _ => {}
+            }
+        }
+    }
+
+    fn add(&mut self, at: u64) {
+        let slot = (at - self.win_start) as usize;
+        if slot >= self.window.len() {
+            self.window.resize(slot + 1, 0);
+        }
+        self.window[slot] += 1;
+    }
+}
+
+/// Stream depth runs `(chrom, start, end, depth)` over every reference of
+/// `path` in header order, zero-depth stretches included.
+pub(crate) fn scan<F>(path: &str, filter: DepthFilter, emit: F) -> Result<(), String>
+where
+    F: FnMut(&str, u64, u64, u32) -> Result<(), String>,
+{
+    let mut reader = BamReader::open(path)?;
+    let refs = reader.header().refs.clone();
+    let mut pileup = Pileup {
+        refs: &refs,
+        emit,
+        pending: None,
+        tid: 0,
+        win_start: 0,
+        window: VecDeque::new(),
+    };
+    let (mut last_tid, mut last_pos) = (0usize, 0u64);
+    while let Some(rec) = reader.next_record()? {
+        if rec.ref_id < 0 {
+            break;
+        }
+        let rid = rec.ref_id as usize;
+        let pos = rec.pos.max(0) as u64;
+        if rid < last_tid || (rid == last_tid && pos < last_pos) || rid >= refs.len() {
+            return Err(format!(
+                "{path}: not coordinate-sorted (at read {}); sort the BAM first",
+                rec.qname
+            ));
+        }
+        (last_tid, last_pos) = (rid, pos);
+        if !filter.accepts(&rec) {
+            continue;
+        }
+        while pileup.tid < rid {
+            pileup.next_ref()?;
+        }
+        pileup.advance(pos)?;
+        pileup.pile(&rec, filter.min_base_quality);
+    }
+    while pileup.tid < refs.len() {
+        pileup.next_ref()?;
+    }
+    Ok(())
+}
+
+/// Stream depth runs like [`scan`], but only over `regions`: sorted,
+/// non-overlapping `(tid, start, end)` spans whose reads are fetched
+/// through the BAM index.
+fn scan_regions<F>(
+    path: &str,
+    regions: &[(usize, u64, u64)],
+    filter: DepthFilter,
+    emit: F,
+) -> Result<(), String>
+where
+    F: FnMut(&str, u64, u64, u32) -> Result<(), String>,
+{
+    let index = bam::read_index(path)?;
+    let mut reader = BamReader::open(path)?;
+    let refs = reader.header().refs.clone();
+    let mut pileup = Pileup {
+        refs: &refs,
+        emit,
+        pending: None,
+        tid: 0,
+        win_start: 0,
+        window: VecDeque::new(),
+    };
+    for &(tid, start, end) in regions {
+        pileup.start(tid, start);
+        bam::fetch(&mut reader, &index, tid, start, end, |rec| {
+            if filter.accepts(&rec) {
+                pileup.advance(rec.pos.max(0) as u64)?;
+                pileup.pile(&rec, filter.min_base_quality);
+            }
+            Ok(())
+        })?;
+        pileup.finish(end)?;
+    }
+    Ok(())
+}
+
+/// Write depth as bedGraph: runs of equal depth, or mean depth over fixed
+/// `bin_size` bins when it is non-zero. Zero-depth lines are written only
+/// with `include_zero`. Returns the number of lines written.
+pub(crate) fn write_bedgraph(
+    path: &str,
+    output: &str,
+    filter: DepthFilter,
+    bin_size: u64,
+    include_zero: bool,
+    bgzf: bool,
+) -> Result<u64, String> {
+    let mut out = OutputFile::create(output, bgzf)?;
+    let mut lines = 0u64;
+    let mut write = |chrom: &str, start: u64, end: u64, value: String, zero: bool| {
+        if zero && !include_zero {
+            return Ok(());
+        }
+        lines += 1;
+        out.line(&format!("{chrom}\t{start}\t{end}\t{value}"))
+    };
+
+    if bin_size == 0 {
+        scan(path, filter, |chrom, s, e, d| {
+            write(chrom, s, e, d.to_string(), d == 0)
+        })?;
+    } else {
+        // Open bin: (chrom, bin index, summed depth, end so far).
+        let mut bin: Option<(String, u64, u64, u64)> = None;
+        let mut flush = |(chrom, k, sum, end): (String, u64, u64, u64)| {
+            let start = k * bin_size;
+            let mean = sum as f64 / (end - start) as f64;
+            write(&chrom, start, end, format_mean(mean), sum == 0)
+        };
+        scan(path, filter, |chrom, mut s, e, d| {
+            while s < e {
+                let k = s / bin_size;
+                let bin_end = ((k + 1) * bin_size).min(e);
+                let depth = d as u64 * (bin_end - s);
+                match &mut bin {
+                    Some((c, bk, sum, end)) if c == chrom && *bk == k => {
+                        *sum += depth;
+                        *end = bin_end;
+                    }
+                    current => {
+                        if let Some(done) = current.replace((chrom.to_string(), k, depth, bin_end)) {
+                            flush(done)?;
+                        }
+                    }
+                }
+                s = bin_end;
+            }
+            Ok(())
+        })?;
+        if let Some(done) = bin {
+            flush(done)?;
+        }
+    }
+    out.finish()?;
+    Ok(lines)
+}
+
+fn format_mean(v: f64) -> String {
+    let s = format!("{v:.4}");
+    s.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// Depth distribution over one region or a whole target set.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DepthStats {
+    /// Depth -> number of bases.
+    pub histogram: BTreeMap<u32, u64>,
+}
+
+impl DepthStats {
+    fn add(&mut self, depth: u32, bases: u64) {
+        *self.histogram.entry(depth).or_default() += bases;
+    }
+
+    pub(crate) fn bases(&self) -> u64 {
+        self.histogram.values().sum()
+    }
+
+    pub(crate) fn mean(&self) -> f64 {
+        let n = self.bases();
+        if n == 0 {
+            return 0.0;
+        }
+        self.histogram
+            .iter()
+            .map(|(&d, &c)| d as f64 * c as f64)
+            .sum::<f64>()
+            / n as f64
+    }
+
+    /// Median depth (lower median for an even number of bases).
+    pub(crate) fn median(&self) -> f64 {
+        let n = self.bases();
+        if n == 0 {
+            return 0.0;
+        }
+        let mid = (n - 1) / 2;
+        let mut seen = 0;
+        for (&d, &c) in &self.histogram {
+            seen += c;
+            if seen > mid {
+                return d as f64;
+            }
+        }
+        0.0
+    }
+
+    pub(crate) fn min(&self) -> u32 {
+        self.histogram.keys().next().copied().unwrap_or(0)
+    }
+
+    pub(crate) fn max(&self) -> u32 {
+        self.histogram.keys().next_back().copied().unwrap_or(0)
+    }
+
+    /// Fraction of bases with depth of at least `threshold`.
+    pub(crate) fn fraction_at_least(&self, threshold: u32) -> f64 {
+        let n = self.bases();
+        if n == 0 {
+            return 0.0;
+        }
+        self.histogram.range(threshold..).map(|(_, &c)| c).sum::<u64>() as f64 / n as f64
+    }
+}
+
+/// Depth statistics for each target, plus the pooled distribution over
+/// all target bases (overlapping targets count their shared bases twice).
+/// With a BAI/CSI index only the reads over the merged targets are read;
+/// without one the whole BAM is streamed.
+pub(crate) fn target_depth(
+    path: &str,
+    targets: &[Interval],
+    filter: DepthFilter,
+) -> Result<(Vec<DepthStats>, DepthStats), String> {
+    let index = IntervalIndex::new(targets, StrandMode::Ignore);
+    let mut per_target = vec![DepthStats::default(); targets.len()];
+    let mut total = DepthStats::default();
+    let header = BamReader::open(path)?.header().clone();
+    let mut regions = Vec::with_capacity(targets.len());
+    for t in targets {
+        let tid = header
+            .ref_id(&t.chrom)
+            .ok_or_else(|| format!("target chromosome {} is not in {path}", t.chrom))?;
+        if t.start < t.end {
+            regions.push((tid, t.start, t.end));
+        }
+    }
+    regions.sort_unstable();
+    let mut merged: Vec<(usize, u64, u64)> = Vec::with_capacity(regions.len());
+    for (tid, s, e) in regions {
+        match merged.last_mut() {
+            Some(last) if last.0 == tid && s <= last.2 => last.2 = last.2.max(e),
+            _ => merged.push((tid, s, e)),
+        }
+    }
+
+    let emit = |chrom: &str, s: u64, e: u64, d: u32| {
+        for i in index.overlaps_on(chrom, s, e) {
+            let bases = e.min(targets[i].end) - s.max(targets[i].start);
+            per_target[i].add(d, bases);
+            total.add(d, bases);
+        }
+        Ok(())
+    };
+    if bam::find_index(path).is_some() {
+        scan_regions(path, &merged, filter, emit)?;
+    } else {
+        scan(path, filter, emit)?;
+    }
+    Ok((per_target, total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bam::tests::write_bam;
+
+    #[test]
+    fn indexed_targets_match_a_full_scan() {
+        let dir = std::env::temp_dir().join(format!("cyanea-depth-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let bam = dir.join("reads.bam");
+        write_bam(&bam, 100_000, &[10, 15, 100, 500, 505, 60_000]);
+        let path = bam.to_str().unwrap();
+        let targets: Vec<Interval> = [(0, 20), (12, 30), (495, 520), (900, 950), (59_995, 60_100)]
+            .into_iter()
+            .map(|(s, e)| Interval::new("chr1".into(), s, e, ".").unwrap())
+            .collect();
+        let filter = DepthFilter {
+            min_mapq: 0,
+            exclude_flags: 0,
+            min_base_quality: 0,
+        };
+
+        let (streamed, streamed_total) = target_depth(path, &targets, filter).unwrap();
+        crate::bam::build_index(path, false).unwrap();
+        let (indexed, indexed_total) = target_depth(path, &targets, filter).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let hist = |s: &DepthStats| s.histogram.clone().into_iter().collect::<Vec<_>>();
+        assert_eq!(hist(&indexed[0]), [(0, 10), (1, 5), (2, 5)]);
+        assert_eq!(hist(&indexed[1]), [(0, 5), (1, 8), (2, 5)]);
+        assert_eq!(hist(&indexed[2]), [(0, 10), (1, 10), (2, 5)]);
+        assert_eq!(hist(&indexed[3]), [(0, 50)]);
+        assert_eq!(hist(&indexed[4]), [(0, 95), (1, 10)]);
+        for (a, b) in indexed.iter().zip(&streamed) {
+            assert_eq!(a.histogram, b.histogram);
+        }
+        assert_eq!(indexed_total.histogram, streamed_total.histogram);
+    }
+}
@@ -0,0 +1,318 @@
+//! FASTA `.fai` indexing and `samtools faidx`-style random access.
+//!
+//! The index records, per sequence, its length, the byte offset of its
+//! first base and its line layout (bases and bytes per line), so a slice
+//! is read with one seek instead of a scan. Every line of a sequence but
+//! the last must have the same length, as samtools requires. Only
+//! uncompressed FASTA can be indexed.
+
+use crate::iupac;
+use crate::reader::{open_input, GZIP_MAGIC, ZSTD_MAGIC};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+
+/// One `.fai` line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct FaiEntry {
+    pub name: String,
+    pub length: u64,
+    /// Byte offset of the first base.
+    pub offset: u64,
+    pub line_bases: u64,
+    /// Bytes per line including the line terminator.
+    pub line_width: u64,
+}
+
+/// Scan `path` and return the index of each sequence in file order.
+pub(crate) fn build_index(path: &str) -> Result<Vec<FaiEntry>, String> {
+    let file = File::open(path).map_err(|e| format!("{path}: {e}"))?;
+    let mut input = BufReader::with_capacity(1 << 16, file);
+    let head = input.fill_buf().map_err(|e| e.to_string())?;
+    if head.starts_with(&GZIP_MAGIC) || head.starts_with(&ZSTD_MAGIC) {
+        return Err(format!("{path}: cannot index a compressed FASTA"));
+    }
+
+    let mut entries: Vec<FaiEntry> = Vec::new();
+    let mut names: HashMap<String, u64> = HashMap::new();
+    let mut line = Vec::new();
+    let mut pos = 0u64;
+    let mut line_no = 0u64;
+    // Set after a blank line or one shorter than `line_bases`; only the
+    // last line of a sequence may be.
+    let mut short_line = false;
+    loop {
+        line.clear();
+        let n = input
+            .read_until(b'\n', &mut line)
+            .map_err(|e| e.to_string())? as u64;
+        if n == 0 {
+            break;
+        }
+        line_no += 1;
+        let start = pos;
+        pos += n;
+        let bases = line
+            .iter()
+            .rev()
+            .skip_while(|&&b| b == b'\n' || b == b'\r')
+            .count() as u64;
+        if line.first() == Some(&b'>') {
+            let header = String::from_utf8_lossy(&line[1..bases as usize]);
+            let name = header
+                .split(char::is_whitespace)
+                .next()
+                .unwrap_or_default()
+                .to_string();
+            if name.is_empty() {
+                return Err(format!("{path}: line {line_no}: empty sequence name"));
+            }
+            if let Some(first) = names.insert(name.clone(), line_no) {
+                return Err(format!(
+                    "{path}: line {line_no}: duplicate sequence {name} (first on line {first})"
+                ));
+            }
+            entries.push(FaiEntry {
+                name,
+                length: 0,
+                offset: pos,
+                line_bases: 0,
+                line_width: 0,
+            });
+            short_line = false;
+            continue;
+        }
+        let Some(entry) = entries.last_mut() else {
+            if bases == 0 {
+                continue;
+            }
+            return Err(format!(
+                "{path}: line {line_no}: sequence before the first header"
+            ));
+        };
+        if bases == 0 {
+            // Blank lines may only trail a sequence.
+            short_line = true;
+            continue;
+        }
+        if short_line {
+            return Err(format!(
+                "{path}: line {line_no}: different line length in sequence {}",
+                entry.name
+            ));
+        }
+        if entry.line_bases == 0 {
+            entry.offset = start;
+            entry.line_bases = bases;
+            entry.line_width = n;
+        } else if bases > entry.line_bases || n - bases != entry.line_width - entry.line_bases {
+            return Err(format!(
+                "{path}: line {line_no}: different line length in sequence {}",
+                entry.name
+            ));
+        }
+        short_line = bases < entry.line_bases;
+        entry.length += bases;
+    }
+    Ok(entries)
+}
+
+/// The index as `.fai` text.
+pub(crate) fn format_index(entries: &[FaiEntry]) -> String {
+    entries
+        .iter()
+        .map(|e| {
+            format!(
+                "{}\t{}\t{}\t{}\t{}\n",
+                e.name, e.length, e.offset, e.line_bases, e.line_width
+            )
+        })
+        .collect()
+}
+
+/// Parse `.fai` text.
+pub(crate) fn read_index(path: &str) -> Result<Vec<FaiEntry>, String> {
+    let mut text = String::new();
+    open_input(path)?
+        .read_to_string(&mut text)
+        .map_err(|e| format!("{path}: {e}"))?;
+    text.lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty())
+        .map(|(i, l)| {
+            let f: Vec<&str> = l.split('\t').collect();
+            let num = |k: usize| -> Result<u64, String> {
+                f.get(k)
+                    .and_then(|v| v.parse().ok())
+                    .ok_or_else(|| format!("{path}: line {}: malformed .fai entry", i + 1))
+            };
+            Ok(FaiEntry {
+                name: f[0].to_string(),
+                length: num(1)?,
+                offset: num(2)?,
+                line_bases: num(3)?,
+                line_width: num(4)?,
+            })
+        })
+        .collect()
+}
+
+/// A 0-based, half-open slice of one sequence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Region {
+    pub name: String,
+    pub start: u64,
+    pub end: u64,
+}
+
+/// Random access to an indexed FASTA.
+pub(crate) struct Faidx {
+    file: File,
+    path: String,
+    entries: Vec<FaiEntry>,
+    by_name: HashMap<String, usize>,
+}
+
+impl Faidx {
+    /// Open `path`, reading `path.fai` when it exists and indexing the
+    /// file in memory otherwise.
+    pub(crate) fn open(path: &str) -> Result<Self, String> {
+        let fai = format!("{path}.fai");
+        let entries = if std::path::Path::new(&fai).exists() {
+            read_index(&fai)?
+        } else {
+            build_index(path)?
+        };
+        let by_name = entries
+            .iter()
+            .enumerate()
+            .map(|(i, e)| (e.name.clone(), i))
+            .collect();
+        Ok(Self {
+            file: File::open(path).map_err(|e| format!("{path}: {e}"))?,
+            path: path.to_string(),
+            entries,
+            by_name,
+        })
+    }
+
+    pub(crate) fn entries(&self) -> &[FaiEntry] {
+        &self.entries
+    }
+
+    pub(crate) fn entry(&self, name: &str) -> Option<&FaiEntry> {
+        self.by_name.get(name).map(|&i| &self.entries[i])
+    }
+
+    /// Parse a samtools region: `name`, `name:start`, `name:start-end` or
+    /// `name:-end`, 1-based and inclusive, with optional thousands commas.
+    /// A name that itself contains `:` is matched whole first. The end is
+    /// clipped to the sequence length.
+    pub(crate) fn parse_region(&self, region: &str) -> Result<Region, String> {
+        if let Some(e) = self.entry(region) {
+            return Ok(Region {
+                name: e.name.clone(),
+                start: 0,
+                end: e.length,
+            });
+        }
+        let (name, range) = region
+            .rsplit_once(':')
+            .ok_or_else(|| format!("{}: no sequence {region}", self.path))?;
+        let entry = self
+            .entry(name)
+            .ok_or_else(|| format!("{}: no sequence {name}", self.path))?;
+        let bad = || format!("invalid region {region}");
+        let num =
+            |s: &str| -> Result<u64, String> { s.replace(',', "").parse().map_err(|_| bad()) };
+        let (start, end) = match range.split_once('-') {
+            Some((s, e)) => (
+                if s.is_empty() { 1 } else { num(s)? },
+                if e.is_empty() { entry.length } else { num(e)? },
+            ),
+            None => (num(range)?, entry.length),
+        };
+        if start == 0 || start > end {
+            return Err(bad());
+        }
+        Ok(Region {
+            name: entry.name.clone(),
+            start: (start - 1).min(entry.length),
+            end: end.min(entry.length),
+        })
+    }
+
+    /// Bases of `region`, as stored (case is kept).
+    pub(crate) fn fetch(&mut self, region: &Region) -> Result<Vec<u8>, String> {
+        let e = self
+            .entry(&region.name)
+            .ok_or_else(|| format!("{}: no sequence {}", self.path, region.name))?
+            .clone();
+        let end = region.end.min(e.length);
+        if region.start >= end {
+            return Ok(Vec::new());
+        }
+        let byte = |base: u64| e.offset + base / e.line_bases * e.line_width + base % e.line_bases;
+        let first = byte(region.start);
+        let last = byte(end - 1);
+        let mut raw = vec![0u8; (last - first + 1) as usize];
+        self.file
+            .seek(SeekFrom::Start(first))
+            .and_then(|_| self.file.read_exact(&mut raw))
+            .map_err(|err| format!("{}: {}: {err}", self.path, region.name))?;
+        raw.retain(|b| !b.is_ascii_whitespace());
+        if raw.len() as u64 != end - region.start {
+            return Err(format!(
+                "{}: {}: index does not match the file",
+                self.path, region.name
+            ));
+        }
+        Ok(raw)
+    }
+
+    /// [`Faidx::parse_region`] then [`Faidx::fetch`]; with `reverse`, the
+    /// reverse complement.
+    pub(crate) fn fetch_region(&mut self, region: &str, reverse: bool) -> Result<Vec<u8>, String> {
+        let region = self.parse_region(region)?;
+        let mut seq = self.fetch(&region)?;
+        if reverse {
+            iupac::reverse_complement(&mut seq);
+        }
+        Ok(seq)
+    }
+}
+
+/// One whole sequence at a time from an indexed FASTA, upper-cased, for
+/// callers that walk variants chromosome by chromosome. Asking for another
+/// sequence drops the one held, so memory is bounded by the longest
+/// chromosome rather than the genome.
+pub(crate) struct ChromCache {
+    faidx: Faidx,
+    name: Option<String>,
+    seq: Vec<u8>,
+}
+
+impl ChromCache {
+    pub(crate) fn open(path: &str) -> Result<Self, String> {
+        Ok(Self {
+            faidx: Faidx::open(path)?,
+            name: None,
+            seq: Vec::new(),
+        })
+    }
+
+    /// The sequence `name`, read from the file unless it is the one held.
+    pub(crate) fn get(&mut self, name: &str) -> Result<&[u8], String> {
+        if self.name.as_deref() != Some(name) {
+            let region = Region {
+                name: name.to_string(),
+                start: 0,
+                end: u64::MAX,
+            };
+            self.seq = self.faidx.fetch(&region)?;
+            self.seq.make_ascii_uppercase();
+            self.name = Some(region.name);
+        }
+        Ok(&self.seq)
+    }
+}
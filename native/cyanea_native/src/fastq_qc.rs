@@ -0,0 +1,407 @@
+//! FastQC-style quality report for a FASTQ file.
+//!
+//! Reads are streamed once, keeping per-cycle quality histograms and base
+//! counts, per-read GC and mean quality, lengths and the counts needed for
+//! duplication and overrepresented sequences. Module statuses use FastQC's
+//! default pass/warn/fail thresholds.
+
+use crate::reader::SeqReader;
+use std::collections::{BTreeMap, HashMap};
+
+/// Highest Phred score tracked (Phred+33 `~`).
+const MAX_QUALITY: usize = 93;
+/// Distinct sequences tracked for duplication, as in FastQC.
+const DUPLICATION_TRACKED: usize = 100_000;
+/// Reads longer than this are truncated to [`DUPLICATION_PREFIX`] bases
+/// for duplication counting.
+const DUPLICATION_LONG: usize = 75;
+const DUPLICATION_PREFIX: usize = 50;
+/// Fraction of reads above which a sequence is overrepresented.
+const OVERREPRESENTED_FRACTION: f64 = 0.001;
+const OVERREPRESENTED_MAX: usize = 20;
+/// Reads up to this long are reported per base; longer reads in groups.
+const PER_BASE_LIMIT: usize = 75;
+
+pub(crate) type Status = &'static str;
+
+/// Quality percentiles for positions `start..end` (0-based, half-open).
+#[derive(Debug, Clone)]
+pub(crate) struct PositionQuality {
+    pub start: usize,
+    pub end: usize,
+    pub mean: f64,
+    pub median: f64,
+    pub lower_quartile: f64,
+    pub upper_quartile: f64,
+    pub p10: f64,
+    pub p90: f64,
+}
+
+/// Base percentages for positions `start..end`.
+#[derive(Debug, Clone)]
+pub(crate) struct PositionContent {
+    pub start: usize,
+    pub end: usize,
+    /// A, C, G, T, N as percentages of the bases at these positions.
+    pub percent: [f64; 5],
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct QcReport {
+    pub reads: u64,
+    pub bases: u64,
+    pub min_length: usize,
+    pub max_length: usize,
+    pub gc_percent: f64,
+    pub per_base_quality: Vec<PositionQuality>,
+    pub per_base_content: Vec<PositionContent>,
+    /// Reads per rounded mean quality.
+    pub per_sequence_quality: Vec<(u32, u64)>,
+    /// Reads per GC percentage, indices 0..=100.
+    pub gc_histogram: Vec<u64>,
+    pub length_distribution: Vec<(usize, u64)>,
+    /// Percentage of reads at each duplication level (`"1"` .. `"9"`,
+    /// `">10"`, `">50"`, `">100"`, `">500"`, `">1k"`, `">5k"`, `">10k"`).
+    pub duplication_levels: Vec<(&'static str, f64)>,
+    /// Percentage of reads left after removing duplicates.
+    pub deduplicated_percent: f64,
+    /// `(sequence, count, percentage)`, most frequent first.
+    pub overrepresented: Vec<(String, u64, f64)>,
+    /// FastQC module name and `pass` / `warn` / `fail`.
+    pub statuses: Vec<(&'static str, Status)>,
+}
+
+#[derive(Default)]
+struct Collector {
+    reads: u64,
+    bases: u64,
+    gc_bases: u64,
+    /// Per position, read counts per Phred score.
+    quality: Vec<[u64; MAX_QUALITY + 1]>,
+    /// Per position, A/C/G/T/N counts.
+    content: Vec<[u64; 5]>,
+    mean_quality: BTreeMap<u32, u64>,
+    gc: Vec<u64>,
+    lengths: BTreeMap<usize, u64>,
+    duplicates: HashMap<Vec<u8>, u64>,
+}
+
+impl Collector {
+    fn add(&mut self, seq: &[u8], qual: &[u8]) {
+        self.reads += 1;
+        self.bases += seq.len() as u64;
+        *self.lengths.entry(seq.len()).or_default() += 1;
+        if self.quality.len() < seq.len() {
+            self.quality.resize(seq.len(), [0; MAX_QUALITY + 1]);
+            self.content.resize(seq.len(), [0; 5]);
+        }
+        let mut gc = 0u64;
+        let mut called = 0u64;
+        for (i, b) in seq.iter().enumerate() {
+            let k = match b.to_ascii_uppercase() {
+                b'A' => 0,
+                b'C' => 1,
+                b'G' => 2,
+                b'T' => 3,
+                _ => 4,
+            };
+            self.content[i][k] += 1;
+            gc += (k == 1 || k == 2) as u64;
+            called += (k != 4) as u64;
+        }
+        self.gc_bases += gc;
+        if called > 0 {
+            self.gc[(gc * 100 / called) as usize] += 1;
+        }
+        let mut sum = 0u64;
+        for (i, &q) in qual.iter().enumerate() {
+            let q = (q.saturating_sub(33) as usize).min(MAX_QUALITY);
+            self.quality[i][q] += 1;
+            sum += q as u64;
+        }
+        if !qual.is_empty() {
+            let mean = (sum as f64 / qual.len() as f64).round() as u32;
+            *self.mean_quality.entry(mean).or_default() += 1;
+        }
+        let key = if seq.len() > DUPLICATION_LONG {
+            &seq[..DUPLICATION_PREFIX]
+        } else {
+            seq
+        };
+        if let Some(count) = self.duplicates.get_mut(key) {
+            *count += 1;
+        } else if self.duplicates.len() < DUPLICATION_TRACKED {
+            self.duplicates.insert(key.to_vec(), 1);
+        }
+    }
+
+    fn finish(self) -> QcReport {
+        let max_length = self.lengths.keys().next_back().copied().unwrap_or(0);
+        let groups = position_groups(max_length);
+        let per_base_quality = groups
+            .iter()
+            .map(|&(start, end)| {
+                let mut hist = [0u64; MAX_QUALITY + 1];
+                for pos in &self.quality[start..end] {
+                    for (h, c) in hist.iter_mut().zip(pos) {
+                        *h += c;
+                    }
+                }
+                let total: u64 = hist.iter().sum();
+                let sum: u64 = hist.iter().enumerate().map(|(q, c)| q as u64 * c).sum();
+                PositionQuality {
+                    start,
+                    end,
+                    mean: if total == 0 {
+                        0.0
+                    } else {
+                        sum as f64 / total as f64
+                    },
+                    median: percentile(&hist, total, 0.5),
+                    lower_quartile: percentile(&hist, total, 0.25),
+                    upper_quartile: percentile(&hist, total, 0.75),
+                    p10: percentile(&hist, total, 0.1),
+                    p90: percentile(&hist, total, 0.9),
+                }
+            })
+            .collect();
+        let per_base_content = groups
+            .iter()
+            .map(|&(start, end)| {
+                let mut counts = [0u64; 5];
+                for pos in &self.content[start..end] {
+                    for (c, n) in counts.iter_mut().zip(pos) {
+                        *c += n;
+                    }
+                }
+                let total = counts.iter().sum::<u64>().max(1) as f64;
+                PositionContent {
+                    start,
+                    end,
+                    percent: counts.map(|c| 100.0 * c as f64 / total),
+                }
+            })
+            .collect();
+
+        let tracked: u64 = self.duplicates.values().sum();
+        let mut levels = [0u64; DUPLICATION_BUCKETS.len()];
+        for &count in self.duplicates.values() {
+            let bucket = DUPLICATION_BUCKETS
+                .iter()
+                .rposition(|&(min, _)| count >= min)
+                .unwrap_or(0);
+            levels[bucket] += count;
+        }
+        let percent_of = |n: u64, total: u64| {
+            if total == 0 {
+                0.0
+            } else {
+                100.0 * n as f64 / total as f64
+            }
+        };
+        let mut overrepresented: Vec<(String, u64, f64)> = self
+            .duplicates
+            .iter()
+            .filter(|&(_, &c)| c > 1 && c as f64 > self.reads as f64 * OVERREPRESENTED_FRACTION)
+            .map(|(s, &c)| {
+                let seq = String::from_utf8_lossy(s).into_owned();
+                (seq, c, percent_of(c, self.reads))
+            })
+            .collect();
+        overrepresented.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        overrepresented.truncate(OVERREPRESENTED_MAX);
+
+        let mut report = QcReport {
+            reads: self.reads,
+            bases: self.bases,
+            min_length: self.lengths.keys().next().copied().unwrap_or(0),
+            max_length,
+            gc_percent: percent_of(
+                self.gc_bases,
+                self.content
+                    .iter()
+                    .map(|c| c[..4].iter().sum::<u64>())
+                    .sum(),
+            ),
+            per_base_quality,
+            per_base_content,
+            per_sequence_quality: self.mean_quality.into_iter().collect(),
+            gc_histogram: self.gc,
+            length_distribution: self.lengths.into_iter().collect(),
+            duplication_levels: DUPLICATION_BUCKETS
+                .iter()
+                .zip(levels)
+                .map(|(&(_, label), n)| (label, percent_of(n, tracked)))
+                .collect(),
+            deduplicated_percent: percent_of(self.duplicates.len() as u64, tracked),
+            overrepresented,
+            statuses: Vec::new(),
+        };
+        report.statuses = statuses(&report);
+        report
+    }
+}
+
+/// Lower bound and label of each duplication level.
+const DUPLICATION_BUCKETS: [(u64, &str); 16] = [
+    (1, "1"),
+    (2, "2"),
+    (3, "3"),
+    (4, "4"),
+    (5, "5"),
+    (6, "6"),
+    (7, "7"),
+    (8, "8"),
+    (9, "9"),
+    (10, ">10"),
+    (50, ">50"),
+    (100, ">100"),
+    (500, ">500"),
+    (1000, ">1k"),
+    (5000, ">5k"),
+    (10000, ">10k"),
+];
+
+/// Positions reported: one per base up to [`PER_BASE_LIMIT`], otherwise
+/// the first nine bases singly and the rest in equal groups.
+fn position_groups(max_length: usize) -> Vec<(usize, usize)> {
+    if max_length <= PER_BASE_LIMIT {
+        return (0..max_length).map(|i| (i, i + 1)).collect();
+    }
+    let single = 9;
+    let width = (max_length - single).div_ceil(PER_BASE_LIMIT - single);
+    let mut out: Vec<(usize, usize)> = (0..single).map(|i| (i, i + 1)).collect();
+    out.extend(
+        (single..max_length)
+            .step_by(width)
+            .map(|s| (s, (s + width).min(max_length))),
+    );
+    out
+}
+
+/// Value at fraction `p` of a histogram holding `total` observations.
+fn percentile(hist: &[u64], total: u64, p: f64) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    let rank = ((total as f64 * p).ceil() as u64).max(1);
+    let mut seen = 0;
+    for (q, &c) in hist.iter().enumerate() {
+        seen += c;
+        if seen >= rank {
+            return q as f64;
+        }
+    }
+    0.0
+}
+
+fn grade(fail: bool, warn: bool) -> Status {
+    if fail {
+        "fail"
+    } else if warn {
+        "warn"
+    } else {
+        "pass"
+    }
+}
+
+/// FastQC default thresholds for each module.
+fn statuses(r: &QcReport) -> Vec<(&'static str, Status)> {
+    let q = &r.per_base_quality;
+    let per_base_quality = grade(
+        q.iter().any(|p| p.lower_quartile < 5.0 || p.median < 20.0),
+        q.iter().any(|p| p.lower_quartile < 10.0 || p.median < 25.0),
+    );
+    let mode_quality = r
+        .per_sequence_quality
+        .iter()
+        .max_by_key(|&&(_, c)| c)
+        .map_or(0, |&(q, _)| q);
+    let per_sequence_quality = grade(mode_quality < 20, mode_quality < 27);
+    let imbalance = r
+        .per_base_content
+        .iter()
+        .map(|p| {
+            (p.percent[0] - p.percent[3])
+                .abs()
+                .max((p.percent[1] - p.percent[2]).abs())
+        })
+        .fold(0.0, f64::max);
+    let per_base_content = grade(imbalance > 20.0, imbalance > 10.0);
+    let gc_deviation = gc_deviation(&r.gc_histogram);
+    let per_sequence_gc = grade(gc_deviation > 30.0, gc_deviation > 15.0);
+    let max_n = r
+        .per_base_content
+        .iter()
+        .map(|p| p.percent[4])
+        .fold(0.0, f64::max);
+    let n_content = grade(max_n > 20.0, max_n > 5.0);
+    let length = grade(
+        r.min_length == 0 && r.reads > 0,
+        r.min_length != r.max_length,
+    );
+    let duplication = grade(r.deduplicated_percent < 50.0, r.deduplicated_percent < 70.0);
+    let max_over = r.overrepresented.first().map_or(0.0, |o| o.2);
+    let overrepresented = grade(max_over > 1.0, max_over > 0.1);
+    vec![
+        ("per_base_quality", per_base_quality),
+        ("per_sequence_quality", per_sequence_quality),
+        ("per_base_content", per_base_content),
+        ("per_sequence_gc", per_sequence_gc),
+        ("n_content", n_content),
+        ("length_distribution", length),
+        ("duplication", duplication),
+        ("overrepresented_sequences", overrepresented),
+    ]
+}
+
+/// Percentage of reads outside a normal distribution fitted to the GC
+/// histogram (FastQC's per-sequence GC measure).
+fn gc_deviation(hist: &[u64]) -> f64 {
+    let total: u64 = hist.iter().sum();
+    if total == 0 {
+        return 0.0;
+    }
+    let n = total as f64;
+    let mean = hist
+        .iter()
+        .enumerate()
+        .map(|(i, &c)| i as f64 * c as f64)
+        .sum::<f64>()
+        / n;
+    let var = hist
+        .iter()
+        .enumerate()
+        .map(|(i, &c)| c as f64 * (i as f64 - mean).powi(2))
+        .sum::<f64>()
+        / n;
+    let sd = var.sqrt().max(1e-9);
+    let density = |x: f64| (-(x - mean).powi(2) / (2.0 * sd * sd)).exp();
+    let norm: f64 = (0..hist.len()).map(|i| density(i as f64)).sum();
+    let deviation: f64 = hist
+        .iter()
+        .enumerate()
+        .map(|(i, &c)| (c as f64 - n * density(i as f64) / norm).abs())
+        .sum();
+    100.0 * deviation / n
+}
+
+/// Build the report for the first `max_reads` reads of `path` (all when
+/// `None`).
+pub(crate) fn fastq_qc(path: &str, max_reads: Option<u64>) -> Result<QcReport, String> {
+    let mut reader = SeqReader::open(path)?;
+    let mut c = Collector {
+        gc: vec![0; 101],
+        ..Collector::default()
+    };
+    loop {
+        if max_reads.is_some_and(|m| c.reads >= m) {
+            break;
+        }
+        let Some(rec) = reader.next_record()? else {
+            break;
+        };
+        c.add(&rec.sequence, &rec.quality);
+    }
+    Ok(c.finish())
+}
@@ -0,0 +1,335 @@
+//! Serializable FM-index used by the persistent index NIFs.
+//!
+//! The index is stored compactly as the suffix array, BWT, cumulative
+//! symbol counts and occurrence checkpoints every [`OCC_STEP`] rows. The
+//! sentinel `$` is implicit (stored as a row number, never as a byte), so
+//! any byte value — including 0 — may appear in the text.
+
+use serde::{Deserialize, Serialize};
+use std::io::{BufReader, BufWriter, Read, Write};
+
+/// Occurrence-table checkpoint spacing (rows of the BWT).
+const OCC_STEP: usize = 64;
+
+/// File header written by [`FmIndex::save`].
+const MAGIC: &[u8; 8] = b"CYFMIDX1";
+
+/// Marker in `code` for bytes that never occur in the text.
+const ABSENT: u16 = u16::MAX;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct FmIndex {
+    /// Suffix array over `text.len() + 1` suffixes (row 0 is the sentinel suffix).
+    sa: Vec<u32>,
+    /// BWT column; the byte at `sentinel_row` is a placeholder.
+    bwt: Vec<u8>,
+    sentinel_row: usize,
+    /// Byte -> dense symbol code (`ABSENT` if the byte never occurs).
+    code: Vec<u16>,
+    /// Number of suffix characters smaller than each dense symbol.
+    c: Vec<u64>,
+    /// Checkpointed occurrence counts, `sigma` entries per checkpoint.
+    occ: Vec<u32>,
+    sigma: usize,
+}
+
+impl FmIndex {
+    /// Build an index over `text`. Texts of 4 GiB or more are rejected.
+    pub(crate) fn build(text: &[u8]) -> Result<Self, String> {
+        if text.len() >= u32::MAX as usize {
+            return Err(format!(
+                "text of {} bytes is too large for a 32-bit FM-index",
+                text.len()
+            ));
+        }
+
+        let mut code = vec![ABSENT; 256];
+        let mut counts = [0u64; 256];
+        for &b in text {
+            counts[b as usize] += 1;
+        }
+        let mut c = Vec::new();
+        let mut acc = 1u64; // the sentinel sorts before everything
+        for (b, &n) in counts.iter().enumerate() {
+            if n > 0 {
+                code[b] = c.len() as u16;
+                c.push(acc);
+                acc += n;
+            }
+        }
+        let sigma = c.len();
+
+        let sa = suffix_array(text);
+        let rows = sa.len();
+        let mut bwt = Vec::with_capacity(rows);
+        let mut sentinel_row = 0;
+        for (row, &pos) in sa.iter().enumerate() {
+            if pos == 0 {
+                sentinel_row = row;
+                bwt.push(0);
+            } else {
+                bwt.push(text[pos as usize - 1]);
+            }
+        }
+
+        let n_checkpoints = rows / OCC_STEP + 1;
+        let mut occ = Vec::with_capacity(n_checkpoints * sigma);
+        let mut running = vec![0u32; sigma];
+        for (row, &b) in bwt.iter().enumerate() {
+            if row % OCC_STEP == 0 {
+                occ.extend_from_slice(&running);
+            }
+            if row != sentinel_row {
+                running[code[b as usize] as usize] += 1;
+            }
+        }
+        if rows % OCC_STEP == 0 {
+            occ.extend_from_slice(&running);
+        }
+
+        Ok(Self {
+            sa,
+            bwt,
+            sentinel_row,
+            code,
+            c,
+            occ,
+            sigma,
+        })
+    }
+
+    /// Number of BWT rows (text length plus the sentinel).
+    pub(crate) fn rows(&self) -> usize {
+        self.sa.len()
+    }
+
+    /// Occurrences of dense symbol `s` in `bwt[0..row]`.
+    fn occ(&self, s: usize, row: usize) -> u64 {
+        let k = row / OCC_STEP;
+        let mut n = self.occ[k * self.sigma + s] as u64;
+        for r in (k * OCC_STEP)..row {
+            if r != self.sentinel_row && self.code[self.bwt[r] as usize] as usize == s {
+                n += 1;
+            }
+        }
+        n
+    }
+
+    /// Narrow a suffix-array interval `[lo, hi)` by prepending `byte`.
+    pub(crate) fn extend_left(&self, lo: usize, hi: usize, byte: u8) -> Option<(usize, usize)> {
+        let s = self.code[byte as usize];
+        if s == ABSENT {
+            return None;
+        }
+        let s = s as usize;
+        let base = self.c[s];
+        let new_lo = (base + self.occ(s, lo)) as usize;
+        let new_hi = (base + self.occ(s, hi)) as usize;
+        (new_lo < new_hi).then_some((new_lo, new_hi))
+    }
+
+    /// Suffix-array interval of all suffixes starting with `pattern`.
+    pub(crate) fn backward_search(&self, pattern: &[u8]) -> Option<(usize, usize)> {
+        if pattern.is_empty() {
+            return None;
+        }
+        let (mut lo, mut hi) = (0, self.rows());
+        for &b in pattern.iter().rev() {
+            (lo, hi) = self.extend_left(lo, hi, b)?;
+        }
+        Some((lo, hi))
+    }
+
+    /// Number of occurrences of `pattern` in the text.
+    pub(crate) fn count(&self, pattern: &[u8]) -> usize {
+        self.backward_search(pattern)
+            .map(|(lo, hi)| hi - lo)
+            .unwrap_or(0)
+    }
+
+    /// Text positions in the suffix-array interval `[lo, hi)`, sorted.
+    pub(crate) fn positions(&self, lo: usize, hi: usize) -> Vec<usize> {
+        let mut positions: Vec<usize> = self.sa[lo..hi].iter().map(|&p| p as usize).collect();
+        positions.sort_unstable();
+        positions
+    }
+
+    /// 0-based start positions of every occurrence of `pattern`, sorted.
+    pub(crate) fn locate(&self, pattern: &[u8]) -> Vec<usize> {
+        match self.backward_search(pattern) {
+            Some((lo, hi)) => self.positions(lo, hi),
+            None => Vec::new(),
+        }
+    }
+
+    /// Write the index to `path` (magic header followed by bincode).
+    pub(crate) fn save(&self, path: &str) -> Result<(), String> {
+        let file = std::fs::File::create(path).map_err(|e| e.to_string())?;
+        let mut writer = BufWriter::new(file);
+        writer.write_all(MAGIC).map_err(|e| e.to_string())?;
+        bincode::serialize_into(&mut writer, self).map_err(|e| e.to_string())?;
+        writer.flush().map_err(|e| e.to_string())
+    }
+
+    /// Read an index previously written with [`FmIndex::save`].
+    pub(crate) fn load(path: &str) -> Result<Self, String> {
+        let file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+        let mut reader = BufReader::new(file);
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic).map_err(|e| e.to_string())?;
+        if &magic != MAGIC {
+            return Err(format!("{path} is not a Cyanea FM-index file"));
+        }
+        let index: Self = bincode::deserialize_from(reader)
+            .map_err(|e| format!("corrupt FM-index {path}: {e}"))?;
+        index
+            .validate()
+            .map_err(|what| format!("corrupt FM-index {path}: {what}"))?;
+        Ok(index)
+    }
+
+    /// Check that the tables agree with each other, so a damaged file is
+    /// rejected here instead of indexing out of range in a query. The
+    /// checkpoints and `c` are recounted from the BWT.
+    fn validate(&self) -> Result<(), String> {
+        let rows = self.sa.len();
+        if rows == 0 || self.bwt.len() != rows {
+            return Err(format!(
+                "{} BWT bytes for {rows} suffix array rows",
+                self.bwt.len()
+            ));
+        }
+        if self.sentinel_row >= rows || self.sa[self.sentinel_row] != 0 {
+            return Err("sentinel row does not hold position 0".into());
+        }
+        if self.sa.iter().any(|&p| p as usize >= rows) {
+            return Err("suffix array position past the end of the text".into());
+        }
+        if self.code.len() != 256 || self.c.len() != self.sigma {
+            return Err("symbol tables do not match the alphabet size".into());
+        }
+        let mut dense = vec![false; self.sigma];
+        for &s in self.code.iter().filter(|&&s| s != ABSENT) {
+            match dense.get_mut(s as usize) {
+                Some(seen) if !*seen => *seen = true,
+                _ => return Err(format!("symbol code {s} is invalid or repeated")),
+            }
+        }
+        if dense.contains(&false) {
+            return Err("symbol codes do not cover the alphabet".into());
+        }
+        if self.occ.len() != (rows / OCC_STEP + 1) * self.sigma {
+            return Err(format!(
+                "{} occurrence checkpoints for {rows} rows",
+                self.occ.len()
+            ));
+        }
+
+        let mut running = vec![0u32; self.sigma];
+        for (row, &b) in self.bwt.iter().enumerate() {
+            if row % OCC_STEP == 0 {
+                let k = row / OCC_STEP * self.sigma;
+                if self.occ[k..k + self.sigma] != running[..] {
+                    return Err(format!("occurrence checkpoint at row {row} is wrong"));
+                }
+            }
+            if row != self.sentinel_row {
+                match self.code[b as usize] {
+                    ABSENT => return Err(format!("BWT byte {b} has no symbol code")),
+                    s => running[s as usize] += 1,
+                }
+            }
+        }
+        let mut acc = 1u64;
+        for (s, &n) in running.iter().enumerate() {
+            if self.c[s] != acc {
+                return Err(format!("cumulative count of symbol {s} is wrong"));
+            }
+            acc += n as u64;
+        }
+        Ok(())
+    }
+}
+
+/// Suffix array of `text` plus an implicit trailing sentinel, by prefix
+/// doubling. Row 0 is always the sentinel suffix (position `text.len()`).
+fn suffix_array(text: &[u8]) -> Vec<u32> {
+    let n = text.len() + 1;
+    let mut sa: Vec<u32> = (0..n as u32).collect();
+    let mut rank: Vec<u32> = text
+        .iter()
+        .map(|&b| b as u32 + 1)
+        .chain(std::iter::once(0))
+        .collect();
+    let mut next = vec![0u32; n];
+    let mut k = 1;
+    loop {
+        let key = |i: u32| {
+            let i = i as usize;
+            let second = if i + k < n { rank[i + k] + 1 } else { 0 };
+            (rank[i], second)
+        };
+        sa.sort_unstable_by_key(|&i| key(i));
+        next[sa[0] as usize] = 0;
+        for j in 1..n {
+            let bump = (key(sa[j - 1]) < key(sa[j])) as u32;
+            next[sa[j] as usize] = next[sa[j - 1] as usize] + bump;
+        }
+        std::mem::swap(&mut rank, &mut next);
+        if rank[sa[n - 1] as usize] as usize == n - 1 {
+            break;
+        }
+        k *= 2;
+    }
+    sa
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("cyanea_fm_{}_{name}", std::process::id()));
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn save_load_round_trip() {
+        let text = b"GATTACAGATTACA\0ACGT".repeat(10);
+        let index = FmIndex::build(&text).unwrap();
+        let path = temp_path("round_trip");
+        index.save(&path).unwrap();
+        let loaded = FmIndex::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.count(b"GATTACA"), 20);
+        assert_eq!(loaded.locate(b"ACGT"), index.locate(b"ACGT"));
+    }
+
+    #[test]
+    fn rejects_tampered_files() {
+        let text = b"GATTACAGATTACA".repeat(10);
+        let path = temp_path("tampered");
+
+        let mut index = FmIndex::build(&text).unwrap();
+        index.bwt.pop();
+        index.save(&path).unwrap();
+        assert!(FmIndex::load(&path).unwrap_err().contains("BWT bytes"));
+
+        let mut index = FmIndex::build(&text).unwrap();
+        index.occ[index.sigma] += 1;
+        index.save(&path).unwrap();
+        assert!(FmIndex::load(&path).unwrap_err().contains("checkpoint"));
+
+        let mut index = FmIndex::build(&text).unwrap();
+        index.c[1] += 5;
+        index.save(&path).unwrap();
+        assert!(FmIndex::load(&path).unwrap_err().contains("cumulative"));
+
+        FmIndex::build(&text).unwrap().save(&path).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &bytes[..bytes.len() / 2]).unwrap();
+        assert!(FmIndex::load(&path).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
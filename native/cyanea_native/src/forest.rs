@@ -0,0 +1,497 @@
+//! Serializable random forest classifier backing the random forest NIFs.
+//!
+//! A CART/Gini forest with bootstrap sampling and per-split feature
+//! subsampling. Fitted trees serialize with bincode, so the model blob can
+//! be stored and reloaded without retraining.
+
+use crate::rng::SplitMix64;
+use serde::{Deserialize, Serialize};
+
+/// Model blob header: [`MAGIC`] followed by the layout version byte.
+const MAGIC: &[u8; 7] = b"CYRFMDL";
+
+/// Serialized layout version, bumped whenever the layout changes.
+const VERSION: u8 = b'1';
+
+#[derive(Debug, Clone)]
+pub(crate) struct ForestConfig {
+    pub n_trees: usize,
+    pub max_depth: usize,
+    pub min_samples_split: usize,
+    /// Features tried per split; `None` uses `sqrt(n_features)`.
+    pub max_features: Option<usize>,
+    pub seed: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum Node {
+    Leaf {
+        /// Class probabilities at this leaf.
+        probs: Vec<f64>,
+    },
+    Split {
+        feature: u32,
+        threshold: f64,
+        left: u32,
+        right: u32,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Tree {
+    nodes: Vec<Node>,
+}
+
+impl Tree {
+    fn leaf_probs(&self, sample: &[f64]) -> &[f64] {
+        let mut i = 0;
+        loop {
+            match &self.nodes[i] {
+                Node::Leaf { probs } => return probs,
+                Node::Split {
+                    feature,
+                    threshold,
+                    left,
+                    right,
+                } => {
+                    i = if sample[*feature as usize] <= *threshold {
+                        *left as usize
+                    } else {
+                        *right as usize
+                    };
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct RandomForestModel {
+    pub n_features: usize,
+    pub n_classes: usize,
+    trees: Vec<Tree>,
+    /// Out-of-bag misclassification rate (`None` if no sample was ever OOB).
+    pub oob_error: Option<f64>,
+    /// Mean decrease in Gini impurity per feature, summing to 1.
+    pub feature_importances: Vec<f64>,
+}
+
+impl RandomForestModel {
+    /// Fit a forest on row-major `data` with integer class `labels`.
+    pub(crate) fn fit(
+        data: &[f64],
+        n_features: usize,
+        labels: &[usize],
+        config: &ForestConfig,
+    ) -> Result<Self, String> {
+        if n_features == 0 {
+            return Err("n_features must be > 0".into());
+        }
+        if data.len() % n_features != 0 {
+            return Err(format!(
+                "data length {} is not divisible by n_features {}",
+                data.len(),
+                n_features
+            ));
+        }
+        let n_samples = data.len() / n_features;
+        if n_samples == 0 {
+            return Err("at least one sample required".into());
+        }
+        if labels.len() != n_samples {
+            return Err(format!(
+                "expected {} labels, got {}",
+                n_samples,
+                labels.len()
+            ));
+        }
+        if config.n_trees == 0 {
+            return Err("n_trees must be > 0".into());
+        }
+        let n_classes = labels.iter().max().map(|&m| m + 1).unwrap_or(0);
+        let mtry = config
+            .max_features
+            .unwrap_or_else(|| (n_features as f64).sqrt().round() as usize)
+            .clamp(1, n_features);
+
+        let mut builder = TreeBuilder {
+            data,
+            n_features,
+            labels,
+            n_classes,
+            mtry,
+            max_depth: config.max_depth,
+            min_samples_split: config.min_samples_split.max(2),
+            importances: vec![0.0; n_features],
+            rng: SplitMix64::new(config.seed),
+        };
+
+        let mut trees = Vec::with_capacity(config.n_trees);
+        let mut importances = vec![0.0; n_features];
+        let mut oob_votes = vec![vec![0.0; n_classes]; n_samples];
+        for _ in 0..config.n_trees {
+            let mut in_bag = vec![false; n_samples];
+            let mut sample: Vec<usize> = (0..n_samples)
+                .map(|_| {
+                    let i = builder.rng.below(n_samples);
+                    in_bag[i] = true;
+                    i
+                })
+                .collect();
+
+            builder.importances.iter_mut().for_each(|v| *v = 0.0);
+            let mut nodes = Vec::new();
+            builder.build(&mut sample, 0, &mut nodes);
+            let tree = Tree { nodes };
+
+            let total: f64 = builder.importances.iter().sum();
+            if total > 0.0 {
+                for (acc, v) in importances.iter_mut().zip(&builder.importances) {
+                    *acc += v / total;
+                }
+            }
+            for (i, votes) in oob_votes.iter_mut().enumerate() {
+                if !in_bag[i] {
+                    let row = &data[i * n_features..(i + 1) * n_features];
+                    for (v, p) in votes.iter_mut().zip(tree.leaf_probs(row)) {
+                        *v += p;
+                    }
+                }
+            }
+            trees.push(tree);
+        }
+
+        let mut oob_seen = 0usize;
+        let mut oob_wrong = 0usize;
+        for (votes, &label) in oob_votes.iter().zip(labels) {
+            if votes.iter().any(|&v| v > 0.0) {
+                oob_seen += 1;
+                if argmax(votes) != label {
+                    oob_wrong += 1;
+                }
+            }
+        }
+        let oob_error = (oob_seen > 0).then(|| oob_wrong as f64 / oob_seen as f64);
+
+        let total: f64 = importances.iter().sum();
+        if total > 0.0 {
+            importances.iter_mut().for_each(|v| *v /= total);
+        }
+
+        Ok(Self {
+            n_features,
+            n_classes,
+            trees,
+            oob_error,
+            feature_importances: importances,
+        })
+    }
+
+    pub(crate) fn n_trees(&self) -> usize {
+        self.trees.len()
+    }
+
+    /// Averaged class probabilities for one sample.
+    pub(crate) fn predict_proba(&self, sample: &[f64]) -> Result<Vec<f64>, String> {
+        if sample.len() != self.n_features {
+            return Err(format!(
+                "sample has {} features, model expects {}",
+                sample.len(),
+                self.n_features
+            ));
+        }
+        let mut probs = vec![0.0; self.n_classes];
+        for tree in &self.trees {
+            for (acc, p) in probs.iter_mut().zip(tree.leaf_probs(sample)) {
+                *acc += p;
+            }
+        }
+        let n = self.trees.len() as f64;
+        probs.iter_mut().for_each(|p| *p /= n);
+        Ok(probs)
+    }
+
+    /// Majority-vote class label for one sample.
+    pub(crate) fn predict(&self, sample: &[f64]) -> Result<usize, String> {
+        self.predict_proba(sample).map(|p| argmax(&p))
+    }
+
+    pub(crate) fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        bincode::serialize_into(&mut bytes, self).map_err(|e| e.to_string())?;
+        Ok(bytes)
+    }
+
+    /// Whether `bytes` carry the model header (of any version). Blobs
+    /// without it predate serialized trees.
+    pub(crate) fn is_versioned(bytes: &[u8]) -> bool {
+        bytes.starts_with(MAGIC)
+    }
+
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let model: Self = match bytes.strip_prefix(MAGIC.as_slice()) {
+            Some([VERSION, body @ ..]) => bincode::deserialize(body).map_err(|e| e.to_string())?,
+            Some([version, ..]) => {
+                return Err(format!(
+                    "random forest model version {} is not supported (expected {})",
+                    *version as char, VERSION as char
+                ))
+            }
+            _ => return Err("not a serialized random forest model".into()),
+        };
+        model.validate()?;
+        Ok(model)
+    }
+
+    /// Reject blobs whose trees could index out of bounds or loop: every
+    /// split must name a real feature and point forward to nodes of its
+    /// own tree, and every leaf must hold one probability per class.
+    fn validate(&self) -> Result<(), String> {
+        let corrupt = |what: String| Err(format!("corrupt random forest model: {what}"));
+        if self.trees.is_empty() {
+            return corrupt("no trees".into());
+        }
+        if self.feature_importances.len() != self.n_features {
+            return corrupt("feature importances do not match n_features".into());
+        }
+        for (t, tree) in self.trees.iter().enumerate() {
+            if tree.nodes.is_empty() {
+                return corrupt(format!("tree {t} has no nodes"));
+            }
+            for (i, node) in tree.nodes.iter().enumerate() {
+                match node {
+                    Node::Leaf { probs } if probs.len() != self.n_classes => {
+                        return corrupt(format!(
+                            "tree {t} node {i} has {} class probabilities, expected {}",
+                            probs.len(),
+                            self.n_classes
+                        ));
+                    }
+                    Node::Split { feature, .. } if *feature as usize >= self.n_features => {
+                        return corrupt(format!(
+                            "tree {t} node {i} splits on feature {feature} of {}",
+                            self.n_features
+                        ));
+                    }
+                    Node::Split { left, right, .. }
+                        if [*left, *right].iter().any(|&c| {
+                            c as usize <= i || c as usize >= tree.nodes.len()
+                        }) =>
+                    {
+                        return corrupt(format!("tree {t} node {i} has an invalid child"));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+struct TreeBuilder<'a> {
+    data: &'a [f64],
+    n_features: usize,
+    labels: &'a [usize],
+    n_classes: usize,
+    mtry: usize,
+    max_depth: usize,
+    min_samples_split: usize,
+    importances: Vec<f64>,
+    rng: SplitMix64,
+}
+
+struct BestSplit {
+    feature: usize,
+    threshold: f64,
+    decrease: f64,
+}
+
+impl TreeBuilder<'_> {
+    fn value(&self, sample: usize, feature: usize) -> f64 {
+        self.data[sample * self.n_features + feature]
+    }
+
+    fn class_counts(&self, samples: &[usize]) -> Vec<f64> {
+        let mut counts = vec![0.0; self.n_classes];
+        for &s in samples {
+            counts[self.labels[s]] += 1.0;
+        }
+        counts
+    }
+
+    /// Append the subtree for `samples` to `nodes`, returning its index.
+    fn build(&mut self, samples: &mut [usize], depth: usize, nodes: &mut Vec<Node>) -> u32 {
+        let counts = self.class_counts(samples);
+        let n = samples.len() as f64;
+        let impurity = gini(&counts, n);
+        let index = nodes.len() as u32;
+
+        let split = if depth < self.max_depth
+            && samples.len() >= self.min_samples_split
+            && impurity > 0.0
+        {
+            self.best_split(samples, impurity)
+        } else {
+            None
+        };
+
+        let Some(split) = split else {
+            let probs = counts.iter().map(|c| c / n).collect();
+            nodes.push(Node::Leaf { probs });
+            return index;
+        };
+
+        self.importances[split.feature] += split.decrease * n;
+        let mid = partition(samples, |&s| self.value(s, split.feature) <= split.threshold);
+        nodes.push(Node::Split {
+            feature: split.feature as u32,
+            threshold: split.threshold,
+            left: 0,
+            right: 0,
+        });
+        let (left_samples, right_samples) = samples.split_at_mut(mid);
+        let left = self.build(left_samples, depth + 1, nodes);
+        let right = self.build(right_samples, depth + 1, nodes);
+        if let Node::Split {
+            left: l, right: r, ..
+        } = &mut nodes[index as usize]
+        {
+            *l = left;
+            *r = right;
+        }
+        index
+    }
+
+    fn best_split(&mut self, samples: &[usize], impurity: f64) -> Option<BestSplit> {
+        let n = samples.len() as f64;
+        let mut features: Vec<usize> = (0..self.n_features).collect();
+        for i in 0..self.mtry {
+            let j = i + self.rng.below(self.n_features - i);
+            features.swap(i, j);
+        }
+
+        let mut best: Option<BestSplit> = None;
+        let mut sorted = samples.to_vec();
+        for &feature in &features[..self.mtry] {
+            sorted.sort_unstable_by(|&a, &b| {
+                self.value(a, feature).total_cmp(&self.value(b, feature))
+            });
+            let mut left = vec![0.0; self.n_classes];
+            let mut right = self.class_counts(&sorted);
+            for k in 0..sorted.len() - 1 {
+                let label = self.labels[sorted[k]];
+                left[label] += 1.0;
+                right[label] -= 1.0;
+                let here = self.value(sorted[k], feature);
+                let next = self.value(sorted[k + 1], feature);
+                if here == next {
+                    continue;
+                }
+                let nl = (k + 1) as f64;
+                let nr = n - nl;
+                let child = (nl * gini(&left, nl) + nr * gini(&right, nr)) / n;
+                let decrease = impurity - child;
+                if decrease > best.as_ref().map_or(1e-12, |b| b.decrease) {
+                    best = Some(BestSplit {
+                        feature,
+                        threshold: here + (next - here) / 2.0,
+                        decrease,
+                    });
+                }
+            }
+        }
+        best
+    }
+}
+
+fn gini(counts: &[f64], n: f64) -> f64 {
+    if n == 0.0 {
+        return 0.0;
+    }
+    1.0 - counts.iter().map(|c| (c / n).powi(2)).sum::<f64>()
+}
+
+fn argmax(values: &[f64]) -> usize {
+    values
+        .iter()
+        .enumerate()
+        .fold((0, f64::NEG_INFINITY), |(bi, bv), (i, &v)| {
+            if v > bv {
+                (i, v)
+            } else {
+                (bi, bv)
+            }
+        })
+        .0
+}
+
+/// Stable in-place partition; returns the number of elements matching `pred`.
+fn partition<T: Copy>(items: &mut [T], pred: impl Fn(&T) -> bool) -> usize {
+    let (yes, no): (Vec<T>, Vec<T>) = items.iter().partition(|x| pred(x));
+    let mid = yes.len();
+    items[..mid].copy_from_slice(&yes);
+    items[mid..].copy_from_slice(&no);
+    mid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model() -> RandomForestModel {
+        let data = [0.0, 0.1, 0.2, 1.0, 1.1, 1.2];
+        let labels = [0, 0, 0, 1, 1, 1];
+        let config = ForestConfig {
+            n_trees: 5,
+            max_depth: 4,
+            min_samples_split: 2,
+            max_features: None,
+            seed: 7,
+        };
+        RandomForestModel::fit(&data, 1, &labels, &config).unwrap()
+    }
+
+    #[test]
+    fn round_trips_through_bytes() {
+        let bytes = model().to_bytes().unwrap();
+        let loaded = RandomForestModel::from_bytes(&bytes).unwrap();
+        assert_eq!(loaded.predict(&[0.05]).unwrap(), 0);
+        assert_eq!(loaded.predict(&[1.15]).unwrap(), 1);
+    }
+
+    #[test]
+    fn rejects_out_of_range_indices() {
+        let mut bad = model();
+        bad.trees[0].nodes[0] = Node::Split {
+            feature: 3,
+            threshold: 0.5,
+            left: 1,
+            right: 2,
+        };
+        let err = RandomForestModel::from_bytes(&bad.to_bytes().unwrap()).unwrap_err();
+        assert!(err.contains("feature 3"), "{err}");
+
+        let mut bad = model();
+        bad.trees[0].nodes = vec![Node::Split {
+            feature: 0,
+            threshold: 0.5,
+            left: 0,
+            right: 9,
+        }];
+        assert!(RandomForestModel::from_bytes(&bad.to_bytes().unwrap()).is_err());
+
+        let bytes = model().to_bytes().unwrap();
+        assert!(RandomForestModel::from_bytes(&bytes[..bytes.len() / 2]).is_err());
+    }
+
+    #[test]
+    fn names_unsupported_versions() {
+        let mut bytes = model().to_bytes().unwrap();
+        bytes[MAGIC.len()] = b'2';
+        let err = RandomForestModel::from_bytes(&bytes).unwrap_err();
+        assert!(err.contains("version 2"), "{err}");
+        assert!(RandomForestModel::is_versioned(&bytes));
+        assert!(!RandomForestModel::is_versioned(b"\x03\0\0\0"));
+    }
+}
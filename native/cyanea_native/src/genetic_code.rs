@@ -0,0 +1,371 @@
+//! NCBI genetic codes, DNA/RNA translation, six-frame translation and ORF
+//! finding with alternative start codons.
+//!
+//! Tables are the NCBI `gc.prt` strings in TCAG codon order; a codon is a
+//! start when its `Starts` entry is `M`. Codons with IUPAC ambiguity codes
+//! translate to the amino acid shared by every expansion, else `X`. The
+//! context-dependent stops of tables 27, 28 and 31 translate as their sense
+//! amino acid, so ORFs are not found with those tables.
+
+use crate::iupac::{self, bits};
+
+/// `(id, name, amino acids, starts)` for each NCBI translation table.
+const TABLES: [(u8, &str, &str, &str); 25] = [
+    (
+        1,
+        "Standard",
+        "FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "---M------**--*----M---------------M----------------------------",
+    ),
+    (
+        2,
+        "Vertebrate Mitochondrial",
+        "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSS**VVVVAAAADDEEGGGG",
+        "----------**--------------------MMMM----------**---M------------",
+    ),
+    (
+        3,
+        "Yeast Mitochondrial",
+        "FFLLSSSSYY**CCWWTTTTPPPPHHQQRRRRIIMMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "----------**----------------------MM---------------M------------",
+    ),
+    (
+        4,
+        "Mold, Protozoan, and Coelenterate Mitochondrial and Mycoplasma/Spiroplasma",
+        "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "--MM------**-------M------------MMMM---------------M------------",
+    ),
+    (
+        5,
+        "Invertebrate Mitochondrial",
+        "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSSSSVVVVAAAADDEEGGGG",
+        "---M------**--------------------MMMM---------------M------------",
+    ),
+    (
+        6,
+        "Ciliate, Dasycladacean and Hexamita Nuclear",
+        "FFLLSSSSYYQQCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "--------------*--------------------M----------------------------",
+    ),
+    (
+        9,
+        "Echinoderm and Flatworm Mitochondrial",
+        "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNNKSSSSVVVVAAAADDEEGGGG",
+        "----------**-----------------------M---------------M------------",
+    ),
+    (
+        10,
+        "Euplotid Nuclear",
+        "FFLLSSSSYY**CCCWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "----------**-----------------------M----------------------------",
+    ),
+    (
+        11,
+        "Bacterial, Archaeal and Plant Plastid",
+        "FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "---M------**--*----M------------MMMM---------------M------------",
+    ),
+    (
+        12,
+        "Alternative Yeast Nuclear",
+        "FFLLSSSSYY**CC*WLLLSPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "----------**--*----M---------------M----------------------------",
+    ),
+    (
+        13,
+        "Ascidian Mitochondrial",
+        "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSSGGVVVVAAAADDEEGGGG",
+        "---M------**----------------------MM---------------M------------",
+    ),
+    (
+        14,
+        "Alternative Flatworm Mitochondrial",
+        "FFLLSSSSYYY*CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNNKSSSSVVVVAAAADDEEGGGG",
+        "-----------*-----------------------M----------------------------",
+    ),
+    (
+        16,
+        "Chlorophycean Mitochondrial",
+        "FFLLSSSSYY*LCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "----------*---*--------------------M----------------------------",
+    ),
+    (
+        21,
+        "Trematode Mitochondrial",
+        "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNNKSSSSVVVVAAAADDEEGGGG",
+        "----------**-----------------------M---------------M------------",
+    ),
+    (
+        22,
+        "Scenedesmus obliquus Mitochondrial",
+        "FFLLSS*SYY*LCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "------*---*---*--------------------M----------------------------",
+    ),
+    (
+        23,
+        "Thraustochytrium Mitochondrial",
+        "FF*LSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "--*-------**--*-----------------M--M---------------M------------",
+    ),
+    (
+        24,
+        "Rhabdopleuridae Mitochondrial",
+        "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSSKVVVVAAAADDEEGGGG",
+        "---M------**-------M---------------M---------------M------------",
+    ),
+    (
+        25,
+        "Candidate Division SR1 and Gracilibacteria",
+        "FFLLSSSSYY**CCGWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "---M------**-----------------------M---------------M------------",
+    ),
+    (
+        26,
+        "Pachysolen tannophilus Nuclear",
+        "FFLLSSSSYY**CC*WLLLAPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "----------**--*----M---------------M----------------------------",
+    ),
+    (
+        27,
+        "Karyorelict Nuclear",
+        "FFLLSSSSYYQQCCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "--------------*--------------------M----------------------------",
+    ),
+    (
+        28,
+        "Condylostoma Nuclear",
+        "FFLLSSSSYYQQCCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "----------**--*--------------------M----------------------------",
+    ),
+    (
+        29,
+        "Mesodinium Nuclear",
+        "FFLLSSSSYYYYCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "--------------*--------------------M----------------------------",
+    ),
+    (
+        30,
+        "Peritrich Nuclear",
+        "FFLLSSSSYYEECC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "--------------*--------------------M----------------------------",
+    ),
+    (
+        31,
+        "Blastocrithidia Nuclear",
+        "FFLLSSSSYYEECCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "----------**-----------------------M----------------------------",
+    ),
+    (
+        33,
+        "Cephalodiscidae Mitochondrial",
+        "FFLLSSSSYYY*CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSSKVVVVAAAADDEEGGGG",
+        "---M-------*-------M---------------M---------------M------------",
+    ),
+];
+
+/// IDs and names of the supported tables.
+pub(crate) fn tables() -> impl Iterator<Item = (u8, &'static str)> {
+    TABLES.iter().map(|&(id, name, _, _)| (id, name))
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct GeneticCode {
+    pub id: u8,
+    pub name: &'static str,
+    amino_acids: &'static [u8],
+    starts: &'static [u8],
+}
+
+impl GeneticCode {
+    /// NCBI translation table `id` (1 is the standard code, 2 vertebrate
+    /// mitochondrial, 11 bacterial and plastid, ...).
+    pub(crate) fn ncbi(id: u8) -> Result<Self, String> {
+        TABLES
+            .iter()
+            .find(|t| t.0 == id)
+            .map(|&(id, name, aa, starts)| Self {
+                id,
+                name,
+                amino_acids: aa.as_bytes(),
+                starts: starts.as_bytes(),
+            })
+            .ok_or_else(|| format!("unknown genetic code {id}"))
+    }
+
+    /// Amino acid of `codon` (`*` for stop, `X` when ambiguous).
+    pub(crate) fn amino_acid(&self, codon: &[u8]) -> u8 {
+        let mut aa = None;
+        for i in expand(codon) {
+            match aa {
+                None => aa = Some(self.amino_acids[i]),
+                Some(a) if a != self.amino_acids[i] => return b'X',
+                _ => {}
+            }
+        }
+        aa.unwrap_or(b'X')
+    }
+
+    /// Whether every reading of `codon` is a stop.
+    pub(crate) fn is_stop(&self, codon: &[u8]) -> bool {
+        self.amino_acid(codon) == b'*'
+    }
+
+    /// Whether `codon` is a start: `ATG` only, or any start codon of the
+    /// table with `alternative`. Ambiguous codons are never starts.
+    pub(crate) fn is_start(&self, codon: &[u8], alternative: bool) -> bool {
+        let mut indices = expand(codon);
+        match (indices.next(), indices.next()) {
+            (Some(i), None) if alternative => self.starts[i] == b'M',
+            (Some(i), None) => i == ATG,
+            _ => false,
+        }
+    }
+
+    /// Translate `seq` codon by codon from its first base; a trailing
+    /// partial codon is dropped. With `to_stop`, translation ends before
+    /// the first stop codon.
+    pub(crate) fn translate(&self, seq: &[u8], to_stop: bool) -> Vec<u8> {
+        let mut protein = Vec::with_capacity(seq.len() / 3);
+        for codon in seq.chunks_exact(3) {
+            let aa = self.amino_acid(codon);
+            if to_stop && aa == b'*' {
+                break;
+            }
+            protein.push(aa);
+        }
+        protein
+    }
+}
+
+/// Codon index of `ATG` in TCAG order.
+const ATG: usize = 2 * 16 + 3 * 4 + 2;
+
+/// Table indices of every unambiguous codon `codon` may stand for; none
+/// when it contains a non-nucleotide.
+fn expand(codon: &[u8]) -> impl Iterator<Item = usize> {
+    let sets = [bits(codon[0]), bits(codon[1]), bits(codon[2])];
+    (0..64).filter(move |i| {
+        sets[0] & (1 << (i / 16)) != 0
+            && sets[1] & (1 << (i / 4 % 4)) != 0
+            && sets[2] & (1 << (i % 4)) != 0
+    })
+}
+
+/// HGVS three-letter code of a one-letter amino acid (`*` is `Ter`,
+/// anything unknown `Xaa`).
+pub(crate) fn three_letter(aa: u8) -> &'static str {
+    match aa {
+        b'A' => "Ala",
+        b'R' => "Arg",
+        b'N' => "Asn",
+        b'D' => "Asp",
+        b'C' => "Cys",
+        b'Q' => "Gln",
+        b'E' => "Glu",
+        b'G' => "Gly",
+        b'H' => "His",
+        b'I' => "Ile",
+        b'L' => "Leu",
+        b'K' => "Lys",
+        b'M' => "Met",
+        b'F' => "Phe",
+        b'P' => "Pro",
+        b'S' => "Ser",
+        b'T' => "Thr",
+        b'W' => "Trp",
+        b'Y' => "Tyr",
+        b'V' => "Val",
+        b'*' => "Ter",
+        _ => "Xaa",
+    }
+}
+
+/// Translation of one reading frame.
+#[derive(Debug, Clone)]
+pub(crate) struct FrameTranslation {
+    /// `+` or `-`.
+    pub strand: char,
+    /// Offset 0, 1 or 2 of the first codon on that strand.
+    pub frame: usize,
+    pub protein: Vec<u8>,
+}
+
+/// Translations of the three forward frames, then the three frames of the
+/// reverse complement.
+pub(crate) fn six_frames(seq: &[u8], code: &GeneticCode) -> Vec<FrameTranslation> {
+    let mut rc = seq.to_vec();
+    iupac::reverse_complement(&mut rc);
+    [('+', seq), ('-', rc.as_slice())]
+        .into_iter()
+        .flat_map(|(strand, s)| {
+            (0..3).map(move |frame| FrameTranslation {
+                strand,
+                frame,
+                protein: code.translate(s.get(frame..).unwrap_or_default(), false),
+            })
+        })
+        .collect()
+}
+
+/// An open reading frame in forward-strand coordinates.
+#[derive(Debug, Clone)]
+pub(crate) struct Orf {
+    /// 0-based, half-open span including the stop codon.
+    pub start: usize,
+    pub end: usize,
+    pub frame: usize,
+    pub strand: char,
+    /// Nucleotides on the ORF's strand, start to stop codon.
+    pub sequence: Vec<u8>,
+    /// Translation without the stop; the start codon reads as `M`.
+    pub protein: Vec<u8>,
+}
+
+/// ORFs of at least `min_length` nucleotides (stop codon included) in all
+/// six frames: from the first start codon after a stop to the next stop.
+/// ORFs without a stop codon before the end of the sequence are skipped.
+pub(crate) fn find_orfs(
+    seq: &[u8],
+    code: &GeneticCode,
+    min_length: usize,
+    alternative_starts: bool,
+) -> Vec<Orf> {
+    let mut rc = seq.to_vec();
+    iupac::reverse_complement(&mut rc);
+    let mut orfs = Vec::new();
+    for (strand, s) in [('+', seq), ('-', rc.as_slice())] {
+        for frame in 0..3 {
+            let mut open: Option<usize> = None;
+            let mut pos = frame;
+            while pos + 3 <= s.len() {
+                let codon = &s[pos..pos + 3];
+                if code.is_stop(codon) {
+                    if let Some(begin) = open.take() {
+                        let end = pos + 3;
+                        if end - begin >= min_length {
+                            let mut protein = code.translate(&s[begin..pos], false);
+                            protein[0] = b'M';
+                            let (start, stop) = match strand {
+                                '+' => (begin, end),
+                                _ => (s.len() - end, s.len() - begin),
+                            };
+                            orfs.push(Orf {
+                                start,
+                                end: stop,
+                                frame,
+                                strand,
+                                sequence: s[begin..end].to_vec(),
+                                protein,
+                            });
+                        }
+                    }
+                } else if open.is_none() && code.is_start(codon, alternative_starts) {
+                    open = Some(pos);
+                }
+                pos += 3;
+            }
+        }
+    }
+    orfs.sort_by_key(|o| (o.start, o.end));
+    orfs
+}
@@ -0,0 +1,478 @@
+//! Interval algebra (intersect, subtract, complement, closest, window).
+//!
+//! Each chromosome (and strand, for strand-aware operations) gets an
+//! implicit augmented interval tree laid out over the start-sorted array,
+//! as in cgranges: building is a sort plus one bottom-up pass, and a query
+//! costs `O(log n + hits)`. All coordinates are 0-based and half-open.
+
+use crate::writer::strand_symbol;
+use std::collections::HashMap;
+
+/// Linear scan threshold: subtrees at or below this level are scanned.
+const SCAN_LEVEL: u32 = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Interval {
+    pub chrom: String,
+    pub start: u64,
+    pub end: u64,
+    /// `+`, `-` or `.`.
+    pub strand: &'static str,
+}
+
+impl Interval {
+    pub(crate) fn new(chrom: String, start: u64, end: u64, strand: &str) -> Result<Self, String> {
+        if start > end {
+            return Err(format!("{chrom}:{start}-{end}: start is after end"));
+        }
+        Ok(Self {
+            chrom,
+            start,
+            end,
+            strand: strand_symbol(strand),
+        })
+    }
+
+    fn with_bounds(&self, start: u64, end: u64) -> Self {
+        Self {
+            start,
+            end,
+            ..self.clone()
+        }
+    }
+}
+
+/// Which strand pairs may interact (`-s` / `-S` in bedtools).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum StrandMode {
+    Ignore,
+    Same,
+    Opposite,
+}
+
+impl StrandMode {
+    pub(crate) fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "ignore" => Ok(Self::Ignore),
+            "same" => Ok(Self::Same),
+            "opposite" => Ok(Self::Opposite),
+            other => Err(format!(
+                "unknown strand mode: {other} (expected ignore, same or opposite)"
+            )),
+        }
+    }
+
+    /// Tree key an interval is stored under.
+    fn store_key(self, strand: &'static str) -> &'static str {
+        match self {
+            Self::Ignore => "*",
+            _ => strand,
+        }
+    }
+
+    /// Tree key to search for a query interval; `None` if nothing can match
+    /// (unstranded queries never match under `Same`/`Opposite`).
+    fn query_key(self, strand: &'static str) -> Option<&'static str> {
+        match (self, strand) {
+            (Self::Ignore, _) => Some("*"),
+            (_, ".") => None,
+            (Self::Same, s) => Some(s),
+            (Self::Opposite, "+") => Some("-"),
+            (Self::Opposite, _) => Some("+"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    start: u64,
+    end: u64,
+    /// Largest `end` in the subtree rooted here.
+    max: u64,
+    id: usize,
+}
+
+/// Implicit augmented interval tree over one start-sorted array.
+#[derive(Debug)]
+struct Tree {
+    nodes: Vec<Node>,
+    root_level: u32,
+    /// `prefix_max[i]` is the largest `end` among `nodes[..=i]`.
+    prefix_max: Vec<u64>,
+}
+
+impl Tree {
+    fn build(mut nodes: Vec<Node>) -> Self {
+        nodes.sort_unstable_by_key(|n| (n.start, n.end, n.id));
+        let n = nodes.len();
+        let prefix_max = nodes
+            .iter()
+            .scan(0, |m, node| {
+                *m = node.end.max(*m);
+                Some(*m)
+            })
+            .collect();
+        if n == 0 {
+            return Self {
+                nodes,
+                root_level: 0,
+                prefix_max,
+            };
+        }
+        let (mut last_i, mut last) = (0, 0);
+        for i in (0..n).step_by(2) {
+            nodes[i].max = nodes[i].end;
+            last_i = i;
+            last = nodes[i].end;
+        }
+        let mut k = 1;
+        while 1usize << k <= n {
+            let x = 1usize << (k - 1);
+            let step = x << 2;
+            let mut i = (x << 1) - 1;
+            while i < n {
+                let left = nodes[i - x].max;
+                let right = if i + x < n { nodes[i + x].max } else { last };
+                nodes[i].max = nodes[i].end.max(left).max(right);
+                i += step;
+            }
+            last_i = if (last_i >> k) & 1 == 1 {
+                last_i - x
+            } else {
+                last_i + x
+            };
+            if last_i < n && nodes[last_i].max > last {
+                last = nodes[last_i].max;
+            }
+            k += 1;
+        }
+        Self {
+            nodes,
+            root_level: k - 1,
+            prefix_max,
+        }
+    }
+
+    /// Node positions overlapping `[beg, end)`, in start order.
+    fn overlaps(&self, beg: u64, end: u64, out: &mut Vec<usize>) {
+        let n = self.nodes.len();
+        if n == 0 {
+            return;
+        }
+        let k0 = self.root_level;
+        let mut stack = vec![((1usize << k0) - 1, k0, false)];
+        while let Some((x, k, left_done)) = stack.pop() {
+            if k <= SCAN_LEVEL {
+                let i0 = x >> k << k;
+                let i1 = (i0 + (1 << (k + 1)) - 1).min(n);
+                for i in i0..i1 {
+                    let node = &self.nodes[i];
+                    if node.start >= end {
+                        break;
+                    }
+                    if beg < node.end {
+                        out.push(i);
+                    }
+                }
+            } else if !left_done {
+                let y = x - (1 << (k - 1));
+                stack.push((x, k, true));
+                if y >= n || self.nodes[y].max > beg {
+                    stack.push((y, k - 1, false));
+                }
+            } else if x < n && self.nodes[x].start < end {
+                if beg < self.nodes[x].end {
+                    out.push(x);
+                }
+                stack.push((x + (1 << (k - 1)), k - 1, false));
+            }
+        }
+    }
+}
+
+/// Interval trees keyed by chromosome and strand, returning indices into
+/// the slice they were built from.
+pub(crate) struct IntervalIndex {
+    trees: HashMap<String, HashMap<&'static str, Tree>>,
+    mode: StrandMode,
+}
+
+impl IntervalIndex {
+    pub(crate) fn new(intervals: &[Interval], mode: StrandMode) -> Self {
+        let mut groups: HashMap<(String, &'static str), Vec<Node>> = HashMap::new();
+        for (id, iv) in intervals.iter().enumerate() {
+            groups
+                .entry((iv.chrom.clone(), mode.store_key(iv.strand)))
+                .or_default()
+                .push(Node {
+                    start: iv.start,
+                    end: iv.end,
+                    max: iv.end,
+                    id,
+                });
+        }
+        let mut trees: HashMap<String, HashMap<&'static str, Tree>> = HashMap::new();
+        for ((chrom, key), nodes) in groups {
+            trees.entry(chrom).or_default().insert(key, Tree::build(nodes));
+        }
+        Self { trees, mode }
+    }
+
+    fn tree(&self, chrom: &str, strand: &'static str) -> Option<&Tree> {
+        let key = self.mode.query_key(strand)?;
+        self.trees.get(chrom)?.get(key)
+    }
+
+    fn find(&self, chrom: &str, strand: &'static str, beg: u64, end: u64) -> Vec<usize> {
+        let Some(tree) = self.tree(chrom, strand) else {
+            return Vec::new();
+        };
+        let mut hits = Vec::new();
+        tree.overlaps(beg, end, &mut hits);
+        hits.into_iter().map(|i| tree.nodes[i].id).collect()
+    }
+
+    /// Indices of intervals overlapping `[beg, end)` on the query's
+    /// chromosome that pass the strand rule, in start order.
+    pub(crate) fn overlaps(&self, query: &Interval, beg: u64, end: u64) -> Vec<usize> {
+        self.find(&query.chrom, query.strand, beg, end)
+    }
+
+    /// Unstranded lookup of `[beg, end)` on `chrom`.
+    pub(crate) fn overlaps_on(&self, chrom: &str, beg: u64, end: u64) -> Vec<usize> {
+        self.find(chrom, ".", beg, end)
+    }
+
+    /// Nearest intervals to `query`: every overlapping one (distance 0),
+    /// otherwise the closest upstream and/or downstream ones, ties
+    /// included. Distances follow `bedtools closest -D a`: non-overlapping
+    /// features are at least 1 apart, and negative values are upstream of
+    /// `query` with respect to its strand.
+    pub(crate) fn closest(&self, query: &Interval) -> Vec<(usize, i64)> {
+        let Some(tree) = self.tree(&query.chrom, query.strand) else {
+            return Vec::new();
+        };
+        let mut hits = Vec::new();
+        tree.overlaps(query.start, query.end.max(query.start + 1), &mut hits);
+        if !hits.is_empty() {
+            return hits.into_iter().map(|i| (tree.nodes[i].id, 0)).collect();
+        }
+        let nodes = &tree.nodes;
+        // With no overlaps, everything starting before the query ends at or
+        // before its start, so the left neighbour has the largest end there.
+        let split = nodes.partition_point(|n| n.start < query.start);
+        let left = split.checked_sub(1).map(|i| tree.prefix_max[i]);
+        let right = nodes.get(split).map(|n| n.start);
+        let left_gap = left.map(|e| query.start - e + 1);
+        let right_gap = right.map(|s| s.saturating_sub(query.end) + 1);
+        let best = match (left_gap, right_gap) {
+            (Some(l), Some(r)) => l.min(r),
+            (Some(g), None) | (None, Some(g)) => g,
+            (None, None) => return Vec::new(),
+        };
+        let sign = |upstream_on_plus: bool| {
+            if upstream_on_plus == (query.strand == "-") {
+                1
+            } else {
+                -1
+            }
+        };
+        let mut out = Vec::new();
+        if let (Some(end), true) = (left, left_gap == Some(best)) {
+            hits.clear();
+            tree.overlaps(end.saturating_sub(1), end, &mut hits);
+            out.extend(
+                hits.iter()
+                    .map(|&i| &nodes[i])
+                    .filter(|n| n.end == end)
+                    .map(|n| (n.id, sign(true) * best as i64)),
+            );
+        }
+        if let (Some(start), true) = (right, right_gap == Some(best)) {
+            out.extend(
+                nodes[split..]
+                    .iter()
+                    .take_while(|n| n.start == start)
+                    .map(|n| (n.id, sign(false) * best as i64)),
+            );
+        }
+        out
+    }
+}
+
+/// What `intersect` reports for each overlapping pair.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Report {
+    /// The overlapping part of A (bedtools default).
+    Overlap,
+    /// A, once per overlap (`-wa`).
+    A,
+    /// The overlapping part of A with B (`-wb`).
+    OverlapB,
+    /// A with B (`-wa -wb`).
+    AB,
+    /// A, once if it overlaps anything (`-u`).
+    Any,
+    /// A if it overlaps nothing (`-v`).
+    None,
+}
+
+impl Report {
+    pub(crate) fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "overlap" => Ok(Self::Overlap),
+            "a" => Ok(Self::A),
+            "b" => Ok(Self::OverlapB),
+            "both" => Ok(Self::AB),
+            "any" => Ok(Self::Any),
+            "none" => Ok(Self::None),
+            other => Err(format!(
+                "unknown intersect report: {other} (expected overlap, a, b, both, any or none)"
+            )),
+        }
+    }
+}
+
+/// bedtools `intersect`. `min_overlap` is the fraction of each A interval
+/// that must be covered for a pair to count (`-f`; 0 accepts any overlap).
+pub(crate) fn intersect(
+    a: &[Interval],
+    b: &[Interval],
+    report: Report,
+    strand: StrandMode,
+    min_overlap: f64,
+) -> Vec<(Interval, Option<Interval>)> {
+    let index = IntervalIndex::new(b, strand);
+    let mut out = Vec::new();
+    for iv in a {
+        let needed = (min_overlap * (iv.end - iv.start) as f64).ceil().max(1.0) as u64;
+        let hits: Vec<(u64, u64, usize)> = index
+            .overlaps(iv, iv.start, iv.end)
+            .into_iter()
+            .map(|j| (iv.start.max(b[j].start), iv.end.min(b[j].end), j))
+            .filter(|(s, e, _)| e - s >= needed)
+            .collect();
+        match report {
+            Report::Any if !hits.is_empty() => out.push((iv.clone(), None)),
+            Report::None if hits.is_empty() => out.push((iv.clone(), None)),
+            Report::Any | Report::None => {}
+            _ => {
+                for (s, e, j) in hits {
+                    let a_part = match report {
+                        Report::A | Report::AB => iv.clone(),
+                        _ => iv.with_bounds(s, e),
+                    };
+                    let b_part = matches!(report, Report::OverlapB | Report::AB)
+                        .then(|| b[j].clone());
+                    out.push((a_part, b_part));
+                }
+            }
+        }
+    }
+    out
+}
+
+/// bedtools `subtract`: the parts of each A interval not covered by B.
+pub(crate) fn subtract(a: &[Interval], b: &[Interval], strand: StrandMode) -> Vec<Interval> {
+    let index = IntervalIndex::new(b, strand);
+    let mut out = Vec::new();
+    for iv in a {
+        let mut cursor = iv.start;
+        for j in index.overlaps(iv, iv.start, iv.end) {
+            if b[j].start > cursor {
+                out.push(iv.with_bounds(cursor, b[j].start));
+            }
+            cursor = cursor.max(b[j].end);
+        }
+        if cursor < iv.end || iv.start == iv.end {
+            out.push(iv.with_bounds(cursor, iv.end));
+        }
+    }
+    out
+}
+
+/// bedtools `complement`: regions of `genome` (`(chrom, length)`, in output
+/// order) covered by no interval. Strand is ignored.
+pub(crate) fn complement(
+    intervals: &[Interval],
+    genome: &[(String, u64)],
+) -> Result<Vec<Interval>, String> {
+    let mut by_chrom: HashMap<&str, Vec<(u64, u64)>> = HashMap::new();
+    for iv in intervals {
+        if !genome.iter().any(|(c, _)| *c == iv.chrom) {
+            return Err(format!("chromosome {} is not in the genome", iv.chrom));
+        }
+        by_chrom
+            .entry(iv.chrom.as_str())
+            .or_default()
+            .push((iv.start, iv.end));
+    }
+    let mut out = Vec::new();
+    for (chrom, len) in genome {
+        let mut spans = by_chrom.remove(chrom.as_str()).unwrap_or_default();
+        spans.sort_unstable();
+        let mut cursor = 0;
+        for (s, e) in spans {
+            if s > cursor {
+                out.push(Interval::new(chrom.clone(), cursor, s.min(*len), ".")?);
+            }
+            cursor = cursor.max(e);
+            if cursor >= *len {
+                break;
+            }
+        }
+        if cursor < *len {
+            out.push(Interval::new(chrom.clone(), cursor, *len, ".")?);
+        }
+    }
+    Ok(out)
+}
+
+/// bedtools `closest -D a -t all`: each A with its nearest B interval(s) and
+/// signed distance, or `None` when no B is on the same chromosome/strand.
+pub(crate) fn closest(
+    a: &[Interval],
+    b: &[Interval],
+    strand: StrandMode,
+) -> Vec<(Interval, Option<Interval>, Option<i64>)> {
+    let index = IntervalIndex::new(b, strand);
+    let mut out = Vec::new();
+    for iv in a {
+        let hits = index.closest(iv);
+        if hits.is_empty() {
+            out.push((iv.clone(), None, None));
+        }
+        for (j, d) in hits {
+            out.push((iv.clone(), Some(b[j].clone()), Some(d)));
+        }
+    }
+    out
+}
+
+/// bedtools `window`: pairs of A and B where B overlaps A extended by
+/// `left` and `right` bases. With `strand_relative` (`-sw`), `left` is
+/// upstream of A with respect to its strand.
+pub(crate) fn window(
+    a: &[Interval],
+    b: &[Interval],
+    left: u64,
+    right: u64,
+    strand_relative: bool,
+    strand: StrandMode,
+) -> Vec<(Interval, Interval)> {
+    let index = IntervalIndex::new(b, strand);
+    let mut out = Vec::new();
+    for iv in a {
+        let (l, r) = if strand_relative && iv.strand == "-" {
+            (right, left)
+        } else {
+            (left, right)
+        };
+        let beg = iv.start.saturating_sub(l);
+        let end = iv.end.saturating_add(r).max(beg + 1);
+        for j in index.overlaps(iv, beg, end) {
+            out.push((iv.clone(), b[j].clone()));
+        }
+    }
+    out
+}
@@ -1,9 +1,15 @@
 //! cyanea-io NIFs — File format parsing (CSV, VCF, BED, GFF3, SAM, BAM,
 //! Parquet, GenBank, EMBL, Stockholm, Clustal, Phylip, bigWig, bedGraph).
 
+use crate::bam::{BamHeader, BamRecord};
+use crate::bigwig::BigWigReader;
 use crate::bridge::*;
+use crate::depth::DepthFilter;
+use crate::intervals::Interval;
 use crate::to_nif_error;
-use std::collections::HashSet;
+use crate::vcf::{TypedValue, VcfHeader, VcfReader, VcfRecord, VcfSummary, VcfValue};
+use crate::writer::{BedLine, GffLine, OutputFile};
+use std::collections::{HashMap, HashSet};
 
 // ===========================================================================
 // Existing NIFs
@@ -23,9 +29,9 @@ pub fn csv_preview(path: String, limit: usize) -> Result<String, String> {
 
 #[rustler::nif(schedule = "DirtyCpu")]
 pub fn vcf_stats(path: String) -> Result<VcfStatsNif, String> {
-    cyanea_io::vcf_stats(&path)
-        .map(VcfStatsNif::from)
-        .map_err(to_nif_error)
+    let sites = cyanea_io::vcf_stats(&path).map_err(to_nif_error)?;
+    let summary = VcfSummary::from_path(&path)?;
+    Ok(VcfStatsNif::from((sites, summary)))
 }
 
 #[rustler::nif(schedule = "DirtyCpu")]
@@ -48,18 +54,27 @@ pub fn gff3_stats(path: String) -> Result<GffStatsNif, String> {
 
 #[rustler::nif(schedule = "DirtyCpu")]
 pub fn parse_vcf(path: String) -> Result<Vec<VcfRecordNif>, String> {
-    let variants = cyanea_io::parse_vcf(&path).map_err(to_nif_error)?;
-    Ok(variants
-        .into_iter()
-        .map(|v| VcfRecordNif {
-            chrom: v.chrom.clone(),
-            position: v.position,
-            ref_allele: String::from_utf8_lossy(&v.ref_allele).to_string(),
-            alt_alleles: v.alt_alleles.iter().map(|a| String::from_utf8_lossy(a).to_string()).collect(),
-            quality: v.quality,
-            filter: format!("{:?}", v.filter),
-        })
-        .collect())
+    let mut reader = VcfReader::open(&path)?;
+    let mut records = Vec::new();
+    while let Some(rec) = reader.next_record()? {
+        records.push(vcf_record_nif(&rec, reader.header()));
+    }
+    Ok(records)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn vcf_header(path: String) -> Result<VcfHeaderNif, String> {
+    let reader = VcfReader::open(&path)?;
+    let h = reader.header();
+    Ok(VcfHeaderNif {
+        meta: h.meta.clone(),
+        fileformat: h.fileformat().map(str::to_string),
+        samples: h.samples.clone(),
+        contigs: h.ids("contig"),
+        filters: h.ids("FILTER"),
+        info: h.definitions("INFO").into_iter().map(VcfFieldNif::from).collect(),
+        format: h.definitions("FORMAT").into_iter().map(VcfFieldNif::from).collect(),
+    })
 }
 
 #[rustler::nif(schedule = "DirtyCpu")]
@@ -368,10 +383,492 @@ pub fn bedgraph_stats(path: String) -> Result<BedGraphStatsNif, String> {
     })
 }
 
+// ===========================================================================
+// Indexed region queries
+// ===========================================================================
+
+/// Reads overlapping the 0-based half-open `[start, end)` on `chrom`, read
+/// through the BAM's `.bai`/`.csi` index.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn bam_query(
+    path: String,
+    chrom: String,
+    start: u64,
+    end: u64,
+) -> Result<Vec<SamRecordNif>, String> {
+    let (header, records) = crate::bam::query(&path, &chrom, start, end)?;
+    Ok(records
+        .iter()
+        .map(|r| sam_record_from_bam(r, &header))
+        .collect())
+}
+
+/// Index a coordinate-sorted BAM. `format` is `"bai"` or `"csi"`; returns
+/// the path of the written index.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn bam_index(path: String, format: String) -> Result<String, String> {
+    match format.as_str() {
+        "bai" => crate::bam::build_index(&path, false),
+        "csi" => crate::bam::build_index(&path, true),
+        other => Err(format!("unknown index format: {other} (expected bai or csi)")),
+    }
+}
+
+/// Lines of a bgzipped, tabix-indexed VCF/BED/GFF3/bedGraph file
+/// overlapping `region` (`chr`, `chr:beg` or `chr:beg-end`, 1-based).
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn tabix_query(path: String, region: String) -> Result<Vec<String>, String> {
+    crate::tabix::query(&path, &region)
+}
+
+/// Bgzip a sorted, uncompressed file and index it. `preset` is `"vcf"`,
+/// `"bed"`, `"gff"`, `"bedgraph"` or `"auto"`; returns the `.gz` path.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn tabix_index(path: String, preset: String) -> Result<String, String> {
+    crate::tabix::build_index(&path, &preset)
+}
+
+// ===========================================================================
+// Writers
+// ===========================================================================
+
+/// Write a VCF. `header.meta` is written verbatim; INFO/FORMAT/contig/FILTER
+/// entries missing from it are added. With `bgzf` the output is
+/// BGZF-compressed and ready for `tabix_index`-style indexing. Returns the
+/// number of records written.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn write_vcf(
+    path: String,
+    header: VcfHeaderNif,
+    records: Vec<VcfRecordNif>,
+    bgzf: bool,
+) -> Result<usize, String> {
+    let header = vcf_header_from_nif(&header);
+    let mut out = OutputFile::create(&path, bgzf)?;
+    for line in header.lines() {
+        out.line(&line)?;
+    }
+    for (i, r) in records.iter().enumerate() {
+        let rec = vcf_record_from_nif(r, &header).map_err(|e| format!("record {}: {e}", i + 1))?;
+        out.line(&rec.to_line(header.samples.len()))?;
+    }
+    out.finish()?;
+    Ok(records.len())
+}
+
+/// Write BED features using the fewest columns (3–12) that hold every
+/// feature. Returns the number of features written.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn write_bed(path: String, features: Vec<BedFeatureNif>, bgzf: bool) -> Result<usize, String> {
+    let lines: Vec<BedLine> = features.into_iter().map(bed_line_from_nif).collect();
+    for l in &lines {
+        l.validate()?;
+    }
+    let columns = lines.iter().map(BedLine::columns).max().unwrap_or(3);
+    let mut out = OutputFile::create(&path, bgzf)?;
+    for l in &lines {
+        out.line(&l.to_line(columns))?;
+    }
+    out.finish()?;
+    Ok(lines.len())
+}
+
+/// Write GFF3 features under a `##gff-version 3` header, percent-encoding
+/// reserved characters. Returns the number of features written.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn write_gff3(path: String, features: Vec<GffFeatureNif>, bgzf: bool) -> Result<usize, String> {
+    let mut out = OutputFile::create(&path, bgzf)?;
+    out.line("##gff-version 3")?;
+    for f in &features {
+        let line = GffLine {
+            seqid: f.seqid.clone(),
+            source: f.source.clone(),
+            feature_type: f.feature_type.clone(),
+            start: f.start,
+            end: f.end,
+            score: f.score,
+            strand: f.strand.clone(),
+            phase: f.phase,
+            attributes: f.attributes.clone(),
+        };
+        out.line(&line.to_line()?)?;
+    }
+    out.finish()?;
+    Ok(features.len())
+}
+
+// ===========================================================================
+// Depth and coverage
+// ===========================================================================
+
+/// Per-base depth of a coordinate-sorted BAM written to `output` as
+/// bedGraph: runs of equal depth, or mean depth per `bin_size` bases when
+/// non-zero. Returns the number of lines written.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn bam_depth_bedgraph(
+    path: String,
+    output: String,
+    bin_size: u64,
+    min_mapq: u8,
+    exclude_flags: u16,
+    min_base_quality: u8,
+    include_zero: bool,
+    bgzf: bool,
+) -> Result<u64, String> {
+    let filter = DepthFilter {
+        min_mapq,
+        exclude_flags,
+        min_base_quality,
+    };
+    crate::depth::write_bedgraph(&path, &output, filter, bin_size, include_zero, bgzf)
+}
+
+/// Mean/median/min/max depth per target and the fraction of bases at or
+/// above each threshold, per target and pooled.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn bam_target_depth(
+    path: String,
+    targets: Vec<GenomicIntervalNif>,
+    thresholds: Vec<u32>,
+    min_mapq: u8,
+    exclude_flags: u16,
+    min_base_quality: u8,
+) -> Result<DepthReportNif, String> {
+    let filter = DepthFilter {
+        min_mapq,
+        exclude_flags,
+        min_base_quality,
+    };
+    let targets = targets
+        .into_iter()
+        .map(|t| Interval::new(t.chrom, t.start, t.end, "."))
+        .collect::<Result<Vec<_>, _>>()?;
+    let (per_target, total) = crate::depth::target_depth(&path, &targets, filter)?;
+    let fractions = |s: &crate::depth::DepthStats| {
+        thresholds.iter().map(|&t| s.fraction_at_least(t)).collect()
+    };
+    Ok(DepthReportNif {
+        targets: targets
+            .iter()
+            .zip(&per_target)
+            .map(|(t, s)| TargetDepthNif {
+                chrom: t.chrom.clone(),
+                start: t.start,
+                end: t.end,
+                mean_depth: s.mean(),
+                median_depth: s.median(),
+                min_depth: s.min(),
+                max_depth: s.max(),
+                fraction_above: fractions(s),
+            })
+            .collect(),
+        total_bases: total.bases(),
+        mean_depth: total.mean(),
+        median_depth: total.median(),
+        fraction_above: fractions(&total),
+        thresholds,
+    })
+}
+
+// ===========================================================================
+// bigWig signal
+// ===========================================================================
+
+/// bigWig records overlapping `start..end` (0-based, half-open), unclipped.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn bigwig_values(
+    path: String,
+    chrom: String,
+    start: u64,
+    end: u64,
+) -> Result<Vec<BedGraphRecordNif>, String> {
+    let mut reader = BigWigReader::open(&path)?;
+    Ok(reader
+        .intervals(&chrom, start, end)?
+        .into_iter()
+        .map(|(start, end, value)| BedGraphRecordNif {
+            chrom: chrom.clone(),
+            start,
+            end,
+            value: value as f64,
+        })
+        .collect())
+}
+
+/// Mean/min/max/coverage over `bins` equal bins of `start..end`, from the
+/// zoom levels unless `exact`.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn bigwig_summary(
+    path: String,
+    chrom: String,
+    start: u64,
+    end: u64,
+    bins: usize,
+    exact: bool,
+) -> Result<Vec<BigWigBinNif>, String> {
+    let mut reader = BigWigReader::open(&path)?;
+    Ok(reader
+        .summary(&chrom, start, end, bins, exact)?
+        .into_iter()
+        .map(|(start, end, s)| {
+            // Zero-width bins (more bins than bases) never receive data.
+            let has_data = end > start && s.bases > 0.0;
+            BigWigBinNif {
+                chrom: chrom.clone(),
+                start,
+                end,
+                mean: s.mean().filter(|_| has_data),
+                min: has_data.then_some(s.min),
+                max: has_data.then_some(s.max),
+                coverage: if has_data { (s.bases / (end - start) as f64).min(1.0) } else { 0.0 },
+            }
+        })
+        .collect())
+}
+
+/// Write bedGraph records to a bigWig. Returns the number of records.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn write_bigwig(
+    path: String,
+    records: Vec<BedGraphRecordNif>,
+    chrom_sizes: Vec<(String, u64)>,
+) -> Result<usize, String> {
+    let records: Vec<_> = records
+        .into_iter()
+        .map(|r| (r.chrom, r.start, r.end, r.value))
+        .collect();
+    crate::bigwig::write(&path, &records, &chrom_sizes)
+}
+
+/// Convert a bedGraph file to bigWig. Returns the number of records.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn bedgraph_to_bigwig(
+    input: String,
+    output: String,
+    chrom_sizes: Vec<(String, u64)>,
+) -> Result<usize, String> {
+    let records = crate::bigwig::read_bedgraph(&input)?;
+    crate::bigwig::write(&output, &records, &chrom_sizes)
+}
+
 // ===========================================================================
 // Helpers
 // ===========================================================================
 
+/// Scalar fields unwrap to a single term; a missing scalar (`.`) is dropped.
+fn vcf_value_nif(v: TypedValue) -> Option<VcfValueNif> {
+    Some(match (v.value, v.scalar) {
+        (VcfValue::Flag, _) => VcfValueNif::Flag(true),
+        (VcfValue::Integer(xs), true) => VcfValueNif::Integer(xs.into_iter().next()??),
+        (VcfValue::Float(xs), true) => VcfValueNif::Float(xs.into_iter().next()??),
+        (VcfValue::String(xs), true) => VcfValueNif::String(xs.into_iter().next()??),
+        (VcfValue::Integer(xs), false) => VcfValueNif::IntegerList(xs),
+        (VcfValue::Float(xs), false) => {
+            VcfValueNif::FloatList(xs.into_iter().map(|x| x.map(VcfFloat)).collect())
+        }
+        (VcfValue::String(xs), false) => VcfValueNif::StringList(xs),
+    })
+}
+
+fn vcf_values_nif(values: Vec<(String, TypedValue)>) -> HashMap<String, VcfValueNif> {
+    values
+        .into_iter()
+        .filter_map(|(k, v)| Some((k, vcf_value_nif(v)?)))
+        .collect()
+}
+
+fn vcf_record_nif(r: &VcfRecord, header: &VcfHeader) -> VcfRecordNif {
+    let samples = 0..r.samples.len();
+    VcfRecordNif {
+        chrom: r.chrom.clone(),
+        position: r.pos,
+        ids: r.ids.clone(),
+        ref_allele: r.ref_allele.clone(),
+        alt_alleles: r.alt_alleles.clone(),
+        quality: r.qual,
+        filter: if r.filters.is_empty() {
+            ".".to_string()
+        } else {
+            r.filters.join(";")
+        },
+        filters: r.filters.clone(),
+        info: vcf_values_nif(r.info_typed(header)),
+        format: r.format.clone(),
+        genotypes: samples
+            .clone()
+            .map(|i| r.sample_raw(i, "GT").unwrap_or(".").to_string())
+            .collect(),
+        depths: samples.clone().map(|i| r.sample_int(i, "DP")).collect(),
+        genotype_qualities: samples.clone().map(|i| r.sample_int(i, "GQ")).collect(),
+        allele_depths: samples.clone().map(|i| r.sample_ints(i, "AD")).collect(),
+        sample_data: samples
+            .map(|i| vcf_values_nif(r.sample_typed(header, i)))
+            .collect(),
+    }
+}
+
+/// Header from `meta`, plus definitions only present in the structured
+/// `info`/`format`/`contigs`/`filters` lists.
+fn vcf_header_from_nif(h: &VcfHeaderNif) -> VcfHeader {
+    let mut meta = h.meta.clone();
+    if let Some(ff) = &h.fileformat {
+        if !meta.iter().any(|m| m.starts_with("fileformat=")) {
+            meta.insert(0, format!("fileformat={ff}"));
+        }
+    }
+    let parsed = VcfHeader::from_meta(&meta, Vec::new());
+    let quote = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+    for (key, fields) in [("INFO", &h.info), ("FORMAT", &h.format)] {
+        let known = parsed.ids(key);
+        for f in fields.iter().filter(|f| !known.contains(&f.id)) {
+            meta.push(format!(
+                "{key}=<ID={},Number={},Type={},Description=\"{}\">",
+                f.id,
+                f.number,
+                f.value_type,
+                quote(&f.description)
+            ));
+        }
+    }
+    let filters = parsed.ids("FILTER");
+    for id in h.filters.iter().filter(|id| !filters.contains(id)) {
+        meta.push(format!("FILTER=<ID={id},Description=\"{}\">", quote(id)));
+    }
+    let contigs = parsed.ids("contig");
+    for id in h.contigs.iter().filter(|id| !contigs.contains(id)) {
+        meta.push(format!("contig=<ID={id}>"));
+    }
+    VcfHeader::from_meta(&meta, h.samples.clone())
+}
+
+fn vcf_value_text(v: &VcfValueNif) -> Option<String> {
+    fn list<T: ToString>(xs: &[Option<T>]) -> String {
+        xs.iter()
+            .map(|x| x.as_ref().map_or_else(|| ".".to_string(), T::to_string))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+    match v {
+        VcfValueNif::Flag(_) => None,
+        VcfValueNif::Integer(x) => Some(x.to_string()),
+        VcfValueNif::Float(x) => Some(x.to_string()),
+        VcfValueNif::String(x) => Some(x.clone()),
+        VcfValueNif::IntegerList(xs) => Some(list(xs)),
+        VcfValueNif::FloatList(xs) => Some(list(xs)),
+        VcfValueNif::StringList(xs) => Some(list(xs)),
+    }
+}
+
+/// Inverse of [`vcf_record_nif`]. INFO keys follow header order, then the
+/// rest alphabetically; FORMAT values come from `sample_data`, falling back
+/// to `genotypes`/`depths`/`genotype_qualities`/`allele_depths`.
+fn vcf_record_from_nif(r: &VcfRecordNif, header: &VcfHeader) -> Result<VcfRecord, String> {
+    let header_order = header.ids("INFO");
+    let mut keys: Vec<&String> = r.info.keys().collect();
+    keys.sort_by_key(|k| (header_order.iter().position(|h| h == *k).unwrap_or(usize::MAX), *k));
+    let info = keys
+        .into_iter()
+        .filter(|k| !matches!(r.info[*k], VcfValueNif::Flag(false)))
+        .map(|k| (k.clone(), vcf_value_text(&r.info[k])))
+        .collect();
+
+    let mut format = r.format.clone();
+    if format.is_empty() && !r.genotypes.is_empty() {
+        format.push("GT".to_string());
+    }
+    let n = header.samples.len();
+    let samples = if format.is_empty() {
+        Vec::new()
+    } else {
+        let given = r.genotypes.len().max(r.sample_data.len());
+        if given != n {
+            return Err(format!("{n} samples in header but {given} in record"));
+        }
+        (0..n)
+            .map(|i| {
+                format
+                    .iter()
+                    .map(|k| {
+                        let from_data = r
+                            .sample_data
+                            .get(i)
+                            .and_then(|d| d.get(k))
+                            .and_then(vcf_value_text);
+                        let fallback = || match k.as_str() {
+                            "GT" => r.genotypes.get(i).cloned(),
+                            "DP" => r.depths.get(i).copied().flatten().map(|x| x.to_string()),
+                            "GQ" => r
+                                .genotype_qualities
+                                .get(i)
+                                .copied()
+                                .flatten()
+                                .map(|x| x.to_string()),
+                            "AD" => r
+                                .allele_depths
+                                .get(i)
+                                .filter(|xs| !xs.is_empty())
+                                .and_then(|xs| vcf_value_text(&VcfValueNif::IntegerList(xs.clone()))),
+                            _ => None,
+                        };
+                        from_data.or_else(fallback).unwrap_or_else(|| ".".to_string())
+                    })
+                    .collect()
+            })
+            .collect()
+    };
+
+    Ok(VcfRecord {
+        chrom: r.chrom.clone(),
+        pos: r.position,
+        ids: r.ids.clone(),
+        ref_allele: r.ref_allele.clone(),
+        alt_alleles: r.alt_alleles.clone(),
+        qual: r.quality,
+        filters: if r.filters.is_empty() {
+            r.filter
+                .split(';')
+                .filter(|f| !f.is_empty() && *f != ".")
+                .map(str::to_string)
+                .collect()
+        } else {
+            r.filters.clone()
+        },
+        info,
+        format,
+        samples,
+    })
+}
+
+fn bed_line_from_nif(f: BedFeatureNif) -> BedLine {
+    let blocks = (!f.block_sizes.is_empty() || !f.block_starts.is_empty())
+        .then_some((f.block_sizes, f.block_starts));
+    BedLine {
+        chrom: f.chrom,
+        start: f.start,
+        end: f.end,
+        name: f.name,
+        score: f.score,
+        strand: f.strand,
+        thick_start: f.thick_start,
+        thick_end: f.thick_end,
+        item_rgb: f.item_rgb,
+        blocks,
+    }
+}
+
+fn sam_record_from_bam(r: &BamRecord, header: &BamHeader) -> SamRecordNif {
+    SamRecordNif {
+        qname: r.qname.clone(),
+        flag: r.flag,
+        rname: header.ref_name(r.ref_id).to_string(),
+        pos: (r.pos + 1).max(0) as u64,
+        mapq: r.mapq,
+        cigar: r.cigar_string(),
+        sequence: String::from_utf8_lossy(&r.sequence).into_owned(),
+        quality: r.quality_string(),
+    }
+}
+
 fn extract_pdb_resolution(text: &str) -> Option<f64> {
     for line in text.lines() {
         if line.starts_with("REMARK   2 RESOLUTION.") {
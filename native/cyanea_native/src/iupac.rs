@@ -0,0 +1,297 @@
+//! IUPAC nucleotide codes: validation that keeps soft-masking (lowercase),
+//! complements, GC content and ambiguity-aware pattern search.
+//!
+//! Each code is the set of bases it stands for; a text base matches a
+//! pattern base when the text set is contained in the pattern set (`N` in a
+//! pattern matches anything, `N` in the text only matches `N`). Comparisons
+//! ignore case.
+
+/// Bases a nucleotide code stands for: bit 0 T/U, 1 C, 2 A, 3 G (TCAG
+/// order, as in the NCBI codon tables). 0 for anything else.
+pub(crate) fn bits(b: u8) -> u8 {
+    match b.to_ascii_uppercase() {
+        b'T' | b'U' => 0b0001,
+        b'C' => 0b0010,
+        b'A' => 0b0100,
+        b'G' => 0b1000,
+        b'R' => 0b1100,
+        b'Y' => 0b0011,
+        b'S' => 0b1010,
+        b'W' => 0b0101,
+        b'K' => 0b1001,
+        b'M' => 0b0110,
+        b'B' => 0b1011,
+        b'D' => 0b1101,
+        b'H' => 0b0111,
+        b'V' => 0b1110,
+        b'N' => 0b1111,
+        _ => 0,
+    }
+}
+
+/// Check that `seq` holds only IUPAC nucleotide codes (DNA or RNA).
+pub(crate) fn check_nucleotides(seq: &[u8]) -> Result<(), String> {
+    match seq.iter().position(|&b| bits(b) == 0) {
+        Some(i) => Err(format!(
+            "invalid nucleotide '{}' at position {}",
+            seq[i].escape_ascii(),
+            i + 1
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Validate a DNA (`T`) or RNA (`U`) sequence of IUPAC codes. Case is
+/// kept, so soft-masked bases stay lowercase.
+pub(crate) fn validate(seq: &[u8], rna: bool) -> Result<(), String> {
+    check_nucleotides(seq)?;
+    let (wrong, kind) = if rna { (b'T', "RNA") } else { (b'U', "DNA") };
+    match seq.iter().position(|b| b.to_ascii_uppercase() == wrong) {
+        Some(i) => Err(format!(
+            "invalid {kind} base '{}' at position {}",
+            seq[i] as char,
+            i + 1
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Complement of one code, keeping case (`U` complements to `A`).
+pub(crate) fn complement(b: u8) -> u8 {
+    let c = match b.to_ascii_uppercase() {
+        b'A' => b'T',
+        b'C' => b'G',
+        b'G' => b'C',
+        b'T' | b'U' => b'A',
+        b'R' => b'Y',
+        b'Y' => b'R',
+        b'K' => b'M',
+        b'M' => b'K',
+        b'B' => b'V',
+        b'V' => b'B',
+        b'D' => b'H',
+        b'H' => b'D',
+        other => other,
+    };
+    if b.is_ascii_lowercase() {
+        c.to_ascii_lowercase()
+    } else {
+        c
+    }
+}
+
+/// Reverse-complement in place, keeping case.
+pub(crate) fn reverse_complement(seq: &mut [u8]) {
+    seq.reverse();
+    for b in seq.iter_mut() {
+        *b = complement(*b);
+    }
+}
+
+/// `T` to `U`, keeping case.
+pub(crate) fn transcribe(seq: &[u8]) -> Vec<u8> {
+    seq.iter()
+        .map(|&b| match b {
+            b'T' => b'U',
+            b't' => b'u',
+            other => other,
+        })
+        .collect()
+}
+
+/// Fraction of G, C and S among the bases known to be strong or weak
+/// (`G C S A T U W`); `N` and other ambiguity codes are left out. 0 when
+/// there are none.
+pub(crate) fn gc_content(seq: &[u8]) -> f64 {
+    let (mut gc, mut known) = (0u64, 0u64);
+    for &b in seq {
+        match b.to_ascii_uppercase() {
+            b'G' | b'C' | b'S' => {
+                gc += 1;
+                known += 1;
+            }
+            b'A' | b'T' | b'U' | b'W' => known += 1,
+            _ => {}
+        }
+    }
+    if known == 0 {
+        0.0
+    } else {
+        gc as f64 / known as f64
+    }
+}
+
+/// Lowercase (soft-masked) runs as 0-based, half-open spans.
+pub(crate) fn soft_masked(seq: &[u8]) -> Vec<(usize, usize)> {
+    let mut out = Vec::new();
+    let mut start = None;
+    for (i, b) in seq.iter().enumerate() {
+        match (b.is_ascii_lowercase(), start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                out.push((s, i));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        out.push((s, seq.len()));
+    }
+    out
+}
+
+/// Whether text base `t` matches pattern base `p`.
+fn matches(t: u8, p: u8) -> bool {
+    let (t, p) = (bits(t), bits(p));
+    if t == 0 || p == 0 {
+        return false;
+    }
+    t & !p == 0
+}
+
+/// For every byte, the pattern positions it matches.
+fn match_table(pattern: &[u8]) -> Vec<Vec<bool>> {
+    (0..=255u8)
+        .map(|c| pattern.iter().map(|&p| matches(c, p)).collect())
+        .collect()
+}
+
+/// Start positions of every occurrence of `pattern` in `text` (Horspool,
+/// with the shift for a text byte taken from the rightmost pattern base it
+/// matches).
+pub(crate) fn horspool(text: &[u8], pattern: &[u8]) -> Vec<usize> {
+    let m = pattern.len();
+    if m == 0 || m > text.len() {
+        return Vec::new();
+    }
+    let table = match_table(pattern);
+    let shift: Vec<usize> = table
+        .iter()
+        .map(|row| {
+            row[..m - 1]
+                .iter()
+                .rposition(|&ok| ok)
+                .map_or(m, |i| m - 1 - i)
+        })
+        .collect();
+    let mut out = Vec::new();
+    let mut pos = 0;
+    while pos + m <= text.len() {
+        let window = &text[pos..pos + m];
+        if (0..m).rev().all(|i| table[window[i] as usize][i]) {
+            out.push(pos);
+        }
+        pos += shift[window[m - 1] as usize];
+    }
+    out
+}
+
+/// `(end, distance)` for every text position where `pattern` ends with at
+/// most `max_dist` edits (`end` inclusive). Myers' bit-parallel algorithm
+/// for patterns up to 64 bases; longer patterns use the plain dynamic
+/// programme.
+pub(crate) fn myers(text: &[u8], pattern: &[u8], max_dist: usize) -> Vec<(usize, usize)> {
+    let m = pattern.len();
+    if m == 0 {
+        return Vec::new();
+    }
+    let table = match_table(pattern);
+    if m > 64 {
+        return semiglobal(text, &table, m, max_dist);
+    }
+    let peq: Vec<u64> = table
+        .iter()
+        .map(|row| {
+            row.iter()
+                .enumerate()
+                .fold(0u64, |acc, (i, &ok)| acc | ((ok as u64) << i))
+        })
+        .collect();
+    let high = 1u64 << (m - 1);
+    let (mut pv, mut mv) = (!0u64, 0u64);
+    let mut score = m;
+    let mut out = Vec::new();
+    for (j, &c) in text.iter().enumerate() {
+        let eq = peq[c as usize];
+        let xv = eq | mv;
+        let xh = (((eq & pv).wrapping_add(pv)) ^ pv) | eq;
+        let mut ph = mv | !(xh | pv);
+        let mut mh = pv & xh;
+        if ph & high != 0 {
+            score += 1;
+        } else if mh & high != 0 {
+            score -= 1;
+        }
+        ph <<= 1;
+        mh <<= 1;
+        pv = mh | !(xv | ph);
+        mv = ph & xv;
+        if score <= max_dist {
+            out.push((j, score));
+        }
+    }
+    out
+}
+
+fn semiglobal(text: &[u8], table: &[Vec<bool>], m: usize, max_dist: usize) -> Vec<(usize, usize)> {
+    let mut col: Vec<usize> = (0..=m).collect();
+    let mut out = Vec::new();
+    for (j, &c) in text.iter().enumerate() {
+        let row = &table[c as usize];
+        let mut diag = col[0];
+        for i in 1..=m {
+            let next = (diag + !row[i - 1] as usize)
+                .min(col[i] + 1)
+                .min(col[i - 1] + 1);
+            diag = col[i];
+            col[i] = next;
+        }
+        if col[m] <= max_dist {
+            out.push((j, col[m]));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_ambiguity_codes_and_case() {
+        assert!(validate(b"ACGTNRYKMSWBDHVacgtnrykmswbdhv", false).is_ok());
+        assert!(validate(b"ACGUNRYacgun", true).is_ok());
+        assert_eq!(
+            validate(b"ACGU", false).unwrap_err(),
+            "invalid DNA base 'U' at position 4"
+        );
+        assert_eq!(
+            validate(b"ACGX", false).unwrap_err(),
+            "invalid nucleotide 'X' at position 4"
+        );
+    }
+
+    #[test]
+    fn complements_keep_case() {
+        let mut seq = b"AcgTNRyKmBdHV".to_vec();
+        reverse_complement(&mut seq);
+        assert_eq!(seq, b"BDhVkMrYNAcgT");
+        assert_eq!(transcribe(b"ACGTNacgtn"), b"ACGUNacgun");
+        assert_eq!(gc_content(b"GCsaWtNNRY"), 0.5);
+    }
+
+    #[test]
+    fn ambiguity_codes_match_the_bases_they_stand_for() {
+        // `R` in the pattern matches A or G; an `N` in the text only
+        // matches `N`.
+        assert_eq!(horspool(b"ACGTacgaNCGT", b"aCGR"), [4]);
+        assert_eq!(horspool(b"ACGTNCGT", b"NCGT"), [0, 4]);
+        assert_eq!(horspool(b"NCGT", b"ACGT"), Vec::<usize>::new());
+        // W = A or T: exact hits end on either, C costs one edit.
+        assert_eq!(myers(b"ACGAgacgt", b"ACGW", 0), [(3, 0), (8, 0)]);
+        assert_eq!(myers(b"GGACGCGG", b"ACGW", 1), [(4, 1), (5, 1)]);
+        let long: Vec<u8> = b"ACGTN".iter().copied().cycle().take(70).collect();
+        assert_eq!(myers(&long, &long, 0), [(69, 0)]);
+    }
+}
@@ -0,0 +1,188 @@
+//! Canonical k-mer counting, abundance histograms and genome size /
+//! heterozygosity estimates from the k-mer spectrum.
+//!
+//! K-mers of up to 32 bases are packed 2 bits per base into a `u64`, and a
+//! k-mer and its reverse complement share one key (the smaller encoding),
+//! as in Jellyfish with `-C`. K-mers containing anything other than `ACGT`
+//! are skipped.
+
+use crate::reader::SeqReader;
+use std::collections::HashMap;
+
+pub(crate) const MAX_K: usize = 32;
+
+/// Canonical k-mer counts.
+pub(crate) struct KmerCounts {
+    pub k: usize,
+    /// K-mers counted, including repeats.
+    pub total: u64,
+    counts: HashMap<u64, u32>,
+}
+
+impl KmerCounts {
+    pub(crate) fn new(k: usize) -> Result<Self, String> {
+        if k == 0 || k > MAX_K {
+            return Err(format!("k must be between 1 and {MAX_K}, got {k}"));
+        }
+        Ok(Self {
+            k,
+            total: 0,
+            counts: HashMap::new(),
+        })
+    }
+
+    /// Count every k-mer of the FASTA or FASTQ records in `path`.
+    pub(crate) fn from_file(path: &str, k: usize) -> Result<Self, String> {
+        let mut counts = Self::new(k)?;
+        let mut reader = SeqReader::open(path)?;
+        while let Some(rec) = reader.next_record()? {
+            counts.add(&rec.sequence);
+        }
+        Ok(counts)
+    }
+
+    pub(crate) fn add(&mut self, seq: &[u8]) {
+        let k = self.k;
+        let mask = u64::MAX >> (64 - 2 * k);
+        let shift = 2 * (k - 1);
+        let (mut fwd, mut rev, mut len) = (0u64, 0u64, 0);
+        for &b in seq {
+            let code = match b.to_ascii_uppercase() {
+                b'A' => 0,
+                b'C' => 1,
+                b'G' => 2,
+                b'T' => 3,
+                _ => {
+                    len = 0;
+                    continue;
+                }
+            };
+            fwd = ((fwd << 2) | code) & mask;
+            rev = (rev >> 2) | ((3 - code) << shift);
+            len += 1;
+            if len >= k {
+                *self.counts.entry(fwd.min(rev)).or_default() += 1;
+                self.total += 1;
+            }
+        }
+    }
+
+    pub(crate) fn distinct(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// `histogram[c]` distinct k-mers seen `c` times; the last bin,
+    /// `max_count`, also holds every k-mer seen more often.
+    pub(crate) fn histogram(&self, max_count: u32) -> Vec<u64> {
+        let max_count = max_count.max(1);
+        let mut hist = vec![0u64; max_count as usize + 1];
+        for &c in self.counts.values() {
+            hist[c.min(max_count) as usize] += 1;
+        }
+        hist
+    }
+
+    /// The `n` most frequent k-mers, most frequent first (ties by
+    /// sequence).
+    pub(crate) fn top(&self, n: usize) -> Vec<(String, u32)> {
+        let mut all: Vec<(u64, u32)> = self.counts.iter().map(|(&k, &c)| (k, c)).collect();
+        let by_count = |a: &(u64, u32), b: &(u64, u32)| b.1.cmp(&a.1).then(a.0.cmp(&b.0));
+        if n < all.len() {
+            all.select_nth_unstable_by(n, by_count);
+            all.truncate(n);
+        }
+        all.sort_unstable_by(by_count);
+        all.into_iter()
+            .map(|(kmer, c)| (decode(kmer, self.k), c))
+            .collect()
+    }
+}
+
+/// The bases of a packed k-mer.
+pub(crate) fn decode(kmer: u64, k: usize) -> String {
+    (0..k)
+        .rev()
+        .map(|i| b"ACGT"[((kmer >> (2 * i)) & 3) as usize] as char)
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct GenomeEstimate {
+    /// Histogram minimum separating error k-mers from genomic ones.
+    pub error_cutoff: u32,
+    /// K-mer coverage of homozygous (both haplotypes) sequence.
+    pub kmer_coverage: f64,
+    /// Whether a heterozygous peak at half coverage was found.
+    pub heterozygous_peak: bool,
+    /// Haploid genome length.
+    pub genome_size: u64,
+    /// Fraction of bases that differ between haplotypes.
+    pub heterozygosity: f64,
+    /// Fraction of genomic k-mers in more than 1.5x coverage.
+    pub repeat_fraction: f64,
+}
+
+/// Genome size and heterozygosity from a k-mer histogram (as returned by
+/// [`KmerCounts::histogram`]; the saturated last bin is ignored).
+///
+/// A peak-based estimate rather than GenomeScope's model fit: k-mers up to
+/// the first minimum of the histogram are errors; the tallest later peak
+/// is taken as homozygous coverage unless there is a peak at least a
+/// quarter as tall near twice its coverage, in which case it is the
+/// heterozygous peak. Genome size is the genomic k-mer total over the
+/// homozygous coverage. Distinct k-mers below 0.75x coverage are
+/// heterozygous; each heterozygous site contributes `2k` of them.
+/// `None` when the histogram has no minimum or no later peak.
+pub(crate) fn estimate_genome(hist: &[u64], k: usize) -> Option<GenomeEstimate> {
+    let last = hist.len().checked_sub(1)?;
+    let hist = &hist[..last];
+    let cutoff = (1..hist.len().saturating_sub(1)).find(|&c| hist[c] < hist[c + 1])?;
+    let peak = (cutoff + 1..hist.len()).max_by_key(|&c| (hist[c], std::cmp::Reverse(c)))?;
+    if hist[peak] == 0 {
+        return None;
+    }
+    // A peak near twice this one means this is the heterozygous peak.
+    let lo = (peak * 7).div_ceil(4);
+    let hi = ((peak * 9) / 4).min(hist.len() - 1);
+    let double = (lo..=hi)
+        .filter(|&c| hist[c] >= hist[c - 1] && hist[c] >= hist.get(c + 1).copied().unwrap_or(0))
+        .max_by_key(|&c| hist[c])
+        .filter(|&c| hist[c] * 4 >= hist[peak]);
+    let hom = double.unwrap_or(peak);
+    // The mode sits below the mean of a Poisson peak; use the mean of the
+    // bins around it.
+    let around = (hom * 3).div_ceil(4)..=(hom * 3 / 2).min(hist.len() - 1);
+    let (sum, count) = around.fold((0u64, 0u64), |(s, n), c| {
+        (s + c as u64 * hist[c], n + hist[c])
+    });
+    let coverage = sum as f64 / count as f64;
+
+    let mut kmers = 0u64;
+    let mut repeat_kmers = 0u64;
+    let (mut het, mut single) = (0u64, 0u64);
+    for (c, &n) in hist.iter().enumerate().skip(cutoff + 1) {
+        let seen = c as u64 * n;
+        kmers += seen;
+        if (c as f64) < 0.75 * coverage {
+            het += n;
+        } else if c as f64 <= 1.5 * coverage {
+            single += n;
+        } else {
+            repeat_kmers += seen;
+        }
+    }
+    let genome_kmers = single as f64 + het as f64 / 2.0;
+    let heterozygosity = if genome_kmers > 0.0 {
+        het as f64 / (2.0 * k as f64 * genome_kmers)
+    } else {
+        0.0
+    };
+    Some(GenomeEstimate {
+        error_cutoff: cutoff as u32,
+        kmer_coverage: coverage,
+        heterozygous_peak: double.is_some(),
+        genome_size: (kmers as f64 / coverage).round() as u64,
+        heterozygosity,
+        repeat_fraction: repeat_kmers as f64 / kmers.max(1) as f64,
+    })
+}
@@ -18,6 +18,37 @@ mod structs;
 mod phylo;
 mod gpu;
 
+mod bam;
+mod bgzf;
+mod bigwig;
+mod binning;
+mod concordance;
+mod consequence;
+mod cursor;
+mod depth;
+mod faidx;
+mod fastq_qc;
+mod fm_index;
+mod forest;
+mod genetic_code;
+mod intervals;
+mod iupac;
+mod kmer;
+mod mapper;
+mod normalize;
+mod paired;
+mod pairwise;
+mod reader;
+mod render;
+mod rng;
+mod search;
+mod significance;
+mod substitution;
+mod tabix;
+mod trim;
+mod vcf;
+mod writer;
+
 /// Convert a `cyanea_core::CyaneaError` into a NIF-friendly `String`.
 pub(crate) fn to_nif_error(e: cyanea_core::CyaneaError) -> String {
     e.to_string()
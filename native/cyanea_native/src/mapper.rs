@@ -0,0 +1,593 @@
+//! Short-read mapper for small references (viral, bacterial): FM-index
+//! seeds, colinear chaining and banded Smith-Waterman extension, written
+//! out as SAM with MAPQ, CIGAR and `NM`/`MD`/`AS`/`XS` tags.
+//!
+//! All contigs share one [`FmIndex`] over their upper-cased sequences,
+//! separated by a 0 byte so no seed spans two contigs. Seeds are maximal
+//! exact matches found right to left: the longest match ending at the read
+//! end, then the longest ending just before the base that stopped it, and
+//! so on. When extending a seed loses occurrences, the shorter match is
+//! kept too, so a read from one copy of a repeat still seeds the others.
+//! Seeds on each strand are chained by diagonal, the best chains are
+//! extended against the reference window they span, and MAPQ follows the
+//! gap between the best and second-best alignment scores.
+
+use crate::fm_index::FmIndex;
+use crate::iupac;
+use crate::reader::{SeqReader, SeqRecord};
+use crate::to_nif_error;
+use crate::writer::OutputFile;
+use cyanea_align::cigar;
+use std::collections::HashSet;
+
+const SEPARATOR: u8 = 0;
+
+/// Chains extended per read.
+const MAX_CHAINS: usize = 5;
+
+const MAX_MAPQ: u8 = 60;
+
+#[derive(Debug, Clone)]
+pub(crate) struct MapOptions {
+    /// Shortest exact match used as a seed.
+    pub min_seed_len: usize,
+    /// Seeds with more reference hits are dropped as repetitive.
+    pub max_occurrences: usize,
+    /// Largest net indel allowed within a chain and during extension.
+    pub bandwidth: usize,
+    pub match_score: i32,
+    /// Penalties, given as negative scores like the `cyanea_align`
+    /// scoring matrices.
+    pub mismatch_score: i32,
+    pub gap_open: i32,
+    pub gap_extend: i32,
+    /// Local alignments scoring lower are reported unmapped.
+    pub min_score: i32,
+}
+
+struct Contig {
+    name: String,
+    /// Start in the concatenated text.
+    offset: usize,
+    len: usize,
+}
+
+/// Reference contigs with an FM-index over them.
+pub(crate) struct ReferenceIndex {
+    contigs: Vec<Contig>,
+    text: Vec<u8>,
+    fm: FmIndex,
+}
+
+/// One read's primary alignment, or the read unmapped.
+#[derive(Debug, Clone)]
+pub(crate) struct MappedRead {
+    pub name: String,
+    pub flag: u16,
+    /// Empty when unmapped.
+    pub contig: String,
+    /// 1-based leftmost position; 0 when unmapped.
+    pub pos: u64,
+    pub mapq: u8,
+    pub cigar: String,
+    /// Read bases and qualities as they align to the forward strand.
+    pub sequence: Vec<u8>,
+    pub quality: Vec<u8>,
+    pub score: i32,
+    /// Best score at another locus, if any.
+    pub suboptimal_score: Option<i32>,
+    pub edit_distance: u32,
+    pub md: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Seed {
+    /// Position in the concatenated text.
+    r: usize,
+    /// Position in the (strand-oriented) read.
+    q: usize,
+    len: usize,
+}
+
+impl Seed {
+    fn diagonal(&self) -> isize {
+        self.r as isize - self.q as isize
+    }
+}
+
+struct Hit {
+    contig: usize,
+    /// 0-based start and end on the contig.
+    pos: usize,
+    end: usize,
+    reverse: bool,
+    score: i32,
+    cigar: String,
+    edit_distance: u32,
+    md: String,
+}
+
+impl ReferenceIndex {
+    /// Index every record of a FASTA file (plain or compressed).
+    pub(crate) fn build(path: &str) -> Result<Self, String> {
+        let mut reader = SeqReader::open(path)?;
+        let mut contigs = Vec::new();
+        let mut names = HashSet::new();
+        let mut text = Vec::new();
+        while let Some(rec) = reader.next_record()? {
+            if !names.insert(rec.name.clone()) {
+                return Err(format!("duplicate sequence name '{}' in {path}", rec.name));
+            }
+            contigs.push(Contig {
+                name: rec.name,
+                offset: text.len(),
+                len: rec.sequence.len(),
+            });
+            text.extend(rec.sequence.iter().map(u8::to_ascii_uppercase));
+            text.push(SEPARATOR);
+        }
+        if contigs.is_empty() {
+            return Err(format!("no sequences in {path}"));
+        }
+        let fm = FmIndex::build(&text)?;
+        Ok(Self { contigs, text, fm })
+    }
+
+    /// `(name, length)` of every contig, in file order.
+    pub(crate) fn contigs(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.contigs.iter().map(|c| (c.name.as_str(), c.len))
+    }
+
+    fn contig_at(&self, pos: usize) -> usize {
+        self.contigs.partition_point(|c| c.offset <= pos) - 1
+    }
+
+    /// Map one read, keeping its name, bases and qualities.
+    pub(crate) fn map(&self, rec: &SeqRecord, opts: &MapOptions) -> Result<MappedRead, String> {
+        let forward = rec.sequence.to_ascii_uppercase();
+        let mut reverse = forward.clone();
+        iupac::reverse_complement(&mut reverse);
+
+        let mut hits = Vec::new();
+        for (read, is_reverse) in [(&forward, false), (&reverse, true)] {
+            for chain in self.chains(read, opts) {
+                if let Some(hit) = self.extend(read, &chain, is_reverse, opts)? {
+                    hits.push(hit);
+                }
+            }
+        }
+        hits.sort_by(|a, b| b.score.cmp(&a.score).then(a.pos.cmp(&b.pos)));
+        // Chains from one locus can extend to overlapping alignments; keep
+        // the best of them.
+        let mut loci: Vec<Hit> = Vec::new();
+        for hit in hits {
+            let same = |h: &Hit| {
+                h.contig == hit.contig
+                    && h.reverse == hit.reverse
+                    && h.pos < hit.end
+                    && hit.pos < h.end
+            };
+            if !loci.iter().any(same) {
+                loci.push(hit);
+            }
+        }
+        let hits = loci;
+
+        let Some(best) = hits.first() else {
+            return Ok(MappedRead {
+                name: rec.name.clone(),
+                flag: 0x4,
+                contig: String::new(),
+                pos: 0,
+                mapq: 0,
+                cigar: String::new(),
+                sequence: rec.sequence.clone(),
+                quality: rec.quality.clone(),
+                score: 0,
+                suboptimal_score: None,
+                edit_distance: 0,
+                md: String::new(),
+            });
+        };
+        let second = hits.get(1).map(|h| h.score);
+        let mut sequence = rec.sequence.clone();
+        let mut quality = rec.quality.clone();
+        if best.reverse {
+            iupac::reverse_complement(&mut sequence);
+            quality.reverse();
+        }
+        Ok(MappedRead {
+            name: rec.name.clone(),
+            flag: if best.reverse { 0x10 } else { 0 },
+            contig: self.contigs[best.contig].name.clone(),
+            pos: best.pos as u64 + 1,
+            mapq: mapq(best.score, second, opts.match_score),
+            cigar: best.cigar.clone(),
+            sequence,
+            quality,
+            score: best.score,
+            suboptimal_score: second,
+            edit_distance: best.edit_distance,
+            md: best.md.clone(),
+        })
+    }
+
+    /// Maximal exact matches of `read`, each expanded to its reference
+    /// positions.
+    fn seeds(&self, read: &[u8], opts: &MapOptions) -> Vec<Seed> {
+        let min_len = opts.min_seed_len.max(1);
+        let mut out = Vec::new();
+        let mut add = |s: usize, e: usize, lo: usize, hi: usize| {
+            if e - s >= min_len && hi - lo <= opts.max_occurrences {
+                for r in self.fm.positions(lo, hi) {
+                    out.push(Seed {
+                        r,
+                        q: s,
+                        len: e - s,
+                    });
+                }
+            }
+        };
+        let mut e = read.len();
+        while e >= min_len {
+            let (mut lo, mut hi) = (0, self.fm.rows());
+            let mut s = e;
+            // The longest match before the last loss of occurrences.
+            let mut wider = None;
+            while s > 0 && matches!(read[s - 1], b'A' | b'C' | b'G' | b'T') {
+                let Some((l, h)) = self.fm.extend_left(lo, hi, read[s - 1]) else {
+                    break;
+                };
+                if h - l < hi - lo && e - s >= min_len {
+                    wider = Some((s, lo, hi));
+                }
+                (lo, hi) = (l, h);
+                s -= 1;
+            }
+            if s < e {
+                add(s, e, lo, hi);
+                if let Some((ws, wlo, whi)) = wider {
+                    add(ws, e, wlo, whi);
+                }
+            }
+            // Skip the base that ended the match.
+            e = if s == e { e - 1 } else { s.saturating_sub(1) };
+        }
+        out.sort_unstable();
+        out.dedup();
+        out
+    }
+
+    /// Colinear seed chains, best first: at most [`MAX_CHAINS`], each
+    /// scoring at least half the best. A chain's score is the read bases
+    /// its seeds cover.
+    fn chains(&self, read: &[u8], opts: &MapOptions) -> Vec<Vec<Seed>> {
+        let seeds = self.seeds(read, opts);
+        if seeds.is_empty() {
+            return Vec::new();
+        }
+        let contig: Vec<usize> = seeds.iter().map(|s| self.contig_at(s.r)).collect();
+        let band = opts.bandwidth as isize;
+        let span = read.len() + opts.bandwidth;
+        let mut score: Vec<usize> = seeds.iter().map(|s| s.len).collect();
+        let mut prev: Vec<Option<usize>> = vec![None; seeds.len()];
+        for i in 0..seeds.len() {
+            let a = seeds[i];
+            for j in (0..i).rev() {
+                let b = seeds[j];
+                if a.r - b.r > span || contig[j] != contig[i] {
+                    break;
+                }
+                if b.q >= a.q || b.r >= a.r || (a.diagonal() - b.diagonal()).abs() > band {
+                    continue;
+                }
+                let overlap = (b.q + b.len).saturating_sub(a.q);
+                let gained = score[j] + a.len.saturating_sub(overlap);
+                if gained > score[i] {
+                    score[i] = gained;
+                    prev[i] = Some(j);
+                }
+            }
+        }
+
+        let mut order: Vec<usize> = (0..seeds.len()).collect();
+        order.sort_by(|&a, &b| score[b].cmp(&score[a]).then(a.cmp(&b)));
+        let best = score[order[0]];
+        let mut used = vec![false; seeds.len()];
+        let mut chains = Vec::new();
+        for end in order {
+            if chains.len() == MAX_CHAINS || score[end] * 2 < best {
+                break;
+            }
+            if used[end] {
+                continue;
+            }
+            let mut chain = Vec::new();
+            let mut at = Some(end);
+            while let Some(i) = at {
+                if used[i] {
+                    break;
+                }
+                used[i] = true;
+                chain.push(seeds[i]);
+                at = prev[i];
+            }
+            chain.reverse();
+            chains.push(chain);
+        }
+        chains
+    }
+
+    /// Local alignment of `read` against the reference window around
+    /// `chain`, or `None` below `min_score`.
+    fn extend(
+        &self,
+        read: &[u8],
+        chain: &[Seed],
+        reverse: bool,
+        opts: &MapOptions,
+    ) -> Result<Option<Hit>, String> {
+        let c = self.contig_at(chain[0].r);
+        let contig = &self.contigs[c];
+        let band = opts.bandwidth as isize;
+        let first = chain.iter().map(Seed::diagonal).min().unwrap_or_default();
+        let last = chain.iter().map(Seed::diagonal).max().unwrap_or_default();
+        let start = (first - band).max(contig.offset as isize) as usize;
+        let end = ((last + read.len() as isize + band) as usize).min(contig.offset + contig.len);
+        let window = &self.text[start..end];
+        // The read runs along diagonals `first - start ..= last - start`
+        // of the window; widen the band to cover them plus the indels.
+        let offset = (first - start as isize)
+            .abs()
+            .max((last - start as isize).abs());
+        let bandwidth = (offset + band) as usize;
+
+        let matrix = cyanea_align::ScoringMatrix::new(
+            opts.match_score,
+            opts.mismatch_score,
+            opts.gap_open,
+            opts.gap_extend,
+        )
+        .map_err(to_nif_error)?;
+        let scoring = cyanea_align::ScoringScheme::Simple(matrix);
+        let aln = cyanea_align::simd::banded_sw(read, window, &scoring, bandwidth)
+            .map_err(to_nif_error)?;
+        if aln.score < opts.min_score {
+            return Ok(None);
+        }
+
+        let mut ops: Vec<(char, usize)> = Vec::new();
+        let mut push = |op: char, n: usize| match ops.last_mut() {
+            Some((last, len)) if *last == op => *len += n,
+            _ if n > 0 => ops.push((op, n)),
+            _ => {}
+        };
+        push('S', aln.query_start);
+        let (mut query_len, mut ref_len, mut edit_distance) = (0, 0, 0);
+        for (&q, &t) in aln.aligned_query.iter().zip(&aln.aligned_target) {
+            match (q, t) {
+                (b'-', _) => {
+                    push('D', 1);
+                    ref_len += 1;
+                    edit_distance += 1;
+                }
+                (_, b'-') => {
+                    push('I', 1);
+                    query_len += 1;
+                    edit_distance += 1;
+                }
+                _ => {
+                    push('M', 1);
+                    query_len += 1;
+                    ref_len += 1;
+                    edit_distance += (q != t) as u32;
+                }
+            }
+        }
+        push('S', read.len() - aln.query_start - query_len);
+        let cigar_string: String = ops.iter().map(|(op, n)| format!("{n}{op}")).collect();
+
+        let ref_start = start + aln.target_start;
+        let ops = cigar::parse_cigar(&cigar_string).map_err(to_nif_error)?;
+        let md = cigar::generate_md_tag(&ops, read, &self.text[ref_start..ref_start + ref_len])
+            .map_err(to_nif_error)?;
+        Ok(Some(Hit {
+            contig: c,
+            pos: ref_start - contig.offset,
+            end: ref_start - contig.offset + ref_len,
+            reverse,
+            score: aln.score,
+            cigar: cigar_string,
+            edit_distance,
+            md,
+        }))
+    }
+}
+
+/// BWA-style mapping quality: about 6 per match-score unit between the
+/// best and second-best alignment, capped at 60.
+fn mapq(best: i32, second: Option<i32>, match_score: i32) -> u8 {
+    let gap = (best - second.unwrap_or(0)).max(0) as f64;
+    (6.02 * gap / match_score.max(1) as f64)
+        .round()
+        .min(MAX_MAPQ as f64) as u8
+}
+
+impl MappedRead {
+    pub(crate) fn is_mapped(&self) -> bool {
+        self.flag & 0x4 == 0
+    }
+
+    /// The read as a SAM line (no trailing newline).
+    pub(crate) fn to_sam(&self) -> String {
+        let or_star = |s: &[u8]| {
+            if s.is_empty() {
+                "*".to_string()
+            } else {
+                String::from_utf8_lossy(s).into_owned()
+            }
+        };
+        let mut line = format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t*\t0\t0\t{}\t{}",
+            self.name,
+            self.flag,
+            or_star(self.contig.as_bytes()),
+            self.pos,
+            self.mapq,
+            or_star(self.cigar.as_bytes()),
+            or_star(&self.sequence),
+            or_star(&self.quality),
+        );
+        if self.is_mapped() {
+            line.push_str(&format!(
+                "\tNM:i:{}\tMD:Z:{}\tAS:i:{}",
+                self.edit_distance, self.md, self.score
+            ));
+            if let Some(xs) = self.suboptimal_score {
+                line.push_str(&format!("\tXS:i:{xs}"));
+            }
+        }
+        line
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MapReport {
+    pub reads: usize,
+    pub mapped: usize,
+    pub mapq_sum: u64,
+    pub bases: u64,
+}
+
+/// Map every read of a FASTA/FASTQ file and write SAM to `output`.
+pub(crate) fn map_file(
+    index: &ReferenceIndex,
+    reads: &str,
+    output: &str,
+    opts: &MapOptions,
+) -> Result<MapReport, String> {
+    let mut reader = SeqReader::open(reads)?;
+    let mut out = OutputFile::create(output, false)?;
+    out.line("@HD\tVN:1.6\tSO:unsorted")?;
+    for (name, len) in index.contigs() {
+        out.line(&format!("@SQ\tSN:{name}\tLN:{len}"))?;
+    }
+    out.line("@PG\tID:cyanea\tPN:cyanea")?;
+    let mut report = MapReport::default();
+    while let Some(rec) = reader.next_record()? {
+        let mapped = index.map(&rec, opts)?;
+        report.reads += 1;
+        report.bases += rec.sequence.len() as u64;
+        if mapped.is_mapped() {
+            report.mapped += 1;
+            report.mapq_sum += mapped.mapq as u64;
+        }
+        out.line(&mapped.to_sam())?;
+    }
+    out.finish()?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::SplitMix64;
+
+    fn options() -> MapOptions {
+        MapOptions {
+            min_seed_len: 19,
+            max_occurrences: 500,
+            bandwidth: 100,
+            match_score: 1,
+            mismatch_score: -4,
+            gap_open: -6,
+            gap_extend: -1,
+            min_score: 30,
+        }
+    }
+
+    fn read(name: &str, sequence: &[u8]) -> SeqRecord {
+        SeqRecord {
+            name: name.to_string(),
+            description: String::new(),
+            sequence: sequence.to_vec(),
+            quality: vec![b'I'; sequence.len()],
+        }
+    }
+
+    /// `chr1` random; `chr2` random with a copy of `chr1[600..700]` at 200.
+    fn reference() -> (ReferenceIndex, Vec<u8>) {
+        let mut rng = SplitMix64::new(21);
+        let mut random = |n: usize| -> Vec<u8> { (0..n).map(|_| b"ACGT"[rng.below(4)]).collect() };
+        let chr1 = random(1000);
+        let chr2 = [random(200), chr1[600..700].to_vec(), random(200)].concat();
+        let path = std::env::temp_dir().join(format!("cyanea-mapper-{}.fa", std::process::id()));
+        let fasta = format!(
+            ">chr1\n{}\n>chr2\n{}\n",
+            String::from_utf8_lossy(&chr1),
+            String::from_utf8_lossy(&chr2)
+        );
+        std::fs::write(&path, fasta).unwrap();
+        let index = ReferenceIndex::build(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        (index, chr1)
+    }
+
+    #[test]
+    fn maps_a_read_with_a_deletion_and_a_mismatch() {
+        let (index, chr1) = reference();
+        // chr1[100..200] without chr1[150..152], and base 120 changed.
+        let mut seq = [&chr1[100..150], &chr1[152..200]].concat();
+        seq[20] = iupac::complement(seq[20]);
+        // The deletion has one placement: it cannot shift either way.
+        assert!(chr1[149] != chr1[151] && chr1[150] != chr1[152]);
+        let deleted = String::from_utf8_lossy(&chr1[150..152]);
+        let md = format!("20{}29^{deleted}48", chr1[120] as char);
+
+        let mapped = index.map(&read("r1", &seq), &options()).unwrap();
+        assert_eq!(
+            (mapped.contig.as_str(), mapped.pos, mapped.flag),
+            ("chr1", 101, 0)
+        );
+        assert_eq!(mapped.cigar, "50M2D48M");
+        assert_eq!(mapped.md, md);
+        assert_eq!(mapped.edit_distance, 3);
+        // 97 matches, one mismatch, a 2-base gap (6 + 2 * 1).
+        assert_eq!(mapped.score, 97 - 4 - 8);
+        assert_eq!((mapped.mapq, mapped.suboptimal_score), (60, None));
+
+        // The reverse complement maps to the same place on the minus strand,
+        // reported as the forward-strand bases.
+        let mut rc = seq.clone();
+        iupac::reverse_complement(&mut rc);
+        let minus = index.map(&read("r2", &rc), &options()).unwrap();
+        assert_eq!((minus.pos, minus.flag), (101, 0x10));
+        assert_eq!(
+            (minus.cigar.as_str(), minus.md.as_str()),
+            ("50M2D48M", md.as_str())
+        );
+        assert_eq!(minus.sequence, seq);
+    }
+
+    #[test]
+    fn repeats_get_zero_mapq_and_unrelated_reads_stay_unmapped() {
+        let (index, chr1) = reference();
+        let repeat = index.map(&read("r3", &chr1[610..690]), &options()).unwrap();
+        assert_eq!((repeat.cigar.as_str(), repeat.score), ("80M", 80));
+        assert_eq!((repeat.mapq, repeat.suboptimal_score), (0, Some(80)));
+
+        let mut rng = SplitMix64::new(99);
+        let noise: Vec<u8> = (0..80).map(|_| b"ACGT"[rng.below(4)]).collect();
+        let unmapped = index.map(&read("r4", &noise), &options()).unwrap();
+        assert!(!unmapped.is_mapped());
+        assert!(unmapped.to_sam().starts_with("r4\t4\t*\t0\t0\t*\t"));
+    }
+
+    #[test]
+    fn mapq_scales_with_the_score_gap() {
+        assert_eq!(mapq(80, Some(80), 1), 0);
+        assert_eq!(mapq(80, Some(75), 1), 30);
+        assert_eq!(mapq(80, Some(70), 2), 30);
+        assert_eq!(mapq(80, None, 1), 60);
+    }
+}
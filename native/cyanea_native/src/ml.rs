@@ -1,6 +1,7 @@
 //! cyanea-ml NIFs — Clustering, PCA, t-SNE, UMAP, embeddings, distances, KNN, regression, HMM.
 
 use crate::bridge::*;
+use crate::forest::{ForestConfig, RandomForestModel};
 use crate::to_nif_error;
 
 // ===========================================================================
@@ -43,6 +44,66 @@ pub(crate) fn flat_to_slices(data: &[f64], n_features: usize) -> Result<Vec<&[f6
     Ok(data.chunks(n_features).collect())
 }
 
+/// Blob written by `random_forest_fit` before fitted trees were serialized:
+/// the training set and config, refitted with `cyanea_ml` on every call.
+type LegacyForestState = (Vec<f64>, usize, Vec<usize>, usize, usize, u64);
+
+const LEGACY_FOREST: &str =
+    "legacy random forest model (training-set blob without a version header)";
+
+enum Forest {
+    Fitted(RandomForestModel),
+    Legacy(cyanea_ml::RandomForest),
+}
+
+impl Forest {
+    fn predict(&self, sample: &[f64]) -> Result<usize, String> {
+        match self {
+            Forest::Fitted(model) => model.predict(sample),
+            Forest::Legacy(model) => Ok(model.predict(sample)),
+        }
+    }
+
+    fn fitted(self, what: &str) -> Result<RandomForestModel, String> {
+        match self {
+            Forest::Fitted(model) => Ok(model),
+            Forest::Legacy(_) => Err(format!(
+                "{LEGACY_FOREST} has no {what}; refit it with random_forest_fit"
+            )),
+        }
+    }
+}
+
+/// Decode a model blob. Versioned blobs hold fitted trees; older blobs are
+/// refitted with `cyanea_ml::RandomForest` exactly as they were when written,
+/// so stored models keep predicting the same labels.
+fn decode_forest(model_data: &[u8], n_features: usize) -> Result<Forest, String> {
+    let (forest, trained_on) = if RandomForestModel::is_versioned(model_data) {
+        let model = RandomForestModel::from_bytes(model_data)?;
+        let trained_on = model.n_features;
+        (Forest::Fitted(model), trained_on)
+    } else {
+        let (data, trained_on, labels, n_trees, max_depth, seed): LegacyForestState =
+            bincode::deserialize(model_data)
+                .map_err(|_| "not a serialized random forest model".to_string())?;
+        let config = cyanea_ml::RandomForestConfig {
+            n_trees,
+            max_depth,
+            seed,
+            ..Default::default()
+        };
+        let model = cyanea_ml::RandomForest::fit(&data, trained_on, &labels, &config)
+            .map_err(to_nif_error)?;
+        (Forest::Legacy(model), trained_on)
+    };
+    if trained_on != n_features {
+        return Err(format!(
+            "model was trained on {trained_on} features, got n_features {n_features}"
+        ));
+    }
+    Ok(forest)
+}
+
 fn parse_linkage(s: &str) -> Result<cyanea_ml::Linkage, String> {
     match s {
         "single" => Ok(cyanea_ml::Linkage::Single),
@@ -287,36 +348,68 @@ pub fn random_forest_fit(
     max_depth: usize,
     seed: u64,
 ) -> Result<Vec<u8>, String> {
-    let config = cyanea_ml::RandomForestConfig {
+    let config = ForestConfig {
         n_trees,
         max_depth,
+        min_samples_split: 2,
+        max_features: None,
         seed,
-        ..Default::default()
     };
-    let _model = cyanea_ml::RandomForest::fit(&data, n_features, &labels, &config).map_err(to_nif_error)?;
-    // RandomForest doesn't implement Serialize. Serialize the training state
-    // so predict can re-train (not ideal but avoids needing serde on the model).
-    let state = (data, n_features, labels, n_trees, max_depth, seed);
-    bincode::serialize(&state).map_err(|e| e.to_string())
+    let model = RandomForestModel::fit(&data, n_features, &labels, &config)?;
+    model.to_bytes()
 }
 
-#[rustler::nif]
+#[rustler::nif(schedule = "DirtyCpu")]
 pub fn random_forest_predict(
     model_data: Vec<u8>,
     sample: Vec<f64>,
-    _n_features: usize,
+    n_features: usize,
 ) -> Result<usize, String> {
-    let (data, n_features, labels, n_trees, max_depth, seed): (Vec<f64>, usize, Vec<usize>, usize, usize, u64) =
-        bincode::deserialize(&model_data).map_err(|e| e.to_string())?;
-    let config = cyanea_ml::RandomForestConfig {
-        n_trees,
-        max_depth,
-        seed,
-        ..Default::default()
-    };
-    let model = cyanea_ml::RandomForest::fit(&data, n_features, &labels, &config)
-        .map_err(to_nif_error)?;
-    Ok(model.predict(&sample))
+    let model = decode_forest(&model_data, n_features)?;
+    model.predict(&sample)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn random_forest_predict_batch(
+    model_data: Vec<u8>,
+    data: Vec<f64>,
+    n_features: usize,
+) -> Result<Vec<usize>, String> {
+    let model = decode_forest(&model_data, n_features)?;
+    flat_to_slices(&data, n_features)?
+        .into_iter()
+        .map(|row| model.predict(row))
+        .collect()
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn random_forest_predict_proba(
+    model_data: Vec<u8>,
+    data: Vec<f64>,
+    n_features: usize,
+) -> Result<Vec<Vec<f64>>, String> {
+    let model = decode_forest(&model_data, n_features)?.fitted("class probabilities")?;
+    flat_to_slices(&data, n_features)?
+        .into_iter()
+        .map(|row| model.predict_proba(row))
+        .collect()
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn random_forest_info(model_data: Vec<u8>) -> Result<RandomForestInfoNif, String> {
+    if !RandomForestModel::is_versioned(&model_data) {
+        return Err(format!(
+            "{LEGACY_FOREST} has no tree summary; refit it with random_forest_fit"
+        ));
+    }
+    let model = RandomForestModel::from_bytes(&model_data)?;
+    Ok(RandomForestInfoNif {
+        n_trees: model.n_trees(),
+        n_features: model.n_features,
+        n_classes: model.n_classes,
+        oob_error: model.oob_error,
+        feature_importances: model.feature_importances,
+    })
 }
 
 #[rustler::nif]
@@ -0,0 +1,362 @@
+//! Variant normalization: allele trimming, indel left-alignment and
+//! multi-allelic splitting.
+//!
+//! An indel in a repeat can be written at several positions, so calls from
+//! different callers compare unequal until normalized. Normalization
+//! follows the vt / `bcftools norm` definition: alleles are trimmed of
+//! shared trailing bases (extending to the left from the reference whenever
+//! an allele would become empty), then of shared leading bases while every
+//! allele keeps at least one. The result is the leftmost, most parsimonious
+//! representation.
+
+use crate::faidx::ChromCache;
+use crate::vcf::{FieldDef, VcfHeader, VcfReader, VcfRecord};
+use crate::writer::OutputFile;
+use std::collections::HashMap;
+
+/// Normalized records may move left; output is held back this many bases
+/// behind the input position so it stays sorted. Shifts across longer
+/// repeats can leave records out of order.
+const SORT_WINDOW: u64 = 1000;
+
+/// Normalize `ref_allele`/`alts` at 1-based `pos` against the chromosome
+/// `seq` (upper-case). Returns the new position and alleles; symbolic,
+/// `*` and missing alleles are returned unchanged.
+pub(crate) fn normalize_alleles(
+    seq: &[u8],
+    pos: u64,
+    ref_allele: &str,
+    alts: &[String],
+) -> Result<(u64, String, Vec<String>), String> {
+    let mut start = pos
+        .checked_sub(1)
+        .ok_or_else(|| "POS must be at least 1".to_string())? as usize;
+    let ref_upper = ref_allele.to_ascii_uppercase();
+    if ref_upper.is_empty() || seq.get(start..start + ref_upper.len()) != Some(ref_upper.as_bytes())
+    {
+        return Err(format!(
+            "REF {ref_allele} does not match the reference at position {pos}"
+        ));
+    }
+    if alts.is_empty() || alts.iter().any(|a| VcfRecord::is_symbolic(a) || a == ".") {
+        return Ok((pos, ref_allele.to_string(), alts.to_vec()));
+    }
+    let mut alleles: Vec<Vec<u8>> = std::iter::once(ref_upper)
+        .chain(alts.iter().map(|a| a.to_ascii_uppercase()))
+        .map(String::into_bytes)
+        .collect();
+    if alleles[1..].iter().all(|a| *a == alleles[0]) {
+        return Ok((pos, ref_allele.to_string(), alts.to_vec()));
+    }
+
+    loop {
+        if alleles.iter().any(Vec::is_empty) {
+            if start == 0 {
+                return Err("empty allele at the first base".into());
+            }
+            start -= 1;
+            for a in &mut alleles {
+                a.insert(0, seq[start]);
+            }
+            continue;
+        }
+        let last = alleles[0][alleles[0].len() - 1];
+        // At the first base there is nothing to extend into, so stop
+        // before an allele would become empty.
+        let shared = alleles.iter().all(|a| a[a.len() - 1] == last);
+        if !shared || (start == 0 && alleles.iter().any(|a| a.len() == 1)) {
+            break;
+        }
+        for a in &mut alleles {
+            a.pop();
+        }
+    }
+    while alleles
+        .iter()
+        .all(|a| a.len() >= 2 && a[0] == alleles[0][0])
+    {
+        for a in &mut alleles {
+            a.remove(0);
+        }
+        start += 1;
+    }
+
+    let mut alleles = alleles
+        .into_iter()
+        .map(|a| String::from_utf8_lossy(&a).into_owned());
+    let ref_allele = alleles.next().unwrap_or_default();
+    Ok((start as u64 + 1, ref_allele, alleles.collect()))
+}
+
+/// Normalize the alleles of `rec` in place. Returns whether the position
+/// or any allele changed.
+pub(crate) fn normalize_record(rec: &mut VcfRecord, seq: &[u8]) -> Result<bool, String> {
+    let (pos, ref_allele, alts) =
+        normalize_alleles(seq, rec.pos, &rec.ref_allele, &rec.alt_alleles)?;
+    let changed = pos != rec.pos || ref_allele != rec.ref_allele || alts != rec.alt_alleles;
+    rec.pos = pos;
+    rec.ref_allele = ref_allele;
+    rec.alt_alleles = alts;
+    Ok(changed)
+}
+
+/// One biallelic record per ALT allele. INFO and FORMAT values declared
+/// `Number=A`, `R` or `G` are subset to the allele; in `GT` the allele
+/// becomes `1` and other ALT alleles become `0`, as `bcftools norm -m-`
+/// does.
+pub(crate) fn split_alleles(rec: &VcfRecord, header: &VcfHeader) -> Vec<VcfRecord> {
+    let n_alt = rec.alt_alleles.len();
+    if n_alt < 2 {
+        return vec![rec.clone()];
+    }
+    let format_numbers: Vec<&str> = rec
+        .format
+        .iter()
+        .map(|k| number(&header.format, k))
+        .collect();
+    (1..=n_alt)
+        .map(|k| {
+            let info = rec
+                .info
+                .iter()
+                .map(|(key, value)| {
+                    let value = value
+                        .as_deref()
+                        .map(|v| subset_values(v, number(&header.info, key), k, n_alt));
+                    (key.clone(), value)
+                })
+                .collect();
+            let samples = rec
+                .samples
+                .iter()
+                .map(|sample| {
+                    sample
+                        .iter()
+                        .zip(&rec.format)
+                        .zip(&format_numbers)
+                        .map(|((v, key), number)| {
+                            if key == "GT" {
+                                recode_genotype(v, k)
+                            } else {
+                                subset_values(v, number, k, n_alt)
+                            }
+                        })
+                        .collect()
+                })
+                .collect();
+            VcfRecord {
+                alt_alleles: vec![rec.alt_alleles[k - 1].clone()],
+                info,
+                samples,
+                ..rec.clone()
+            }
+        })
+        .collect()
+}
+
+/// Declared `Number` of a field; `.` when undeclared.
+fn number<'a>(defs: &'a HashMap<String, FieldDef>, key: &str) -> &'a str {
+    defs.get(key).map_or(".", |d| d.number.as_str())
+}
+
+/// The entries of a comma-separated value that belong to ALT allele `k`
+/// (1-based). Values whose length does not fit `number` are kept whole.
+fn subset_values(value: &str, number: &str, k: usize, n_alt: usize) -> String {
+    let items: Vec<&str> = value.split(',').collect();
+    let pick = |idx: &[usize]| idx.iter().map(|&i| items[i]).collect::<Vec<_>>().join(",");
+    // Diploid genotype order: (j, k) with j <= k sits at k * (k + 1) / 2 + j.
+    let diploid = |j: usize, k: usize| k * (k + 1) / 2 + j;
+    match number {
+        "A" if items.len() == n_alt => pick(&[k - 1]),
+        "R" if items.len() == n_alt + 1 => pick(&[0, k]),
+        "G" if items.len() == n_alt + 1 => pick(&[0, k]),
+        "G" if items.len() == (n_alt + 1) * (n_alt + 2) / 2 => {
+            pick(&[diploid(0, 0), diploid(0, k), diploid(k, k)])
+        }
+        _ => value.to_string(),
+    }
+}
+
+/// `GT` with allele `k` recoded as `1` and other ALT alleles as `0`,
+/// keeping separators and missing calls.
+fn recode_genotype(gt: &str, k: usize) -> String {
+    let mut out = String::with_capacity(gt.len());
+    let mut rest = gt;
+    loop {
+        let end = rest.find(['/', '|']).unwrap_or(rest.len());
+        let allele = &rest[..end];
+        out.push_str(match allele.parse::<usize>() {
+            Ok(i) if i == k => "1",
+            Ok(_) => "0",
+            Err(_) => allele,
+        });
+        if end == rest.len() {
+            return out;
+        }
+        out.push_str(&rest[end..=end]);
+        rest = &rest[end + 1..];
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct NormalizeStats {
+    pub records_in: u64,
+    pub records_out: u64,
+    /// Output records whose position or alleles changed.
+    pub normalized: u64,
+    /// Input records split into biallelic records.
+    pub split: u64,
+}
+
+/// Normalize every record of `input` against `fasta` and write the result
+/// to `output`, optionally splitting multi-allelic records first. The
+/// reference is read one chromosome at a time as the VCF reaches it.
+pub(crate) fn normalize_vcf(
+    input: &str,
+    output: &str,
+    fasta: &str,
+    split: bool,
+    bgzf: bool,
+) -> Result<NormalizeStats, String> {
+    let mut genome = ChromCache::open(fasta)?;
+    let mut reader = VcfReader::open(input)?;
+    let header = reader.header().clone();
+    let n_samples = header.samples.len();
+    let mut out = OutputFile::create(output, bgzf)?;
+    for line in header.lines() {
+        out.line(&line)?;
+    }
+    let mut stats = NormalizeStats::default();
+    let mut pending: Vec<VcfRecord> = Vec::new();
+    while let Some(rec) = reader.next_record()? {
+        stats.records_in += 1;
+        if pending.first().is_some_and(|p| p.chrom != rec.chrom) {
+            flush(&mut pending, u64::MAX, n_samples, &mut out, &mut stats)?;
+        }
+        let seq = genome.get(&rec.chrom)?;
+        let input_pos = rec.pos;
+        let records = if split && rec.alt_alleles.len() > 1 {
+            stats.split += 1;
+            split_alleles(&rec, &header)
+        } else {
+            vec![rec]
+        };
+        for mut r in records {
+            let changed =
+                normalize_record(&mut r, seq).map_err(|e| format!("{}:{}: {e}", r.chrom, r.pos))?;
+            stats.normalized += changed as u64;
+            pending.push(r);
+        }
+        flush(
+            &mut pending,
+            input_pos.saturating_sub(SORT_WINDOW),
+            n_samples,
+            &mut out,
+            &mut stats,
+        )?;
+    }
+    flush(&mut pending, u64::MAX, n_samples, &mut out, &mut stats)?;
+    out.finish()?;
+    Ok(stats)
+}
+
+/// Write the pending records that start before `before`, in position
+/// order (input order among equal positions).
+fn flush(
+    pending: &mut Vec<VcfRecord>,
+    before: u64,
+    n_samples: usize,
+    out: &mut OutputFile,
+    stats: &mut NormalizeStats,
+) -> Result<(), String> {
+    pending.sort_by_key(|r| r.pos);
+    let n = pending.partition_point(|r| r.pos < before);
+    for r in pending.drain(..n) {
+        out.line(&r.to_line(n_samples))?;
+        stats.records_out += 1;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn norm(seq: &str, pos: u64, ref_allele: &str, alts: &[&str]) -> (u64, String, Vec<String>) {
+        let alts: Vec<String> = alts.iter().map(|a| a.to_string()).collect();
+        normalize_alleles(seq.as_bytes(), pos, ref_allele, &alts).unwrap()
+    }
+
+    #[test]
+    fn homopolymer_indels_move_to_the_left_end() {
+        //          12345678
+        let seq = "GCAAAAAT";
+        let del = (2, "CA".to_string(), vec!["C".to_string()]);
+        assert_eq!(norm(seq, 6, "AA", &["A"]), del);
+        assert_eq!(norm(seq, 5, "AAAT", &["AAT"]), del);
+        let ins = (2, "C".to_string(), vec!["CA".to_string()]);
+        assert_eq!(norm(seq, 7, "A", &["AA"]), ins);
+        assert_eq!(norm(seq, 7, "AT", &["AAT"]), ins);
+        // SNVs stay put; soft-masked alleles come back upper-case.
+        assert_eq!(
+            norm(seq, 3, "a", &["g"]),
+            (3, "A".to_string(), vec!["G".to_string()])
+        );
+    }
+
+    #[test]
+    fn repeat_unit_indels_at_the_contig_start() {
+        // Nothing lies left of base 1, so the anchor base stays on the left
+        // and the repeat unit shifts as far as it can.
+        let seq = "CACACAG";
+        let del = (1, "CAC".to_string(), vec!["C".to_string()]);
+        assert_eq!(norm(seq, 5, "CAG", &["G"]), del);
+        assert_eq!(norm(seq, 3, "CAC", &["C"]), del);
+        let ins = (1, "C".to_string(), vec!["CAC".to_string()]);
+        assert_eq!(norm(seq, 6, "A", &["ACA"]), ins);
+        // An indel of the first base itself keeps the following base.
+        assert_eq!(
+            norm("AAAT", 2, "AA", &["A"]),
+            (1, "AA".to_string(), vec!["A".to_string()])
+        );
+        assert!(normalize_alleles(b"ACGT", 1, "AC", &["".to_string()]).is_err());
+        assert!(normalize_alleles(b"ACGT", 2, "AC", &["A".to_string()]).is_err());
+    }
+
+    #[test]
+    fn split_subsets_per_allele_fields_then_normalizes() {
+        let meta = [
+            "##INFO=<ID=AF,Number=A,Type=Float,Description=\"\">",
+            "##INFO=<ID=DP,Number=1,Type=Integer,Description=\"\">",
+            "##FORMAT=<ID=GT,Number=1,Type=String,Description=\"\">",
+            "##FORMAT=<ID=AD,Number=R,Type=Integer,Description=\"\">",
+            "##FORMAT=<ID=PL,Number=G,Type=Integer,Description=\"\">",
+        ]
+        .map(String::from);
+        let header = VcfHeader::from_meta(&meta, vec!["s1".into(), "s2".into()]);
+        let rec = VcfRecord::parse(
+            "chr1\t2\t.\tCAAA\tCAA,CAAAA\t50\tPASS\tAF=0.25,0.5;DP=30\tGT:AD:PL\t\
+             1/2:1,5,6:90,60,50,40,0,30\t0|0:9,.,1:0,20,200,25,210,220",
+            2,
+        )
+        .unwrap();
+
+        let seq = b"GCAAAT";
+        let lines: Vec<String> = split_alleles(&rec, &header)
+            .into_iter()
+            .map(|mut r| {
+                normalize_record(&mut r, seq).unwrap();
+                r.to_line(2)
+            })
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "chr1\t2\t.\tCA\tC\t50\tPASS\tAF=0.25;DP=30\tGT:AD:PL\t\
+                 1/0:1,5:90,60,50\t0|0:9,.:0,20,200",
+                "chr1\t2\t.\tC\tCA\t50\tPASS\tAF=0.5;DP=30\tGT:AD:PL\t\
+                 0/1:1,6:90,40,30\t0|0:9,1:0,25,220",
+            ]
+        );
+    }
+}
@@ -1,6 +1,11 @@
 //! cyanea-omics NIFs — Variant classification, genomic intervals, expression matrices.
 
 use crate::bridge::*;
+use crate::concordance::{self, ClassCounts};
+use crate::consequence::{Annotator, Consequence};
+use crate::faidx::ChromCache;
+use crate::intervals::{self, Interval, Report, StrandMode};
+use crate::normalize::{self, normalize_alleles};
 use crate::to_nif_error;
 
 #[rustler::nif]
@@ -64,6 +69,239 @@ pub fn genomic_coverage(
     Ok(set.coverage(&query_chrom))
 }
 
+/// bedtools `intersect`. `report` is `"overlap"` (default output), `"a"`
+/// (`-wa`), `"b"` (`-wb`), `"both"` (`-wa -wb`), `"any"` (`-u`) or `"none"`
+/// (`-v`); `strand` is `"ignore"`, `"same"` (`-s`) or `"opposite"` (`-S`);
+/// `min_overlap` is the fraction of A that must overlap (`-f`).
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn interval_intersect(
+    a: Vec<GenomicIntervalNif>,
+    b: Vec<GenomicIntervalNif>,
+    report: String,
+    strand: String,
+    min_overlap: f64,
+) -> Result<Vec<IntervalPairNif>, String> {
+    let report = Report::parse(&report)?;
+    let strand = StrandMode::parse(&strand)?;
+    if !(0.0..=1.0).contains(&min_overlap) {
+        return Err("min_overlap must be between 0 and 1".into());
+    }
+    let (a, b) = (intervals_from_nif(a)?, intervals_from_nif(b)?);
+    Ok(intervals::intersect(&a, &b, report, strand, min_overlap)
+        .into_iter()
+        .map(|(a, b)| IntervalPairNif {
+            a: a.into(),
+            b: b.map(GenomicIntervalNif::from),
+        })
+        .collect())
+}
+
+/// bedtools `subtract`: the parts of each A interval not covered by B.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn interval_subtract(
+    a: Vec<GenomicIntervalNif>,
+    b: Vec<GenomicIntervalNif>,
+    strand: String,
+) -> Result<Vec<GenomicIntervalNif>, String> {
+    let strand = StrandMode::parse(&strand)?;
+    let (a, b) = (intervals_from_nif(a)?, intervals_from_nif(b)?);
+    Ok(intervals::subtract(&a, &b, strand)
+        .into_iter()
+        .map(GenomicIntervalNif::from)
+        .collect())
+}
+
+/// bedtools `complement` against `genome`, a list of `{chrom, length}` in
+/// output order.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn interval_complement(
+    regions: Vec<GenomicIntervalNif>,
+    genome: Vec<(String, u64)>,
+) -> Result<Vec<GenomicIntervalNif>, String> {
+    let regions = intervals_from_nif(regions)?;
+    Ok(intervals::complement(&regions, &genome)?
+        .into_iter()
+        .map(GenomicIntervalNif::from)
+        .collect())
+}
+
+/// bedtools `closest -D a -t all`: nearest B interval(s) for each A.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn interval_closest(
+    a: Vec<GenomicIntervalNif>,
+    b: Vec<GenomicIntervalNif>,
+    strand: String,
+) -> Result<Vec<ClosestIntervalNif>, String> {
+    let strand = StrandMode::parse(&strand)?;
+    let (a, b) = (intervals_from_nif(a)?, intervals_from_nif(b)?);
+    Ok(intervals::closest(&a, &b, strand)
+        .into_iter()
+        .map(|(a, b, distance)| ClosestIntervalNif {
+            a: a.into(),
+            b: b.map(GenomicIntervalNif::from),
+            distance,
+        })
+        .collect())
+}
+
+/// bedtools `window`: A/B pairs with B within `left`/`right` bases of A
+/// (upstream/downstream of A's strand when `strand_relative`, as `-sw`).
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn interval_window(
+    a: Vec<GenomicIntervalNif>,
+    b: Vec<GenomicIntervalNif>,
+    left: u64,
+    right: u64,
+    strand_relative: bool,
+    strand: String,
+) -> Result<Vec<IntervalPairNif>, String> {
+    let strand = StrandMode::parse(&strand)?;
+    let (a, b) = (intervals_from_nif(a)?, intervals_from_nif(b)?);
+    Ok(intervals::window(&a, &b, left, right, strand_relative, strand)
+        .into_iter()
+        .map(|(a, b)| IntervalPairNif {
+            a: a.into(),
+            b: Some(b.into()),
+        })
+        .collect())
+}
+
+/// Predict consequences and HGVS for `(chrom, pos, ref, alt)` alleles
+/// (VCF coordinates) against the transcripts of a GFF3 and a reference
+/// FASTA. One result per overlapping transcript, or only the most severe
+/// per allele with `most_severe`.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn annotate_variants(
+    variants: Vec<(String, u64, String, String)>,
+    gff_path: String,
+    fasta_path: String,
+    most_severe: bool,
+) -> Result<Vec<VariantEffectNif>, String> {
+    let mut annotator = Annotator::load(&gff_path, &fasta_path)?;
+    let mut out = Vec::new();
+    for index in by_chrom(&variants, |v| v.0.as_str()) {
+        let (chrom, position, ref_allele, alt) = &variants[index];
+        let mut effects = annotator.annotate(chrom, *position, ref_allele, alt)?;
+        if most_severe {
+            let best = (0..effects.len()).min_by_key(|&i| effects[i].consequences.first().copied());
+            if let Some(i) = best {
+                effects = vec![effects.swap_remove(i)];
+            }
+        }
+        out.extend(effects.into_iter().map(|e| {
+            let tx = e.transcript.map(|i| &annotator.transcripts[i]);
+            let worst = e.consequences.first().copied().unwrap_or(Consequence::Intergenic);
+            let effect = VariantEffectNif {
+                chrom: chrom.clone(),
+                position: *position,
+                ref_allele: ref_allele.clone(),
+                alt_allele: alt.clone(),
+                gene: tx.and_then(|t| t.gene.clone()),
+                transcript: tx.map(|t| t.id.clone()),
+                consequences: e.consequences.iter().map(|c| c.as_str().to_string()).collect(),
+                impact: worst.impact().to_string(),
+                hgvs_c: e.hgvs_c,
+                hgvs_p: e.hgvs_p,
+            };
+            (index, effect)
+        }));
+    }
+    out.sort_by_key(|&(index, _)| index);
+    Ok(out.into_iter().map(|(_, effect)| effect).collect())
+}
+
+/// Trim, left-align and optionally split `(chrom, pos, ref, alts)` variants
+/// against a reference FASTA, so equivalent representations compare equal.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn normalize_variants(
+    variants: Vec<(String, u64, String, Vec<String>)>,
+    fasta_path: String,
+    split: bool,
+) -> Result<Vec<NormalizedVariantNif>, String> {
+    let mut genome = ChromCache::open(&fasta_path)?;
+    let mut out = Vec::with_capacity(variants.len());
+    for index in by_chrom(&variants, |v| v.0.as_str()) {
+        let (chrom, position, ref_allele, alts) = &variants[index];
+        let seq = genome.get(chrom)?;
+        let groups = if split && alts.len() > 1 {
+            alts.iter().map(|a| vec![a.clone()]).collect()
+        } else {
+            vec![alts.clone()]
+        };
+        for alts in groups {
+            let (pos, r, a) = normalize_alleles(seq, *position, ref_allele, &alts)
+                .map_err(|e| format!("{chrom}:{position}: {e}"))?;
+            out.push(NormalizedVariantNif {
+                index,
+                chrom: chrom.clone(),
+                changed: pos != *position || r != *ref_allele || a != alts,
+                position: pos,
+                ref_allele: r,
+                alt_alleles: a,
+            });
+        }
+    }
+    out.sort_by_key(|v| v.index);
+    Ok(out)
+}
+
+/// Indices of `variants` grouped by chromosome (input order within one),
+/// so the reference is read one chromosome at a time.
+fn by_chrom<T>(variants: &[T], chrom: impl Fn(&T) -> &str) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..variants.len()).collect();
+    order.sort_by(|&i, &j| chrom(&variants[i]).cmp(chrom(&variants[j])));
+    order
+}
+
+/// Normalize every record of a VCF against a reference FASTA and write the
+/// result, optionally splitting multi-allelic records first.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn normalize_vcf(
+    input: String,
+    output: String,
+    fasta_path: String,
+    split: bool,
+    bgzf: bool,
+) -> Result<NormalizeStatsNif, String> {
+    let stats = normalize::normalize_vcf(&input, &output, &fasta_path, split, bgzf)?;
+    Ok(NormalizeStatsNif {
+        records_in: stats.records_in,
+        records_out: stats.records_out,
+        normalized: stats.normalized,
+        split: stats.split,
+    })
+}
+
+/// Match a query VCF against a truth VCF after splitting and normalizing
+/// both, optionally within confident BED regions and PASS records only.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn vcf_concordance(
+    truth: String,
+    query: String,
+    fasta_path: String,
+    confident_bed: Option<String>,
+    pass_only: bool,
+) -> Result<ConcordanceNif, String> {
+    let c = concordance::compare(&truth, &query, &fasta_path, confident_bed.as_deref(), pass_only)?;
+    let counts = |c: ClassCounts| ConcordanceCountsNif {
+        truth_total: c.truth,
+        query_total: c.query,
+        true_positives: c.true_positives,
+        false_positives: c.false_positives,
+        false_negatives: c.false_negatives,
+        genotype_mismatches: c.genotype_mismatches,
+        precision: c.precision(),
+        recall: c.recall(),
+        f1: c.f1(),
+    };
+    Ok(ConcordanceNif {
+        all: counts(c.all()),
+        snv: counts(c.snv),
+        indel: counts(c.indel),
+        other: counts(c.other),
+    })
+}
+
 #[rustler::nif]
 pub fn expression_summary(
     data: Vec<Vec<f64>>,
@@ -100,3 +338,9 @@ pub fn log_transform_matrix(
         .map(|row| row.iter().map(|&x| (x + pseudocount).log2()).collect())
         .collect()
 }
+
+fn intervals_from_nif(ivs: Vec<GenomicIntervalNif>) -> Result<Vec<Interval>, String> {
+    ivs.into_iter()
+        .map(|iv| Interval::new(iv.chrom, iv.start, iv.end, &iv.strand))
+        .collect()
+}
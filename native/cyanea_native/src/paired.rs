@@ -0,0 +1,246 @@
+//! Paired-end FASTQ: split R1/R2 files or interleaved input, with mate
+//! name validation and per-mate and insert-size statistics.
+//!
+//! Pairs are read in lockstep and their names must agree once a trailing
+//! `/1`/`/2` is removed. Insert sizes are estimated without a reference,
+//! the way fastp does: read 2 is reverse-complemented and aligned ungapped
+//! against read 1, and a confident overlap gives the fragment length.
+
+use crate::reader::{SeqReader, SeqRecord};
+
+/// Shortest mate overlap accepted for an insert-size estimate.
+const MIN_OVERLAP: usize = 30;
+/// Mismatches allowed in an overlap: at most this many, and at most
+/// one per [`MISMATCH_DIVISOR`] overlapping bases.
+const MAX_MISMATCHES: usize = 5;
+const MISMATCH_DIVISOR: usize = 5;
+
+pub(crate) enum PairedReader {
+    Split(SeqReader, SeqReader),
+    Interleaved(SeqReader),
+}
+
+impl PairedReader {
+    /// Read pairs from `r1` and `r2`, or from interleaved `r1` when `r2`
+    /// is `None`.
+    pub(crate) fn open(r1: &str, r2: Option<&str>) -> Result<Self, String> {
+        Ok(match r2 {
+            Some(r2) => Self::Split(SeqReader::open(r1)?, SeqReader::open(r2)?),
+            None => Self::Interleaved(SeqReader::open(r1)?),
+        })
+    }
+
+    pub(crate) fn next_pair(&mut self) -> Result<Option<(SeqRecord, SeqRecord)>, String> {
+        let (a, b) = match self {
+            Self::Split(r1, r2) => (r1.next_record()?, r2.next_record()?),
+            Self::Interleaved(r) => {
+                let a = r.next_record()?;
+                let b = if a.is_some() { r.next_record()? } else { None };
+                (a, b)
+            }
+        };
+        match (a, b) {
+            (None, None) => Ok(None),
+            (Some(a), Some(b)) => {
+                if mate_name(&a.name) != mate_name(&b.name) {
+                    return Err(format!("mate names differ: {} and {}", a.name, b.name));
+                }
+                Ok(Some((a, b)))
+            }
+            (Some(a), None) => Err(format!("read {} has no mate", a.name)),
+            (None, Some(b)) => Err(format!("read {} has no mate", b.name)),
+        }
+    }
+
+    /// Read up to `n` pairs; an empty vector means end of input, so `n` must
+    /// be at least 1.
+    pub(crate) fn next_chunk(&mut self, n: usize) -> Result<Vec<(SeqRecord, SeqRecord)>, String> {
+        if n == 0 {
+            return Err("chunk size must be at least 1".into());
+        }
+        let mut out = Vec::with_capacity(n.min(1 << 16));
+        while out.len() < n {
+            match self.next_pair()? {
+                Some(p) => out.push(p),
+                None => break,
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// Read name without a `/1` or `/2` mate suffix.
+fn mate_name(name: &str) -> &str {
+    name.strip_suffix("/1")
+        .or_else(|| name.strip_suffix("/2"))
+        .unwrap_or(name)
+}
+
+/// Per-mate read statistics (Phred+33 qualities).
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ReadStats {
+    pub reads: u64,
+    pub bases: u64,
+    pub gc: u64,
+    pub quality_sum: u64,
+    pub q20: u64,
+    pub q30: u64,
+}
+
+impl ReadStats {
+    fn add(&mut self, rec: &SeqRecord) {
+        self.reads += 1;
+        self.bases += rec.sequence.len() as u64;
+        self.gc += rec
+            .sequence
+            .iter()
+            .filter(|b| matches!(b.to_ascii_uppercase(), b'G' | b'C'))
+            .count() as u64;
+        for &q in &rec.quality {
+            let q = q.saturating_sub(33) as u64;
+            self.quality_sum += q;
+            self.q20 += (q >= 20) as u64;
+            self.q30 += (q >= 30) as u64;
+        }
+    }
+
+    pub(crate) fn fraction(&self, n: u64) -> f64 {
+        if self.bases == 0 {
+            0.0
+        } else {
+            n as f64 / self.bases as f64
+        }
+    }
+
+    pub(crate) fn mean_length(&self) -> f64 {
+        if self.reads == 0 {
+            0.0
+        } else {
+            self.bases as f64 / self.reads as f64
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PairedStats {
+    pub pairs: u64,
+    pub read1: ReadStats,
+    pub read2: ReadStats,
+    /// Count of pairs per insert size, for pairs whose mates overlap.
+    pub insert_sizes: Vec<u64>,
+}
+
+impl PairedStats {
+    pub(crate) fn from_reader(reader: &mut PairedReader) -> Result<Self, String> {
+        let mut s = Self::default();
+        while let Some((a, b)) = reader
+            .next_pair()
+            .map_err(|e| format!("pair {}: {e}", s.pairs + 1))?
+        {
+            s.pairs += 1;
+            s.read1.add(&a);
+            s.read2.add(&b);
+            if let Some(size) = insert_size(&a.sequence, &b.sequence) {
+                if s.insert_sizes.len() <= size {
+                    s.insert_sizes.resize(size + 1, 0);
+                }
+                s.insert_sizes[size] += 1;
+            }
+        }
+        Ok(s)
+    }
+
+    pub(crate) fn overlapping_pairs(&self) -> u64 {
+        self.insert_sizes.iter().sum()
+    }
+
+    pub(crate) fn insert_size_mean(&self) -> Option<f64> {
+        let n = self.overlapping_pairs();
+        (n > 0).then(|| {
+            let sum: u64 = self
+                .insert_sizes
+                .iter()
+                .enumerate()
+                .map(|(size, &c)| size as u64 * c)
+                .sum();
+            sum as f64 / n as f64
+        })
+    }
+
+    pub(crate) fn insert_size_sd(&self) -> Option<f64> {
+        let n = self.overlapping_pairs();
+        let mean = self.insert_size_mean()?;
+        let ss: f64 = self
+            .insert_sizes
+            .iter()
+            .enumerate()
+            .map(|(size, &c)| c as f64 * (size as f64 - mean).powi(2))
+            .sum();
+        Some((ss / n as f64).sqrt())
+    }
+
+    pub(crate) fn insert_size_median(&self) -> Option<f64> {
+        let n = self.overlapping_pairs();
+        if n == 0 {
+            return None;
+        }
+        // Sizes at ranks floor((n-1)/2) and n/2 (0-based); equal for odd n.
+        let nth = |rank: u64| {
+            let mut seen = 0;
+            self.insert_sizes
+                .iter()
+                .position(|&c| {
+                    seen += c;
+                    seen > rank
+                })
+                .unwrap_or(0)
+        };
+        Some((nth((n - 1) / 2) + nth(n / 2)) as f64 / 2.0)
+    }
+}
+
+/// Fragment length implied by the overlap of read 1 with the reverse
+/// complement of read 2, or `None` when the mates do not overlap.
+pub(crate) fn insert_size(read1: &[u8], read2: &[u8]) -> Option<usize> {
+    let rc: Vec<u8> = read2
+        .iter()
+        .rev()
+        .map(|b| match b.to_ascii_uppercase() {
+            b'A' => b'T',
+            b'C' => b'G',
+            b'G' => b'C',
+            b'T' => b'A',
+            _ => b'N',
+        })
+        .collect();
+    let matches = |a: &[u8], b: &[u8]| {
+        let limit = MAX_MISMATCHES.min(a.len() / MISMATCH_DIVISOR);
+        let mut mismatches = 0;
+        for (x, y) in a.iter().zip(b) {
+            if !x.eq_ignore_ascii_case(y) {
+                mismatches += 1;
+                if mismatches > limit {
+                    return false;
+                }
+            }
+        }
+        true
+    };
+    // Reverse-complemented read 2 starts `offset` bases into read 1 and
+    // ends where the fragment does.
+    for offset in 0..read1.len().saturating_sub(MIN_OVERLAP - 1) {
+        let len = (read1.len() - offset).min(rc.len());
+        if len >= MIN_OVERLAP && matches(&read1[offset..offset + len], &rc[..len]) {
+            return Some(offset + rc.len());
+        }
+    }
+    // The fragment is shorter than read 2, which reads through into the
+    // adapter: read 1 starts `offset` bases into reverse-complemented read 2.
+    for offset in 1..rc.len().saturating_sub(MIN_OVERLAP - 1) {
+        let len = (rc.len() - offset).min(read1.len());
+        if len >= MIN_OVERLAP && matches(&read1[..len], &rc[offset..offset + len]) {
+            return Some(rc.len() - offset);
+        }
+    }
+    None
+}
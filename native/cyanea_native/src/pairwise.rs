@@ -0,0 +1,331 @@
+//! Affine-gap pairwise alignment (Gotoh) scored by a [`SubstitutionMatrix`].
+//!
+//! Any matrix and caller-chosen penalties can be used. As in
+//! `cyanea_align`, penalties are negative scores and a gap of length `L`
+//! scores `gap_open + L * gap_extend`.
+
+use crate::substitution::SubstitutionMatrix;
+
+const NEG: i32 = i32::MIN / 4;
+
+// Traceback byte: bits 0-1 where H came from, bit 2 E opened (rather than
+// extended), bit 3 F opened.
+const FROM_DIAG: u8 = 0;
+const FROM_E: u8 = 1;
+const FROM_F: u8 = 2;
+const FROM_START: u8 = 3;
+const E_OPEN: u8 = 4;
+const F_OPEN: u8 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Mode {
+    Global,
+    Local,
+    /// Gaps before and after either sequence are free.
+    SemiGlobal,
+}
+
+impl Mode {
+    pub(crate) fn parse(mode: &str) -> Result<Self, String> {
+        match mode {
+            "local" => Ok(Self::Local),
+            "global" => Ok(Self::Global),
+            "semiglobal" => Ok(Self::SemiGlobal),
+            _ => Err(format!(
+                "unknown alignment mode: {mode} (expected local, global, or semiglobal)"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Scoring<'a> {
+    pub matrix: &'a SubstitutionMatrix,
+    pub gap_open: i32,
+    pub gap_extend: i32,
+}
+
+impl<'a> Scoring<'a> {
+    pub(crate) fn new(
+        matrix: &'a SubstitutionMatrix,
+        gap_open: i32,
+        gap_extend: i32,
+    ) -> Result<Self, String> {
+        if gap_open > 0 || gap_extend >= 0 {
+            return Err(format!(
+                "gap penalties must be negative scores (gap_open <= 0, gap_extend < 0), got {gap_open}/{gap_extend}"
+            ));
+        }
+        Ok(Self {
+            matrix,
+            gap_open,
+            gap_extend,
+        })
+    }
+}
+
+/// A pairwise alignment; coordinates are 0-based, ends exclusive.
+#[derive(Debug, Clone)]
+pub(crate) struct Alignment {
+    pub score: i32,
+    pub aligned_query: Vec<u8>,
+    pub aligned_target: Vec<u8>,
+    pub query_start: usize,
+    pub query_end: usize,
+    pub target_start: usize,
+    pub target_end: usize,
+}
+
+impl Alignment {
+    /// Columns with identical residues (ignoring case).
+    pub(crate) fn matches(&self) -> usize {
+        self.columns()
+            .filter(|&(q, t)| q != b'-' && q.eq_ignore_ascii_case(&t))
+            .count()
+    }
+
+    pub(crate) fn mismatches(&self) -> usize {
+        self.columns()
+            .filter(|&(q, t)| q != b'-' && t != b'-' && !q.eq_ignore_ascii_case(&t))
+            .count()
+    }
+
+    /// Gap columns (not gap openings).
+    pub(crate) fn gaps(&self) -> usize {
+        self.columns()
+            .filter(|&(q, t)| q == b'-' || t == b'-')
+            .count()
+    }
+
+    pub(crate) fn length(&self) -> usize {
+        self.aligned_query.len()
+    }
+
+    pub(crate) fn identity(&self) -> f64 {
+        if self.length() == 0 {
+            0.0
+        } else {
+            self.matches() as f64 / self.length() as f64
+        }
+    }
+
+    /// CIGAR with `=`/`X` for matches and mismatches, `I` for query
+    /// residues against a gap and `D` for target residues against a gap.
+    pub(crate) fn cigar(&self) -> String {
+        let mut cigar = String::new();
+        let mut run: Option<(char, usize)> = None;
+        for (q, t) in self.columns() {
+            let op = match (q, t) {
+                (b'-', _) => 'D',
+                (_, b'-') => 'I',
+                _ if q.eq_ignore_ascii_case(&t) => '=',
+                _ => 'X',
+            };
+            run = match run {
+                Some((prev, n)) if prev == op => Some((op, n + 1)),
+                Some((prev, n)) => {
+                    cigar.push_str(&format!("{n}{prev}"));
+                    Some((op, 1))
+                }
+                None => Some((op, 1)),
+            };
+        }
+        if let Some((op, n)) = run {
+            cigar.push_str(&format!("{n}{op}"));
+        }
+        cigar
+    }
+
+    fn columns(&self) -> impl Iterator<Item = (u8, u8)> + '_ {
+        self.aligned_query
+            .iter()
+            .copied()
+            .zip(self.aligned_target.iter().copied())
+    }
+}
+
+/// Align `query` against `target`.
+pub(crate) fn align(query: &[u8], target: &[u8], mode: Mode, scoring: &Scoring) -> Alignment {
+    let (n, m) = (query.len(), target.len());
+    let (open, extend) = (scoring.gap_open, scoring.gap_extend);
+    let free_ends = mode != Mode::Global;
+    let width = m + 1;
+    let mut trace = vec![FROM_START; (n + 1) * width];
+
+    // Row i-1 of H and F while row i is filled.
+    let mut h_prev: Vec<i32> = (0..=m)
+        .map(|j| {
+            if free_ends || j == 0 {
+                0
+            } else {
+                open + j as i32 * extend
+            }
+        })
+        .collect();
+    let mut f_prev = vec![NEG; m + 1];
+    if !free_ends {
+        for (j, tb) in trace.iter_mut().enumerate().take(width).skip(1) {
+            *tb = FROM_E | if j == 1 { E_OPEN } else { 0 };
+        }
+    }
+    let mut h_row = vec![0; m + 1];
+
+    let mut best = (if mode == Mode::Local { 0 } else { NEG }, 0, 0);
+    let consider = |score: i32, i: usize, j: usize, best: &mut (i32, usize, usize)| {
+        if score > best.0 {
+            *best = (score, i, j);
+        }
+    };
+
+    for i in 1..=n {
+        let qi = query[i - 1];
+        h_row[0] = if free_ends {
+            0
+        } else {
+            open + i as i32 * extend
+        };
+        if !free_ends {
+            trace[i * width] = FROM_F | if i == 1 { F_OPEN } else { 0 };
+        }
+        let mut e = NEG;
+        for j in 1..=m {
+            let mut tb = 0;
+
+            let e_open = h_row[j - 1] + open + extend;
+            let e_ext = e + extend;
+            e = if e_open >= e_ext {
+                tb |= E_OPEN;
+                e_open
+            } else {
+                e_ext
+            };
+
+            let f_open = h_prev[j] + open + extend;
+            let f_ext = f_prev[j] + extend;
+            let f = if f_open >= f_ext {
+                tb |= F_OPEN;
+                f_open
+            } else {
+                f_ext
+            };
+            f_prev[j] = f;
+
+            let diag = h_prev[j - 1] + scoring.matrix.score(qi, target[j - 1]);
+            let (mut h, mut from) = (diag, FROM_DIAG);
+            if e > h {
+                (h, from) = (e, FROM_E);
+            }
+            if f > h {
+                (h, from) = (f, FROM_F);
+            }
+            if mode == Mode::Local && h <= 0 {
+                (h, from) = (0, FROM_START);
+            }
+            trace[i * width + j] = tb | from;
+            h_row[j] = h;
+
+            if mode == Mode::Local || (mode == Mode::SemiGlobal && (i == n || j == m)) {
+                consider(h, i, j, &mut best);
+            }
+        }
+        std::mem::swap(&mut h_prev, &mut h_row);
+    }
+
+    let (score, end_i, end_j) = match mode {
+        Mode::Global => (h_prev[m], n, m),
+        // An empty sequence: nothing to align.
+        Mode::SemiGlobal if best.0 == NEG => (0, n, m),
+        _ => best,
+    };
+
+    // Trace back from the end cell.
+    let (mut aq, mut at) = (Vec::new(), Vec::new());
+    let (mut i, mut j) = (end_i, end_j);
+    let mut state = FROM_DIAG;
+    loop {
+        if i == 0 && j == 0 {
+            break;
+        }
+        let tb = trace[i * width + j];
+        if state == FROM_DIAG {
+            if (free_ends && (i == 0 || j == 0)) || tb & 3 == FROM_START {
+                break;
+            }
+            state = tb & 3;
+            if state == FROM_DIAG {
+                aq.push(query[i - 1]);
+                at.push(target[j - 1]);
+                i -= 1;
+                j -= 1;
+            }
+        } else if state == FROM_E {
+            aq.push(b'-');
+            at.push(target[j - 1]);
+            if tb & E_OPEN != 0 {
+                state = FROM_DIAG;
+            }
+            j -= 1;
+        } else {
+            aq.push(query[i - 1]);
+            at.push(b'-');
+            if tb & F_OPEN != 0 {
+                state = FROM_DIAG;
+            }
+            i -= 1;
+        }
+    }
+    aq.reverse();
+    at.reverse();
+
+    Alignment {
+        score,
+        aligned_query: aq,
+        aligned_target: at,
+        query_start: i,
+        query_end: end_i,
+        target_start: j,
+        target_end: end_j,
+    }
+}
+
+/// Levenshtein distance: the fewest substitutions, insertions and
+/// deletions turning `a` into `b`, with bytes compared exactly.
+pub(crate) fn edit_distance(a: &[u8], b: &[u8]) -> usize {
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, x) in a.iter().enumerate() {
+        let mut diag = row[0];
+        row[0] = i + 1;
+        for (j, y) in b.iter().enumerate() {
+            let next = (diag + (x != y) as usize)
+                .min(row[j] + 1)
+                .min(row[j + 1] + 1);
+            diag = row[j + 1];
+            row[j + 1] = next;
+        }
+    }
+    row[b.len()]
+}
+
+/// Best local alignment score without traceback, in linear memory.
+pub(crate) fn local_score(query: &[u8], target: &[u8], scoring: &Scoring) -> i32 {
+    let (open, extend) = (scoring.gap_open, scoring.gap_extend);
+    let mut h_prev = vec![0; target.len() + 1];
+    let mut h_row = vec![0; target.len() + 1];
+    let mut f = vec![NEG; target.len() + 1];
+    let mut best = 0;
+    for &qi in query {
+        let mut e = NEG;
+        for (j, &tj) in target.iter().enumerate() {
+            e = (h_row[j] + open + extend).max(e + extend);
+            f[j + 1] = (h_prev[j + 1] + open + extend).max(f[j + 1] + extend);
+            let h = (h_prev[j] + scoring.matrix.score(qi, tj))
+                .max(e)
+                .max(f[j + 1])
+                .max(0);
+            h_row[j + 1] = h;
+            best = best.max(h);
+        }
+        std::mem::swap(&mut h_prev, &mut h_row);
+    }
+    best
+}
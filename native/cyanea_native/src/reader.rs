@@ -0,0 +1,212 @@
+//! Streaming FASTA/FASTQ reader with transparent gzip/zstd decompression.
+//!
+//! The reader pulls one record at a time from a buffered (optionally
+//! decompressing) stream so NIFs can hand records to the BEAM in chunks.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+pub(crate) const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+pub(crate) const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Open `path` for buffered reading, decompressing gzip (including BGZF)
+/// or zstd input detected from the leading magic bytes.
+pub(crate) fn open_input(path: &str) -> Result<Box<dyn BufRead + Send>, String> {
+    let file = File::open(path).map_err(|e| format!("{path}: {e}"))?;
+    let mut buffered = BufReader::with_capacity(1 << 16, file);
+    let head = buffered.fill_buf().map_err(|e| e.to_string())?;
+    if head.starts_with(&GZIP_MAGIC) {
+        let decoder = flate2::bufread::MultiGzDecoder::new(buffered);
+        Ok(Box::new(BufReader::with_capacity(1 << 16, decoder)))
+    } else if head.starts_with(&ZSTD_MAGIC) {
+        let decoder = zstd::stream::read::Decoder::with_buffer(buffered)
+            .map_err(|e| e.to_string())?;
+        Ok(Box::new(BufReader::with_capacity(1 << 16, decoder)))
+    } else {
+        Ok(Box::new(buffered))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SeqFormat {
+    Fasta,
+    Fastq,
+}
+
+/// One FASTA or FASTQ record. `quality` is empty for FASTA.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SeqRecord {
+    pub name: String,
+    pub description: String,
+    pub sequence: Vec<u8>,
+    pub quality: Vec<u8>,
+}
+
+/// Pull-based reader over FASTA or FASTQ (format sniffed from the first
+/// non-blank byte: `>` or `@`).
+pub(crate) struct SeqReader {
+    input: Box<dyn BufRead + Send>,
+    format: Option<SeqFormat>,
+    line: String,
+    /// FASTA header already consumed while reading the previous record.
+    pending_header: Option<String>,
+    line_no: u64,
+}
+
+impl SeqReader {
+    pub(crate) fn open(path: &str) -> Result<Self, String> {
+        Ok(Self::new(open_input(path)?))
+    }
+
+    pub(crate) fn new(input: Box<dyn BufRead + Send>) -> Self {
+        Self {
+            input,
+            format: None,
+            line: String::new(),
+            pending_header: None,
+            line_no: 0,
+        }
+    }
+
+    /// Read the next line (without the trailing newline); `None` at EOF.
+    fn next_line(&mut self) -> Result<Option<&str>, String> {
+        self.line.clear();
+        let n = self
+            .input
+            .read_line(&mut self.line)
+            .map_err(|e| e.to_string())?;
+        if n == 0 {
+            return Ok(None);
+        }
+        self.line_no += 1;
+        let trimmed = self.line.trim_end_matches(['\n', '\r']);
+        Ok(Some(trimmed))
+    }
+
+    /// Next non-blank line, owned.
+    fn next_content_line(&mut self) -> Result<Option<String>, String> {
+        loop {
+            match self.next_line()? {
+                None => return Ok(None),
+                Some(l) if l.trim().is_empty() => continue,
+                Some(l) => return Ok(Some(l.to_string())),
+            }
+        }
+    }
+
+    pub(crate) fn next_record(&mut self) -> Result<Option<SeqRecord>, String> {
+        let header = match self.pending_header.take() {
+            Some(h) => h,
+            None => match self.next_content_line()? {
+                Some(h) => h,
+                None => return Ok(None),
+            },
+        };
+        if self.format.is_none() {
+            self.format = match header.as_bytes().first() {
+                Some(b'>') => Some(SeqFormat::Fasta),
+                Some(b'@') => Some(SeqFormat::Fastq),
+                _ => {
+                    return Err(format!(
+                        "line {}: expected FASTA '>' or FASTQ '@' header",
+                        self.line_no
+                    ))
+                }
+            };
+        }
+        match self.format {
+            Some(SeqFormat::Fasta) => self.read_fasta(header).map(Some),
+            _ => self.read_fastq(header).map(Some),
+        }
+    }
+
+    /// Format of the input, known once the first record has been read.
+    pub(crate) fn format(&self) -> Option<SeqFormat> {
+        self.format
+    }
+
+    /// Read up to `n` records; an empty vector means end of input, so `n`
+    /// must be at least 1.
+    pub(crate) fn next_chunk(&mut self, n: usize) -> Result<Vec<SeqRecord>, String> {
+        if n == 0 {
+            return Err("chunk size must be at least 1".into());
+        }
+        let mut out = Vec::with_capacity(n.min(1 << 16));
+        while out.len() < n {
+            match self.next_record()? {
+                Some(r) => out.push(r),
+                None => break,
+            }
+        }
+        Ok(out)
+    }
+
+    fn read_fasta(&mut self, header: String) -> Result<SeqRecord, String> {
+        let Some(rest) = header.strip_prefix('>') else {
+            return Err(format!("line {}: expected '>' header", self.line_no));
+        };
+        let (name, description) = split_header(rest);
+        let mut sequence = Vec::new();
+        loop {
+            match self.next_line()? {
+                None => break,
+                Some(l) if l.starts_with('>') => {
+                    self.pending_header = Some(l.to_string());
+                    break;
+                }
+                Some(l) => sequence.extend(l.bytes().filter(|b| !b.is_ascii_whitespace())),
+            }
+        }
+        Ok(SeqRecord {
+            name,
+            description,
+            sequence,
+            quality: Vec::new(),
+        })
+    }
+
+    fn read_fastq(&mut self, header: String) -> Result<SeqRecord, String> {
+        let Some(rest) = header.strip_prefix('@') else {
+            return Err(format!("line {}: expected '@' header", self.line_no));
+        };
+        let (name, description) = split_header(rest);
+        let sequence = match self.next_line()? {
+            Some(l) => l.as_bytes().to_vec(),
+            None => return Err(format!("record {name}: truncated before sequence")),
+        };
+        match self.next_line()? {
+            Some(l) if l.starts_with('+') => {}
+            _ => {
+                return Err(format!(
+                    "line {}: record {name}: expected '+' separator",
+                    self.line_no
+                ))
+            }
+        }
+        let quality = match self.next_line()? {
+            Some(l) => l.as_bytes().to_vec(),
+            None => return Err(format!("record {name}: truncated before quality")),
+        };
+        if quality.len() != sequence.len() {
+            return Err(format!(
+                "record {name}: sequence length {} != quality length {}",
+                sequence.len(),
+                quality.len()
+            ));
+        }
+        Ok(SeqRecord {
+            name,
+            description,
+            sequence,
+            quality,
+        })
+    }
+}
+
+/// Split a header (without its `>`/`@`) into name and description.
+fn split_header(rest: &str) -> (String, String) {
+    match rest.split_once(char::is_whitespace) {
+        Some((name, desc)) => (name.to_string(), desc.trim().to_string()),
+        None => (rest.to_string(), String::new()),
+    }
+}
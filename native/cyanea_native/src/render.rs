@@ -0,0 +1,253 @@
+//! Pairwise alignment output: column annotation, identity / similarity /
+//! gap summaries, a BLAST-style text block, and SAM and PAF records.
+//!
+//! The match line follows EMBOSS: `|` identical residues, `:` a positive
+//! substitution score, `.` a zero score, space otherwise. Without a matrix
+//! only identities are marked.
+
+use crate::pairwise::Alignment;
+use crate::substitution::SubstitutionMatrix;
+use crate::to_nif_error;
+use cyanea_align::cigar;
+use std::fmt::Write;
+
+/// Column counts of an alignment; coordinates are 1-based and inclusive,
+/// as BLAST prints them (start > end for an empty alignment).
+#[derive(Debug, Clone)]
+pub(crate) struct Summary {
+    pub length: usize,
+    pub identities: usize,
+    pub positives: usize,
+    pub mismatches: usize,
+    pub gaps: usize,
+    pub gap_opens: usize,
+    pub query_start: usize,
+    pub query_end: usize,
+    pub target_start: usize,
+    pub target_end: usize,
+    pub match_line: String,
+}
+
+impl Summary {
+    pub(crate) fn new(aln: &Alignment, matrix: Option<&SubstitutionMatrix>) -> Self {
+        let line = match_line(aln, matrix);
+        let mut gap_opens = 0;
+        let mut prev = (false, false);
+        for (&q, &t) in aln.aligned_query.iter().zip(&aln.aligned_target) {
+            let gap = (q == b'-', t == b'-');
+            if (gap.0 && !prev.0) || (gap.1 && !prev.1) {
+                gap_opens += 1;
+            }
+            prev = gap;
+        }
+        Self {
+            length: aln.length(),
+            identities: aln.matches(),
+            positives: line.iter().filter(|&&c| c == b'|' || c == b':').count(),
+            mismatches: aln.mismatches(),
+            gaps: aln.gaps(),
+            gap_opens,
+            query_start: aln.query_start + 1,
+            query_end: aln.query_end,
+            target_start: aln.target_start + 1,
+            target_end: aln.target_end,
+            match_line: String::from_utf8_lossy(&line).into_owned(),
+        }
+    }
+
+    fn fraction(&self, n: usize) -> f64 {
+        if self.length == 0 {
+            0.0
+        } else {
+            n as f64 / self.length as f64
+        }
+    }
+
+    pub(crate) fn identity(&self) -> f64 {
+        self.fraction(self.identities)
+    }
+
+    pub(crate) fn similarity(&self) -> f64 {
+        self.fraction(self.positives)
+    }
+
+    pub(crate) fn gap_fraction(&self) -> f64 {
+        self.fraction(self.gaps)
+    }
+}
+
+/// One annotation character per alignment column.
+pub(crate) fn match_line(aln: &Alignment, matrix: Option<&SubstitutionMatrix>) -> Vec<u8> {
+    aln.aligned_query
+        .iter()
+        .zip(&aln.aligned_target)
+        .map(|(&q, &t)| {
+            if q == b'-' || t == b'-' {
+                b' '
+            } else if q.eq_ignore_ascii_case(&t) {
+                b'|'
+            } else {
+                match matrix.map(|m| m.score(q, t)) {
+                    Some(s) if s > 0 => b':',
+                    Some(0) => b'.',
+                    _ => b' ',
+                }
+            }
+        })
+        .collect()
+}
+
+/// BLAST-style pairwise text: a score / identities / positives / gaps
+/// header, then blocks of `width` columns with 1-based coordinates.
+pub(crate) fn pretty(
+    aln: &Alignment,
+    matrix: Option<&SubstitutionMatrix>,
+    query_name: &str,
+    target_name: &str,
+    width: usize,
+) -> String {
+    let s = Summary::new(aln, matrix);
+    let percent = |n: usize| (100.0 * s.fraction(n)).round() as u32;
+    let mut out = format!(
+        "Score = {}, Identities = {}/{} ({}%), Positives = {}/{} ({}%), Gaps = {}/{} ({}%)\n",
+        aln.score,
+        s.identities,
+        s.length,
+        percent(s.identities),
+        s.positives,
+        s.length,
+        percent(s.positives),
+        s.gaps,
+        s.length,
+        percent(s.gaps),
+    );
+
+    let name_width = query_name.len().max(target_name.len());
+    let pos_width = aln.query_end.max(aln.target_end).to_string().len();
+    let width = width.max(1);
+    let (mut qpos, mut tpos) = (aln.query_start, aln.target_start);
+    let line = s.match_line.as_bytes();
+    for (i, (q, t)) in aln
+        .aligned_query
+        .chunks(width)
+        .zip(aln.aligned_target.chunks(width))
+        .enumerate()
+    {
+        let m = &line[i * width..i * width + q.len()];
+        out.push('\n');
+        write_row(&mut out, query_name, name_width, pos_width, q, &mut qpos);
+        let pad = name_width + pos_width + 2;
+        let m = String::from_utf8_lossy(m);
+        let _ = writeln!(out, "{:pad$}{}", "", m.trim_end());
+        write_row(&mut out, target_name, name_width, pos_width, t, &mut tpos);
+    }
+    out
+}
+
+fn write_row(
+    out: &mut String,
+    name: &str,
+    name_width: usize,
+    pos_width: usize,
+    seq: &[u8],
+    pos: &mut usize,
+) {
+    let residues = seq.iter().filter(|&&c| c != b'-').count();
+    // A block of gaps shows the position before it, as BLAST does.
+    let start = if residues > 0 { *pos + 1 } else { *pos };
+    *pos += residues;
+    let _ = writeln!(
+        out,
+        "{name:<name_width$} {start:>pos_width$} {} {}",
+        String::from_utf8_lossy(seq),
+        *pos
+    );
+}
+
+/// `(op, length)` runs with `M` for aligned columns.
+fn cigar_ops(aln: &Alignment) -> Vec<(char, usize)> {
+    let mut ops: Vec<(char, usize)> = Vec::new();
+    for (&q, &t) in aln.aligned_query.iter().zip(&aln.aligned_target) {
+        let op = match (q, t) {
+            (b'-', _) => 'D',
+            (_, b'-') => 'I',
+            _ => 'M',
+        };
+        match ops.last_mut() {
+            Some((prev, n)) if *prev == op => *n += 1,
+            _ => ops.push((op, 1)),
+        }
+    }
+    ops
+}
+
+fn cigar_string(ops: &[(char, usize)]) -> String {
+    ops.iter().map(|(op, n)| format!("{n}{op}")).collect()
+}
+
+/// A forward-strand SAM line (no newline) for `query` aligned to
+/// `target_name`; unaligned query ends are soft-clipped. An empty
+/// alignment is written unmapped.
+pub(crate) fn sam_record(
+    aln: &Alignment,
+    query: &[u8],
+    query_name: &str,
+    target_name: &str,
+) -> Result<String, String> {
+    let seq = if query.is_empty() {
+        "*".to_string()
+    } else {
+        String::from_utf8_lossy(query).into_owned()
+    };
+    if aln.length() == 0 {
+        return Ok(format!("{query_name}\t4\t*\t0\t0\t*\t*\t0\t0\t{seq}\t*"));
+    }
+    let mut ops = Vec::new();
+    if aln.query_start > 0 {
+        ops.push(('S', aln.query_start));
+    }
+    ops.extend(cigar_ops(aln));
+    let clipped = query.len().saturating_sub(aln.query_end);
+    if clipped > 0 {
+        ops.push(('S', clipped));
+    }
+    let cigar_string = cigar_string(&ops);
+    let reference: Vec<u8> = aln
+        .aligned_target
+        .iter()
+        .copied()
+        .filter(|&t| t != b'-')
+        .collect();
+    let md = cigar::parse_cigar(&cigar_string)
+        .and_then(|ops| cigar::generate_md_tag(&ops, query, &reference))
+        .map_err(to_nif_error)?;
+    Ok(format!(
+        "{query_name}\t0\t{target_name}\t{}\t255\t{cigar_string}\t*\t0\t0\t{seq}\t*\tNM:i:{}\tMD:Z:{md}\tAS:i:{}",
+        aln.target_start + 1,
+        aln.mismatches() + aln.gaps(),
+        aln.score,
+    ))
+}
+
+/// A forward-strand PAF line (no newline), with `NM`, `AS` and `cg` tags
+/// as minimap2 writes them.
+pub(crate) fn paf_record(
+    aln: &Alignment,
+    query_name: &str,
+    query_len: usize,
+    target_name: &str,
+    target_len: usize,
+) -> String {
+    format!(
+        "{query_name}\t{query_len}\t{}\t{}\t+\t{target_name}\t{target_len}\t{}\t{}\t{}\t{}\t255\tNM:i:{}\tAS:i:{}\tcg:Z:{}",
+        aln.query_start,
+        aln.query_end,
+        aln.target_start,
+        aln.target_end,
+        aln.matches(),
+        aln.length(),
+        aln.mismatches() + aln.gaps(),
+        aln.score,
+        cigar_string(&cigar_ops(aln)),
+    )
+}
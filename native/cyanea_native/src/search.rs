@@ -0,0 +1,698 @@
+//! BLAST-style similarity search of a query against a FASTA collection.
+//!
+//! The database is indexed by fixed-length words: 2-bit codes of `ACGT`
+//! words for nucleotides, base-20 codes of amino acid words for proteins.
+//! A protein query looks up its neighbourhood, every word scoring at least
+//! `threshold` against a query word; a nucleotide query looks up its own
+//! words on one or both strands. Word hits are extended without gaps
+//! until the score drops `x_drop` below its best, either from every hit or,
+//! with a two-hit window, only when a second hit falls on the same
+//! diagonal within the window (Altschul et al. 1997). Extensions scoring
+//! above `gap_trigger` are realigned with gaps against the stretch of the
+//! target they could reach, and reported with Karlin–Altschul E-values
+//! over the length-adjusted search space.
+
+use crate::iupac;
+use crate::pairwise::{self, Alignment, Mode, Scoring};
+use crate::reader::SeqReader;
+use crate::significance::{KarlinAltschul, AMINO_ACIDS};
+use crate::substitution::SubstitutionMatrix;
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+
+const INVALID: u8 = u8::MAX;
+
+/// Target residues allowed beyond the query's reach on either side of an
+/// ungapped extension when it is realigned with gaps.
+const GAP_MARGIN: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Alphabet {
+    Protein,
+    Nucleotide,
+}
+
+impl Alphabet {
+    /// `"protein"`, `"nucleotide"`, or `None` for `"auto"`.
+    pub(crate) fn parse(alphabet: &str) -> Result<Option<Self>, String> {
+        match alphabet {
+            "protein" => Ok(Some(Self::Protein)),
+            "nucleotide" => Ok(Some(Self::Nucleotide)),
+            "auto" => Ok(None),
+            _ => Err(format!(
+                "unknown alphabet: {alphabet} (expected protein, nucleotide, or auto)"
+            )),
+        }
+    }
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::Protein => "protein",
+            Self::Nucleotide => "nucleotide",
+        }
+    }
+
+    fn radix(self) -> u32 {
+        match self {
+            Self::Protein => AMINO_ACIDS.len() as u32,
+            Self::Nucleotide => 4,
+        }
+    }
+
+    /// Accepted word sizes. Nucleotide words are capped so their codes fit
+    /// a `u32`; protein words at 5, since the neighbourhood of each query
+    /// word grows about twentyfold per residue.
+    fn word_sizes(self) -> RangeInclusive<usize> {
+        match self {
+            Self::Protein => 3..=5,
+            Self::Nucleotide => 1..=16,
+        }
+    }
+
+    /// Residue codes for word building; [`INVALID`] for residues that
+    /// never seed (ambiguity codes, `X`, stops).
+    fn codes(self) -> [u8; 256] {
+        let mut codes = [INVALID; 256];
+        let residues: &[u8] = match self {
+            Self::Protein => AMINO_ACIDS,
+            Self::Nucleotide => b"ACGT",
+        };
+        for (i, &r) in residues.iter().enumerate() {
+            codes[r as usize] = i as u8;
+            codes[r.to_ascii_lowercase() as usize] = i as u8;
+        }
+        if self == Self::Nucleotide {
+            codes[b'U' as usize] = 3;
+            codes[b'u' as usize] = 3;
+        }
+        codes
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Strand {
+    Both,
+    Plus,
+    Minus,
+}
+
+impl Strand {
+    pub(crate) fn parse(strand: &str) -> Result<Self, String> {
+        match strand {
+            "both" => Ok(Self::Both),
+            "plus" => Ok(Self::Plus),
+            "minus" => Ok(Self::Minus),
+            _ => Err(format!(
+                "unknown strand: {strand} (expected both, plus, or minus)"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct SearchOptions {
+    /// Penalties as negative scores, as for the pairwise aligners.
+    pub gap_open: i32,
+    pub gap_extend: i32,
+    /// Lowest score of a neighbourhood word (proteins only).
+    pub threshold: i32,
+    /// Two-hit window in residues; 0 extends from every word hit.
+    pub window: usize,
+    /// Ungapped X-drop, in bits.
+    pub x_drop: f64,
+    /// Ungapped score, in bits, above which a gapped alignment is tried.
+    pub gap_trigger: f64,
+    pub max_evalue: f64,
+    /// Target sequences reported, best first.
+    pub max_targets: usize,
+    /// Query strands searched (nucleotides only).
+    pub strand: Strand,
+}
+
+struct Target {
+    name: String,
+    sequence: Vec<u8>,
+}
+
+/// Database sequences with a word index over them.
+pub(crate) struct SearchIndex {
+    alphabet: Alphabet,
+    word_size: usize,
+    targets: Vec<Target>,
+    residues: usize,
+    /// Word code to `(target, position)` of every occurrence.
+    words: HashMap<u32, Vec<(u32, u32)>>,
+}
+
+/// One gapped HSP. For minus-strand hits the query coordinates of the
+/// alignment are on the reverse complement of the query.
+#[derive(Debug, Clone)]
+pub(crate) struct SearchHit {
+    pub target: String,
+    pub target_length: usize,
+    pub reverse: bool,
+    pub bit_score: f64,
+    pub evalue: f64,
+    pub alignment: Alignment,
+}
+
+/// An ungapped extension; ends exclusive.
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    query_start: usize,
+    query_end: usize,
+    target_start: usize,
+    target_end: usize,
+    score: i32,
+}
+
+/// Per-diagonal seeding state.
+#[derive(Default)]
+struct Diagonal {
+    /// Target position of the last unextended word hit.
+    last_hit: Option<usize>,
+    /// Target position the last extension reached.
+    extended_to: usize,
+}
+
+impl SearchIndex {
+    /// Index every record of a FASTA file (plain or compressed).
+    pub(crate) fn build(
+        path: &str,
+        alphabet: Option<Alphabet>,
+        word_size: Option<usize>,
+    ) -> Result<Self, String> {
+        let mut reader = SeqReader::open(path)?;
+        let mut sequences = Vec::new();
+        while let Some(rec) = reader.next_record()? {
+            sequences.push((rec.name, rec.sequence));
+        }
+        if sequences.is_empty() {
+            return Err(format!("no sequences in {path}"));
+        }
+        Self::from_sequences(sequences, alphabet, word_size)
+    }
+
+    /// Index `(name, sequence)` pairs. With `alphabet` `None` they are
+    /// nucleotide if at least 90% of the residues are `ACGTUN`.
+    pub(crate) fn from_sequences(
+        sequences: Vec<(String, Vec<u8>)>,
+        alphabet: Option<Alphabet>,
+        word_size: Option<usize>,
+    ) -> Result<Self, String> {
+        if sequences.is_empty() {
+            return Err("no sequences to index".to_string());
+        }
+        if sequences.len() > u32::MAX as usize {
+            return Err(format!("too many sequences to index: {}", sequences.len()));
+        }
+        let targets: Vec<Target> = sequences
+            .into_iter()
+            .map(|(name, sequence)| Target {
+                name,
+                sequence: sequence.to_ascii_uppercase(),
+            })
+            .collect();
+        let residues: usize = targets.iter().map(|t| t.sequence.len()).sum();
+        let alphabet = alphabet.unwrap_or_else(|| {
+            let nucleotides: usize = targets
+                .iter()
+                .map(|t| {
+                    t.sequence
+                        .iter()
+                        .filter(|b| matches!(b, b'A' | b'C' | b'G' | b'T' | b'U' | b'N'))
+                        .count()
+                })
+                .sum();
+            if nucleotides * 10 >= residues * 9 {
+                Alphabet::Nucleotide
+            } else {
+                Alphabet::Protein
+            }
+        });
+        let word_size = word_size.unwrap_or(match alphabet {
+            Alphabet::Protein => 3,
+            Alphabet::Nucleotide => 11,
+        });
+        let sizes = alphabet.word_sizes();
+        if !sizes.contains(&word_size) {
+            return Err(format!(
+                "{} word size must be {} to {}, got {word_size}",
+                alphabet.name(),
+                sizes.start(),
+                sizes.end()
+            ));
+        }
+
+        let codes = alphabet.codes();
+        let mut words: HashMap<u32, Vec<(u32, u32)>> = HashMap::new();
+        for (t, target) in targets.iter().enumerate() {
+            if target.sequence.len() > u32::MAX as usize {
+                return Err(format!("sequence '{}' is too long to index", target.name));
+            }
+            for (pos, code) in word_codes(&target.sequence, &codes, alphabet.radix(), word_size) {
+                words.entry(code).or_default().push((t as u32, pos as u32));
+            }
+        }
+        Ok(Self {
+            alphabet,
+            word_size,
+            targets,
+            residues,
+            words,
+        })
+    }
+
+    pub(crate) fn alphabet(&self) -> Alphabet {
+        self.alphabet
+    }
+
+    pub(crate) fn word_size(&self) -> usize {
+        self.word_size
+    }
+
+    pub(crate) fn sequences(&self) -> usize {
+        self.targets.len()
+    }
+
+    pub(crate) fn residues(&self) -> usize {
+        self.residues
+    }
+
+    /// Gapped HSPs of `query` with E-values at most `max_evalue`, best
+    /// first.
+    pub(crate) fn search(
+        &self,
+        query: &[u8],
+        matrix: &SubstitutionMatrix,
+        opts: &SearchOptions,
+    ) -> Result<Vec<SearchHit>, String> {
+        let scoring = Scoring::new(matrix, opts.gap_open, opts.gap_extend)?;
+        let ungapped = KarlinAltschul::ungapped(matrix)?;
+        // BLAST's tabulated gapped parameters where they exist; otherwise
+        // the ungapped ones, which suit nucleotide scoring with the usual
+        // gap costs but overstate significance for cheap gaps.
+        let params = KarlinAltschul::gapped(matrix, opts.gap_open, opts.gap_extend)
+            .unwrap_or(ungapped);
+        let bits_to_raw = std::f64::consts::LN_2 / ungapped.lambda;
+        let x_drop = (opts.x_drop * bits_to_raw).ceil() as i32;
+        let trigger = ((opts.gap_trigger * std::f64::consts::LN_2 + ungapped.k.ln())
+            / ungapped.lambda)
+            .ceil() as i32;
+
+        let forward = query.to_ascii_uppercase();
+        let mut strands = vec![];
+        if self.alphabet == Alphabet::Protein || opts.strand != Strand::Minus {
+            strands.push((forward.clone(), false));
+        }
+        if self.alphabet == Alphabet::Nucleotide && opts.strand != Strand::Plus {
+            let mut reverse = forward;
+            iupac::reverse_complement(&mut reverse);
+            strands.push((reverse, true));
+        }
+
+        let ell = params.length_adjustment(query.len(), self.residues, self.targets.len());
+        let query_space = query.len().saturating_sub(ell).max(1);
+        let db_space = self
+            .residues
+            .saturating_sub(self.targets.len() * ell)
+            .max(1);
+
+        let mut alignments: Vec<(usize, bool, Alignment)> = Vec::new();
+        for (q, reverse) in &strands {
+            for (t, aln) in self.search_strand(q, &scoring, opts, x_drop, trigger) {
+                alignments.push((t, *reverse, aln));
+            }
+        }
+
+        // Windows around neighbouring extensions can realign to the same
+        // HSP; keep the best of any that overlap on both sequences.
+        alignments.sort_by(|a, b| {
+            b.2.score
+                .cmp(&a.2.score)
+                .then(a.0.cmp(&b.0))
+                .then(a.2.target_start.cmp(&b.2.target_start))
+        });
+        let mut kept: Vec<(usize, bool, Alignment)> = Vec::new();
+        for (t, reverse, aln) in alignments {
+            let overlaps = kept.iter().any(|(kt, kr, k)| {
+                *kt == t
+                    && *kr == reverse
+                    && k.query_start < aln.query_end
+                    && aln.query_start < k.query_end
+                    && k.target_start < aln.target_end
+                    && aln.target_start < k.target_end
+            });
+            if !overlaps {
+                kept.push((t, reverse, aln));
+            }
+        }
+
+        let mut hits: Vec<(usize, SearchHit)> = kept
+            .into_iter()
+            .map(|(t, reverse, aln)| {
+                let target = &self.targets[t];
+                let hit = SearchHit {
+                    target: target.name.clone(),
+                    target_length: target.sequence.len(),
+                    reverse,
+                    bit_score: params.bit_score(aln.score),
+                    evalue: params.evalue(aln.score, query_space, db_space),
+                    alignment: aln,
+                };
+                (t, hit)
+            })
+            .filter(|(_, hit)| hit.evalue <= opts.max_evalue)
+            .collect();
+        hits.sort_by(|a, b| {
+            a.1.evalue
+                .total_cmp(&b.1.evalue)
+                .then(b.1.alignment.score.cmp(&a.1.alignment.score))
+                .then(a.0.cmp(&b.0))
+        });
+
+        let mut reported = Vec::new();
+        Ok(hits
+            .into_iter()
+            .filter(|(t, _)| {
+                if reported.contains(t) {
+                    true
+                } else if reported.len() < opts.max_targets {
+                    reported.push(*t);
+                    true
+                } else {
+                    false
+                }
+            })
+            .map(|(_, hit)| hit)
+            .collect())
+    }
+
+    /// Gapped alignments of one query strand, as `(target, alignment)`.
+    fn search_strand(
+        &self,
+        query: &[u8],
+        scoring: &Scoring,
+        opts: &SearchOptions,
+        x_drop: i32,
+        trigger: i32,
+    ) -> Vec<(usize, Alignment)> {
+        let w = self.word_size;
+        let codes = self.alphabet.codes();
+        let query_words = word_codes(query, &codes, self.alphabet.radix(), w);
+        let mut diagonals: HashMap<(u32, isize), Diagonal> = HashMap::new();
+        let mut found: Vec<(usize, Alignment)> = Vec::new();
+
+        for (i, code) in query_words {
+            let lookups = match self.alphabet {
+                Alphabet::Protein => {
+                    neighbourhood(&query[i..i + w], scoring.matrix, opts.threshold)
+                }
+                Alphabet::Nucleotide => vec![code],
+            };
+            for word in lookups {
+                let Some(occurrences) = self.words.get(&word) else {
+                    continue;
+                };
+                for &(t, p) in occurrences {
+                    let p = p as usize;
+                    let diag = diagonals.entry((t, p as isize - i as isize)).or_default();
+                    if p < diag.extended_to {
+                        continue;
+                    }
+                    if opts.window > 0 {
+                        match diag.last_hit {
+                            Some(last) if p < last + w => continue,
+                            Some(last) if p - last <= opts.window => {}
+                            _ => {
+                                diag.last_hit = Some(p);
+                                continue;
+                            }
+                        }
+                    }
+                    let target = &self.targets[t as usize].sequence;
+                    let seg = extend_ungapped(query, target, i, p, w, scoring.matrix, x_drop);
+                    diag.extended_to = seg.target_end;
+                    diag.last_hit = None;
+                    if seg.score < trigger {
+                        continue;
+                    }
+                    // Already inside an alignment of this target.
+                    let covered = found.iter().any(|(ft, a)| {
+                        *ft == t as usize
+                            && a.query_start <= seg.query_start
+                            && seg.query_end <= a.query_end
+                            && a.target_start <= seg.target_start
+                            && seg.target_end <= a.target_end
+                    });
+                    if !covered {
+                        found.push((t as usize, extend_gapped(query, target, &seg, scoring)));
+                    }
+                }
+            }
+        }
+        found
+    }
+}
+
+/// `(position, code)` of every word of `seq` made only of coded residues.
+fn word_codes(seq: &[u8], codes: &[u8; 256], radix: u32, w: usize) -> Vec<(usize, u32)> {
+    let modulus = radix.pow(w as u32 - 1);
+    let mut out = Vec::new();
+    let (mut code, mut run) = (0u32, 0usize);
+    for (i, &b) in seq.iter().enumerate() {
+        let c = codes[b as usize];
+        if c == INVALID {
+            run = 0;
+            continue;
+        }
+        // Drop the leading residue once the word is full.
+        let kept = match run {
+            0 => 0,
+            _ if run >= w => code % modulus,
+            _ => code,
+        };
+        code = kept * radix + c as u32;
+        run += 1;
+        if run >= w {
+            out.push((i + 1 - w, code));
+        }
+    }
+    out
+}
+
+/// Codes of every word scoring at least `threshold` against `word`,
+/// found depth first with the best remaining score as a bound.
+fn neighbourhood(word: &[u8], matrix: &SubstitutionMatrix, threshold: i32) -> Vec<u32> {
+    let rows: Vec<Vec<i32>> = word
+        .iter()
+        .map(|&q| AMINO_ACIDS.iter().map(|&a| matrix.score(q, a)).collect())
+        .collect();
+    // Best score still reachable from each position onward.
+    let mut bound = vec![0; word.len() + 1];
+    for k in (0..word.len()).rev() {
+        bound[k] = bound[k + 1] + rows[k].iter().copied().max().unwrap_or_default();
+    }
+    let mut out = Vec::new();
+    let mut stack = vec![(0usize, 0i32, 0u32)];
+    while let Some((k, score, code)) = stack.pop() {
+        if k == word.len() {
+            out.push(code);
+            continue;
+        }
+        for (a, &s) in rows[k].iter().enumerate() {
+            if score + s + bound[k + 1] >= threshold {
+                stack.push((k + 1, score + s, code * AMINO_ACIDS.len() as u32 + a as u32));
+            }
+        }
+    }
+    out
+}
+
+/// Extend a word hit at query `i`, target `p` in both directions without
+/// gaps, stopping `x_drop` below the best score.
+fn extend_ungapped(
+    query: &[u8],
+    target: &[u8],
+    i: usize,
+    p: usize,
+    w: usize,
+    matrix: &SubstitutionMatrix,
+    x_drop: i32,
+) -> Segment {
+    let seed: i32 = (0..w)
+        .map(|k| matrix.score(query[i + k], target[p + k]))
+        .sum();
+
+    let (mut score, mut best, mut right) = (0, 0, 0);
+    let mut k = 0;
+    while i + w + k < query.len() && p + w + k < target.len() {
+        score += matrix.score(query[i + w + k], target[p + w + k]);
+        k += 1;
+        if score > best {
+            (best, right) = (score, k);
+        } else if score < best - x_drop {
+            break;
+        }
+    }
+    let right_best = best;
+
+    let (mut score, mut best, mut left) = (0, 0, 0);
+    let mut k = 0;
+    while k < i && k < p {
+        score += matrix.score(query[i - k - 1], target[p - k - 1]);
+        k += 1;
+        if score > best {
+            (best, left) = (score, k);
+        } else if score < best - x_drop {
+            break;
+        }
+    }
+
+    Segment {
+        query_start: i - left,
+        query_end: i + w + right,
+        target_start: p - left,
+        target_end: p + w + right,
+        score: seed + right_best + best,
+    }
+}
+
+/// Local alignment of the query against the part of the target that an
+/// alignment through `seg` could reach, in target coordinates.
+fn extend_gapped(query: &[u8], target: &[u8], seg: &Segment, scoring: &Scoring) -> Alignment {
+    let start = seg
+        .target_start
+        .saturating_sub(seg.query_start + GAP_MARGIN);
+    let end = (seg.target_end + (query.len() - seg.query_end) + GAP_MARGIN).min(target.len());
+    let mut aln = pairwise::align(query, &target[start..end], Mode::Local, scoring);
+    aln.target_start += start;
+    aln.target_end += start;
+    aln
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::SplitMix64;
+
+    fn random(rng: &mut SplitMix64, residues: &[u8], n: usize) -> Vec<u8> {
+        (0..n)
+            .map(|_| residues[rng.below(residues.len())])
+            .collect()
+    }
+
+    fn options() -> SearchOptions {
+        SearchOptions {
+            gap_open: -11,
+            gap_extend: -1,
+            threshold: 11,
+            window: 40,
+            x_drop: 7.0,
+            gap_trigger: 22.0,
+            max_evalue: 1e-3,
+            max_targets: 500,
+            strand: Strand::Both,
+        }
+    }
+
+    #[test]
+    fn ranks_protein_hits_by_evalue() {
+        let mut rng = SplitMix64::new(5);
+        let query = random(&mut rng, AMINO_ACIDS, 80);
+        let flank = |rng: &mut SplitMix64| random(rng, AMINO_ACIDS, 30);
+        // One substitution every eighth residue, and a 3-residue deletion.
+        let mut mutated = query.clone();
+        for i in (4..mutated.len()).step_by(8) {
+            mutated[i] = if mutated[i] == b'W' { b'A' } else { b'W' };
+        }
+        mutated.drain(40..43);
+        let mut db: Vec<(String, Vec<u8>)> = (0..20)
+            .map(|i| (format!("random{i}"), random(&mut rng, AMINO_ACIDS, 200)))
+            .collect();
+        db.insert(3, ("mutated".into(), [flank(&mut rng), mutated].concat()));
+        db.insert(
+            11,
+            ("exact".into(), [flank(&mut rng), query.clone()].concat()),
+        );
+        let index = SearchIndex::from_sequences(db.clone(), None, None).unwrap();
+        assert_eq!(index.alphabet(), Alphabet::Protein);
+
+        let matrix = SubstitutionMatrix::builtin("blosum62").unwrap();
+        let hits = index.search(&query, &matrix, &options()).unwrap();
+        let names: Vec<&str> = hits.iter().map(|h| h.target.as_str()).collect();
+        assert_eq!(names, ["exact", "mutated"]);
+
+        let exact = &hits[0].alignment;
+        assert_eq!((exact.query_start, exact.query_end), (0, 80));
+        assert_eq!((exact.target_start, exact.target_end), (30, 110));
+        assert_eq!(exact.identity(), 1.0);
+        let mutated = &hits[1].alignment;
+        assert_eq!(mutated.gaps(), 3);
+
+        // Gapped scores are optimal, and E-values use BLAST's gapped λ and K
+        // over the length-adjusted search space.
+        let scoring = Scoring::new(&matrix, -11, -1).unwrap();
+        let params = KarlinAltschul::gapped(&matrix, -11, -1).unwrap();
+        let ell = params.length_adjustment(80, index.residues(), index.sequences());
+        for hit in &hits {
+            let (_, seq) = db.iter().find(|(n, _)| *n == hit.target).unwrap();
+            assert_eq!(
+                hit.alignment.score,
+                pairwise::local_score(&query, seq, &scoring)
+            );
+            let evalue = params.evalue(
+                hit.alignment.score,
+                80 - ell,
+                index.residues() - index.sequences() * ell,
+            );
+            assert!((hit.evalue / evalue - 1.0).abs() < 1e-12);
+            assert_eq!(hit.bit_score, params.bit_score(hit.alignment.score));
+        }
+        assert!(hits[0].evalue < 1e-40 && hits[0].evalue < hits[1].evalue);
+
+        let one = SearchOptions {
+            max_targets: 1,
+            ..options()
+        };
+        assert_eq!(index.search(&query, &matrix, &one).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn finds_nucleotide_hits_on_the_requested_strand() {
+        let mut rng = SplitMix64::new(9);
+        let query = random(&mut rng, b"ACGT", 60);
+        let mut minus = query.clone();
+        iupac::reverse_complement(&mut minus);
+        let db = vec![
+            ("background".to_string(), random(&mut rng, b"ACGT", 500)),
+            (
+                "minus".to_string(),
+                [random(&mut rng, b"ACGT", 20), minus].concat(),
+            ),
+        ];
+        let index = SearchIndex::from_sequences(db, None, None).unwrap();
+        assert_eq!(index.alphabet(), Alphabet::Nucleotide);
+        let matrix = SubstitutionMatrix::nucleotide(1, -3, -3);
+        let opts = SearchOptions {
+            gap_open: -5,
+            gap_extend: -2,
+            window: 0,
+            ..options()
+        };
+
+        let hits = index.search(&query, &matrix, &opts).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!((hits[0].target.as_str(), hits[0].reverse), ("minus", true));
+        assert_eq!(hits[0].alignment.score, 60);
+        assert_eq!(
+            (hits[0].alignment.target_start, hits[0].alignment.target_end),
+            (20, 80)
+        );
+        let plus = SearchOptions {
+            strand: Strand::Plus,
+            ..opts
+        };
+        assert!(index.search(&query, &matrix, &plus).unwrap().is_empty());
+    }
+}
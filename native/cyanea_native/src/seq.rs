@@ -1,7 +1,17 @@
 //! cyanea-seq NIFs — Sequence I/O, validation, operations, k-mers, pattern matching.
 
 use crate::bridge::*;
+use crate::faidx::Faidx;
+use crate::fm_index::FmIndex;
+use crate::genetic_code::{self, GeneticCode};
+use crate::iupac::{self, check_nucleotides};
+use crate::kmer::{self, KmerCounts};
+use crate::paired::{PairedReader, PairedStats};
+use crate::reader::{SeqFormat, SeqReader, SeqRecord};
 use crate::to_nif_error;
+use crate::trim::{TrimOptions, Trimmer};
+use rustler::ResourceArc;
+use std::sync::Mutex;
 
 
 // ===========================================================================
@@ -15,16 +25,18 @@ pub fn fasta_stats(path: String) -> Result<FastaStatsNif, String> {
         .map_err(to_nif_error)
 }
 
+/// IUPAC DNA codes, returned with their case kept.
 #[rustler::nif]
 pub fn validate_dna(data: Vec<u8>) -> Result<Vec<u8>, String> {
-    let seq = cyanea_seq::DnaSequence::new(&data).map_err(to_nif_error)?;
-    Ok(seq.into_bytes())
+    iupac::validate(&data, false)?;
+    Ok(data)
 }
 
+/// IUPAC RNA codes, returned with their case kept.
 #[rustler::nif]
 pub fn validate_rna(data: Vec<u8>) -> Result<Vec<u8>, String> {
-    let seq = cyanea_seq::RnaSequence::new(&data).map_err(to_nif_error)?;
-    Ok(seq.into_bytes())
+    iupac::validate(&data, true)?;
+    Ok(data)
 }
 
 #[rustler::nif]
@@ -34,40 +46,48 @@ pub fn validate_protein(data: Vec<u8>) -> Result<Vec<u8>, String> {
 }
 
 #[rustler::nif]
-pub fn dna_reverse_complement(data: Vec<u8>) -> Result<Vec<u8>, String> {
-    let seq = cyanea_seq::DnaSequence::new(&data).map_err(to_nif_error)?;
-    Ok(seq.reverse_complement().into_bytes())
+pub fn dna_reverse_complement(mut data: Vec<u8>) -> Result<Vec<u8>, String> {
+    iupac::validate(&data, false)?;
+    iupac::reverse_complement(&mut data);
+    Ok(data)
 }
 
 #[rustler::nif]
 pub fn dna_transcribe(data: Vec<u8>) -> Result<Vec<u8>, String> {
-    let seq = cyanea_seq::DnaSequence::new(&data).map_err(to_nif_error)?;
-    Ok(seq.transcribe().into_bytes())
+    iupac::validate(&data, false)?;
+    Ok(iupac::transcribe(&data))
 }
 
+/// GC fraction over bases known to be strong or weak; `N` and other
+/// ambiguity codes are excluded.
 #[rustler::nif]
 pub fn dna_gc_content(data: Vec<u8>) -> Result<f64, String> {
-    let seq = cyanea_seq::DnaSequence::new(&data).map_err(to_nif_error)?;
-    Ok(seq.gc_content())
+    iupac::validate(&data, false)?;
+    Ok(iupac::gc_content(&data))
+}
+
+/// Lowercase (soft-masked) runs as 0-based, half-open `{start, end}`.
+#[rustler::nif]
+pub fn soft_masked_regions(data: Vec<u8>) -> Result<Vec<(usize, usize)>, String> {
+    check_nucleotides(&data)?;
+    Ok(iupac::soft_masked(&data))
 }
 
+/// Translate RNA with the standard code; stops read as `*`.
 #[rustler::nif]
 pub fn rna_translate(data: Vec<u8>) -> Result<Vec<u8>, String> {
-    let seq = cyanea_seq::RnaSequence::new(&data).map_err(to_nif_error)?;
-    seq.translate()
-        .map(|p| p.into_bytes())
-        .map_err(to_nif_error)
+    iupac::validate(&data, true)?;
+    Ok(GeneticCode::ncbi(1)?.translate(&data, false))
 }
 
+/// Overlapping k-mers of a DNA sequence, in order and with case kept.
 #[rustler::nif]
 pub fn sequence_kmers(data: Vec<u8>, k: usize) -> Result<Vec<Vec<u8>>, String> {
-    let seq = cyanea_seq::DnaSequence::new(&data).map_err(to_nif_error)?;
-    let kmers = seq
-        .kmers(k)
-        .map_err(to_nif_error)?
-        .map(|kmer| kmer.to_vec())
-        .collect();
-    Ok(kmers)
+    iupac::validate(&data, false)?;
+    if k == 0 {
+        return Err("k must be at least 1".into());
+    }
+    Ok(data.windows(k).map(<[u8]>::to_vec).collect())
 }
 
 #[rustler::nif(schedule = "DirtyCpu")]
@@ -104,36 +124,145 @@ pub fn myers_search(text: Vec<u8>, pattern: Vec<u8>, max_dist: usize) -> Vec<(us
     cyanea_seq::myers_bitparallel(&text, &pattern, max_dist)
 }
 
+/// `horspool_search/3`: with `iupac_codes`, the pattern is nucleotides
+/// whose ambiguity codes match any base they stand for, ignoring case;
+/// otherwise bytes must match exactly.
+#[rustler::nif(name = "horspool_search", schedule = "DirtyCpu")]
+pub fn horspool_search_with(
+    text: Vec<u8>,
+    pattern: Vec<u8>,
+    iupac_codes: bool,
+) -> Result<Vec<usize>, String> {
+    if !iupac_codes {
+        return Ok(cyanea_seq::horspool(&text, &pattern));
+    }
+    check_nucleotides(&pattern)?;
+    Ok(iupac::horspool(&text, &pattern))
+}
+
+/// `myers_search/4`: as `myers_search/3`, with IUPAC-aware base matching
+/// when `iupac_codes` is set.
+#[rustler::nif(name = "myers_search", schedule = "DirtyCpu")]
+pub fn myers_search_with(
+    text: Vec<u8>,
+    pattern: Vec<u8>,
+    max_dist: usize,
+    iupac_codes: bool,
+) -> Result<Vec<(usize, usize)>, String> {
+    if !iupac_codes {
+        return Ok(cyanea_seq::myers_bitparallel(&text, &pattern, max_dist));
+    }
+    check_nucleotides(&pattern)?;
+    Ok(iupac::myers(&text, &pattern, max_dist))
+}
+
+// ===========================================================================
+// FM-index (persistent resource)
+// ===========================================================================
+
+/// A built FM-index held by the BEAM as an opaque reference.
+pub struct FmIndexResource {
+    pub(crate) index: FmIndex,
+}
+
+#[rustler::resource_impl]
+impl rustler::Resource for FmIndexResource {}
+
 #[rustler::nif(schedule = "DirtyCpu")]
-pub fn fm_index_build(text: Vec<u8>) -> Vec<u8> {
-    // Build to validate input, then return the raw text.
-    // FmIndex doesn't implement Serialize, so we store the raw text and
-    // rebuild the index lazily on each query.
-    let _index = cyanea_seq::FmIndex::build(&text);
-    text
+pub fn fm_index_build(text: Vec<u8>) -> Result<ResourceArc<FmIndexResource>, String> {
+    let index = FmIndex::build(&text)?;
+    Ok(ResourceArc::new(FmIndexResource { index }))
 }
 
 #[rustler::nif]
-pub fn fm_index_count(index_data: Vec<u8>, pattern: Vec<u8>) -> usize {
-    let index = cyanea_seq::FmIndex::build(&index_data);
-    index.count(&pattern)
+pub fn fm_index_count(index: ResourceArc<FmIndexResource>, pattern: Vec<u8>) -> usize {
+    index.index.count(&pattern)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn fm_index_count_batch(
+    index: ResourceArc<FmIndexResource>,
+    patterns: Vec<Vec<u8>>,
+) -> Vec<usize> {
+    patterns.iter().map(|p| index.index.count(p)).collect()
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn fm_index_locate(index: ResourceArc<FmIndexResource>, pattern: Vec<u8>) -> Vec<usize> {
+    index.index.locate(&pattern)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn fm_index_save(index: ResourceArc<FmIndexResource>, path: String) -> Result<String, String> {
+    index.index.save(&path)?;
+    Ok(path)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn fm_index_load(path: String) -> Result<ResourceArc<FmIndexResource>, String> {
+    let index = FmIndex::load(&path)?;
+    Ok(ResourceArc::new(FmIndexResource { index }))
+}
+
+/// ORFs in all six frames with the standard genetic code, starting at ATG.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn find_orfs(seq: Vec<u8>, min_length: usize) -> Result<Vec<OrfResultNif>, String> {
+    orfs(&seq, min_length, 1, false)
+}
+
+/// ORFs in all six frames with NCBI table `table_id`; alternative start
+/// codons of the table count when `alternative_starts` is set.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn find_orfs_with_code(
+    seq: Vec<u8>,
+    min_length: usize,
+    table_id: u8,
+    alternative_starts: bool,
+) -> Result<Vec<OrfResultNif>, String> {
+    orfs(&seq, min_length, table_id, alternative_starts)
+}
+
+fn orfs(
+    seq: &[u8],
+    min_length: usize,
+    table_id: u8,
+    alternative_starts: bool,
+) -> Result<Vec<OrfResultNif>, String> {
+    check_nucleotides(seq)?;
+    let code = GeneticCode::ncbi(table_id)?;
+    Ok(genetic_code::find_orfs(seq, &code, min_length, alternative_starts)
+        .into_iter()
+        .map(OrfResultNif::from)
+        .collect())
 }
 
+/// Translate DNA or RNA with NCBI table `table_id`, optionally stopping at
+/// the first stop codon.
 #[rustler::nif]
-pub fn find_orfs(seq: Vec<u8>, min_length: usize) -> Vec<OrfResultNif> {
-    let results = cyanea_seq::find_orfs_both_strands(&seq, min_length);
-    results
+pub fn translate(data: Vec<u8>, table_id: u8, to_stop: bool) -> Result<Vec<u8>, String> {
+    check_nucleotides(&data)?;
+    Ok(GeneticCode::ncbi(table_id)?.translate(&data, to_stop))
+}
+
+/// Translations of the three forward and three reverse-strand frames.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn six_frame_translate(
+    data: Vec<u8>,
+    table_id: u8,
+) -> Result<Vec<FrameTranslationNif>, String> {
+    check_nucleotides(&data)?;
+    let code = GeneticCode::ncbi(table_id)?;
+    Ok(genetic_code::six_frames(&data, &code)
         .into_iter()
-        .map(|orf| OrfResultNif {
-            start: orf.start,
-            end: orf.end,
-            frame: orf.frame,
-            strand: match orf.strand {
-                cyanea_seq::Strand::Reverse => "-".into(),
-                _ => "+".into(),
-            },
-            sequence: orf.sequence.clone(),
-        })
+        .map(FrameTranslationNif::from)
+        .collect())
+}
+
+/// `{id, name}` of each supported NCBI translation table.
+#[rustler::nif]
+pub fn genetic_codes() -> Vec<(u8, String)> {
+    genetic_code::tables()
+        .map(|(id, name)| (id, name.to_string()))
         .collect()
 }
 
@@ -143,3 +272,299 @@ pub fn minhash_sketch(seq: Vec<u8>, k: usize, sketch_size: usize) -> Result<Vec<
     mh.add_sequence(&seq);
     Ok(mh.hashes().to_vec())
 }
+
+// ===========================================================================
+// Streaming reader (chunked FASTA/FASTQ)
+// ===========================================================================
+
+/// An open FASTA/FASTQ reader; `None` once closed.
+pub struct SeqReaderResource {
+    reader: Mutex<Option<SeqReader>>,
+}
+
+#[rustler::resource_impl]
+impl rustler::Resource for SeqReaderResource {}
+
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn seq_reader_open(path: String) -> Result<ResourceArc<SeqReaderResource>, String> {
+    let reader = SeqReader::open(&path)?;
+    Ok(ResourceArc::new(SeqReaderResource {
+        reader: Mutex::new(Some(reader)),
+    }))
+}
+
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn seq_reader_next_chunk(
+    handle: ResourceArc<SeqReaderResource>,
+    n: usize,
+) -> Result<Vec<FastqRecordNif>, String> {
+    let mut guard = handle.reader.lock().map_err(|e| e.to_string())?;
+    let reader = guard.as_mut().ok_or("reader is closed")?;
+    let records = reader.next_chunk(n)?;
+    Ok(records.into_iter().map(FastqRecordNif::from).collect())
+}
+
+/// Waits for a `next_chunk` in progress, so it runs on a dirty IO
+/// scheduler rather than blocking a normal one.
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn seq_reader_close(handle: ResourceArc<SeqReaderResource>) -> bool {
+    match handle.reader.lock() {
+        Ok(mut guard) => guard.take().is_some(),
+        Err(_) => false,
+    }
+}
+
+// ===========================================================================
+// FASTA records and indexed access
+// ===========================================================================
+
+/// Parse every record of a FASTA file.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn parse_fasta(path: String) -> Result<Vec<FastaRecordNif>, String> {
+    let mut reader = SeqReader::open(&path)?;
+    let mut records = Vec::new();
+    while let Some(rec) = reader.next_record()? {
+        if reader.format() != Some(SeqFormat::Fasta) {
+            return Err(format!("{path}: not a FASTA file"));
+        }
+        records.push(FastaRecordNif::from(rec));
+    }
+    Ok(records)
+}
+
+/// Index an uncompressed FASTA, write `<path>.fai` and return the entries.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn fasta_index(path: String) -> Result<Vec<FaiEntryNif>, String> {
+    let entries = crate::faidx::build_index(&path)?;
+    let fai = format!("{path}.fai");
+    std::fs::write(&fai, crate::faidx::format_index(&entries))
+        .map_err(|e| format!("{fai}: {e}"))?;
+    Ok(entries.iter().map(FaiEntryNif::from).collect())
+}
+
+/// An open indexed FASTA; `None` once closed.
+pub struct FaidxResource {
+    faidx: Mutex<Option<Faidx>>,
+}
+
+#[rustler::resource_impl]
+impl rustler::Resource for FaidxResource {}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn faidx_open(path: String) -> Result<ResourceArc<FaidxResource>, String> {
+    let faidx = Faidx::open(&path)?;
+    Ok(ResourceArc::new(FaidxResource {
+        faidx: Mutex::new(Some(faidx)),
+    }))
+}
+
+/// Like `faidx_close`, this locks the index a `faidx_fetch` may be
+/// reading through, so both run on a dirty IO scheduler.
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn faidx_sequences(handle: ResourceArc<FaidxResource>) -> Result<Vec<FaiEntryNif>, String> {
+    let guard = handle.faidx.lock().map_err(|e| e.to_string())?;
+    let faidx = guard.as_ref().ok_or("index is closed")?;
+    Ok(faidx.entries().iter().map(FaiEntryNif::from).collect())
+}
+
+/// Bases of a samtools-style region (`chr1:1000-2000`, 1-based inclusive),
+/// reverse-complemented when `reverse`.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn faidx_fetch(
+    handle: ResourceArc<FaidxResource>,
+    region: String,
+    reverse: bool,
+) -> Result<Vec<u8>, String> {
+    let mut guard = handle.faidx.lock().map_err(|e| e.to_string())?;
+    let faidx = guard.as_mut().ok_or("index is closed")?;
+    faidx.fetch_region(&region, reverse)
+}
+
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn faidx_close(handle: ResourceArc<FaidxResource>) -> bool {
+    match handle.faidx.lock() {
+        Ok(mut guard) => guard.take().is_some(),
+        Err(_) => false,
+    }
+}
+
+// ===========================================================================
+// Paired-end FASTQ
+// ===========================================================================
+
+/// An open paired-end reader; `None` once closed.
+pub struct PairedReaderResource {
+    reader: Mutex<Option<PairedReader>>,
+}
+
+#[rustler::resource_impl]
+impl rustler::Resource for PairedReaderResource {}
+
+/// Open R1/R2 files, or an interleaved file when `r2` is `nil`.
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn paired_reader_open(
+    r1: String,
+    r2: Option<String>,
+) -> Result<ResourceArc<PairedReaderResource>, String> {
+    let reader = PairedReader::open(&r1, r2.as_deref())?;
+    Ok(ResourceArc::new(PairedReaderResource {
+        reader: Mutex::new(Some(reader)),
+    }))
+}
+
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn paired_reader_next_chunk(
+    handle: ResourceArc<PairedReaderResource>,
+    n: usize,
+) -> Result<Vec<(FastqRecordNif, FastqRecordNif)>, String> {
+    let mut guard = handle.reader.lock().map_err(|e| e.to_string())?;
+    let reader = guard.as_mut().ok_or("reader is closed")?;
+    let pairs = reader.next_chunk(n)?;
+    Ok(pairs
+        .into_iter()
+        .map(|(a, b)| (FastqRecordNif::from(a), FastqRecordNif::from(b)))
+        .collect())
+}
+
+/// Runs on a dirty IO scheduler: it waits for any `next_chunk` in progress.
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn paired_reader_close(handle: ResourceArc<PairedReaderResource>) -> bool {
+    match handle.reader.lock() {
+        Ok(mut guard) => guard.take().is_some(),
+        Err(_) => false,
+    }
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn paired_fastq_stats(r1: String, r2: Option<String>) -> Result<PairedFastqStatsNif, String> {
+    let mut reader = PairedReader::open(&r1, r2.as_deref())?;
+    let s = PairedStats::from_reader(&mut reader)?;
+    Ok(PairedFastqStatsNif {
+        pair_count: s.pairs,
+        read1: FastqStatsNif::from(&s.read1),
+        read2: FastqStatsNif::from(&s.read2),
+        overlapping_pairs: s.overlapping_pairs(),
+        insert_size_mean: s.insert_size_mean(),
+        insert_size_median: s.insert_size_median(),
+        insert_size_sd: s.insert_size_sd(),
+        insert_size_histogram: s.insert_sizes,
+    })
+}
+
+// ===========================================================================
+// Read trimming
+// ===========================================================================
+
+fn trim_options(o: TrimOptionsNif) -> TrimOptions {
+    TrimOptions {
+        adapters: o.adapters.into_iter().map(String::into_bytes).collect(),
+        adapter_error_rate: o.adapter_error_rate,
+        min_adapter_overlap: o.min_adapter_overlap,
+        quality_cutoff: o.quality_cutoff,
+        window_size: o.window_size,
+        window_quality: o.window_quality,
+        poly_g: o.poly_g,
+        poly_a: o.poly_a,
+        min_length: o.min_length,
+    }
+}
+
+/// Trim a FASTQ file into `output` and report what each step removed.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn trim_fastq(
+    input: String,
+    output: String,
+    options: TrimOptionsNif,
+    bgzf: bool,
+) -> Result<TrimReportNif, String> {
+    let detect = options.detect_adapter;
+    let (r, adapters) = crate::trim::trim_file(&input, &output, trim_options(options), detect, bgzf)?;
+    Ok(TrimReportNif {
+        reads_in: r.reads_in,
+        reads_out: r.reads_out,
+        too_short: r.too_short,
+        bases_in: r.bases_in,
+        bases_out: r.bases_out,
+        adapter_reads: r.adapter_reads,
+        adapter_bases: r.adapter_bases,
+        quality_reads: r.quality_reads,
+        quality_bases: r.quality_bases,
+        poly_g_reads: r.poly_g_reads,
+        poly_g_bases: r.poly_g_bases,
+        poly_a_reads: r.poly_a_reads,
+        poly_a_bases: r.poly_a_bases,
+        adapters,
+    })
+}
+
+/// Trim a chunk of records, dropping those left shorter than `min_length`.
+/// A record may lack qualities, but otherwise they must match its length.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn trim_reads(
+    records: Vec<FastqRecordNif>,
+    options: TrimOptionsNif,
+) -> Result<Vec<FastqRecordNif>, String> {
+    let mut trimmer = Trimmer::new(trim_options(options));
+    let mut out = Vec::with_capacity(records.len());
+    for r in records {
+        if !r.quality.is_empty() && r.quality.len() != r.sequence.len() {
+            return Err(format!(
+                "record {}: sequence length {} != quality length {}",
+                r.name,
+                r.sequence.len(),
+                r.quality.len()
+            ));
+        }
+        let trimmed = trimmer.trim(SeqRecord {
+            name: r.name,
+            description: r.description,
+            sequence: r.sequence,
+            quality: r.quality,
+        });
+        out.extend(trimmed.map(FastqRecordNif::from));
+    }
+    Ok(out)
+}
+
+// ===========================================================================
+// FASTQ quality report
+// ===========================================================================
+
+/// FastQC-style report over the first `max_reads` reads (all when `None`).
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn fastq_qc(path: String, max_reads: Option<u64>) -> Result<FastqQcReportNif, String> {
+    crate::fastq_qc::fastq_qc(&path, max_reads).map(FastqQcReportNif::from)
+}
+
+// ===========================================================================
+// K-mer spectrum
+// ===========================================================================
+
+/// Count canonical k-mers (k <= 32) in a FASTA/FASTQ file. Returns the
+/// abundance histogram capped at `max_count`, the `top_n` most frequent
+/// k-mers and, when the histogram has an error trough and a coverage peak,
+/// genome size and heterozygosity estimates.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn kmer_spectrum(
+    path: String,
+    k: usize,
+    max_count: u32,
+    top_n: usize,
+) -> Result<KmerSpectrumNif, String> {
+    let counts = KmerCounts::from_file(&path, k)?;
+    let hist = counts.histogram(max_count);
+    let genome = kmer::estimate_genome(&hist, k).map(GenomeEstimateNif::from);
+    Ok(KmerSpectrumNif {
+        k,
+        total_kmers: counts.total,
+        distinct_kmers: counts.distinct(),
+        histogram: hist
+            .iter()
+            .enumerate()
+            .filter(|&(_, &n)| n > 0)
+            .map(|(c, &n)| (c as u32, n))
+            .collect(),
+        top_kmers: counts.top(top_n),
+        genome,
+    })
+}
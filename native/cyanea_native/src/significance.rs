@@ -0,0 +1,515 @@
+//! Karlin–Altschul statistics for local alignment scores: λ, K and H,
+//! bit scores and E-values.
+//!
+//! Ungapped parameters are computed from the matrix and background
+//! residue frequencies (Robinson & Robinson for proteins, uniform for
+//! nucleotides) as in Karlin & Altschul (1990). Gapped parameters have no
+//! closed form; NCBI BLAST ships values fitted by simulation for its
+//! matrices and gap costs, and those are tabulated here. Any other
+//! scoring is estimated by aligning against shuffled targets and fitting
+//! an extreme value distribution to the scores.
+
+use crate::pairwise::{self, Scoring};
+use crate::rng::SplitMix64;
+use crate::substitution::SubstitutionMatrix;
+
+pub(crate) const AMINO_ACIDS: &[u8] = b"ARNDCQEGHILKMFPSTWYV";
+
+/// Robinson & Robinson (1991) amino acid frequencies, in `AMINO_ACIDS`
+/// order, as used by BLAST.
+const ROBINSON: [f64; 20] = [
+    0.07805, 0.05129, 0.04487, 0.05364, 0.01925, 0.04264, 0.06295, 0.07377, 0.02199, 0.05142,
+    0.09019, 0.05744, 0.02243, 0.03856, 0.05203, 0.07120, 0.05841, 0.01330, 0.03216, 0.06441,
+];
+
+/// `(open, extend, λ, K, H)`, gap costs as positive numbers (a gap of
+/// length `L` costs `open + L * extend`).
+type GappedRow = (i32, i32, f64, f64, f64);
+
+/// NCBI BLAST gapped parameters per matrix.
+const GAPPED: &[(&str, &[GappedRow])] = &[
+    (
+        "blosum45",
+        &[
+            (13, 3, 0.207, 0.049, 0.14),
+            (12, 3, 0.199, 0.039, 0.11),
+            (11, 3, 0.190, 0.031, 0.095),
+            (10, 3, 0.179, 0.023, 0.075),
+            (16, 2, 0.210, 0.051, 0.14),
+            (15, 2, 0.203, 0.041, 0.12),
+            (14, 2, 0.195, 0.032, 0.10),
+            (13, 2, 0.185, 0.024, 0.084),
+            (12, 2, 0.171, 0.016, 0.061),
+            (19, 1, 0.205, 0.040, 0.11),
+            (18, 1, 0.198, 0.032, 0.10),
+            (17, 1, 0.189, 0.024, 0.079),
+            (16, 1, 0.176, 0.016, 0.063),
+        ],
+    ),
+    (
+        "blosum50",
+        &[
+            (13, 3, 0.212, 0.063, 0.19),
+            (12, 3, 0.206, 0.055, 0.17),
+            (11, 3, 0.197, 0.042, 0.14),
+            (10, 3, 0.186, 0.031, 0.11),
+            (9, 3, 0.172, 0.022, 0.082),
+            (16, 2, 0.215, 0.066, 0.20),
+            (15, 2, 0.210, 0.058, 0.17),
+            (14, 2, 0.202, 0.045, 0.14),
+            (13, 2, 0.193, 0.035, 0.12),
+            (12, 2, 0.181, 0.025, 0.095),
+            (19, 1, 0.212, 0.057, 0.18),
+            (18, 1, 0.207, 0.050, 0.15),
+            (17, 1, 0.198, 0.037, 0.12),
+            (16, 1, 0.186, 0.025, 0.10),
+            (15, 1, 0.171, 0.015, 0.063),
+        ],
+    ),
+    (
+        "blosum62",
+        &[
+            (11, 2, 0.297, 0.082, 0.27),
+            (10, 2, 0.291, 0.075, 0.23),
+            (9, 2, 0.279, 0.058, 0.19),
+            (8, 2, 0.264, 0.045, 0.15),
+            (7, 2, 0.239, 0.027, 0.10),
+            (6, 2, 0.201, 0.012, 0.061),
+            (13, 1, 0.292, 0.071, 0.23),
+            (12, 1, 0.283, 0.059, 0.19),
+            (11, 1, 0.267, 0.041, 0.14),
+            (10, 1, 0.243, 0.024, 0.10),
+            (9, 1, 0.206, 0.010, 0.052),
+        ],
+    ),
+    (
+        "blosum80",
+        &[
+            (25, 2, 0.342, 0.17, 0.66),
+            (13, 2, 0.336, 0.15, 0.57),
+            (9, 2, 0.319, 0.11, 0.42),
+            (8, 2, 0.308, 0.090, 0.35),
+            (7, 2, 0.293, 0.070, 0.27),
+            (6, 2, 0.268, 0.045, 0.19),
+            (11, 1, 0.314, 0.095, 0.35),
+            (10, 1, 0.299, 0.071, 0.27),
+            (9, 1, 0.279, 0.048, 0.20),
+        ],
+    ),
+    (
+        "blosum90",
+        &[
+            (9, 2, 0.310, 0.12, 0.46),
+            (8, 2, 0.300, 0.099, 0.39),
+            (7, 2, 0.283, 0.072, 0.30),
+            (6, 2, 0.259, 0.048, 0.22),
+            (11, 1, 0.302, 0.093, 0.39),
+            (10, 1, 0.290, 0.075, 0.28),
+            (9, 1, 0.265, 0.044, 0.20),
+        ],
+    ),
+    (
+        "pam30",
+        &[
+            (7, 2, 0.305, 0.15, 0.87),
+            (6, 2, 0.287, 0.11, 0.68),
+            (5, 2, 0.264, 0.079, 0.45),
+            (10, 1, 0.309, 0.15, 0.88),
+            (9, 1, 0.294, 0.11, 0.61),
+            (8, 1, 0.270, 0.072, 0.40),
+        ],
+    ),
+    (
+        "pam70",
+        &[
+            (8, 2, 0.301, 0.12, 0.65),
+            (7, 2, 0.286, 0.093, 0.48),
+            (6, 2, 0.264, 0.064, 0.31),
+            (11, 1, 0.305, 0.12, 0.52),
+            (10, 1, 0.291, 0.091, 0.41),
+            (9, 1, 0.270, 0.060, 0.28),
+        ],
+    ),
+    (
+        "pam250",
+        &[
+            (15, 3, 0.205, 0.049, 0.13),
+            (14, 3, 0.200, 0.043, 0.12),
+            (13, 3, 0.194, 0.036, 0.10),
+            (12, 3, 0.186, 0.029, 0.085),
+            (11, 3, 0.174, 0.020, 0.070),
+            (17, 2, 0.204, 0.047, 0.12),
+            (16, 2, 0.198, 0.038, 0.11),
+            (15, 2, 0.191, 0.031, 0.087),
+            (14, 2, 0.182, 0.024, 0.073),
+            (13, 2, 0.171, 0.017, 0.059),
+            (21, 1, 0.205, 0.045, 0.11),
+            (20, 1, 0.199, 0.038, 0.10),
+            (19, 1, 0.192, 0.031, 0.088),
+            (18, 1, 0.183, 0.024, 0.072),
+            (17, 1, 0.171, 0.017, 0.058),
+        ],
+    ),
+];
+
+/// Terms of the series for K; it converges geometrically.
+const MAX_K_TERMS: usize = 200;
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct KarlinAltschul {
+    pub lambda: f64,
+    pub k: f64,
+    /// Relative entropy, nats per aligned pair.
+    pub h: f64,
+    pub gapped: bool,
+}
+
+impl KarlinAltschul {
+    /// NCBI's gapped parameters for a built-in matrix with the given gap
+    /// penalties (negative scores, as passed to the aligners). `None` for
+    /// any other matrix, even one named like a built-in.
+    pub(crate) fn gapped(
+        matrix: &SubstitutionMatrix,
+        gap_open: i32,
+        gap_extend: i32,
+    ) -> Option<Self> {
+        if !matrix.is_builtin() {
+            return None;
+        }
+        let lower = matrix.name.to_ascii_lowercase();
+        let (_, table) = GAPPED.iter().find(|(name, _)| *name == lower)?;
+        table
+            .iter()
+            .find(|&&(open, extend, ..)| open == -gap_open && extend == -gap_extend)
+            .map(|&(_, _, lambda, k, h)| Self {
+                lambda,
+                k,
+                h,
+                gapped: true,
+            })
+    }
+
+    /// Ungapped parameters of a matrix under its background frequencies.
+    pub(crate) fn ungapped(matrix: &SubstitutionMatrix) -> Result<Self, String> {
+        let freqs = background(matrix);
+        let mut probs: Vec<(i32, f64)> = Vec::new();
+        for &(a, pa) in &freqs {
+            for &(b, pb) in &freqs {
+                probs.push((matrix.score(a, b), pa * pb));
+            }
+        }
+        ungapped_from_scores(&probs)
+    }
+
+    /// Normalized score in bits.
+    pub(crate) fn bit_score(&self, score: i32) -> f64 {
+        (self.lambda * score as f64 - self.k.ln()) / std::f64::consts::LN_2
+    }
+
+    /// Expected chance alignments scoring at least `score` between a query
+    /// of `query_len` residues and a database of `db_len` residues.
+    pub(crate) fn evalue(&self, score: i32, query_len: usize, db_len: usize) -> f64 {
+        self.k * query_len as f64 * db_len as f64 * (-self.lambda * score as f64).exp()
+    }
+
+    /// Expected HSP length, subtracted from the query and from every
+    /// database sequence for edge effects: the fixed point of
+    /// `ℓ = ln(K (m - ℓ)(n - N ℓ)) / H` (Altschul & Gish 1996).
+    pub(crate) fn length_adjustment(
+        &self,
+        query_len: usize,
+        db_len: usize,
+        db_seqs: usize,
+    ) -> usize {
+        if self.h <= 0.0 {
+            return 0;
+        }
+        let (m, n, seqs) = (query_len as f64, db_len as f64, db_seqs as f64);
+        let mut ell = 0.0f64;
+        for _ in 0..20 {
+            let space = (m - ell) * (n - seqs * ell);
+            if space <= 1.0 {
+                break;
+            }
+            let next = (self.k * space).ln().max(0.0) / self.h;
+            // Leave at least one residue of query and database.
+            let next = next.min(m - 1.0).min((n - 1.0) / seqs).max(0.0);
+            if (next - ell).abs() < 0.5 {
+                ell = next;
+                break;
+            }
+            ell = next;
+        }
+        ell.floor() as usize
+    }
+}
+
+/// Background residue frequencies: Robinson & Robinson for protein
+/// matrices, uniform `ACGT` for nucleotide matrices, otherwise uniform over
+/// the alphabet (without `*`).
+fn background(matrix: &SubstitutionMatrix) -> Vec<(u8, f64)> {
+    let has = |residues: &[u8]| {
+        residues
+            .iter()
+            .all(|r| matrix.alphabet.iter().any(|a| a.eq_ignore_ascii_case(r)))
+    };
+    if has(AMINO_ACIDS) {
+        AMINO_ACIDS.iter().copied().zip(ROBINSON).collect()
+    } else if has(b"ACGT") {
+        b"ACGT".iter().map(|&b| (b, 0.25)).collect()
+    } else {
+        let residues: Vec<u8> = matrix
+            .alphabet
+            .iter()
+            .copied()
+            .filter(|&a| a != b'*')
+            .collect();
+        let p = 1.0 / residues.len().max(1) as f64;
+        residues.into_iter().map(|a| (a, p)).collect()
+    }
+}
+
+fn gcd(a: i32, b: i32) -> i32 {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// λ, K and H for i.i.d. scores with the given `(score, probability)`
+/// pairs. Needs a negative expected score and some positive score.
+fn ungapped_from_scores(probs: &[(i32, f64)]) -> Result<KarlinAltschul, String> {
+    let probs: Vec<(i32, f64)> = probs.iter().copied().filter(|&(_, p)| p > 0.0).collect();
+    let expected: f64 = probs.iter().map(|&(s, p)| s as f64 * p).sum();
+    if expected >= 0.0 || !probs.iter().any(|&(s, _)| s > 0) {
+        return Err(format!(
+            "Karlin-Altschul statistics need a negative expected score and a positive score (expected score {expected:.3})"
+        ));
+    }
+    // Work in units of the scores' gcd; λ scales back, K is unchanged.
+    let delta = probs.iter().fold(0, |g, &(s, _)| gcd(g, s));
+    let low = probs.iter().map(|&(s, _)| s / delta).min().unwrap_or(0);
+    let high = probs.iter().map(|&(s, _)| s / delta).max().unwrap_or(0);
+    let mut dist = vec![0.0; (high - low + 1) as usize];
+    for &(s, p) in &probs {
+        dist[(s / delta - low) as usize] += p;
+    }
+    let mgf = |lambda: f64| -> f64 {
+        dist.iter()
+            .enumerate()
+            .map(|(i, &p)| p * (lambda * (i as i32 + low) as f64).exp())
+            .sum()
+    };
+
+    // E[exp(λS)] = 1 has one positive root; bracket it and bisect.
+    let mut hi = 1.0;
+    while mgf(hi) < 1.0 {
+        hi *= 2.0;
+    }
+    let mut lo = 0.0;
+    for _ in 0..100 {
+        let mid = (lo + hi) / 2.0;
+        if mgf(mid) < 1.0 {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    let lambda = (lo + hi) / 2.0;
+    let h = lambda
+        * dist
+            .iter()
+            .enumerate()
+            .map(|(i, &p)| {
+                let s = (i as i32 + low) as f64;
+                p * s * (lambda * s).exp()
+            })
+            .sum::<f64>();
+
+    // K = exp(-2σ) / ((H/λ)(1 - exp(-λ))), where
+    // σ = Σ_k 1/k (E[exp(λS_k); S_k < 0] + P(S_k >= 0)) over sums S_k of k
+    // scores (Karlin & Altschul 1990).
+    let mut sigma = 0.0;
+    let mut sum_dist = vec![1.0];
+    let mut sum_low = 0i32;
+    for k in 1..=MAX_K_TERMS {
+        let mut next = vec![0.0; sum_dist.len() + dist.len() - 1];
+        for (i, &a) in sum_dist.iter().enumerate() {
+            if a == 0.0 {
+                continue;
+            }
+            for (j, &b) in dist.iter().enumerate() {
+                next[i + j] += a * b;
+            }
+        }
+        sum_dist = next;
+        sum_low += low;
+        let term: f64 = sum_dist
+            .iter()
+            .enumerate()
+            .map(|(i, &p)| {
+                let s = i as i32 + sum_low;
+                if s < 0 {
+                    p * (lambda * s as f64).exp()
+                } else {
+                    p
+                }
+            })
+            .sum::<f64>()
+            / k as f64;
+        sigma += term;
+        if term < 1e-10 {
+            break;
+        }
+    }
+    let k = (-2.0 * sigma).exp() / ((h / lambda) * (1.0 - (-lambda).exp()));
+
+    Ok(KarlinAltschul {
+        lambda: lambda / delta as f64,
+        k,
+        h,
+        gapped: false,
+    })
+}
+
+/// Gumbel fit to local alignment scores against shuffled targets.
+#[derive(Debug, Clone)]
+pub(crate) struct ShuffleFit {
+    pub score: i32,
+    pub shuffles: usize,
+    pub mean: f64,
+    pub sd: f64,
+    pub lambda: f64,
+    pub k: f64,
+    /// Gumbel probability of a shuffled score of at least `score`.
+    pub p_value: f64,
+    /// `(shuffled scores >= score) + 1` over `shuffles + 1`.
+    pub empirical_p: f64,
+}
+
+/// Score the local alignment of `query` and `target`, then of `query`
+/// against `shuffles` shuffles of `target` (same composition), and fit
+/// λ and K by the method of moments.
+pub(crate) fn shuffle_fit(
+    query: &[u8],
+    target: &[u8],
+    scoring: &Scoring,
+    shuffles: usize,
+    seed: u64,
+) -> Result<ShuffleFit, String> {
+    if shuffles < 2 {
+        return Err(format!("need at least 2 shuffles, got {shuffles}"));
+    }
+    if query.is_empty() || target.is_empty() {
+        return Err("cannot estimate significance for an empty sequence".to_string());
+    }
+    let score = pairwise::local_score(query, target, scoring);
+    let mut rng = SplitMix64::new(seed);
+    let mut shuffled = target.to_vec();
+    let scores: Vec<f64> = (0..shuffles)
+        .map(|_| {
+            for i in (1..shuffled.len()).rev() {
+                shuffled.swap(i, rng.below(i + 1));
+            }
+            pairwise::local_score(query, &shuffled, scoring) as f64
+        })
+        .collect();
+
+    let n = scores.len() as f64;
+    let mean = scores.iter().sum::<f64>() / n;
+    let sd = (scores.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt();
+    if sd == 0.0 {
+        return Err("shuffled scores do not vary; sequences too short to fit".to_string());
+    }
+    // Gumbel: sd = π / (λ√6), mean = μ + γ/λ, K = exp(λμ) / (m n).
+    const EULER_GAMMA: f64 = 0.577_215_664_901_532_9;
+    let lambda = std::f64::consts::PI / (sd * 6f64.sqrt());
+    let mu = mean - EULER_GAMMA / lambda;
+    let k = (lambda * mu).exp() / (query.len() as f64 * target.len() as f64);
+    let p_value = -(-(-lambda * (score as f64 - mu)).exp()).exp_m1();
+    let above = scores.iter().filter(|&&s| s >= score as f64).count();
+    Ok(ShuffleFit {
+        score,
+        shuffles,
+        mean,
+        sd,
+        lambda,
+        k,
+        p_value,
+        empirical_p: (above + 1) as f64 / (n + 1.0),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(actual: f64, expected: f64, tolerance: f64) -> bool {
+        (actual - expected).abs() <= tolerance
+    }
+
+    #[test]
+    fn blosum62_ungapped_matches_blast() {
+        // BLAST's ungapped BLOSUM62 values: λ 0.3176, K 0.134, H 0.401.
+        let m = SubstitutionMatrix::builtin("blosum62").unwrap();
+        let ka = KarlinAltschul::ungapped(&m).unwrap();
+        assert!(close(ka.lambda, 0.3176, 5e-4), "{ka:?}");
+        assert!(close(ka.k, 0.134, 2e-3), "{ka:?}");
+        assert!(close(ka.h, 0.401, 5e-3), "{ka:?}");
+        assert!(!ka.gapped);
+    }
+
+    #[test]
+    fn nucleotide_ungapped_matches_blast() {
+        // +1/-3: λ 1.374, K 0.711, H 1.31.
+        let ka = KarlinAltschul::ungapped(&SubstitutionMatrix::nucleotide(1, -3, -3)).unwrap();
+        assert!(close(ka.lambda, 1.374, 1e-3), "{ka:?}");
+        assert!(close(ka.k, 0.711, 2e-3), "{ka:?}");
+        assert!(close(ka.h, 1.31, 1e-2), "{ka:?}");
+    }
+
+    #[test]
+    fn blosum62_gapped_parameters_and_evalues() {
+        let m = SubstitutionMatrix::builtin("blosum62").unwrap();
+        let ka = KarlinAltschul::gapped(&m, -11, -1).unwrap();
+        assert_eq!((ka.lambda, ka.k, ka.gapped), (0.267, 0.041, true));
+        assert!(KarlinAltschul::gapped(&m, -11, -3).is_none());
+        // A custom matrix under a built-in name has no tabulated values.
+        let mut scores = m.rows().concat();
+        scores[0] += 1;
+        let custom = SubstitutionMatrix::new("BLOSUM62", m.alphabet.clone(), scores).unwrap();
+        assert!(KarlinAltschul::gapped(&custom, -11, -1).is_none());
+
+        // bits = (λS - ln K) / ln 2, and E = m n 2^-bits.
+        let bits = ka.bit_score(100);
+        assert!(close(bits, 43.13, 0.01), "{bits}");
+        let e = ka.evalue(100, 250, 1_000_000);
+        assert!(close(e, 250e6 * 2f64.powf(-bits), 1e-12), "{e}");
+    }
+
+    #[test]
+    fn shuffle_fit_recovers_the_gapped_lambda() {
+        let m = SubstitutionMatrix::builtin("blosum62").unwrap();
+        let scoring = Scoring::new(&m, -11, -1).unwrap();
+        let mut rng = SplitMix64::new(11);
+        let mut random = |n: usize| -> Vec<u8> {
+            (0..n)
+                .map(|_| {
+                    let mut x = rng.next_u64() as f64 / u64::MAX as f64;
+                    let i = ROBINSON.iter().position(|&p| {
+                        x -= p;
+                        x < 0.0
+                    });
+                    AMINO_ACIDS[i.unwrap_or(AMINO_ACIDS.len() - 1)]
+                })
+                .collect()
+        };
+        let (query, target) = (random(300), random(300));
+        let fit = shuffle_fit(&query, &target, &scoring, 400, 3).unwrap();
+        // Method-of-moments estimates on finite sequences are rough.
+        assert!(close(fit.lambda, 0.267, 0.05), "{fit:?}");
+        assert!(fit.p_value > 0.001 && fit.empirical_p > 0.001, "{fit:?}");
+        assert!(shuffle_fit(&query, &target, &scoring, 1, 3).is_err());
+    }
+}
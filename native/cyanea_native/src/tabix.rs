@@ -0,0 +1,305 @@
+//! Tabix: region queries over BGZF-compressed, position-sorted text files.
+//!
+//! Works with `.tbi` and tabix-flavoured `.csi` indexes produced by htslib
+//! as well as the ones written here. Only the VCF, BED, GFF3 and bedGraph
+//! layouts are built in; a query on any other file follows the column
+//! layout recorded in its index header.
+
+use crate::bgzf::{BgzfReader, BgzfWriter};
+use crate::binning::{BinningIndex, IndexBuilder, BAI_DEPTH, BAI_MIN_SHIFT};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+
+const FORMAT_VCF: i32 = 2;
+/// Flag for 0-based, half-open coordinates (BED-like files).
+const FORMAT_UCSC: i32 = 0x10000;
+
+/// Column layout stored in the tabix header. Columns are 1-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct TabixConfig {
+    pub format: i32,
+    pub col_seq: i32,
+    pub col_beg: i32,
+    pub col_end: i32,
+    pub meta: u8,
+    pub skip: i32,
+}
+
+impl TabixConfig {
+    pub(crate) fn preset(name: &str) -> Result<Self, String> {
+        let (format, col_seq, col_beg, col_end) = match name {
+            "vcf" => (FORMAT_VCF, 1, 2, 0),
+            "bed" | "bedgraph" => (FORMAT_UCSC, 1, 2, 3),
+            "gff" | "gff3" => (0, 1, 4, 5),
+            other => {
+                return Err(format!(
+                    "unknown tabix preset: {other} (expected vcf, bed, gff or bedgraph)"
+                ))
+            }
+        };
+        Ok(Self {
+            format,
+            col_seq,
+            col_beg,
+            col_end,
+            meta: b'#',
+            skip: 0,
+        })
+    }
+
+    /// Preset for a file name, ignoring a trailing `.gz`.
+    pub(crate) fn preset_for_path(path: &str) -> Result<Self, String> {
+        let lower = path.to_ascii_lowercase();
+        let stem = lower.strip_suffix(".gz").unwrap_or(&lower);
+        let ext = stem.rsplit('.').next().unwrap_or("");
+        match ext {
+            "vcf" => Self::preset("vcf"),
+            "bed" => Self::preset("bed"),
+            "bedgraph" | "bg" => Self::preset("bedgraph"),
+            "gff" | "gff3" => Self::preset("gff"),
+            _ => Err(format!("{path}: cannot infer tabix preset from extension")),
+        }
+    }
+
+    /// Header lines: meta-character lines plus UCSC `track`/`browser` lines.
+    fn is_header(&self, line: &[u8]) -> bool {
+        line.first() == Some(&self.meta)
+            || line.is_empty()
+            || (self.format & FORMAT_UCSC != 0
+                && (line.starts_with(b"track") || line.starts_with(b"browser")))
+    }
+
+    /// Sequence name and 0-based half-open interval of a data line.
+    fn interval<'a>(&self, line: &'a str) -> Result<(&'a str, u64, u64), String> {
+        let fields: Vec<&str> = line.split('\t').collect();
+        let field = |col: i32| {
+            usize::try_from(col - 1)
+                .ok()
+                .and_then(|i| fields.get(i).copied())
+                .ok_or_else(|| format!("missing column {col} in line: {line}"))
+        };
+        let number = |col: i32| -> Result<u64, String> {
+            let f = field(col)?;
+            f.trim()
+                .parse()
+                .map_err(|_| format!("invalid coordinate {f:?} in column {col}"))
+        };
+
+        let name = field(self.col_seq)?;
+        let raw_beg = number(self.col_beg)?;
+        let beg = if self.format & FORMAT_UCSC != 0 {
+            raw_beg
+        } else {
+            raw_beg.saturating_sub(1)
+        };
+        let end = if self.format & 0xffff == FORMAT_VCF {
+            let info_end = fields.get(7).and_then(|info| {
+                info.split(';')
+                    .find_map(|kv| kv.strip_prefix("END="))
+                    .and_then(|v| v.parse::<u64>().ok())
+            });
+            let ref_len = fields.get(3).map_or(1, |r| r.len() as u64);
+            info_end.unwrap_or(beg + ref_len)
+        } else if self.col_end > 0 {
+            number(self.col_end)?
+        } else {
+            beg + 1
+        };
+        Ok((name, beg, end.max(beg + 1)))
+    }
+
+    /// Serialize as the 28-byte tabix header followed by `names`.
+    fn to_aux(self, names: &[String]) -> Vec<u8> {
+        let mut nm = Vec::new();
+        for n in names {
+            nm.extend_from_slice(n.as_bytes());
+            nm.push(0);
+        }
+        let mut aux = Vec::with_capacity(28 + nm.len());
+        for v in [
+            self.format,
+            self.col_seq,
+            self.col_beg,
+            self.col_end,
+            self.meta as i32,
+            self.skip,
+            nm.len() as i32,
+        ] {
+            aux.extend_from_slice(&v.to_le_bytes());
+        }
+        aux.extend_from_slice(&nm);
+        aux
+    }
+
+    fn from_aux(aux: &[u8]) -> Result<(Self, Vec<String>), String> {
+        if aux.len() < 28 {
+            return Err("index has no tabix header".into());
+        }
+        let int = |i: usize| i32::from_le_bytes(aux[i * 4..i * 4 + 4].try_into().unwrap());
+        let config = Self {
+            format: int(0),
+            col_seq: int(1),
+            col_beg: int(2),
+            col_end: int(3),
+            meta: int(4) as u8,
+            skip: int(5),
+        };
+        let names = aux[28..]
+            .split(|&b| b == 0)
+            .filter(|n| !n.is_empty())
+            .map(|n| String::from_utf8_lossy(n).into_owned())
+            .collect();
+        Ok((config, names))
+    }
+}
+
+/// Parse `chr`, `chr:beg` or `chr:beg-end` (1-based, inclusive, commas
+/// allowed) into a name and 0-based half-open interval.
+pub(crate) fn parse_region(region: &str) -> Result<(String, u64, u64), String> {
+    let region = region.trim();
+    let parsed = region.rsplit_once(':').and_then(|(name, range)| {
+        let range = range.replace(',', "");
+        let (b, e) = match range.split_once('-') {
+            Some((b, "")) => (b.parse::<u64>().ok()?, u64::MAX),
+            Some((b, e)) => (b.parse::<u64>().ok()?, e.parse::<u64>().ok()?),
+            None => (range.parse::<u64>().ok()?, u64::MAX),
+        };
+        Some((name.to_string(), b.saturating_sub(1), e))
+    });
+    let (name, beg, end) = parsed.unwrap_or_else(|| (region.to_string(), 0, u64::MAX));
+    if name.is_empty() {
+        return Err(format!("invalid region: {region:?}"));
+    }
+    if beg >= end {
+        return Err(format!("empty region: {region}"));
+    }
+    Ok((name, beg, end))
+}
+
+/// Locate the index for `path`: `<path>.tbi`, then `<path>.csi`.
+fn find_index(path: &str) -> Option<String> {
+    [format!("{path}.tbi"), format!("{path}.csi")]
+        .into_iter()
+        .find(|c| std::path::Path::new(c).is_file())
+}
+
+/// Data lines of a bgzipped, tabix-indexed file overlapping `region`.
+pub(crate) fn query(path: &str, region: &str) -> Result<Vec<String>, String> {
+    let index_path = find_index(path)
+        .ok_or_else(|| format!("{path}: no .tbi or .csi index found; build one first"))?;
+    let bytes = std::fs::read(&index_path).map_err(|e| format!("{index_path}: {e}"))?;
+    let (index, _) = BinningIndex::from_bytes(&bytes)?;
+    let (config, names) = TabixConfig::from_aux(&index.aux)?;
+    // A sequence name may itself contain ':' (e.g. HLA contigs).
+    let (chrom, beg, end) = if names.iter().any(|n| n == region.trim()) {
+        (region.trim().to_string(), 0, u64::MAX)
+    } else {
+        parse_region(region)?
+    };
+    let Some(tid) = names.iter().position(|n| *n == chrom) else {
+        return Ok(Vec::new());
+    };
+
+    let file = File::open(path).map_err(|e| format!("{path}: {e}"))?;
+    let mut reader = BgzfReader::new(BufReader::new(file));
+    let mut hits = Vec::new();
+    let mut line = Vec::new();
+    for chunk in index.query(tid, beg, end) {
+        reader.seek_virtual(chunk.beg).map_err(|e| e.to_string())?;
+        while reader.virtual_offset() < chunk.end {
+            line.clear();
+            if !reader.read_line(&mut line).map_err(|e| e.to_string())? {
+                break;
+            }
+            if config.is_header(&line) {
+                continue;
+            }
+            let text = String::from_utf8_lossy(&line);
+            let (name, b, e) = config.interval(&text)?;
+            if name != chrom || b >= end {
+                break;
+            }
+            if e > beg {
+                hits.push(text.into_owned());
+            }
+        }
+    }
+    Ok(hits)
+}
+
+/// BGZF-compress a sorted, uncompressed text file to `<path>.gz` and write
+/// `<path>.gz.tbi` next to it. `preset` is `vcf`, `bed`, `gff`, `bedgraph`
+/// or `auto` (inferred from the extension). Returns the compressed path.
+pub(crate) fn build_index(path: &str, preset: &str) -> Result<String, String> {
+    let config = if preset == "auto" {
+        TabixConfig::preset_for_path(path)?
+    } else {
+        TabixConfig::preset(preset)?
+    };
+    let input = File::open(path).map_err(|e| format!("{path}: {e}"))?;
+    let mut input = BufReader::with_capacity(1 << 16, input);
+    if input
+        .fill_buf()
+        .map_err(|e| e.to_string())?
+        .starts_with(&[0x1f, 0x8b])
+    {
+        return Err(format!("{path}: input must be uncompressed"));
+    }
+
+    let out_path = format!("{path}.gz");
+    let out = File::create(&out_path).map_err(|e| format!("{out_path}: {e}"))?;
+    let mut writer = BgzfWriter::new(BufWriter::new(out));
+    let mut builder = IndexBuilder::new(0, BAI_MIN_SHIFT, BAI_DEPTH, true);
+    let mut names: Vec<String> = Vec::new();
+    let mut line = Vec::new();
+    let mut line_no = 0u64;
+    loop {
+        line.clear();
+        if input
+            .read_until(b'\n', &mut line)
+            .map_err(|e| e.to_string())?
+            == 0
+        {
+            break;
+        }
+        line_no += 1;
+        while matches!(line.last(), Some(b'\n' | b'\r')) {
+            line.pop();
+        }
+        let vbeg = writer.virtual_offset();
+        writer.write_all(&line).map_err(|e| e.to_string())?;
+        writer.write_all(b"\n").map_err(|e| e.to_string())?;
+        if config.is_header(&line) {
+            continue;
+        }
+        let vend = writer.virtual_offset();
+
+        let text = String::from_utf8_lossy(&line);
+        let (name, beg, end) = config
+            .interval(&text)
+            .map_err(|e| format!("{path}:{line_no}: {e}"))?;
+        let tid = match names.iter().rposition(|n| n == name) {
+            Some(t) if t == names.len() - 1 => t,
+            Some(_) => {
+                return Err(format!(
+                    "{path}:{line_no}: {name} appears in more than one block; sort the file first"
+                ))
+            }
+            None => {
+                names.push(name.to_string());
+                names.len() - 1
+            }
+        };
+        builder
+            .push(tid, beg, end, vbeg, vend, true)
+            .map_err(|e| format!("{path}:{line_no}: {e}"))?;
+    }
+    writer.finish().map_err(|e| e.to_string())?;
+
+    let mut index = builder.finish();
+    index.aux = config.to_aux(&names);
+    index.n_no_coor = None;
+    let index_path = format!("{out_path}.tbi");
+    std::fs::write(&index_path, index.to_tbi()?).map_err(|e| format!("{index_path}: {e}"))?;
+    Ok(out_path)
+}
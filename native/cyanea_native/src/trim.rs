@@ -0,0 +1,304 @@
+//! FASTQ read trimming: poly-G/poly-A tails, adapters, sliding-window and
+//! 3' quality trimming, and a minimum-length filter.
+//!
+//! Full adapter occurrences are found with `cyanea_seq::myers_bitparallel`
+//! (which reports the end of each approximate match); adapters cut off by
+//! the end of the read are matched as prefix/suffix overlaps. Steps run in
+//! the order fastp uses: poly-G, adapter, poly-A, then quality.
+
+use crate::pairwise::edit_distance;
+use crate::reader::{SeqReader, SeqRecord};
+use crate::writer::OutputFile;
+
+/// Adapters recognised by [`detect_adapter`], as Trim Galore lists them:
+/// Illumina TruSeq, Nextera and Illumina small RNA.
+pub(crate) const KNOWN_ADAPTERS: [&str; 3] = ["AGATCGGAAGAGC", "CTGTCTCTTATA", "TGGAATTCTCGG"];
+
+/// Reads inspected by [`detect_adapter`].
+const DETECT_READS: usize = 100_000;
+
+#[derive(Debug, Clone)]
+pub(crate) struct TrimOptions {
+    pub adapters: Vec<Vec<u8>>,
+    /// Edit distance allowed per adapter base.
+    pub adapter_error_rate: f64,
+    /// Shortest adapter prefix removed at the 3' end of a read.
+    pub min_adapter_overlap: usize,
+    /// BWA-style 3' quality cutoff; 0 disables.
+    pub quality_cutoff: u8,
+    /// Sliding window width; 0 disables.
+    pub window_size: usize,
+    pub window_quality: u8,
+    /// Shortest poly-G / poly-A tail removed; 0 disables.
+    pub poly_g: usize,
+    pub poly_a: usize,
+    pub min_length: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TrimReport {
+    pub reads_in: u64,
+    pub reads_out: u64,
+    pub too_short: u64,
+    pub bases_in: u64,
+    pub bases_out: u64,
+    /// Reads trimmed, and bases removed, by each step.
+    pub adapter_reads: u64,
+    pub adapter_bases: u64,
+    pub quality_reads: u64,
+    pub quality_bases: u64,
+    pub poly_g_reads: u64,
+    pub poly_g_bases: u64,
+    pub poly_a_reads: u64,
+    pub poly_a_bases: u64,
+}
+
+impl TrimReport {
+    fn cut(reads: &mut u64, bases: &mut u64, len: &mut usize, cut: Option<usize>) {
+        if let Some(cut) = cut.filter(|&c| c < *len) {
+            *reads += 1;
+            *bases += (*len - cut) as u64;
+            *len = cut;
+        }
+    }
+}
+
+pub(crate) struct Trimmer {
+    opts: TrimOptions,
+    pub report: TrimReport,
+}
+
+impl Trimmer {
+    pub(crate) fn new(mut opts: TrimOptions) -> Self {
+        for a in &mut opts.adapters {
+            a.make_ascii_uppercase();
+        }
+        Self {
+            opts,
+            report: TrimReport::default(),
+        }
+    }
+
+    /// Trim one read; `None` when it ends up shorter than `min_length`.
+    pub(crate) fn trim(&mut self, mut rec: SeqRecord) -> Option<SeqRecord> {
+        let o = &self.opts;
+        let r = &mut self.report;
+        r.reads_in += 1;
+        r.bases_in += rec.sequence.len() as u64;
+        let upper = rec.sequence.to_ascii_uppercase();
+        let mut len = upper.len();
+        if o.poly_g > 0 {
+            let cut = poly_tail(&upper[..len], b'G', o.poly_g);
+            TrimReport::cut(&mut r.poly_g_reads, &mut r.poly_g_bases, &mut len, cut);
+        }
+        let cut = o
+            .adapters
+            .iter()
+            .filter_map(|a| {
+                adapter_start(
+                    &upper[..len],
+                    a,
+                    o.adapter_error_rate,
+                    o.min_adapter_overlap,
+                )
+            })
+            .min();
+        TrimReport::cut(&mut r.adapter_reads, &mut r.adapter_bases, &mut len, cut);
+        if o.poly_a > 0 {
+            let cut = poly_tail(&upper[..len], b'A', o.poly_a);
+            TrimReport::cut(&mut r.poly_a_reads, &mut r.poly_a_bases, &mut len, cut);
+        }
+        if !rec.quality.is_empty() {
+            let mut cut = len;
+            if o.window_size > 0 {
+                cut = sliding_window(&rec.quality[..cut], o.window_size, o.window_quality);
+            }
+            if o.quality_cutoff > 0 {
+                cut = quality_3p(&rec.quality[..cut], o.quality_cutoff);
+            }
+            TrimReport::cut(
+                &mut r.quality_reads,
+                &mut r.quality_bases,
+                &mut len,
+                Some(cut),
+            );
+        }
+        if len < o.min_length {
+            r.too_short += 1;
+            return None;
+        }
+        rec.sequence.truncate(len);
+        rec.quality.truncate(len);
+        r.reads_out += 1;
+        r.bases_out += len as u64;
+        Some(rec)
+    }
+}
+
+/// Start of the adapter in `seq`: the leftmost full approximate match, or
+/// else the longest adapter prefix matching a suffix of the read.
+pub(crate) fn adapter_start(
+    seq: &[u8],
+    adapter: &[u8],
+    error_rate: f64,
+    min_overlap: usize,
+) -> Option<usize> {
+    if adapter.is_empty() {
+        return None;
+    }
+    let max_dist = (adapter.len() as f64 * error_rate) as usize;
+    let full = cyanea_seq::myers_bitparallel(seq, adapter, max_dist)
+        .into_iter()
+        .filter_map(|(end, _)| match_start(seq, adapter, end, max_dist))
+        .min();
+    if full.is_some() {
+        return full;
+    }
+    let longest = adapter.len().saturating_sub(1).min(seq.len());
+    (min_overlap.max(1)..=longest).rev().find_map(|k| {
+        let allowed = (k as f64 * error_rate) as usize;
+        let tail = &seq[seq.len() - k..];
+        let mismatches = tail.iter().zip(adapter).filter(|(a, b)| a != b).count();
+        (mismatches <= allowed).then_some(seq.len() - k)
+    })
+}
+
+/// Leftmost start of the best alignment of `adapter` to `seq` ending at
+/// `end` (inclusive); indels shift it by up to `max_dist` either way.
+fn match_start(seq: &[u8], adapter: &[u8], end: usize, max_dist: usize) -> Option<usize> {
+    let stop = end + 1;
+    let nominal = stop.saturating_sub(adapter.len());
+    let lo = nominal.saturating_sub(max_dist);
+    let hi = (nominal + max_dist).min(end);
+    (lo..=hi)
+        .map(|s| (edit_distance(&seq[s..stop], adapter), s))
+        .filter(|&(d, _)| d <= max_dist)
+        .min()
+        .map(|(_, s)| s)
+}
+
+/// Start of a 3' run of `base` at least `min_len` long, allowing one
+/// mismatch per 8 bases (fastp's poly-G rule).
+fn poly_tail(seq: &[u8], base: u8, min_len: usize) -> Option<usize> {
+    let mut mismatches = 0;
+    let mut cut = None;
+    for (i, &b) in seq.iter().enumerate().rev() {
+        let run = seq.len() - i;
+        if b != base {
+            mismatches += 1;
+            if mismatches > run / 8 + 1 {
+                break;
+            }
+        } else if mismatches <= run / 8 {
+            cut = Some(i);
+        }
+    }
+    cut.filter(|&c| seq.len() - c >= min_len)
+}
+
+/// Trimmomatic `SLIDINGWINDOW`: cut at the first window whose mean
+/// quality is below `threshold`, keeping its leading bases that pass.
+fn sliding_window(quality: &[u8], window: usize, threshold: u8) -> usize {
+    let q = |i: usize| quality[i].saturating_sub(33) as usize;
+    if quality.len() < window {
+        return quality.len();
+    }
+    let needed = threshold as usize * window;
+    let mut sum: usize = (0..window).map(q).sum();
+    for start in 0..=quality.len() - window {
+        if start > 0 {
+            sum = sum + q(start + window - 1) - q(start - 1);
+        }
+        if sum < needed {
+            let mut cut = start;
+            while cut < start + window && q(cut) >= threshold as usize {
+                cut += 1;
+            }
+            return cut;
+        }
+    }
+    quality.len()
+}
+
+/// BWA / cutadapt 3' trimming: cut where the running sum of
+/// `cutoff - quality` from the 3' end peaks.
+fn quality_3p(quality: &[u8], cutoff: u8) -> usize {
+    let mut sum = 0i64;
+    let mut best = 0i64;
+    let mut cut = quality.len();
+    for (i, &q) in quality.iter().enumerate().rev() {
+        sum += cutoff as i64 - q.saturating_sub(33) as i64;
+        if sum < 0 {
+            break;
+        }
+        if sum > best {
+            best = sum;
+            cut = i;
+        }
+    }
+    cut
+}
+
+/// The known adapter found (exactly) in most of the first reads of
+/// `path`; `None` when none occurs.
+pub(crate) fn detect_adapter(path: &str) -> Result<Option<&'static str>, String> {
+    let mut reader = SeqReader::open(path)?;
+    let mut counts = [0u64; KNOWN_ADAPTERS.len()];
+    for _ in 0..DETECT_READS {
+        let Some(rec) = reader.next_record()? else {
+            break;
+        };
+        let seq = rec.sequence.to_ascii_uppercase();
+        for (count, adapter) in counts.iter_mut().zip(KNOWN_ADAPTERS) {
+            *count += seq.windows(adapter.len()).any(|w| w == adapter.as_bytes()) as u64;
+        }
+    }
+    let best = (0..counts.len()).max_by_key(|&i| counts[i]);
+    Ok(best.filter(|&i| counts[i] > 0).map(|i| KNOWN_ADAPTERS[i]))
+}
+
+/// Trim every read of `input` into FASTQ `output`. With no adapters given
+/// and `detect` set, the adapter is chosen by [`detect_adapter`]. Returns
+/// the report and the adapters used.
+pub(crate) fn trim_file(
+    input: &str,
+    output: &str,
+    mut opts: TrimOptions,
+    detect: bool,
+    bgzf: bool,
+) -> Result<(TrimReport, Vec<String>), String> {
+    if detect && opts.adapters.is_empty() {
+        if let Some(adapter) = detect_adapter(input)? {
+            opts.adapters.push(adapter.as_bytes().to_vec());
+        }
+    }
+    let adapters = opts
+        .adapters
+        .iter()
+        .map(|a| String::from_utf8_lossy(a).to_ascii_uppercase())
+        .collect();
+    let mut trimmer = Trimmer::new(opts);
+    let mut reader = SeqReader::open(input)?;
+    let mut out = OutputFile::create(output, bgzf)?;
+    while let Some(rec) = reader.next_record()? {
+        if rec.quality.is_empty() {
+            return Err(format!("{input}: record {} has no qualities", rec.name));
+        }
+        if let Some(rec) = trimmer.trim(rec) {
+            write_fastq(&mut out, &rec)?;
+        }
+    }
+    out.finish()?;
+    Ok((trimmer.report, adapters))
+}
+
+fn write_fastq(out: &mut OutputFile, rec: &SeqRecord) -> Result<(), String> {
+    if rec.description.is_empty() {
+        out.line(&format!("@{}", rec.name))?;
+    } else {
+        out.line(&format!("@{} {}", rec.name, rec.description))?;
+    }
+    out.line(&String::from_utf8_lossy(&rec.sequence))?;
+    out.line("+")?;
+    out.line(&String::from_utf8_lossy(&rec.quality))
+}
@@ -0,0 +1,627 @@
+//! Streaming VCF reader with header-typed INFO/FORMAT fields and genotypes.
+//!
+//! Records retain every column as text (so they can be written back
+//! unchanged) and decode INFO/FORMAT values on demand using the
+//! `##INFO`/`##FORMAT` definitions.
+
+use crate::reader::open_input;
+use std::collections::HashMap;
+use std::io::BufRead;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ValueType {
+    Integer,
+    Float,
+    Flag,
+    Character,
+    String,
+}
+
+impl ValueType {
+    fn parse(s: &str) -> Self {
+        match s {
+            "Integer" => Self::Integer,
+            "Float" => Self::Float,
+            "Flag" => Self::Flag,
+            "Character" => Self::Character,
+            _ => Self::String,
+        }
+    }
+
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Integer => "Integer",
+            Self::Float => "Float",
+            Self::Flag => "Flag",
+            Self::Character => "Character",
+            Self::String => "String",
+        }
+    }
+}
+
+/// One `##INFO` or `##FORMAT` definition.
+#[derive(Debug, Clone)]
+pub(crate) struct FieldDef {
+    pub id: String,
+    /// `Number` as written: an integer, `A`, `R`, `G` or `.`.
+    pub number: String,
+    pub value_type: ValueType,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct VcfHeader {
+    /// All `##` lines, verbatim and in order (without the leading `##`).
+    pub meta: Vec<String>,
+    pub info: HashMap<String, FieldDef>,
+    pub format: HashMap<String, FieldDef>,
+    pub samples: Vec<String>,
+}
+
+impl VcfHeader {
+    /// Build a header from `##` lines (without the `##`) and sample names.
+    pub(crate) fn from_meta(meta: &[String], samples: Vec<String>) -> Self {
+        let mut header = Self {
+            samples,
+            ..Self::default()
+        };
+        for line in meta {
+            header.add_meta(line.strip_prefix("##").unwrap_or(line));
+        }
+        header
+    }
+
+    /// Header lines as written to a file, ending with `#CHROM`. A missing
+    /// `fileformat` line is added as VCFv4.2.
+    pub(crate) fn lines(&self) -> Vec<String> {
+        let mut out = Vec::with_capacity(self.meta.len() + 2);
+        if self.fileformat().is_none() {
+            out.push("##fileformat=VCFv4.2".to_string());
+        }
+        out.extend(self.meta.iter().map(|m| format!("##{m}")));
+        let mut chrom = String::from("#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO");
+        if !self.samples.is_empty() {
+            chrom.push_str("\tFORMAT");
+            for s in &self.samples {
+                chrom.push('\t');
+                chrom.push_str(s);
+            }
+        }
+        out.push(chrom);
+        out
+    }
+
+    pub(crate) fn fileformat(&self) -> Option<&str> {
+        self.meta.iter().find_map(|m| m.strip_prefix("fileformat="))
+    }
+
+    /// IDs of structured lines with the given key (e.g. `contig`, `FILTER`).
+    pub(crate) fn ids(&self, key: &str) -> Vec<String> {
+        self.meta
+            .iter()
+            .filter_map(|m| m.strip_prefix(key)?.strip_prefix("=<"))
+            .filter_map(|body| structured_fields(body).remove("ID"))
+            .collect()
+    }
+
+    /// Definitions in header order.
+    pub(crate) fn definitions(&self, key: &str) -> Vec<&FieldDef> {
+        let defs = if key == "INFO" { &self.info } else { &self.format };
+        self.ids(key).iter().filter_map(|id| defs.get(id)).collect()
+    }
+
+    fn add_meta(&mut self, line: &str) {
+        for (key, defs) in [("INFO=<", &mut self.info), ("FORMAT=<", &mut self.format)] {
+            if let Some(body) = line.strip_prefix(key) {
+                let mut f = structured_fields(body);
+                if let Some(id) = f.remove("ID") {
+                    defs.insert(
+                        id.clone(),
+                        FieldDef {
+                            id,
+                            number: f.remove("Number").unwrap_or_else(|| ".".into()),
+                            value_type: ValueType::parse(f.get("Type").map_or("", |s| s)),
+                            description: f.remove("Description").unwrap_or_default(),
+                        },
+                    );
+                }
+            }
+        }
+        self.meta.push(line.to_string());
+    }
+
+    /// `(type, scalar)` for a field; undeclared fields are strings, or
+    /// flags for INFO keys without a value.
+    fn field_type(&self, key: &str, is_info: bool, has_value: bool) -> (ValueType, bool) {
+        let defs = if is_info { &self.info } else { &self.format };
+        match defs.get(key) {
+            Some(d) => (d.value_type, d.number == "1" || d.number == "0"),
+            None if is_info && !has_value => (ValueType::Flag, true),
+            None => (ValueType::String, false),
+        }
+    }
+}
+
+/// Split `ID=DP,Number=1,Description="a, b"` (the part after `<`).
+fn structured_fields(body: &str) -> HashMap<String, String> {
+    let body = body.strip_suffix('>').unwrap_or(body);
+    let mut out = HashMap::new();
+    let mut key = String::new();
+    let mut value = String::new();
+    let mut in_key = true;
+    let mut quoted = false;
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => quoted = !quoted,
+            '\\' if quoted => {
+                if let Some(n) = chars.next() {
+                    value.push(n);
+                }
+            }
+            '=' if in_key => in_key = false,
+            ',' if !quoted => {
+                out.insert(std::mem::take(&mut key), std::mem::take(&mut value));
+                in_key = true;
+            }
+            _ if in_key => key.push(c),
+            _ => value.push(c),
+        }
+    }
+    if !key.is_empty() {
+        out.insert(key, value);
+    }
+    out
+}
+
+/// A decoded INFO or FORMAT value. List entries of `.` are `None`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum VcfValue {
+    Flag,
+    Integer(Vec<Option<i64>>),
+    Float(Vec<Option<f64>>),
+    String(Vec<Option<String>>),
+}
+
+/// Typed value plus whether the header declares it single-valued.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TypedValue {
+    pub value: VcfValue,
+    pub scalar: bool,
+}
+
+fn decode_value(raw: Option<&str>, ty: ValueType, scalar: bool) -> TypedValue {
+    let items = || raw.unwrap_or("").split(',').map(|v| (v != ".").then_some(v));
+    let strings = || VcfValue::String(items().map(|v| v.map(str::to_string)).collect());
+    let value = match ty {
+        ValueType::Flag => VcfValue::Flag,
+        ValueType::Integer => items()
+            .map(|v| v.map(str::parse::<i64>).transpose())
+            .collect::<Result<_, _>>()
+            .map(VcfValue::Integer)
+            .unwrap_or_else(|_| strings()),
+        ValueType::Float => items()
+            .map(|v| v.map(str::parse::<f64>).transpose())
+            .collect::<Result<_, _>>()
+            .map(VcfValue::Float)
+            .unwrap_or_else(|_| strings()),
+        ValueType::Character | ValueType::String => strings(),
+    };
+    TypedValue { value, scalar }
+}
+
+/// Parsed `GT` value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Genotype {
+    /// Allele indices; `None` for a missing call (`.`). Phasing is not
+    /// kept; the raw `GT` string carries it.
+    pub alleles: Vec<Option<u32>>,
+}
+
+impl Genotype {
+    pub(crate) fn parse(s: &str) -> Option<Self> {
+        if s.is_empty() {
+            return None;
+        }
+        let alleles = s
+            .split(['/', '|'])
+            .map(|a| if a == "." { Ok(None) } else { a.parse().map(Some) })
+            .collect::<Result<Vec<_>, _>>()
+            .ok()?;
+        Some(Self { alleles })
+    }
+
+    pub(crate) fn is_called(&self) -> bool {
+        !self.alleles.is_empty() && self.alleles.iter().all(Option::is_some)
+    }
+
+    pub(crate) fn is_het(&self) -> bool {
+        self.is_called() && self.alleles.windows(2).any(|w| w[0] != w[1])
+    }
+
+    pub(crate) fn is_hom_alt(&self) -> bool {
+        self.is_called()
+            && !self.is_het()
+            && self.alleles.first().copied().flatten().unwrap_or(0) > 0
+    }
+}
+
+/// One data line. Columns are kept as text; see [`VcfRecord::info_typed`]
+/// and [`VcfRecord::sample_typed`] for decoded values.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct VcfRecord {
+    pub chrom: String,
+    /// 1-based position.
+    pub pos: u64,
+    pub ids: Vec<String>,
+    pub ref_allele: String,
+    pub alt_alleles: Vec<String>,
+    pub qual: Option<f64>,
+    pub filters: Vec<String>,
+    /// `(key, value)` in file order; flags have no value.
+    pub info: Vec<(String, Option<String>)>,
+    pub format: Vec<String>,
+    /// Per-sample values aligned with `format` (trailing fields may be absent).
+    pub samples: Vec<Vec<String>>,
+}
+
+impl VcfRecord {
+    pub(crate) fn parse(line: &str, n_samples: usize) -> Result<Self, String> {
+        let cols: Vec<&str> = line.split('\t').collect();
+        if cols.len() < 8 {
+            return Err(format!("expected at least 8 columns, found {}", cols.len()));
+        }
+        let list = |s: &str, sep: char| -> Vec<String> {
+            if s == "." || s.is_empty() {
+                Vec::new()
+            } else {
+                s.split(sep).map(str::to_string).collect()
+            }
+        };
+        let pos = cols[1]
+            .parse()
+            .map_err(|_| format!("invalid POS {:?}", cols[1]))?;
+        let qual = match cols[5] {
+            "." => None,
+            q => Some(q.parse().map_err(|_| format!("invalid QUAL {q:?}"))?),
+        };
+        let info = list(cols[7], ';')
+            .into_iter()
+            .map(|kv| match kv.split_once('=') {
+                Some((k, v)) => (k.to_string(), Some(v.to_string())),
+                None => (kv, None),
+            })
+            .collect();
+        let format = cols.get(8).map_or_else(Vec::new, |f| list(f, ':'));
+        let samples: Vec<Vec<String>> = cols
+            .iter()
+            .skip(9)
+            .map(|s| s.split(':').map(str::to_string).collect())
+            .collect();
+        if !format.is_empty() && samples.len() != n_samples {
+            return Err(format!(
+                "expected {n_samples} sample columns, found {}",
+                samples.len()
+            ));
+        }
+        Ok(Self {
+            chrom: cols[0].to_string(),
+            pos,
+            ids: list(cols[2], ';'),
+            ref_allele: cols[3].to_string(),
+            alt_alleles: list(cols[4], ','),
+            qual,
+            filters: list(cols[6], ';'),
+            info,
+            format,
+            samples,
+        })
+    }
+
+    /// The record as a tab-separated data line (without newline) under a
+    /// header with `n_samples` samples. A record without FORMAT gets `GT`
+    /// and samples it leaves out are written as `.`, so every line has the
+    /// header's column count.
+    pub(crate) fn to_line(&self, n_samples: usize) -> String {
+        let list = |v: &[String], sep: &str| {
+            if v.is_empty() {
+                ".".to_string()
+            } else {
+                v.join(sep)
+            }
+        };
+        let info: Vec<String> = self
+            .info
+            .iter()
+            .map(|(k, v)| match v {
+                Some(v) => format!("{k}={v}"),
+                None => k.clone(),
+            })
+            .collect();
+        let mut cols = vec![
+            self.chrom.clone(),
+            self.pos.to_string(),
+            list(&self.ids, ";"),
+            self.ref_allele.clone(),
+            list(&self.alt_alleles, ","),
+            self.qual.map_or_else(|| ".".into(), |q| q.to_string()),
+            list(&self.filters, ";"),
+            list(&info, ";"),
+        ];
+        if n_samples > 0 {
+            cols.push(if self.format.is_empty() {
+                "GT".to_string()
+            } else {
+                self.format.join(":")
+            });
+            cols.extend((0..n_samples).map(|i| {
+                self.samples
+                    .get(i)
+                    .filter(|_| !self.format.is_empty())
+                    .map_or_else(|| ".".to_string(), |s| list(s, ":"))
+            }));
+        }
+        cols.join("\t")
+    }
+
+    /// INFO fields decoded according to the header, in file order.
+    pub(crate) fn info_typed(&self, header: &VcfHeader) -> Vec<(String, TypedValue)> {
+        self.info
+            .iter()
+            .map(|(k, v)| {
+                let (ty, scalar) = header.field_type(k, true, v.is_some());
+                (k.clone(), decode_value(v.as_deref(), ty, scalar))
+            })
+            .collect()
+    }
+
+    /// Raw FORMAT value for one sample, `None` if absent or `.`.
+    pub(crate) fn sample_raw(&self, sample: usize, key: &str) -> Option<&str> {
+        let i = self.format.iter().position(|f| f == key)?;
+        let v = self.samples.get(sample)?.get(i)?;
+        (v != ".").then_some(v.as_str())
+    }
+
+    /// FORMAT fields of one sample decoded according to the header.
+    pub(crate) fn sample_typed(&self, header: &VcfHeader, sample: usize) -> Vec<(String, TypedValue)> {
+        self.format
+            .iter()
+            .filter_map(|k| {
+                let raw = self.sample_raw(sample, k)?;
+                let (ty, scalar) = header.field_type(k, false, true);
+                Some((k.clone(), decode_value(Some(raw), ty, scalar)))
+            })
+            .collect()
+    }
+
+    pub(crate) fn genotype(&self, sample: usize) -> Option<Genotype> {
+        self.sample_raw(sample, "GT").and_then(Genotype::parse)
+    }
+
+    /// Scalar integer FORMAT value (e.g. `DP`, `GQ`) for one sample.
+    pub(crate) fn sample_int(&self, sample: usize, key: &str) -> Option<i64> {
+        self.sample_raw(sample, key)?.parse().ok()
+    }
+
+    /// Integer list FORMAT value (e.g. `AD`) for one sample.
+    pub(crate) fn sample_ints(&self, sample: usize, key: &str) -> Vec<Option<i64>> {
+        self.sample_raw(sample, key)
+            .map(|v| v.split(',').map(|x| x.parse().ok()).collect())
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn is_symbolic(allele: &str) -> bool {
+        allele.starts_with('<') || allele == "*" || allele.contains(['[', ']'])
+    }
+
+    /// Alternate allele frequency from called genotypes, falling back to
+    /// the sum of INFO/AF for sites-only files.
+    pub(crate) fn alt_allele_frequency(&self) -> Option<f64> {
+        let mut called = 0u64;
+        let mut alt = 0u64;
+        for i in 0..self.samples.len() {
+            if let Some(gt) = self.genotype(i) {
+                for a in gt.alleles.iter().flatten() {
+                    called += 1;
+                    if *a > 0 {
+                        alt += 1;
+                    }
+                }
+            }
+        }
+        if called > 0 {
+            return Some(alt as f64 / called as f64);
+        }
+        let af = self.info.iter().find(|(k, _)| k == "AF")?.1.as_deref()?;
+        af.split(',')
+            .map(|v| v.parse::<f64>().ok())
+            .sum::<Option<f64>>()
+    }
+}
+
+pub(crate) struct VcfReader {
+    input: Box<dyn BufRead + Send>,
+    header: VcfHeader,
+    line: String,
+    line_no: u64,
+}
+
+impl VcfReader {
+    /// Open a plain, gzip or bgzip VCF and read its header.
+    pub(crate) fn open(path: &str) -> Result<Self, String> {
+        let mut reader = Self {
+            input: open_input(path)?,
+            header: VcfHeader::default(),
+            line: String::new(),
+            line_no: 0,
+        };
+        loop {
+            if !reader.read_line()? {
+                return Err(format!("{path}: missing #CHROM header line"));
+            }
+            let line = reader.line.trim_end_matches(['\n', '\r']);
+            if let Some(meta) = line.strip_prefix("##") {
+                let meta = meta.to_string();
+                reader.header.add_meta(&meta);
+            } else if let Some(cols) = line.strip_prefix("#CHROM") {
+                reader.header.samples = cols.split('\t').skip(9).map(str::to_string).collect();
+                break;
+            } else {
+                return Err(format!("{path}: missing #CHROM header line"));
+            }
+        }
+        Ok(reader)
+    }
+
+    fn read_line(&mut self) -> Result<bool, String> {
+        self.line.clear();
+        let n = self
+            .input
+            .read_line(&mut self.line)
+            .map_err(|e| e.to_string())?;
+        self.line_no += 1;
+        Ok(n > 0)
+    }
+
+    pub(crate) fn header(&self) -> &VcfHeader {
+        &self.header
+    }
+
+    pub(crate) fn next_record(&mut self) -> Result<Option<VcfRecord>, String> {
+        loop {
+            if !self.read_line()? {
+                return Ok(None);
+            }
+            let line = self.line.trim_end_matches(['\n', '\r']);
+            if line.is_empty() {
+                continue;
+            }
+            return VcfRecord::parse(line, self.header.samples.len())
+                .map(Some)
+                .map_err(|e| format!("line {}: {e}", self.line_no));
+        }
+    }
+}
+
+/// Genotype and allele frequency summaries over a whole VCF. Site counts
+/// come from `cyanea_io::vcf_stats`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct VcfSummary {
+    pub variant_count: u64,
+    pub samples: Vec<String>,
+    pub called: Vec<u64>,
+    pub het: Vec<u64>,
+    pub hom_alt: Vec<u64>,
+    pub af_sum: f64,
+    pub af_sites: u64,
+    /// Alternate allele frequency in ten equal-width bins over `[0, 1]`.
+    pub af_spectrum: Vec<u64>,
+}
+
+impl VcfSummary {
+    pub(crate) fn from_path(path: &str) -> Result<Self, String> {
+        let mut reader = VcfReader::open(path)?;
+        let n = reader.header().samples.len();
+        let mut s = Self {
+            samples: reader.header().samples.clone(),
+            called: vec![0; n],
+            het: vec![0; n],
+            hom_alt: vec![0; n],
+            af_spectrum: vec![0; 10],
+            ..Self::default()
+        };
+        while let Some(rec) = reader.next_record()? {
+            s.variant_count += 1;
+            for i in 0..n {
+                if let Some(gt) = rec.genotype(i).filter(Genotype::is_called) {
+                    s.called[i] += 1;
+                    s.het[i] += gt.is_het() as u64;
+                    s.hom_alt[i] += gt.is_hom_alt() as u64;
+                }
+            }
+            if let Some(af) = rec.alt_allele_frequency() {
+                s.af_sum += af;
+                s.af_sites += 1;
+                s.af_spectrum[((af * 10.0) as usize).min(9)] += 1;
+            }
+        }
+        Ok(s)
+    }
+
+    /// Fraction of sites with a fully called genotype, per sample.
+    pub(crate) fn call_rate(&self) -> Vec<f64> {
+        self.called
+            .iter()
+            .map(|&c| {
+                if self.variant_count == 0 {
+                    0.0
+                } else {
+                    c as f64 / self.variant_count as f64
+                }
+            })
+            .collect()
+    }
+
+    /// Heterozygous / homozygous-alternate ratio per sample (`None` when
+    /// the sample has no hom-alt calls).
+    pub(crate) fn het_hom_ratio(&self) -> Vec<Option<f64>> {
+        self.het
+            .iter()
+            .zip(&self.hom_alt)
+            .map(|(&h, &a)| (a > 0).then(|| h as f64 / a as f64))
+            .collect()
+    }
+
+    pub(crate) fn mean_allele_frequency(&self) -> Option<f64> {
+        (self.af_sites > 0).then(|| self.af_sum / self.af_sites as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn multi_sample_round_trip() {
+        let header = VcfHeader::from_meta(
+            &[
+                "##FORMAT=<ID=GT,Number=1,Type=String,Description=\"Genotype\">".to_string(),
+                "##FORMAT=<ID=DP,Number=1,Type=Integer,Description=\"Depth\">".to_string(),
+            ],
+            vec!["s1".into(), "s2".into(), "s3".into()],
+        );
+        let typed = VcfRecord::parse(
+            "chr1\t10\trs1\tA\tG\t50\tPASS\tDP=9\tGT:DP\t0/1:3\t1/1:4\t./.:.",
+            3,
+        )
+        .unwrap();
+        let bare = VcfRecord {
+            chrom: "chr1".into(),
+            pos: 20,
+            ref_allele: "C".into(),
+            alt_alleles: vec!["T".into()],
+            ..VcfRecord::default()
+        };
+        let path = std::env::temp_dir().join(format!("cyanea_vcf_{}.vcf", std::process::id()));
+        let mut text = header.lines().join("\n");
+        for rec in [&typed, &bare] {
+            text.push('\n');
+            text.push_str(&rec.to_line(header.samples.len()));
+        }
+        text.push('\n');
+        std::fs::write(&path, text).unwrap();
+
+        let mut reader = VcfReader::open(path.to_str().unwrap()).unwrap();
+        assert_eq!(reader.header().samples, ["s1", "s2", "s3"]);
+        let first = reader.next_record().unwrap().unwrap();
+        assert_eq!(first.format, ["GT", "DP"]);
+        assert_eq!(first.samples, typed.samples);
+        assert_eq!(first.sample_raw(1, "GT"), Some("1/1"));
+        assert_eq!(first.sample_raw(2, "DP"), None);
+        let second = reader.next_record().unwrap().unwrap();
+        assert_eq!(second.format, ["GT"]);
+        assert_eq!(second.samples, vec![vec![".".to_string()]; 3]);
+        assert!(reader.next_record().unwrap().is_none());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,255 @@
+//! Text output for BED, GFF3 and VCF writers, optionally BGZF-compressed.
+//!
+//! BGZF output is a valid gzip stream and can be indexed with tabix.
+
+use crate::bgzf::BgzfWriter;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+/// A plain or BGZF output file. Call [`OutputFile::finish`] so the BGZF
+/// end-of-file marker is written.
+pub(crate) enum OutputFile {
+    Plain(BufWriter<File>),
+    Bgzf(BgzfWriter<BufWriter<File>>),
+}
+
+impl OutputFile {
+    pub(crate) fn create(path: &str, bgzf: bool) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e| format!("{path}: {e}"))?;
+        let inner = BufWriter::with_capacity(1 << 16, file);
+        Ok(if bgzf {
+            Self::Bgzf(BgzfWriter::new(inner))
+        } else {
+            Self::Plain(inner)
+        })
+    }
+
+    /// Write one line followed by `\n`.
+    pub(crate) fn line(&mut self, line: &str) -> Result<(), String> {
+        self.write_all(line.as_bytes())
+            .and_then(|_| self.write_all(b"\n"))
+            .map_err(|e| e.to_string())
+    }
+
+    pub(crate) fn finish(self) -> Result<(), String> {
+        match self {
+            Self::Plain(mut w) => w.flush(),
+            Self::Bgzf(w) => w.finish().map(drop),
+        }
+        .map_err(|e| e.to_string())
+    }
+}
+
+impl Write for OutputFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(w) => w.write(buf),
+            Self::Bgzf(w) => w.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Plain(w) => w.flush(),
+            Self::Bgzf(w) => w.flush(),
+        }
+    }
+}
+
+/// Map the strand spellings used across the NIFs (`+`, `Forward`, ...)
+/// to `+`, `-` or `.`.
+pub(crate) fn strand_symbol(strand: &str) -> &'static str {
+    match strand {
+        "+" | "Forward" | "forward" | "Plus" => "+",
+        "-" | "Reverse" | "reverse" | "Minus" => "-",
+        _ => ".",
+    }
+}
+
+/// One BED feature; optional columns are filled with defaults when other
+/// features in the same file need more columns.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct BedLine {
+    pub chrom: String,
+    pub start: u64,
+    pub end: u64,
+    pub name: Option<String>,
+    pub score: Option<f64>,
+    pub strand: Option<String>,
+    pub thick_start: Option<u64>,
+    pub thick_end: Option<u64>,
+    pub item_rgb: Option<String>,
+    /// `(sizes, starts)` for BED12 blocks, starts relative to `start`.
+    pub blocks: Option<(Vec<u64>, Vec<u64>)>,
+}
+
+impl BedLine {
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        if self.start > self.end {
+            return Err(format!(
+                "{}:{}-{}: start is after end",
+                self.chrom, self.start, self.end
+            ));
+        }
+        if let Some((sizes, starts)) = &self.blocks {
+            if sizes.len() != starts.len() || sizes.is_empty() {
+                return Err(format!(
+                    "{}:{}-{}: block sizes and starts must be non-empty and equal in length",
+                    self.chrom, self.start, self.end
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Columns this feature needs to be written without loss (3–12).
+    pub(crate) fn columns(&self) -> usize {
+        if self.blocks.is_some() {
+            12
+        } else if self.item_rgb.is_some() {
+            9
+        } else if self.thick_start.is_some() || self.thick_end.is_some() {
+            8
+        } else if self.strand.as_deref().is_some_and(|s| strand_symbol(s) != ".") {
+            6
+        } else if self.score.is_some() {
+            5
+        } else if self.name.is_some() {
+            4
+        } else {
+            3
+        }
+    }
+
+    pub(crate) fn to_line(&self, columns: usize) -> String {
+        let mut f = vec![
+            self.chrom.clone(),
+            self.start.to_string(),
+            self.end.to_string(),
+        ];
+        if columns >= 4 {
+            f.push(self.name.clone().unwrap_or_else(|| ".".into()));
+        }
+        if columns >= 5 {
+            f.push(self.score.map_or_else(|| "0".into(), |s| s.to_string()));
+        }
+        if columns >= 6 {
+            f.push(strand_symbol(self.strand.as_deref().unwrap_or(".")).into());
+        }
+        if columns >= 7 {
+            f.push(self.thick_start.unwrap_or(self.start).to_string());
+        }
+        if columns >= 8 {
+            f.push(self.thick_end.unwrap_or(self.end).to_string());
+        }
+        if columns >= 9 {
+            f.push(self.item_rgb.clone().unwrap_or_else(|| "0".into()));
+        }
+        if columns >= 10 {
+            let (sizes, starts) = self
+                .blocks
+                .clone()
+                .unwrap_or_else(|| (vec![self.end - self.start], vec![0]));
+            let join = |v: &[u64]| v.iter().map(|x| format!("{x},")).collect::<String>();
+            f.push(sizes.len().to_string());
+            f.push(join(&sizes));
+            f.push(join(&starts));
+        }
+        f.join("\t")
+    }
+}
+
+/// One GFF3 feature line. `start`/`end` are 1-based and inclusive.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct GffLine {
+    pub seqid: String,
+    pub source: String,
+    pub feature_type: String,
+    pub start: u64,
+    pub end: u64,
+    pub score: Option<f64>,
+    pub strand: String,
+    pub phase: Option<u8>,
+    /// Tag/value pairs in output order. Commas in values separate
+    /// multiple values and are kept as-is.
+    pub attributes: Vec<(String, String)>,
+}
+
+impl GffLine {
+    pub(crate) fn to_line(&self) -> Result<String, String> {
+        if self.start == 0 || self.start > self.end {
+            return Err(format!(
+                "{}:{}-{}: GFF3 coordinates must be 1-based with start <= end",
+                self.seqid, self.start, self.end
+            ));
+        }
+        if matches!(self.phase, Some(p) if p > 2) {
+            return Err(format!("{}:{}: phase must be 0, 1 or 2", self.seqid, self.start));
+        }
+        let or_dot = |s: &str| if s.is_empty() { ".".to_string() } else { gff_escape(s, false) };
+        let attributes = if self.attributes.is_empty() {
+            ".".to_string()
+        } else {
+            self.attributes
+                .iter()
+                .map(|(k, v)| format!("{}={}", gff_escape(k, false), gff_escape(v, true)))
+                .collect::<Vec<_>>()
+                .join(";")
+        };
+        Ok([
+            or_dot(&self.seqid),
+            or_dot(&self.source),
+            or_dot(&self.feature_type),
+            self.start.to_string(),
+            self.end.to_string(),
+            self.score.map_or_else(|| ".".into(), |s| s.to_string()),
+            strand_symbol(&self.strand).to_string(),
+            self.phase.map_or_else(|| ".".into(), |p| p.to_string()),
+            attributes,
+        ]
+        .join("\t"))
+    }
+}
+
+/// Percent-encode the characters GFF3 reserves in columns and attributes.
+fn gff_escape(s: &str, keep_commas: bool) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            ',' if keep_commas => out.push(c),
+            ';' | '=' | '&' | ',' | '%' | '\t' | '\n' | '\r' => {
+                out.push_str(&format!("%{:02X}", c as u32))
+            }
+            c if c.is_control() => out.push_str(&format!("%{:02X}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Decode `%XX` escapes written by [`gff_escape`] (or any GFF3 writer);
+/// malformed escapes are kept as they are.
+pub(crate) fn gff_unescape(s: &str) -> String {
+    if !s.contains('%') {
+        return s.to_string();
+    }
+    let b = s.as_bytes();
+    let mut out = Vec::with_capacity(b.len());
+    let mut i = 0;
+    while i < b.len() {
+        let hex = b
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok());
+        match (b[i], hex.and_then(|h| u8::from_str_radix(h, 16).ok())) {
+            (b'%', Some(v)) => {
+                out.push(v);
+                i += 3;
+            }
+            (c, _) => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
@@ -63,6 +63,200 @@ defmodule Cyanea.AlignTest do
     test "defaults to global mode and blosum62" do
       assert {:error, :nif_not_loaded} = Align.protein("MVLK", "MVLK")
     end
+
+    test "accepts gap penalties" do
+      assert {:error, :nif_not_loaded} =
+               Align.protein("MVLK", "MVLK", matrix: :blosum62, gap_open: -10, gap_extend: -2)
+    end
+
+    test "accepts the extended matrix series" do
+      for matrix <- [:blosum50, :blosum90, :pam30, :pam70, "pam70"] do
+        assert {:error, :nif_not_loaded} = Align.protein("MVLK", "MVLK", matrix: matrix)
+      end
+    end
+
+    test "accepts a matrix struct" do
+      matrix = %Cyanea.Native.SubstitutionMatrix{name: "custom", gap_open: -11, gap_extend: -1}
+      assert {:error, :nif_not_loaded} = Align.protein("MVLK", "MVLK", matrix: matrix)
+    end
+
+    test "rejects unknown matrix atom" do
+      assert_raise FunctionClauseError, fn -> Align.protein("MVLK", "MVLK", matrix: :vtml200) end
+    end
+  end
+
+  # ===========================================================================
+  # Substitution matrices
+  # ===========================================================================
+
+  describe "with_matrix/4" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Align.with_matrix("ACGT", "ACGR", :blosum62)
+    end
+
+    test "accepts mode and gap options" do
+      matrix = %Cyanea.Native.SubstitutionMatrix{name: "nuc"}
+
+      assert {:error, :nif_not_loaded} =
+               Align.with_matrix("ACGT", "ACGR", matrix, mode: :local, gap_open: -8, gap_extend: -2)
+    end
+
+    test "rejects non-binary query" do
+      assert_raise FunctionClauseError, fn -> Align.with_matrix(123, "ACGT", :blosum62) end
+    end
+  end
+
+  describe "matrices/0" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Align.matrices()
+    end
+  end
+
+  describe "matrix/1" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Align.matrix(:pam30)
+    end
+
+    test "returns a matrix struct unchanged" do
+      matrix = %Cyanea.Native.SubstitutionMatrix{name: "custom"}
+      assert {:ok, ^matrix} = Align.matrix(matrix)
+    end
+  end
+
+  describe "load_matrix/1" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Align.load_matrix("/tmp/VTML200")
+    end
+
+    test "rejects non-binary path" do
+      assert_raise FunctionClauseError, fn -> Align.load_matrix(:vtml200) end
+    end
+  end
+
+  describe "parse_matrix/2" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Align.parse_matrix("   A  C\nA  1 -1\nC -1  1\n", "ac")
+    end
+  end
+
+  describe "nucleotide_matrix/1" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Align.nucleotide_matrix()
+    end
+
+    test "accepts transition and transversion scores" do
+      assert {:error, :nif_not_loaded} =
+               Align.nucleotide_matrix(match: 2, transition: -1, transversion: -3)
+    end
+  end
+
+  # ===========================================================================
+  # Alignment output
+  # ===========================================================================
+
+  @result %Cyanea.Native.AlignmentResult{
+    score: 12,
+    aligned_query: "ACG-T",
+    aligned_target: "ACGAT",
+    query_start: 0,
+    query_end: 4,
+    target_start: 0,
+    target_end: 5
+  }
+
+  describe "summary/2" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Align.summary(@result)
+    end
+
+    test "resolves the matrix option" do
+      assert {:error, :nif_not_loaded} = Align.summary(@result, matrix: :blosum62)
+    end
+
+    test "rejects non-result" do
+      assert_raise FunctionClauseError, fn -> Align.summary(%{score: 1}) end
+    end
+  end
+
+  describe "format/2" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} =
+               Align.format(@result, query_name: "read", target_name: "chr1", width: 80)
+    end
+
+    test "rejects non-result" do
+      assert_raise FunctionClauseError, fn -> Align.format("ACGT") end
+    end
+  end
+
+  describe "to_sam/3" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Align.to_sam(@result, "ACGT", query_name: "read")
+    end
+
+    test "rejects non-binary query" do
+      assert_raise FunctionClauseError, fn -> Align.to_sam(@result, 123) end
+    end
+  end
+
+  describe "to_paf/2" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Align.to_paf(@result, query_length: 10, target_length: 100)
+    end
+  end
+
+  # ===========================================================================
+  # Alignment significance
+  # ===========================================================================
+
+  describe "karlin_altschul/2" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Align.karlin_altschul()
+    end
+
+    test "accepts ungapped and custom gap options" do
+      assert {:error, :nif_not_loaded} = Align.karlin_altschul(:pam30, gapped: false)
+      assert {:error, :nif_not_loaded} = Align.karlin_altschul(:blosum62, gap_open: -10, gap_extend: -2)
+    end
+
+    test "accepts dna scoring" do
+      assert {:error, :nif_not_loaded} = Align.karlin_altschul(:dna, match: 1, mismatch: -3)
+    end
+  end
+
+  describe "significance/3" do
+    @params %Cyanea.Native.KarlinAltschul{lambda: 0.267, k: 0.041, h: 0.14, gapped: true}
+
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} =
+               Align.significance(60, @params, query_length: 300, database_size: 1_000_000)
+    end
+
+    test "accepts an alignment result" do
+      assert {:error, :nif_not_loaded} =
+               Align.significance(@result, @params, query_length: 4, database_size: 100)
+    end
+
+    test "requires the search space" do
+      assert_raise KeyError, fn -> Align.significance(60, @params, query_length: 300) end
+    end
+  end
+
+  describe "shuffle_test/3" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Align.shuffle_test("MVLKAG", "MVLKSG")
+    end
+
+    test "accepts a matrix struct and shuffle options" do
+      matrix = %Cyanea.Native.SubstitutionMatrix{name: "nuc"}
+
+      assert {:error, :nif_not_loaded} =
+               Align.shuffle_test("ACGT", "ACGA", matrix: matrix, shuffles: 500, seed: 7)
+    end
+
+    test "rejects non-binary target" do
+      assert_raise FunctionClauseError, fn -> Align.shuffle_test("MVLK", 123) end
+    end
   end
 
   # ===========================================================================
@@ -146,4 +340,100 @@ defmodule Cyanea.AlignTest do
       assert_raise FunctionClauseError, fn -> Align.consensus("not_a_list") end
     end
   end
+
+  # ===========================================================================
+  # Read mapping
+  # ===========================================================================
+
+  describe "index_reference/1" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Align.index_reference("/tmp/ref.fa")
+    end
+
+    test "rejects non-binary path" do
+      assert_raise FunctionClauseError, fn -> Align.index_reference(:ref) end
+    end
+  end
+
+  describe "reference_sequences/1" do
+    test "rejects non-reference" do
+      assert_raise FunctionClauseError, fn -> Align.reference_sequences("/tmp/ref.fa") end
+    end
+  end
+
+  describe "map_reads/4" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Align.map_reads("/tmp/ref.fa", "/tmp/reads.fq", "/tmp/out.sam")
+    end
+
+    test "accepts seeding and scoring options" do
+      assert {:error, :nif_not_loaded} =
+               Align.map_reads("/tmp/ref.fa", "/tmp/reads.fq", "/tmp/out.sam",
+                 min_seed_len: 15,
+                 bandwidth: 32,
+                 mismatch: -3,
+                 min_score: 20
+               )
+    end
+
+    test "rejects non-binary reads path" do
+      assert_raise FunctionClauseError, fn -> Align.map_reads("/tmp/ref.fa", 123, "/tmp/out.sam") end
+    end
+  end
+
+  describe "map/3" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Align.map("/tmp/ref.fa", [])
+    end
+
+    test "rejects non-list records" do
+      assert_raise FunctionClauseError, fn -> Align.map("/tmp/ref.fa", "ACGT") end
+    end
+  end
+
+  # ===========================================================================
+  # Database search
+  # ===========================================================================
+
+  describe "index_database/2" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Align.index_database("/tmp/db.fa")
+    end
+
+    test "accepts a sequence list and alphabet" do
+      sequences = [{"s1", "ACGTACGTACGT"}, {"s2", "TTGACCGTA"}]
+
+      assert {:error, :nif_not_loaded} =
+               Align.index_database(sequences, alphabet: :nucleotide, word_size: 7)
+    end
+
+    test "rejects non-binary path" do
+      assert_raise FunctionClauseError, fn -> Align.index_database(:db) end
+    end
+  end
+
+  describe "database_info/1" do
+    test "rejects non-reference" do
+      assert_raise FunctionClauseError, fn -> Align.database_info("/tmp/db.fa") end
+    end
+  end
+
+  describe "search/3" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Align.search("/tmp/db.fa", "MVLSPADKTNVKAAW")
+    end
+
+    test "accepts search options" do
+      assert {:error, :nif_not_loaded} =
+               Align.search([{"s1", "MVLSPADKTNVKAAW"}], "MVLSPADK",
+                 matrix: :blosum45,
+                 evalue: 1.0e-3,
+                 max_targets: 10
+               )
+    end
+
+    test "rejects non-binary query" do
+      assert_raise FunctionClauseError, fn -> Align.search("/tmp/db.fa", 123) end
+    end
+  end
 end
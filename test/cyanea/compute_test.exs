@@ -77,7 +77,7 @@ defmodule Cyanea.ComputeTest do
 
   describe "fm_index_count" do
     test "returns nif_not_loaded without NIF" do
-      assert {:error, :nif_not_loaded} = Compute.fm_index_count(<<0, 1, 2>>, "ATC")
+      assert {:error, :nif_not_loaded} = Compute.fm_index_count(make_ref(), "ATC")
     end
   end
 
@@ -912,12 +912,12 @@ defmodule Cyanea.ComputeTest do
       assert_raise FunctionClauseError, fn -> Compute.fm_index_build(123) end
     end
 
-    test "fm_index_count rejects non-binary index_data" do
-      assert_raise FunctionClauseError, fn -> Compute.fm_index_count(123, "ATC") end
+    test "fm_index_count rejects non-reference index" do
+      assert_raise FunctionClauseError, fn -> Compute.fm_index_count(<<0>>, "ATC") end
     end
 
     test "fm_index_count rejects non-binary pattern" do
-      assert_raise FunctionClauseError, fn -> Compute.fm_index_count(<<0>>, 123) end
+      assert_raise FunctionClauseError, fn -> Compute.fm_index_count(make_ref(), 123) end
     end
 
     test "find_orfs rejects non-binary seq" do
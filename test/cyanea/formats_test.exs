@@ -55,6 +55,16 @@ defmodule Cyanea.FormatsTest do
     end
   end
 
+  describe "vcf_header/1" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Formats.vcf_header("/tmp/test.vcf")
+    end
+
+    test "rejects non-binary" do
+      assert_raise FunctionClauseError, fn -> Formats.vcf_header(123) end
+    end
+  end
+
   # ===========================================================================
   # BED
   # ===========================================================================
@@ -156,4 +166,159 @@ defmodule Cyanea.FormatsTest do
       assert_raise FunctionClauseError, fn -> Formats.parse_bam(123) end
     end
   end
+
+  describe "query_bam/4" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Formats.query_bam("/tmp/test.bam", "chr1", 0, 1000)
+    end
+
+    test "rejects an empty or inverted region" do
+      assert_raise FunctionClauseError, fn -> Formats.query_bam("/tmp/test.bam", "chr1", 10, 10) end
+      assert_raise FunctionClauseError, fn -> Formats.query_bam("/tmp/test.bam", "chr1", -1, 10) end
+    end
+  end
+
+  describe "index_bam/2" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Formats.index_bam("/tmp/test.bam")
+      assert {:error, :nif_not_loaded} = Formats.index_bam("/tmp/test.bam", format: :csi)
+    end
+
+    test "rejects non-binary" do
+      assert_raise FunctionClauseError, fn -> Formats.index_bam(123) end
+    end
+  end
+
+  describe "tabix_query/2" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Formats.tabix_query("/tmp/test.vcf.gz", "chr1:1-1000")
+    end
+
+    test "rejects non-binary region" do
+      assert_raise FunctionClauseError, fn -> Formats.tabix_query("/tmp/test.vcf.gz", 1) end
+    end
+  end
+
+  describe "index_tabix/2" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Formats.index_tabix("/tmp/test.bed", preset: :bed)
+    end
+
+    test "rejects non-binary" do
+      assert_raise FunctionClauseError, fn -> Formats.index_tabix(123) end
+    end
+  end
+
+  describe "bam_depth/3" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} =
+               Formats.bam_depth("/tmp/test.bam", "/tmp/depth.bg.gz", bin_size: 1000, min_mapq: 20)
+    end
+
+    test "rejects non-binary output" do
+      assert_raise FunctionClauseError, fn -> Formats.bam_depth("/tmp/test.bam", nil) end
+    end
+  end
+
+  describe "target_depth/3" do
+    test "returns nif_not_loaded for interval targets" do
+      targets = [%{chrom: "chr1", start: 100, end: 200}]
+
+      assert {:error, :nif_not_loaded} =
+               Formats.target_depth("/tmp/test.bam", targets, thresholds: [1, 10, 30])
+    end
+
+    test "returns nif_not_loaded for a BED path" do
+      assert {:error, :nif_not_loaded} = Formats.target_depth("/tmp/test.bam", "/tmp/targets.bed")
+    end
+  end
+
+  describe "write_vcf/4" do
+    test "returns nif_not_loaded without NIF" do
+      record = %Cyanea.Native.VcfRecord{chrom: "chr1", position: 100, ref_allele: "A",
+                                         alt_alleles: ["G"], quality: 50}
+
+      assert {:error, :nif_not_loaded} =
+               Formats.write_vcf("/tmp/out.vcf.gz", %Cyanea.Native.VcfHeader{}, [record])
+    end
+
+    test "rejects a non-header" do
+      assert_raise FunctionClauseError, fn -> Formats.write_vcf("/tmp/out.vcf", %{}, []) end
+    end
+  end
+
+  describe "write_bed/3" do
+    test "accepts intervals and returns nif_not_loaded without NIF" do
+      features = [
+        %Cyanea.Native.GenomicInterval{chrom: "chr1", start: 0, end: 100, strand: "+"},
+        %{chrom: "chr1", start: 200, end: 300, name: "b", score: 5}
+      ]
+
+      assert {:error, :nif_not_loaded} = Formats.write_bed("/tmp/out.bed", features)
+    end
+
+    test "rejects a feature without coordinates" do
+      assert_raise FunctionClauseError, fn -> Formats.write_bed("/tmp/out.bed", [%{chrom: "chr1"}]) end
+    end
+  end
+
+  describe "write_gff3/3" do
+    test "accepts genes and features and returns nif_not_loaded without NIF" do
+      features = [
+        %Cyanea.Native.GffGene{id: "g1", symbol: "ABC", chrom: "chr1", start: 1, end: 500,
+                               strand: "Forward", gene_type: "protein_coding"},
+        %{seqid: "chr1", feature_type: "exon", start: 1, end: 100,
+          attributes: [ID: "e1", Parent: ["g1"]]}
+      ]
+
+      assert {:error, :nif_not_loaded} = Formats.write_gff3("/tmp/out.gff3", features, bgzf: true)
+    end
+
+    test "rejects non-binary path" do
+      assert_raise FunctionClauseError, fn -> Formats.write_gff3(123, []) end
+    end
+  end
+
+  describe "bigwig_values/4" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Formats.bigwig_values("/tmp/test.bw", "chr1", 0, 1000)
+    end
+
+    test "rejects an empty region" do
+      assert_raise FunctionClauseError, fn -> Formats.bigwig_values("/tmp/test.bw", "chr1", 10, 10) end
+    end
+  end
+
+  describe "bigwig_summary/6" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} =
+               Formats.bigwig_summary("/tmp/test.bw", "chr1", 0, 1_000_000, 100, exact: true)
+    end
+
+    test "rejects zero bins" do
+      assert_raise FunctionClauseError, fn ->
+        Formats.bigwig_summary("/tmp/test.bw", "chr1", 0, 1000, 0)
+      end
+    end
+  end
+
+  describe "write_bigwig/3" do
+    test "accepts maps with integer values and returns nif_not_loaded without NIF" do
+      records = [%{chrom: "chr1", start: 0, end: 100, value: 2}]
+
+      assert {:error, :nif_not_loaded} =
+               Formats.write_bigwig("/tmp/out.bw", records, %{"chr1" => 1000})
+    end
+
+    test "returns the error for a missing chrom sizes file" do
+      assert {:error, :enoent} = Formats.write_bigwig("/tmp/out.bw", [], "/nonexistent/chrom.sizes")
+    end
+  end
+
+  describe "bedgraph_to_bigwig/3" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} =
+               Formats.bedgraph_to_bigwig("/tmp/test.bedgraph", "/tmp/out.bw", [{"chr1", 1000}])
+    end
+  end
 end
@@ -307,6 +307,40 @@ defmodule Cyanea.MLTest do
     end
   end
 
+  describe "predict_forest_batch/3" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = ML.predict_forest_batch(<<0, 1, 2>>, [1.0, 2.0, 3.0, 4.0], 2)
+    end
+
+    test "rejects non-binary model" do
+      assert_raise FunctionClauseError, fn -> ML.predict_forest_batch(123, [1.0], 1) end
+    end
+
+    test "rejects non-list data" do
+      assert_raise FunctionClauseError, fn -> ML.predict_forest_batch(<<0>>, "not", 1) end
+    end
+  end
+
+  describe "predict_forest_proba/3" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = ML.predict_forest_proba(<<0, 1, 2>>, [1.0, 2.0], 2)
+    end
+
+    test "rejects non-binary model" do
+      assert_raise FunctionClauseError, fn -> ML.predict_forest_proba(123, [1.0], 1) end
+    end
+  end
+
+  describe "forest_info/1" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = ML.forest_info(<<0, 1, 2>>)
+    end
+
+    test "rejects non-binary model" do
+      assert_raise FunctionClauseError, fn -> ML.forest_info(123) end
+    end
+  end
+
   # ===========================================================================
   # HMM
   # ===========================================================================
@@ -127,6 +127,12 @@ defmodule Cyanea.NativeTest do
     end
   end
 
+  describe "kmer_spectrum/4" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.kmer_spectrum("/tmp/test.fastq", 21, 10_000, 10) end)
+    end
+  end
+
   describe "parse_fastq/1" do
     test "raises nif_not_loaded" do
       assert_nif_not_loaded(fn -> Native.parse_fastq("/tmp/test.fastq") end)
@@ -139,6 +145,104 @@ defmodule Cyanea.NativeTest do
     end
   end
 
+  describe "seq_reader_open/1" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.seq_reader_open("/tmp/test.fastq.gz") end)
+    end
+  end
+
+  describe "seq_reader_next_chunk/2" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.seq_reader_next_chunk(make_ref(), 1000) end)
+    end
+  end
+
+  describe "seq_reader_close/1" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.seq_reader_close(make_ref()) end)
+    end
+  end
+
+  describe "parse_fasta/1" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.parse_fasta("/tmp/test.fa") end)
+    end
+  end
+
+  describe "fasta_index/1" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.fasta_index("/tmp/test.fa") end)
+    end
+  end
+
+  describe "faidx_open/1" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.faidx_open("/tmp/test.fa") end)
+    end
+  end
+
+  describe "faidx_sequences/1" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.faidx_sequences(make_ref()) end)
+    end
+  end
+
+  describe "faidx_fetch/3" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.faidx_fetch(make_ref(), "chr1:1000-2000", true) end)
+    end
+  end
+
+  describe "faidx_close/1" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.faidx_close(make_ref()) end)
+    end
+  end
+
+  describe "paired_reader_open/2" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.paired_reader_open("/tmp/r1.fastq.gz", "/tmp/r2.fastq.gz") end)
+    end
+  end
+
+  describe "paired_reader_next_chunk/2" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.paired_reader_next_chunk(make_ref(), 1000) end)
+    end
+  end
+
+  describe "paired_reader_close/1" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.paired_reader_close(make_ref()) end)
+    end
+  end
+
+  describe "paired_fastq_stats/2" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.paired_fastq_stats("/tmp/interleaved.fastq", nil) end)
+    end
+  end
+
+  describe "trim_fastq/4" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn ->
+        Native.trim_fastq("/tmp/in.fastq", "/tmp/out.fastq", %Native.TrimOptions{}, false)
+      end)
+    end
+  end
+
+  describe "trim_reads/2" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.trim_reads([], %Native.TrimOptions{}) end)
+    end
+  end
+
+  describe "fastq_qc/2" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.fastq_qc("/tmp/test.fastq", nil) end)
+    end
+  end
+
   describe "protein_molecular_weight/1" do
     test "raises nif_not_loaded" do
       assert_nif_not_loaded(fn -> Native.protein_molecular_weight("MVLK") end)
@@ -159,6 +263,24 @@ defmodule Cyanea.NativeTest do
     end
   end
 
+  describe "horspool_search/3" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.horspool_search("ATCGNNACGT", "ACGN", true) end)
+    end
+  end
+
+  describe "myers_search/4" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.myers_search("ATCGNNACGT", "GAWTTC", 1, true) end)
+    end
+  end
+
+  describe "soft_masked_regions/1" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.soft_masked_regions("ACGTacgtNN") end)
+    end
+  end
+
   describe "fm_index_build/1" do
     test "raises nif_not_loaded" do
       assert_nif_not_loaded(fn -> Native.fm_index_build("ATCGATCG") end)
@@ -167,7 +289,31 @@ defmodule Cyanea.NativeTest do
 
   describe "fm_index_count/2" do
     test "raises nif_not_loaded" do
-      assert_nif_not_loaded(fn -> Native.fm_index_count(<<0, 1, 2>>, "ATC") end)
+      assert_nif_not_loaded(fn -> Native.fm_index_count(make_ref(), "ATC") end)
+    end
+  end
+
+  describe "fm_index_count_batch/2" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.fm_index_count_batch(make_ref(), ["ATC", "GAT"]) end)
+    end
+  end
+
+  describe "fm_index_locate/2" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.fm_index_locate(make_ref(), "ATC") end)
+    end
+  end
+
+  describe "fm_index_save/2" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.fm_index_save(make_ref(), "/tmp/test.fmi") end)
+    end
+  end
+
+  describe "fm_index_load/1" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.fm_index_load("/tmp/test.fmi") end)
     end
   end
 
@@ -177,6 +323,30 @@ defmodule Cyanea.NativeTest do
     end
   end
 
+  describe "find_orfs_with_code/4" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.find_orfs_with_code("ATGATCGATCGTAA", 3, 11, true) end)
+    end
+  end
+
+  describe "translate/3" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.translate("ATGAGATAA", 2, false) end)
+    end
+  end
+
+  describe "six_frame_translate/2" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.six_frame_translate("ATGAGATAA", 1) end)
+    end
+  end
+
+  describe "genetic_codes/0" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.genetic_codes() end)
+    end
+  end
+
   describe "minhash_sketch/3" do
     test "raises nif_not_loaded" do
       assert_nif_not_loaded(fn -> Native.minhash_sketch("ATCGATCG", 3, 100) end)
@@ -229,6 +399,12 @@ defmodule Cyanea.NativeTest do
     end
   end
 
+  describe "vcf_header/1" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.vcf_header("/tmp/test.vcf") end)
+    end
+  end
+
   describe "parse_bed/1" do
     test "raises nif_not_loaded" do
       assert_nif_not_loaded(fn -> Native.parse_bed("/tmp/test.bed") end)
@@ -271,6 +447,98 @@ defmodule Cyanea.NativeTest do
     end
   end
 
+  describe "bam_query/4" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.bam_query("/tmp/test.bam", "chr1", 0, 1000) end)
+    end
+  end
+
+  describe "bam_index/2" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.bam_index("/tmp/test.bam", "bai") end)
+    end
+  end
+
+  describe "tabix_query/2" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.tabix_query("/tmp/test.vcf.gz", "chr1:1-1000") end)
+    end
+  end
+
+  describe "tabix_index/2" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.tabix_index("/tmp/test.vcf", "vcf") end)
+    end
+  end
+
+  describe "bam_depth_bedgraph/8" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn ->
+        Native.bam_depth_bedgraph("/tmp/test.bam", "/tmp/out.bg", 0, 20, 0x704, 0, false, false)
+      end)
+    end
+  end
+
+  describe "bam_target_depth/6" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn ->
+        target = %Native.GenomicInterval{chrom: "chr1", start: 0, end: 100, strand: "."}
+        Native.bam_target_depth("/tmp/test.bam", [target], [10, 30], 0, 0x704, 0)
+      end)
+    end
+  end
+
+  describe "bigwig_values/4" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.bigwig_values("/tmp/test.bw", "chr1", 0, 1000) end)
+    end
+  end
+
+  describe "bigwig_summary/6" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn ->
+        Native.bigwig_summary("/tmp/test.bw", "chr1", 0, 1_000_000, 100, false)
+      end)
+    end
+  end
+
+  describe "write_bigwig/3" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn ->
+        record = %Native.BedGraphRecord{chrom: "chr1", start: 0, end: 100, value: 1.5}
+        Native.write_bigwig("/tmp/out.bw", [record], [{"chr1", 1000}])
+      end)
+    end
+  end
+
+  describe "bedgraph_to_bigwig/3" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn ->
+        Native.bedgraph_to_bigwig("/tmp/test.bedgraph", "/tmp/out.bw", [{"chr1", 1000}])
+      end)
+    end
+  end
+
+  describe "write_vcf/4" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn ->
+        Native.write_vcf("/tmp/out.vcf", %Native.VcfHeader{}, [], false)
+      end)
+    end
+  end
+
+  describe "write_bed/3" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.write_bed("/tmp/out.bed", [], false) end)
+    end
+  end
+
+  describe "write_gff3/3" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.write_gff3("/tmp/out.gff3", [], false) end)
+    end
+  end
+
   # ===========================================================================
   # cyanea-align — Sequence Alignment
   # ===========================================================================
@@ -331,6 +599,159 @@ defmodule Cyanea.NativeTest do
     end
   end
 
+  describe "substitution_matrix/1" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.substitution_matrix("blosum62") end)
+    end
+  end
+
+  describe "substitution_matrices/0" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.substitution_matrices() end)
+    end
+  end
+
+  describe "parse_substitution_matrix_text/2" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn ->
+        Native.parse_substitution_matrix_text("   A\nA  1\n", "a")
+      end)
+    end
+  end
+
+  describe "read_substitution_matrix/1" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.read_substitution_matrix("/tmp/VTML200") end)
+    end
+  end
+
+  describe "nucleotide_matrix/3" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.nucleotide_matrix(5, -1, -4) end)
+    end
+  end
+
+  describe "align_with_matrix/6" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn ->
+        Native.align_with_matrix("MVLK", "MVLK", "global", %Native.SubstitutionMatrix{}, -11, -1)
+      end)
+    end
+  end
+
+  describe "alignment_summary/2" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.alignment_summary(%Native.AlignmentResult{}, nil) end)
+    end
+  end
+
+  describe "format_alignment/5" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn ->
+        Native.format_alignment(%Native.AlignmentResult{}, "Query", "Sbjct", 60, nil)
+      end)
+    end
+  end
+
+  describe "alignment_to_sam/4" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn ->
+        Native.alignment_to_sam(%Native.AlignmentResult{}, "ACGT", "read", "chr1")
+      end)
+    end
+  end
+
+  describe "alignment_to_paf/5" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn ->
+        Native.alignment_to_paf(%Native.AlignmentResult{}, "read", 4, "chr1", 100)
+      end)
+    end
+  end
+
+  describe "karlin_altschul/3" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn ->
+        Native.karlin_altschul(%Native.SubstitutionMatrix{}, -11, -1)
+      end)
+    end
+  end
+
+  describe "alignment_significance/4" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn ->
+        Native.alignment_significance(50, %Native.KarlinAltschul{}, 300, 1_000_000)
+      end)
+    end
+  end
+
+  describe "shuffle_significance/7" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn ->
+        Native.shuffle_significance("MVLK", "MVLK", %Native.SubstitutionMatrix{}, -11, -1, 100, 42)
+      end)
+    end
+  end
+
+  describe "mapper_index/1" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.mapper_index("/tmp/ref.fa") end)
+    end
+  end
+
+  describe "mapper_contigs/1" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.mapper_contigs(make_ref()) end)
+    end
+  end
+
+  describe "map_reads/4" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn ->
+        Native.map_reads(make_ref(), "/tmp/reads.fq", "/tmp/out.sam", %Native.MapOptions{})
+      end)
+    end
+  end
+
+  describe "map_records/3" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.map_records(make_ref(), [], %Native.MapOptions{}) end)
+    end
+  end
+
+  describe "search_index/3" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.search_index("/tmp/db.fa", "auto", nil) end)
+    end
+  end
+
+  describe "search_index_sequences/3" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn ->
+        Native.search_index_sequences([{"p1", "MVLSPADKTNVKAAW"}], "protein", 3)
+      end)
+    end
+  end
+
+  describe "search_index_info/1" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.search_index_info(make_ref()) end)
+    end
+  end
+
+  describe "sequence_search/4" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn ->
+        Native.sequence_search(
+          make_ref(),
+          "MVLSPADK",
+          %Native.SubstitutionMatrix{},
+          %Native.SearchOptions{}
+        )
+      end)
+    end
+  end
+
   describe "poa_consensus/1" do
     test "raises nif_not_loaded" do
       assert_nif_not_loaded(fn ->
@@ -455,6 +876,72 @@ defmodule Cyanea.NativeTest do
     end
   end
 
+  describe "interval_intersect/5" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn ->
+        Native.interval_intersect([%Native.GenomicInterval{chrom: "chr1", start: 0, end: 100, strand: "+"}], [%Native.GenomicInterval{chrom: "chr1", start: 0, end: 100, strand: "+"}], "overlap", "ignore", 0.0)
+      end)
+    end
+  end
+
+  describe "interval_subtract/3" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.interval_subtract([%Native.GenomicInterval{chrom: "chr1", start: 0, end: 100, strand: "+"}], [], "ignore") end)
+    end
+  end
+
+  describe "interval_complement/2" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.interval_complement([%Native.GenomicInterval{chrom: "chr1", start: 0, end: 100, strand: "+"}], [{"chr1", 1000}]) end)
+    end
+  end
+
+  describe "interval_closest/3" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.interval_closest([%Native.GenomicInterval{chrom: "chr1", start: 0, end: 100, strand: "+"}], [%Native.GenomicInterval{chrom: "chr1", start: 0, end: 100, strand: "+"}], "same") end)
+    end
+  end
+
+  describe "interval_window/6" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn ->
+        Native.interval_window([%Native.GenomicInterval{chrom: "chr1", start: 0, end: 100, strand: "+"}], [%Native.GenomicInterval{chrom: "chr1", start: 0, end: 100, strand: "+"}], 1000, 1000, false, "ignore")
+      end)
+    end
+  end
+
+  describe "annotate_variants/4" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn ->
+        Native.annotate_variants([{"chr1", 100, "A", "G"}], "/tmp/genes.gff3", "/tmp/ref.fa", false)
+      end)
+    end
+  end
+
+  describe "normalize_variants/3" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn ->
+        Native.normalize_variants([{"chr1", 100, "CA", ["C"]}], "/tmp/ref.fa", true)
+      end)
+    end
+  end
+
+  describe "normalize_vcf/5" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn ->
+        Native.normalize_vcf("/tmp/in.vcf", "/tmp/out.vcf", "/tmp/ref.fa", true, false)
+      end)
+    end
+  end
+
+  describe "vcf_concordance/5" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn ->
+        Native.vcf_concordance("/tmp/truth.vcf", "/tmp/query.vcf", "/tmp/ref.fa", nil, true)
+      end)
+    end
+  end
+
   describe "expression_summary/3" do
     test "raises nif_not_loaded" do
       assert_nif_not_loaded(fn ->
@@ -591,6 +1078,28 @@ defmodule Cyanea.NativeTest do
     end
   end
 
+  describe "random_forest_predict_batch/3" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn ->
+        Native.random_forest_predict_batch(<<0, 1, 2>>, [1.0, 2.0, 3.0, 4.0], 2)
+      end)
+    end
+  end
+
+  describe "random_forest_predict_proba/3" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn ->
+        Native.random_forest_predict_proba(<<0, 1, 2>>, [1.0, 2.0, 3.0, 4.0], 2)
+      end)
+    end
+  end
+
+  describe "random_forest_info/1" do
+    test "raises nif_not_loaded" do
+      assert_nif_not_loaded(fn -> Native.random_forest_info(<<0, 1, 2>>) end)
+    end
+  end
+
   describe "hmm_viterbi/6" do
     test "raises nif_not_loaded" do
       assert_nif_not_loaded(fn ->
@@ -968,6 +1477,61 @@ defmodule Cyanea.NativeTest do
       assert_struct_fields(Native.MsaResult, [:aligned, :n_sequences, :n_columns, :conservation])
     end
 
+    test "AlignmentSummary has correct fields" do
+      assert_struct_fields(Native.AlignmentSummary, [
+        :length, :identities, :positives, :mismatches, :gaps, :gap_opens,
+        :identity, :similarity, :gap_fraction, :query_start, :query_end,
+        :target_start, :target_end, :match_line
+      ])
+    end
+
+    test "SubstitutionMatrix has correct fields" do
+      assert_struct_fields(Native.SubstitutionMatrix, [
+        :name, :alphabet, :scores, :gap_open, :gap_extend
+      ])
+    end
+
+    test "KarlinAltschul has correct fields" do
+      assert_struct_fields(Native.KarlinAltschul, [:lambda, :k, :h, :gapped])
+    end
+
+    test "AlignmentSignificance has correct fields" do
+      assert_struct_fields(Native.AlignmentSignificance, [:score, :bit_score, :evalue])
+    end
+
+    test "ShuffleFit has correct fields" do
+      assert_struct_fields(Native.ShuffleFit, [
+        :score, :shuffles, :mean, :sd, :lambda, :k, :p_value, :empirical_p
+      ])
+    end
+
+    test "MapOptions has correct fields" do
+      assert_struct_fields(Native.MapOptions, [
+        :min_seed_len, :max_occurrences, :bandwidth, :match_score,
+        :mismatch_score, :gap_open, :gap_extend, :min_score
+      ])
+    end
+
+    test "MappedRead has correct fields" do
+      assert_struct_fields(Native.MappedRead, [
+        :qname, :flag, :rname, :pos, :mapq, :cigar, :sequence, :quality,
+        :score, :suboptimal_score, :edit_distance, :md
+      ])
+    end
+
+    test "SearchOptions has correct fields" do
+      assert_struct_fields(Native.SearchOptions, [
+        :gap_open, :gap_extend, :threshold, :window, :x_drop, :gap_trigger,
+        :max_evalue, :max_targets, :strand
+      ])
+    end
+
+    test "SearchHit has correct fields" do
+      assert_struct_fields(Native.SearchHit, [
+        :target, :target_length, :strand, :score, :bit_score, :evalue, :alignment
+      ])
+    end
+
     test "DescriptiveStats has correct fields" do
       assert_struct_fields(Native.DescriptiveStats, [
         :count, :mean, :median, :variance, :sample_variance,
@@ -995,6 +1559,42 @@ defmodule Cyanea.NativeTest do
       ])
     end
 
+    test "IntervalPair has correct fields" do
+      assert_struct_fields(Native.IntervalPair, [:a, :b])
+    end
+
+    test "ClosestInterval has correct fields" do
+      assert_struct_fields(Native.ClosestInterval, [:a, :b, :distance])
+    end
+
+    test "VariantEffect has correct fields" do
+      assert_struct_fields(Native.VariantEffect, [
+        :chrom, :position, :ref_allele, :alt_allele, :gene, :transcript,
+        :consequences, :impact, :hgvs_c, :hgvs_p
+      ])
+    end
+
+    test "NormalizedVariant has correct fields" do
+      assert_struct_fields(Native.NormalizedVariant, [
+        :index, :chrom, :position, :ref_allele, :alt_alleles, :changed
+      ])
+    end
+
+    test "NormalizeStats has correct fields" do
+      assert_struct_fields(Native.NormalizeStats, [:records_in, :records_out, :normalized, :split])
+    end
+
+    test "ConcordanceCounts has correct fields" do
+      assert_struct_fields(Native.ConcordanceCounts, [
+        :truth_total, :query_total, :true_positives, :false_positives, :false_negatives,
+        :genotype_mismatches, :precision, :recall, :f1
+      ])
+    end
+
+    test "Concordance has correct fields" do
+      assert_struct_fields(Native.Concordance, [:snv, :indel, :other, :all])
+    end
+
     test "ExpressionSummary has correct fields" do
       assert_struct_fields(Native.ExpressionSummary, [
         :n_features, :n_samples, :feature_names, :sample_names,
@@ -1004,7 +1604,9 @@ defmodule Cyanea.NativeTest do
 
     test "VcfStats has correct fields" do
       assert_struct_fields(Native.VcfStats, [
-        :variant_count, :snv_count, :indel_count, :pass_count, :chromosomes
+        :variant_count, :snv_count, :indel_count, :pass_count, :chromosomes,
+        :sample_names, :call_rate, :het_count, :hom_alt_count, :het_hom_ratio,
+        :mean_allele_frequency, :allele_frequency_spectrum
       ])
     end
 
@@ -1077,15 +1679,97 @@ defmodule Cyanea.NativeTest do
   end
 
   describe "bridge structs — new" do
+    test "PairedFastqStats has correct fields" do
+      assert_struct_fields(Native.PairedFastqStats, [
+        :pair_count, :read1, :read2, :overlapping_pairs, :insert_size_mean,
+        :insert_size_median, :insert_size_sd, :insert_size_histogram
+      ])
+    end
+
+    test "TrimOptions has correct fields" do
+      assert_struct_fields(Native.TrimOptions, [
+        :adapters, :detect_adapter, :adapter_error_rate, :min_adapter_overlap,
+        :quality_cutoff, :window_size, :window_quality, :poly_g, :poly_a, :min_length
+      ])
+    end
+
+    test "TrimReport has correct fields" do
+      assert_struct_fields(Native.TrimReport, [
+        :reads_in, :reads_out, :too_short, :bases_in, :bases_out,
+        :adapter_reads, :adapter_bases, :quality_reads, :quality_bases,
+        :poly_g_reads, :poly_g_bases, :poly_a_reads, :poly_a_bases, :adapters
+      ])
+    end
+
+    test "FastaRecord has correct fields" do
+      assert_struct_fields(Native.FastaRecord, [:name, :description, :sequence])
+    end
+
+    test "FaiEntry has correct fields" do
+      assert_struct_fields(Native.FaiEntry, [:name, :length, :offset, :line_bases, :line_width])
+    end
+
+    test "PositionQuality has correct fields" do
+      assert_struct_fields(Native.PositionQuality, [
+        :start, :end, :mean, :median, :lower_quartile, :upper_quartile, :p10, :p90
+      ])
+    end
+
+    test "PositionContent has correct fields" do
+      assert_struct_fields(Native.PositionContent, [:start, :end, :a, :c, :g, :t, :n])
+    end
+
+    test "OverrepresentedSequence has correct fields" do
+      assert_struct_fields(Native.OverrepresentedSequence, [:sequence, :count, :percentage])
+    end
+
+    test "FastqQcReport has correct fields" do
+      assert_struct_fields(Native.FastqQcReport, [
+        :read_count, :total_bases, :min_length, :max_length, :gc_percent,
+        :per_base_quality, :per_base_content, :per_sequence_quality,
+        :gc_histogram, :length_distribution, :duplication_levels,
+        :deduplicated_percent, :overrepresented, :statuses
+      ])
+    end
+
+    test "GenomeEstimate has correct fields" do
+      assert_struct_fields(Native.GenomeEstimate, [
+        :error_cutoff, :kmer_coverage, :heterozygous_peak, :genome_size,
+        :heterozygosity, :repeat_fraction
+      ])
+    end
+
+    test "KmerSpectrum has correct fields" do
+      assert_struct_fields(Native.KmerSpectrum, [
+        :k, :total_kmers, :distinct_kmers, :histogram, :top_kmers, :genome
+      ])
+    end
+
     test "OrfResult has correct fields" do
       assert_struct_fields(Native.OrfResult, [
-        :start, :end, :frame, :strand, :sequence
+        :start, :end, :frame, :strand, :sequence, :protein
       ])
     end
 
+    test "FrameTranslation has correct fields" do
+      assert_struct_fields(Native.FrameTranslation, [:strand, :frame, :protein])
+    end
+
     test "VcfRecord has correct fields" do
       assert_struct_fields(Native.VcfRecord, [
-        :chrom, :position, :ref_allele, :alt_alleles, :quality, :filter
+        :chrom, :position, :ids, :ref_allele, :alt_alleles, :quality, :filter,
+        :info, :format, :genotypes, :depths, :genotype_qualities,
+        :allele_depths, :sample_data
+      ])
+    end
+
+    test "VcfField has correct fields" do
+      assert_struct_fields(Native.VcfField, [:id, :number, :value_type, :description])
+    end
+
+    test "VcfHeader has correct fields" do
+      assert_struct_fields(Native.VcfHeader, [
+        :meta, :fileformat, :samples, :contigs, :filters, :info, :format
       ])
     end
 
@@ -1101,6 +1785,40 @@ defmodule Cyanea.NativeTest do
       ])
     end
 
+    test "TargetDepth has correct fields" do
+      assert_struct_fields(Native.TargetDepth, [
+        :chrom, :start, :end, :mean_depth, :median_depth, :min_depth, :max_depth,
+        :fraction_above
+      ])
+    end
+
+    test "DepthReport has correct fields" do
+      assert_struct_fields(Native.DepthReport, [
+        :thresholds, :targets, :total_bases, :mean_depth, :median_depth, :fraction_above
+      ])
+    end
+
+    test "BedFeature has correct fields" do
+      assert_struct_fields(Native.BedFeature, [
+        :chrom, :start, :end, :name, :score, :strand, :thick_start, :thick_end,
+        :item_rgb, :block_sizes, :block_starts
+      ])
+    end
+
+    test "BedGraphRecord has correct fields" do
+      assert_struct_fields(Native.BedGraphRecord, [:chrom, :start, :end, :value])
+    end
+
+    test "BigWigBin has correct fields" do
+      assert_struct_fields(Native.BigWigBin, [:chrom, :start, :end, :mean, :min, :max, :coverage])
+    end
+
+    test "GffFeature has correct fields" do
+      assert_struct_fields(Native.GffFeature, [
+        :seqid, :source, :feature_type, :start, :end, :score, :strand, :phase, :attributes
+      ])
+    end
+
     test "SamRecord has correct fields" do
       assert_struct_fields(Native.SamRecord, [
         :qname, :flag, :rname, :pos, :mapq, :cigar, :sequence, :quality
@@ -1131,6 +1849,12 @@ defmodule Cyanea.NativeTest do
       ])
     end
 
+    test "RandomForestInfo has correct fields" do
+      assert_struct_fields(Native.RandomForestInfo, [
+        :n_trees, :n_features, :n_classes, :oob_error, :feature_importances
+      ])
+    end
+
     test "ContactMapResult has correct fields" do
       assert_struct_fields(Native.ContactMapResult, [
         :contacts, :n_residues, :contact_density
@@ -48,4 +48,112 @@ defmodule Cyanea.OmicsTest do
       assert {:error, :nif_not_loaded} = Omics.log_transform([[1.0, 2.0]], pseudocount: 0.5)
     end
   end
+
+  describe "intersect/3" do
+    test "accepts interval maps and returns nif_not_loaded without NIF" do
+      a = [%{chrom: "chr1", start: 0, end: 100, strand: "+"}]
+      b = [%Cyanea.Native.GenomicInterval{chrom: "chr1", start: 50, end: 150, strand: "-"}]
+
+      assert {:error, :nif_not_loaded} =
+               Omics.intersect(a, b, report: :both, strand: :opposite, min_overlap: 1)
+    end
+
+    test "rejects non-list input" do
+      assert_raise FunctionClauseError, fn -> Omics.intersect(%{}, []) end
+    end
+  end
+
+  describe "subtract/3" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} =
+               Omics.subtract([%{chrom: "chr1", start: 0, end: 100}], [], strand: :same)
+    end
+  end
+
+  describe "complement/2" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} =
+               Omics.complement([%{chrom: "chr1", start: 10, end: 20}], [{"chr1", 1000}])
+    end
+
+    test "returns a file error for a missing genome file" do
+      assert {:error, :enoent} = Omics.complement([], "/nonexistent/genome.txt")
+    end
+  end
+
+  describe "closest/3" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} =
+               Omics.closest([%{chrom: "chr1", start: 0, end: 10}], [%{chrom: "chr1", start: 50, end: 60}])
+    end
+  end
+
+  describe "window/3" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} =
+               Omics.window([%{chrom: "chr1", start: 0, end: 10}], [], left: 500, right: 0)
+    end
+  end
+
+  describe "annotate_variants/4" do
+    test "accepts maps and VCF records and returns nif_not_loaded without NIF" do
+      variants = [
+        %{chrom: "chr1", position: 100, ref: "A", alt: "G"},
+        %Cyanea.Native.VcfRecord{chrom: "chr1", position: 200, ref_allele: "AT", alt_alleles: ["A", "ATT"]}
+      ]
+
+      assert {:error, :nif_not_loaded} =
+               Omics.annotate_variants(variants, "/tmp/genes.gff3", "/tmp/ref.fa", most_severe: true)
+    end
+
+    test "rejects a variant without alleles" do
+      assert_raise FunctionClauseError, fn ->
+        Omics.annotate_variants([%{chrom: "chr1", position: 100}], "/tmp/genes.gff3", "/tmp/ref.fa")
+      end
+    end
+  end
+
+  describe "normalize_variants/3" do
+    test "accepts maps and VCF records and returns nif_not_loaded without NIF" do
+      variants = [
+        %{chrom: "chr1", position: 100, ref: "CA", alt: "C"},
+        %Cyanea.Native.VcfRecord{chrom: "chr1", position: 200, ref_allele: "AT", alt_alleles: ["A", "ATT"]}
+      ]
+
+      assert {:error, :nif_not_loaded} = Omics.normalize_variants(variants, "/tmp/ref.fa", split: true)
+    end
+
+    test "rejects a variant without alleles" do
+      assert_raise FunctionClauseError, fn ->
+        Omics.normalize_variants([%{chrom: "chr1", position: 100}], "/tmp/ref.fa")
+      end
+    end
+  end
+
+  describe "normalize_vcf/4" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} =
+               Omics.normalize_vcf("/tmp/in.vcf", "/tmp/out.vcf.gz", "/tmp/ref.fa", split: true, bgzf: true)
+    end
+
+    test "requires binary paths" do
+      assert_raise FunctionClauseError, fn ->
+        Omics.normalize_vcf(:in, "/tmp/out.vcf", "/tmp/ref.fa")
+      end
+    end
+  end
+
+  describe "vcf_concordance/4" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} =
+               Omics.vcf_concordance("/tmp/truth.vcf", "/tmp/query.vcf", "/tmp/ref.fa",
+                 confident_regions: "/tmp/confident.bed", pass_only: false)
+    end
+
+    test "requires binary paths" do
+      assert_raise FunctionClauseError, fn ->
+        Omics.vcf_concordance("/tmp/truth.vcf", nil, "/tmp/ref.fa")
+      end
+    end
+  end
 end
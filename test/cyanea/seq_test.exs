@@ -16,6 +16,11 @@ defmodule Cyanea.SeqTest do
       assert {:error, :nif_not_loaded} = Seq.validate("AUCG", :rna)
     end
 
+    test "accepts lowercase bases and IUPAC codes" do
+      assert {:error, :nif_not_loaded} = Seq.validate("ACGTNRYacgtn", :dna)
+      assert {:error, :nif_not_loaded} = Seq.validate("ACGUNWSacgun", :rna)
+    end
+
     test "dispatches to protein validation" do
       assert {:error, :nif_not_loaded} = Seq.validate("MVLK", :protein)
     end
@@ -66,6 +71,10 @@ defmodule Cyanea.SeqTest do
       assert {:error, :nif_not_loaded} = Seq.translate("AUGCGA")
     end
 
+    test "accepts a genetic code and to_stop" do
+      assert {:error, :nif_not_loaded} = Seq.translate("ATGAGATAA", table: 2, to_stop: true)
+    end
+
     test "rejects non-binary" do
       assert_raise FunctionClauseError, fn -> Seq.translate(123) end
     end
@@ -81,6 +90,16 @@ defmodule Cyanea.SeqTest do
     end
   end
 
+  describe "soft_masked_regions/1" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Seq.soft_masked_regions("ACGTacgtNN")
+    end
+
+    test "rejects non-binary" do
+      assert_raise FunctionClauseError, fn -> Seq.soft_masked_regions(123) end
+    end
+  end
+
   describe "kmers/2" do
     test "returns nif_not_loaded without NIF" do
       assert {:error, :nif_not_loaded} = Seq.kmers("ATCGATCG", 3)
@@ -124,6 +143,13 @@ defmodule Cyanea.SeqTest do
   end
 
   describe "search/3 with opts" do
+    test "accepts iupac: true for both algorithms" do
+      assert {:error, :nif_not_loaded} = Seq.search("ATCGNNACGT", "ACGN", iupac: true)
+
+      assert {:error, :nif_not_loaded} =
+               Seq.search("ATCGNNACGT", "GAWTTC", algorithm: :myers, max_distance: 1, iupac: true)
+    end
+
     test "accepts algorithm: :horspool" do
       assert {:error, :nif_not_loaded} = Seq.search("ATCGATCG", "ATC", algorithm: :horspool)
     end
@@ -153,15 +179,49 @@ defmodule Cyanea.SeqTest do
 
   describe "count_occurrences/2" do
     test "returns nif_not_loaded without NIF" do
-      assert {:error, :nif_not_loaded} = Seq.count_occurrences(<<0, 1, 2>>, "ATC")
+      assert {:error, :nif_not_loaded} = Seq.count_occurrences(make_ref(), "ATC")
+    end
+
+    test "accepts a list of patterns" do
+      assert {:error, :nif_not_loaded} = Seq.count_occurrences(make_ref(), ["ATC", "GAT"])
     end
 
-    test "rejects non-binary index_data" do
-      assert_raise FunctionClauseError, fn -> Seq.count_occurrences(123, "ATC") end
+    test "rejects non-reference index" do
+      assert_raise FunctionClauseError, fn -> Seq.count_occurrences(<<0, 1, 2>>, "ATC") end
     end
 
     test "rejects non-binary pattern" do
-      assert_raise FunctionClauseError, fn -> Seq.count_occurrences(<<0>>, 123) end
+      assert_raise FunctionClauseError, fn -> Seq.count_occurrences(make_ref(), 123) end
+    end
+  end
+
+  describe "locate_occurrences/2" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Seq.locate_occurrences(make_ref(), "ATC")
+    end
+
+    test "rejects non-reference index" do
+      assert_raise FunctionClauseError, fn -> Seq.locate_occurrences("ATCG", "ATC") end
+    end
+  end
+
+  describe "save_index/2" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Seq.save_index(make_ref(), "/tmp/test.fmi")
+    end
+
+    test "rejects non-binary path" do
+      assert_raise FunctionClauseError, fn -> Seq.save_index(make_ref(), 123) end
+    end
+  end
+
+  describe "load_index/1" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Seq.load_index("/tmp/test.fmi")
+    end
+
+    test "rejects non-binary path" do
+      assert_raise FunctionClauseError, fn -> Seq.load_index(123) end
     end
   end
 
@@ -178,6 +238,11 @@ defmodule Cyanea.SeqTest do
       assert {:error, :nif_not_loaded} = Seq.find_orfs("ATGATCGATCGTAA", min_length: 50)
     end
 
+    test "accepts a genetic code and alternative starts" do
+      assert {:error, :nif_not_loaded} =
+               Seq.find_orfs("GTGATCGATCGTAA", min_length: 9, table: 11, alternative_starts: true)
+    end
+
     test "defaults min_length to 100" do
       # both should return same error, just testing default path
       assert {:error, :nif_not_loaded} = Seq.find_orfs("ATGATCGATCGTAA")
@@ -253,4 +318,216 @@ defmodule Cyanea.SeqTest do
       assert_raise FunctionClauseError, fn -> Seq.parse_fastq(123) end
     end
   end
+
+  # ===========================================================================
+  # Streaming reader
+  # ===========================================================================
+
+  describe "open_reader/1" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Seq.open_reader("/tmp/test.fastq.gz")
+    end
+
+    test "rejects non-binary" do
+      assert_raise FunctionClauseError, fn -> Seq.open_reader(123) end
+    end
+  end
+
+  describe "next_chunk/2" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Seq.next_chunk(make_ref(), 1000)
+    end
+
+    test "rejects non-positive chunk size" do
+      assert_raise FunctionClauseError, fn -> Seq.next_chunk(make_ref(), 0) end
+    end
+
+    test "rejects non-reference reader" do
+      assert_raise FunctionClauseError, fn -> Seq.next_chunk("/tmp/test.fastq", 10) end
+    end
+  end
+
+  describe "close_reader/1" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Seq.close_reader(make_ref())
+    end
+  end
+
+  describe "stream_records/2" do
+    test "is lazy until enumerated" do
+      stream = Seq.stream_records("/tmp/test.fastq", chunk_size: 100)
+      assert is_function(stream, 2)
+    end
+
+    test "raises when enumerated without NIF" do
+      assert_raise ArgumentError, ~r/cannot open/, fn ->
+        "/tmp/test.fastq" |> Seq.stream_records() |> Enum.take(1)
+      end
+    end
+
+    test "rejects non-binary path" do
+      assert_raise FunctionClauseError, fn -> Seq.stream_records(123) end
+    end
+  end
+
+  describe "open_paired_reader/1" do
+    test "accepts split and interleaved inputs and returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Seq.open_paired_reader({"/tmp/r1.fastq.gz", "/tmp/r2.fastq.gz"})
+      assert {:error, :nif_not_loaded} = Seq.open_paired_reader("/tmp/interleaved.fastq")
+    end
+
+    test "rejects a malformed path pair" do
+      assert_raise FunctionClauseError, fn -> Seq.open_paired_reader({"/tmp/r1.fastq", nil}) end
+    end
+  end
+
+  describe "next_pair_chunk/2" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Seq.next_pair_chunk(make_ref(), 1000)
+    end
+
+    test "rejects non-positive chunk size" do
+      assert_raise FunctionClauseError, fn -> Seq.next_pair_chunk(make_ref(), 0) end
+    end
+  end
+
+  describe "close_paired_reader/1" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Seq.close_paired_reader(make_ref())
+    end
+  end
+
+  describe "stream_pairs/2" do
+    test "is lazy until enumerated" do
+      stream = Seq.stream_pairs({"/tmp/r1.fastq", "/tmp/r2.fastq"}, chunk_size: 100)
+      assert is_function(stream, 2)
+    end
+
+    test "raises when enumerated without NIF" do
+      assert_raise ArgumentError, ~r/cannot open/, fn ->
+        "/tmp/interleaved.fastq" |> Seq.stream_pairs() |> Enum.take(1)
+      end
+    end
+  end
+
+  describe "paired_fastq_stats/1" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Seq.paired_fastq_stats({"/tmp/r1.fastq", "/tmp/r2.fastq"})
+    end
+
+    test "rejects non-binary path" do
+      assert_raise FunctionClauseError, fn -> Seq.paired_fastq_stats(123) end
+    end
+  end
+
+  describe "trim_fastq/3" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} =
+               Seq.trim_fastq("/tmp/in.fastq", "/tmp/out.fastq.gz",
+                 adapters: ["AGATCGGAAGAGC"], window_size: 4, min_length: 30, bgzf: true)
+    end
+
+    test "rejects non-binary paths" do
+      assert_raise FunctionClauseError, fn -> Seq.trim_fastq(:in, "/tmp/out.fastq") end
+    end
+  end
+
+  describe "trim_reads/2" do
+    test "returns nif_not_loaded without NIF" do
+      record = %Cyanea.Native.FastqRecord{name: "r1", description: "", sequence: "ACGT", quality: "IIII"}
+      assert {:error, :nif_not_loaded} = Seq.trim_reads([record], poly_a: 8)
+    end
+  end
+
+  describe "trim_stream/2" do
+    test "is lazy until enumerated" do
+      stream = "/tmp/test.fastq" |> Seq.stream_records() |> Seq.trim_stream(chunk_size: 100)
+      assert is_function(stream, 2)
+    end
+
+    test "raises when a chunk cannot be trimmed" do
+      record = %Cyanea.Native.FastqRecord{name: "r1", description: "", sequence: "ACGT", quality: "IIII"}
+
+      assert_raise ArgumentError, ~r/trimming failed/, fn ->
+        [record] |> Seq.trim_stream() |> Enum.to_list()
+      end
+    end
+  end
+
+  describe "fastq_qc/2" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Seq.fastq_qc("/tmp/test.fastq")
+      assert {:error, :nif_not_loaded} = Seq.fastq_qc("/tmp/test.fastq", max_reads: 200_000)
+    end
+
+    test "rejects non-binary paths" do
+      assert_raise FunctionClauseError, fn -> Seq.fastq_qc(:reads) end
+    end
+  end
+
+  describe "parse_fasta/1" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Seq.parse_fasta("/tmp/test.fa")
+    end
+  end
+
+  describe "stream_fasta/2" do
+    test "is lazy until enumerated" do
+      assert %Stream{} = Seq.stream_fasta("/tmp/test.fa", chunk_size: 100)
+    end
+  end
+
+  describe "index_fasta/1" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Seq.index_fasta("/tmp/test.fa")
+    end
+  end
+
+  describe "open_fasta/1" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Seq.open_fasta("/tmp/test.fa")
+    end
+  end
+
+  describe "fetch_sequence/3" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Seq.fetch_sequence("/tmp/test.fa", "chr1:1000-2000")
+      assert {:error, :nif_not_loaded} = Seq.fetch_sequence(make_ref(), {"chr1", 1000, 2000}, strand: "-")
+
+      interval = %Cyanea.Native.GenomicInterval{chrom: "chr1", start: 999, end: 2000, strand: "-"}
+      assert {:error, :nif_not_loaded} = Seq.fetch_sequence(make_ref(), interval)
+    end
+
+    test "rejects malformed regions" do
+      assert_raise FunctionClauseError, fn -> Seq.fetch_sequence(make_ref(), {"chr1", "1000"}) end
+    end
+  end
+
+  describe "six_frame_translate/2" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Seq.six_frame_translate("ATGAAACCCGGGTTTTAG")
+      assert {:error, :nif_not_loaded} = Seq.six_frame_translate("ATGAAACCCGGGTTTTAG", table: 4)
+    end
+
+    test "rejects non-binary" do
+      assert_raise FunctionClauseError, fn -> Seq.six_frame_translate(123) end
+    end
+  end
+
+  describe "genetic_codes/0" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Seq.genetic_codes()
+    end
+  end
+
+  describe "kmer_spectrum/2" do
+    test "returns nif_not_loaded without NIF" do
+      assert {:error, :nif_not_loaded} = Seq.kmer_spectrum("/tmp/test.fastq")
+      assert {:error, :nif_not_loaded} = Seq.kmer_spectrum("/tmp/test.fa", k: 31, top: 5)
+    end
+
+    test "rejects non-binary paths" do
+      assert_raise FunctionClauseError, fn -> Seq.kmer_spectrum(:reads) end
+    end
+  end
 end